@@ -1,5 +1,4 @@
-use tell_me_when::{EventSystem, FsEventData, ProcessEventData, SystemEventData, NetworkEventData, PowerEventData};
-use std::path::Path;
+use tell_me_when::{EventSystem, FsEventData, ProcessEventData, PowerEventData};
 use tokio::time::{sleep, Duration};
 
 #[tokio::main]
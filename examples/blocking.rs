@@ -0,0 +1,32 @@
+use tell_me_when::{EventSystemBlocking, FsEventData, SystemEventData};
+use std::time::Duration;
+
+// No #[tokio::main]: the blocking wrapper owns its runtime
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    env_logger::init();
+
+    let mut event_system = EventSystemBlocking::new()?;
+    event_system.start()?;
+
+    let _fs_id = event_system.on_fs_created("./", |event: FsEventData| {
+        println!("📁 File created: {:?}", event.path);
+    })?;
+
+    let _fs_deleted_id = event_system.on_fs_deleted("./", |event: FsEventData| {
+        println!("🗑️  File deleted: {:?}", event.path);
+    })?;
+
+    let _cpu_high_id = event_system.on_cpu_usage_high(75.0, |event: SystemEventData| {
+        if let Some(cpu_usage) = event.cpu_usage {
+            println!("🔥 High CPU usage detected: {:.1}%", cpu_usage);
+        }
+    })?;
+
+    println!("Watching ./ for 30 seconds...");
+    std::thread::sleep(Duration::from_secs(30));
+
+    event_system.stop()?;
+    println!("✅ Event system stopped");
+
+    Ok(())
+}
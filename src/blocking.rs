@@ -0,0 +1,213 @@
+//! A synchronous front end to [`EventSystem`] for code that doesn't run inside
+//! a tokio runtime.
+
+#[cfg(any(
+    feature = "fs",
+    feature = "process",
+    feature = "system",
+    feature = "network",
+    feature = "power"
+))]
+use crate::events::*;
+use crate::{EventId, EventSystem, Result};
+use std::future::Future;
+#[cfg(feature = "fs")]
+use std::path::Path;
+
+/// Wraps an [`EventSystem`] together with the tokio runtime it runs on.
+///
+/// Every method blocks the calling thread until the underlying async call
+/// finishes, so it must not be called from inside another tokio runtime.
+/// Handlers and callbacks run on the wrapper's own worker threads.
+pub struct EventSystemBlocking {
+    // Declared before the runtime so handlers drop while it's still alive
+    inner: EventSystem,
+    runtime: tokio::runtime::Runtime,
+}
+
+impl EventSystemBlocking {
+    pub fn new() -> Result<Self> {
+        Self::with_event_system(EventSystem::new())
+    }
+
+    /// Wraps an already configured event system, such as one from
+    /// [`EventSystem::builder`].
+    pub fn with_event_system(inner: EventSystem) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .thread_name("tell_me_when-runtime")
+            .build()?;
+
+        Ok(Self { inner, runtime })
+    }
+
+    pub fn start(&mut self) -> Result<()> {
+        self.runtime.block_on(self.inner.start())
+    }
+
+    pub fn stop(&mut self) -> Result<()> {
+        self.runtime.block_on(self.inner.stop())
+    }
+
+    pub fn stop_draining(&mut self, timeout: std::time::Duration) -> Result<bool> {
+        self.runtime.block_on(self.inner.stop_draining(timeout))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.inner.is_running()
+    }
+
+    pub fn unsubscribe(&self, event_id: EventId) -> bool {
+        self.runtime.block_on(self.inner.unsubscribe(event_id))
+    }
+
+    /// Runs any other async `EventSystem` method to completion, for the parts
+    /// of the API without a blocking counterpart here.
+    pub fn block_on<'a, F, Fut>(&'a mut self, f: F) -> Fut::Output
+    where
+        F: FnOnce(&'a mut EventSystem) -> Fut,
+        Fut: Future + 'a,
+    {
+        self.runtime.block_on(f(&mut self.inner))
+    }
+
+    pub fn event_system(&self) -> &EventSystem {
+        &self.inner
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn on_fs_event<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
+    where
+        F: Fn(FsEventData) + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        self.runtime.block_on(self.inner.on_fs_event(path, callback))
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn on_fs_created<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
+    where
+        F: Fn(FsEventData) + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        self.runtime.block_on(self.inner.on_fs_created(path, callback))
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn on_fs_modified<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
+    where
+        F: Fn(FsEventData) + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        self.runtime.block_on(self.inner.on_fs_modified(path, callback))
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn on_fs_deleted<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
+    where
+        F: Fn(FsEventData) + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        self.runtime.block_on(self.inner.on_fs_deleted(path, callback))
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn on_fs_renamed<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
+    where
+        F: Fn(FsEventData) + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        self.runtime.block_on(self.inner.on_fs_renamed(path, callback))
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn on_fs_moved<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
+    where
+        F: Fn(FsEventData) + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        self.runtime.block_on(self.inner.on_fs_moved(path, callback))
+    }
+
+    #[cfg(feature = "process")]
+    pub fn on_process_event<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        self.runtime.block_on(self.inner.on_process_event(callback))
+    }
+
+    #[cfg(feature = "process")]
+    pub fn on_process_started<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        self.runtime.block_on(self.inner.on_process_started(callback))
+    }
+
+    #[cfg(feature = "process")]
+    pub fn on_process_terminated<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        self.runtime.block_on(self.inner.on_process_terminated(callback))
+    }
+
+    #[cfg(feature = "system")]
+    pub fn on_system_event<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(SystemEventData) + Send + Sync + 'static,
+    {
+        self.runtime.block_on(self.inner.on_system_event(callback))
+    }
+
+    #[cfg(feature = "system")]
+    pub fn on_cpu_usage_high<F>(&mut self, threshold: f32, callback: F) -> Result<EventId>
+    where
+        F: Fn(SystemEventData) + Send + Sync + 'static,
+    {
+        self.runtime.block_on(self.inner.on_cpu_usage_high(threshold, callback))
+    }
+
+    #[cfg(feature = "system")]
+    pub fn on_memory_usage_high<F>(&mut self, threshold: f32, callback: F) -> Result<EventId>
+    where
+        F: Fn(SystemEventData) + Send + Sync + 'static,
+    {
+        self.runtime.block_on(self.inner.on_memory_usage_high(threshold, callback))
+    }
+
+    #[cfg(feature = "network")]
+    pub fn on_network_event<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(NetworkEventData) + Send + Sync + 'static,
+    {
+        self.runtime.block_on(self.inner.on_network_event(callback))
+    }
+
+    #[cfg(feature = "power")]
+    pub fn on_power_event<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(PowerEventData) + Send + Sync + 'static,
+    {
+        self.runtime.block_on(self.inner.on_power_event(callback))
+    }
+
+    #[cfg(feature = "power")]
+    pub fn on_battery_low<F>(&mut self, threshold: f32, callback: F) -> Result<EventId>
+    where
+        F: Fn(PowerEventData) + Send + Sync + 'static,
+    {
+        self.runtime.block_on(self.inner.on_battery_low(threshold, callback))
+    }
+}
+
+impl Drop for EventSystemBlocking {
+    fn drop(&mut self) {
+        if self.inner.is_running() {
+            if let Err(e) = self.runtime.block_on(self.inner.stop()) {
+                log::error!("Failed to stop event system: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use crate::{Result, TellMeWhenError};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+
+/// Monitoring setup written by `EventSystem::save_config`: handler configs
+/// and the paths being watched. Callbacks are code and aren't saved.
+///
+/// Stored as JSON. Missing fields fall back to their defaults, so files
+/// written by older versions keep loading.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedConfig {
+    #[cfg(feature = "fs")]
+    pub watched_paths: Vec<PathBuf>,
+    #[cfg(feature = "fs")]
+    pub fs: crate::handlers::fs::FsWatchConfig,
+    #[cfg(feature = "process")]
+    pub process: crate::handlers::process::ProcessConfig,
+    #[cfg(feature = "system")]
+    pub system: crate::handlers::system::SystemConfig,
+    #[cfg(feature = "network")]
+    pub network: crate::handlers::network::NetworkConfig,
+    #[cfg(feature = "power")]
+    pub power: crate::handlers::power::PowerConfig,
+}
+
+impl SavedConfig {
+    pub fn read_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map_err(|e| TellMeWhenError::Config(format!("Invalid saved configuration: {}", e)))
+    }
+
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let contents = serde_json::to_string_pretty(self)
+            .map_err(|e| TellMeWhenError::Config(format!("Failed to serialize configuration: {}", e)))?;
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+}
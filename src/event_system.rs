@@ -1,363 +1,2895 @@
 use crate::events::*;
+#[cfg(any(
+    feature = "fs",
+    feature = "process",
+    feature = "system",
+    feature = "network",
+    feature = "power",
+    all(windows, feature = "registry")
+))]
 use crate::handlers::*;
+// For the handlers driven through `EventHandler`
+#[cfg(any(
+    feature = "fs",
+    feature = "system",
+    feature = "power",
+    all(windows, feature = "registry")
+))]
 use crate::traits::*;
-use crate::{EventBus, EventId, EventMessage, HandlerId, Result, TellMeWhenError};
+use crate::{DispatchMode, EventBus, EventId, EventMessage, HandlerId, Result, TellMeWhenError};
 use std::collections::HashMap;
+#[cfg(feature = "fs")]
 use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+// Recorded messages are shared with the subscribers that received them
+type History = Arc<std::sync::Mutex<std::collections::VecDeque<Arc<EventMessage>>>>;
 
 pub struct EventSystem {
     event_bus: Arc<EventBus>,
+    #[cfg(feature = "fs")]
     fs_handler: Option<FileSystemHandler>,
+    #[cfg(feature = "fs")]
+    fs_config: crate::handlers::fs::FsWatchConfig,
+    #[cfg(feature = "process")]
     process_handler: Option<ProcessHandler>,
-    // system_handler: Option<SystemHandler>,
-    // network_handler: Option<NetworkHandler>,
+    #[cfg(feature = "process")]
+    process_config: crate::handlers::process::ProcessConfig,
+    #[cfg(feature = "system")]
+    system_handler: Option<SystemHandler>,
+    #[cfg(feature = "system")]
+    system_config: crate::handlers::system::SystemConfig,
+    #[cfg(feature = "network")]
+    network_handler: Option<NetworkHandler>,
+    #[cfg(feature = "network")]
+    network_config: crate::handlers::network::NetworkConfig,
+    #[cfg(feature = "power")]
     power_handler: Option<PowerHandler>,
+    #[cfg(feature = "power")]
+    power_config: crate::handlers::power::PowerConfig,
+    #[cfg(all(windows, feature = "registry"))]
+    registry_handler: Option<RegistryHandler>,
+    #[cfg(any(feature = "process", feature = "system", feature = "network"))]
+    shared_system: Arc<SharedSystem>,
+    // Disabled domains, and whether each had a running handler to bring back
+    disabled_domains: HashMap<EventKind, bool>,
+    #[cfg(feature = "fs")]
+    disabled_fs_paths: Vec<std::path::PathBuf>,
+    // Paths watched with a recursion other than the config's, kept so they
+    // come back the same way when the domain is re-enabled
+    #[cfg(feature = "fs")]
+    fs_recursion: HashMap<std::path::PathBuf, bool>,
+    #[cfg(feature = "system")]
+    degradation_monitors: Arc<crate::subscription::DegradationMonitors>,
+    #[cfg(feature = "system")]
+    degradation_check: Arc<tokio::sync::Notify>,
+    #[cfg(all(windows, feature = "registry"))]
+    disabled_registry_keys: Vec<(crate::handlers::registry::RegistryHive, String)>,
+    // Most recent events, oldest first, once history is enabled
+    history: Option<(History, EventId)>,
+    // Prefixed to handler ids, set through `EventSystemBuilder::with_name`
+    name: Option<String>,
     is_running: bool,
 }
 
 impl EventSystem {
     pub fn new() -> Self {
-        let event_bus = Arc::new(EventBus::new());
-        
+        Self::with_event_bus(EventBus::new())
+    }
+
+    pub fn builder() -> EventSystemBuilder {
+        EventSystemBuilder::new()
+    }
+
+    fn with_event_bus(event_bus: EventBus) -> Self {
+        let event_bus = Arc::new(event_bus);
+
         Self {
             event_bus,
+            #[cfg(feature = "fs")]
             fs_handler: None,
+            #[cfg(feature = "fs")]
+            fs_config: Default::default(),
+            #[cfg(feature = "process")]
             process_handler: None,
-            // system_handler: None,
-            // network_handler: None,
+            #[cfg(feature = "process")]
+            process_config: Default::default(),
+            #[cfg(feature = "system")]
+            system_handler: None,
+            #[cfg(feature = "system")]
+            system_config: Default::default(),
+            #[cfg(feature = "network")]
+            network_handler: None,
+            #[cfg(feature = "network")]
+            network_config: Default::default(),
+            #[cfg(feature = "power")]
             power_handler: None,
+            #[cfg(feature = "power")]
+            power_config: Default::default(),
+            #[cfg(all(windows, feature = "registry"))]
+            registry_handler: None,
+            #[cfg(any(feature = "process", feature = "system", feature = "network"))]
+            shared_system: Arc::new(SharedSystem::new()),
+            disabled_domains: HashMap::new(),
+            #[cfg(feature = "fs")]
+            disabled_fs_paths: Vec::new(),
+            #[cfg(feature = "fs")]
+            fs_recursion: HashMap::new(),
+            #[cfg(feature = "system")]
+            degradation_monitors: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            #[cfg(feature = "system")]
+            degradation_check: Arc::new(tokio::sync::Notify::new()),
+            #[cfg(all(windows, feature = "registry"))]
+            disabled_registry_keys: Vec::new(),
+            history: None,
+            name: None,
             is_running: false,
         }
     }
 
+    /// The name given through `EventSystemBuilder::with_name`, if any.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    // "<name>/<handler>" for a named system, so its events can be told apart
+    fn handler_id(&self, handler: &str) -> HandlerId {
+        match &self.name {
+            Some(name) => format!("{}/{}", name, handler),
+            None => handler.to_string(),
+        }
+    }
+
+    /// Starts delivering events. Events queued before the call, such as ones
+    /// injected early or left over from a previous run, reach their
+    /// subscribers before it returns, so callbacks registered afterwards only
+    /// see what happens from then on.
     pub async fn start(&mut self) -> Result<()> {
         if self.is_running {
             return Ok(());
         }
 
         self.event_bus.start_processing().await;
+        // Whatever was queued before, like the SystemStopped of a previous
+        // run, goes to the subscribers registered by now and no later ones
+        self.event_bus.drain().await;
         self.is_running = true;
-        
+
         log::info!("EventSystem started");
         Ok(())
     }
 
     pub async fn stop(&mut self) -> Result<()> {
+        self.stop_with_reason(StopReason::UserRequested).await
+    }
+
+    /// Like `stop`, then waits up to `timeout` for every event already queued
+    /// to reach its subscribers, so the tail of a burst isn't lost. Returns
+    /// whether the queue drained in time.
+    pub async fn stop_draining(&mut self, timeout: Duration) -> Result<bool> {
+        if !self.is_running {
+            return Ok(true);
+        }
+
+        // Counts events through the bus rather than waiting for the
+        // SystemStopped sent last by `stop`, which a full bus may drop
+        self.stop().await?;
+        let drained = self.event_bus.flush(timeout).await;
+
+        if !drained {
+            log::warn!(
+                "{} event(s) still queued after waiting {:?} to drain",
+                self.event_bus.queued_len(),
+                timeout
+            );
+        }
+        Ok(drained)
+    }
+
+    pub async fn stop_with_reason(&mut self, reason: StopReason) -> Result<()> {
         if !self.is_running {
             return Ok(());
         }
 
         // Stop all handlers
+        #[cfg(feature = "fs")]
         if let Some(ref mut handler) = self.fs_handler {
             handler.stop().await?;
         }
+        #[cfg(feature = "process")]
         if let Some(ref mut handler) = self.process_handler {
             handler.stop().await?;
         }
-        // if let Some(ref mut handler) = self.system_handler {
-        //     handler.stop().await?;
-        // }
-        // if let Some(ref mut handler) = self.network_handler {
-        //     handler.stop().await?;
-        // }
+        #[cfg(feature = "system")]
+        if let Some(ref mut handler) = self.system_handler {
+            handler.stop().await?;
+        }
+        #[cfg(feature = "network")]
+        if let Some(ref mut handler) = self.network_handler {
+            handler.stop().await?;
+        }
+        #[cfg(feature = "power")]
         if let Some(ref mut handler) = self.power_handler {
             handler.stop().await?;
         }
+        #[cfg(all(windows, feature = "registry"))]
+        if let Some(ref mut handler) = self.registry_handler {
+            handler.stop().await?;
+        }
+        #[cfg(feature = "system")]
+        for (_, monitor) in self.degradation_monitors.lock().unwrap().drain() {
+            monitor.abort();
+        }
+        #[cfg(any(feature = "process", feature = "system", feature = "network"))]
+        self.shared_system.stop();
 
         self.is_running = false;
-        log::info!("EventSystem stopped");
+        log::info!("EventSystem stopped: {:?}", reason);
+
+        self.event_bus
+            .publish(EventMessage::lifecycle(
+                self.handler_id("event_system"),
+                LifecycleEventType::SystemStopped(reason),
+            ))
+            .await;
         Ok(())
     }
 
     // Filesystem event methods
+    #[cfg(feature = "fs")]
     pub async fn on_fs_event<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
     where
         F: Fn(FsEventData) + Send + Sync + 'static,
         P: AsRef<Path>,
     {
-        self.ensure_fs_handler().await?;
-        
-        if let Some(ref mut handler) = self.fs_handler {
-            handler.watch_path(path).await?;
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::FileSystem, move |message| {
+                if let EventData::FileSystem(fs_data) = &message.data {
+                    callback(fs_data.clone());
+                }
+            })
+            .await;
+        self.watch_for(event_id, path, None).await?;
+
+        Ok(event_id)
+    }
+
+    /// Filesystem events as a `Stream`, after watching `path`. Like
+    /// `on_fs_event`, it yields events from every watched path. Dropping the
+    /// stream unsubscribes.
+    #[cfg(feature = "fs")]
+    pub async fn fs_event_stream<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<crate::EventStream<FsEventData>> {
+        let stream = self
+            .kind_stream(EventKind::FileSystem, |data| match data {
+                EventData::FileSystem(data) => Some(data),
+                _ => None,
+            })
+            .await;
+        self.watch_fs_path(path).await?;
+        Ok(stream)
+    }
+
+    /// Like `on_fs_event`, but only events `predicate` accepts are delivered.
+    #[cfg(feature = "fs")]
+    pub async fn on_fs_event_if<Q, F, P>(
+        &mut self,
+        path: P,
+        predicate: Q,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        Q: Fn(&EventData) -> bool + Send + Sync + 'static,
+        F: Fn(FsEventData) + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        let event_id = self
+            .subscribe_kind_if(EventKind::FileSystem, predicate, move |data| {
+                if let EventData::FileSystem(fs_data) = data {
+                    callback(fs_data);
+                }
+            })
+            .await;
+        self.watch_for(event_id, path, None).await?;
+
+        Ok(event_id)
+    }
+
+    /// Like `on_fs_event`, but `recursive` decides whether subdirectories of
+    /// `path` are watched, in place of `FsWatchConfig::watch_subdirectories`.
+    /// A path that's already watched keeps its recursion.
+    #[cfg(feature = "fs")]
+    pub async fn on_fs_event_with<F, P>(
+        &mut self,
+        path: P,
+        recursive: bool,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn(FsEventData) + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::FileSystem, move |message| {
+                if let EventData::FileSystem(fs_data) = &message.data {
+                    callback(fs_data.clone());
+                }
+            })
+            .await;
+        self.watch_for(event_id, path, Some(recursive)).await?;
+
+        Ok(event_id)
+    }
+
+    /// Calls `callback` for the next filesystem event only, then unsubscribes.
+    #[cfg(feature = "fs")]
+    pub async fn on_fs_event_once<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
+    where
+        F: FnOnce(FsEventData) + Send + 'static,
+        P: AsRef<Path>,
+    {
+        let callback = std::sync::Mutex::new(Some(callback));
+        let event_id = self
+            .event_bus
+            .subscribe_kind_take(EventKind::FileSystem, 1, move |message| {
+                if let (EventData::FileSystem(fs_data), Some(callback)) =
+                    (&message.data, callback.lock().unwrap().take())
+                {
+                    callback(fs_data.clone());
+                }
+            })
+            .await;
+        self.watch_for(event_id, path, None).await?;
+
+        Ok(event_id)
+    }
+
+    #[cfg(feature = "fs")]
+    pub async fn on_fs_event_with_outcome<F, P>(
+        &mut self,
+        path: P,
+        callback: F,
+    ) -> Result<SubscribeOutcome>
+    where
+        F: Fn(FsEventData) + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::FileSystem, move |message| {
+                if let EventData::FileSystem(fs_data) = &message.data {
+                    callback(fs_data.clone());
+                }
+            })
+            .await;
+        let opened_new_watch = self.watch_for(event_id, path, None).await?;
+
+        Ok(SubscribeOutcome {
+            event_id,
+            opened_new_watch,
+        })
+    }
+
+    /// Like `on_fs_event`, but the callback only sees events under `path`
+    /// rather than those of every watched path. Moves count when either end
+    /// is under `path`.
+    #[cfg(feature = "fs")]
+    pub async fn on_fs_event_scoped<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
+    where
+        F: Fn(FsEventData) + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        let root = path.as_ref().to_path_buf();
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::FileSystem, move |message| {
+                if let EventData::FileSystem(fs_data) = &message.data {
+                    let within = fs_data.path.starts_with(&root)
+                        || match &fs_data.event_type {
+                            FsEventType::Renamed { old_path, .. } => old_path.starts_with(&root),
+                            FsEventType::Moved { from, .. } => from.starts_with(&root),
+                            _ => false,
+                        };
+                    if within {
+                        callback(fs_data.clone());
+                    }
+                }
+            })
+            .await;
+        self.watch_for(event_id, path, None).await?;
+
+        Ok(event_id)
+    }
+
+    /// Watches every path in `paths` behind a single subscription, which sees
+    /// events under any of them. Duplicates are watched once; paths that don't
+    /// exist or can't be watched are skipped, and the outcome lists the rest.
+    #[cfg(feature = "fs")]
+    pub async fn watch_paths<I, F>(&mut self, paths: I, callback: F) -> Result<WatchPathsOutcome>
+    where
+        I: IntoIterator<Item = std::path::PathBuf>,
+        F: Fn(FsEventData) + Send + Sync + 'static,
+    {
+        let mut candidates: Vec<std::path::PathBuf> = Vec::new();
+        for path in paths {
+            if candidates.contains(&path) {
+                continue;
+            }
+            if !path.exists() {
+                log::warn!("Not watching {:?}: no such path", path);
+                continue;
+            }
+            candidates.push(path);
         }
 
-        let event_id = self.event_bus.subscribe(move |message| {
-            if let EventData::FileSystem(fs_data) = message.data {
-                callback(fs_data);
+        // A root that fails to watch below stays in the filter, but has no
+        // events of its own to let through
+        let roots = candidates.clone();
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::FileSystem, move |message| {
+                if let EventData::FileSystem(fs_data) = &message.data {
+                    let under = |path: &Path| roots.iter().any(|root| path.starts_with(root));
+                    let within = under(&fs_data.path)
+                        || match &fs_data.event_type {
+                            FsEventType::Renamed { old_path, .. } => under(old_path),
+                            FsEventType::Moved { from, .. } => under(from),
+                            _ => false,
+                        };
+                    if within {
+                        callback(fs_data.clone());
+                    }
+                }
+            })
+            .await;
+
+        let mut watched = Vec::new();
+        for path in candidates {
+            match self.watch_fs_path(&path).await {
+                Ok(_) => watched.push(path),
+                Err(e) => log::warn!("Failed to watch {:?}: {}", path, e),
             }
-        }).await;
+        }
 
-        Ok(event_id)
+        Ok(WatchPathsOutcome { event_id, watched })
     }
 
+    #[cfg(feature = "fs")]
     pub async fn on_fs_created<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
     where
         F: Fn(FsEventData) + Send + Sync + 'static,
         P: AsRef<Path>,
     {
-        self.on_fs_event_filtered(path, FsEventType::Created, callback).await
+        self.on_fs_event_filtered(path, FsEventType::Created, callback)
+            .await
     }
 
+    #[cfg(feature = "fs")]
     pub async fn on_fs_modified<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
     where
         F: Fn(FsEventData) + Send + Sync + 'static,
         P: AsRef<Path>,
     {
-        self.on_fs_event_filtered(path, FsEventType::Modified, callback).await
+        self.on_fs_event_filtered(path, FsEventType::Modified, callback)
+            .await
     }
 
+    #[cfg(feature = "fs")]
     pub async fn on_fs_deleted<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
     where
         F: Fn(FsEventData) + Send + Sync + 'static,
         P: AsRef<Path>,
     {
-        self.on_fs_event_filtered(path, FsEventType::Deleted, callback).await
+        self.on_fs_event_filtered(path, FsEventType::Deleted, callback)
+            .await
     }
 
-    async fn on_fs_event_filtered<F, P>(&mut self, path: P, event_type: FsEventType, callback: F) -> Result<EventId>
+    /// Fires for renames under any watched path, with the old and new path in
+    /// `event_type`; only the kind of event is compared.
+    #[cfg(feature = "fs")]
+    pub async fn on_fs_renamed<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
     where
         F: Fn(FsEventData) + Send + Sync + 'static,
         P: AsRef<Path>,
     {
-        self.ensure_fs_handler().await?;
-        
-        if let Some(ref mut handler) = self.fs_handler {
-            handler.watch_path(path).await?;
-        }
+        let renamed = FsEventType::Renamed {
+            old_path: Default::default(),
+            new_path: Default::default(),
+        };
+        self.on_fs_event_filtered(path, renamed, callback).await
+    }
 
-        let event_id = self.event_bus.subscribe(move |message| {
-            if let EventData::FileSystem(fs_data) = message.data {
-                if std::mem::discriminant(&fs_data.event_type) == std::mem::discriminant(&event_type) {
-                    callback(fs_data);
-                }
+    /// Like `on_fs_renamed`, for moves between directories.
+    #[cfg(feature = "fs")]
+    pub async fn on_fs_moved<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
+    where
+        F: Fn(FsEventData) + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        let moved = FsEventType::Moved {
+            from: Default::default(),
+            to: Default::default(),
+        };
+        self.on_fs_event_filtered(path, moved, callback).await
+    }
+
+    #[cfg(feature = "fs")]
+    pub async fn on_fs_modified_recent<F, P>(
+        &mut self,
+        path: P,
+        within: Duration,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn(FsEventData) + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        self.on_fs_modified(path, move |fs_data| {
+            // Files already gone by the time we stat them have no content time to judge
+            let Ok(modified) =
+                std::fs::metadata(&fs_data.path).and_then(|metadata| metadata.modified())
+            else {
+                return;
+            };
+
+            // An mtime in the future (clock skew) counts as recent
+            let age = std::time::SystemTime::now()
+                .duration_since(modified)
+                .unwrap_or_default();
+            if age <= within {
+                callback(fs_data);
             }
-        }).await;
+        })
+        .await
+    }
 
-        Ok(event_id)
+    #[cfg(feature = "fs")]
+    pub async fn watch_home_config<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(FsEventData) + Send + Sync + 'static,
+    {
+        // Editors and apps leave lock files, backups and caches next to config files
+        const CONFIG_IGNORE_PATTERNS: &[&str] = &[
+            "*.lock", "*.lck", "*~", "*.swp", "*.tmp", "*cache*", "*Cache*",
+        ];
+
+        let config_dir = dirs::config_dir().ok_or_else(|| {
+            TellMeWhenError::Config(
+                "Could not resolve the user config directory on this platform".to_string(),
+            )
+        })?;
+
+        self.watch_dir_ignoring(config_dir, CONFIG_IGNORE_PATTERNS, callback)
+            .await
     }
 
-    // Process event methods
-    pub async fn on_process_event<F>(&mut self, callback: F) -> Result<EventId>
+    #[cfg(feature = "fs")]
+    pub async fn watch_downloads<F>(&mut self, callback: F) -> Result<EventId>
     where
-        F: Fn(ProcessEventData) + Send + Sync + 'static,
+        F: Fn(FsEventData) + Send + Sync + 'static,
     {
-        self.ensure_process_handler().await?;
+        // In-progress downloads from the common browsers
+        const DOWNLOAD_IGNORE_PATTERNS: &[&str] =
+            &["*.part", "*.crdownload", "*.download", "*.tmp"];
 
-        let event_id = self.event_bus.subscribe(move |message| {
-            if let EventData::Process(process_data) = message.data {
-                callback(process_data);
-            }
-        }).await;
+        let download_dir = dirs::download_dir().ok_or_else(|| {
+            TellMeWhenError::Config(
+                "Could not resolve the user downloads directory on this platform".to_string(),
+            )
+        })?;
 
-        Ok(event_id)
+        self.watch_dir_ignoring(download_dir, DOWNLOAD_IGNORE_PATTERNS, callback)
+            .await
     }
 
-    pub async fn on_process_started<F>(&mut self, callback: F) -> Result<EventId>
+    #[cfg(feature = "fs")]
+    async fn watch_dir_ignoring<F>(
+        &mut self,
+        dir: std::path::PathBuf,
+        ignore_patterns: &'static [&'static str],
+        callback: F,
+    ) -> Result<EventId>
     where
-        F: Fn(ProcessEventData) + Send + Sync + 'static,
+        F: Fn(FsEventData) + Send + Sync + 'static,
     {
-        self.on_process_event_filtered(ProcessEventType::Started, callback).await
+        let root = dir.clone();
+        let ignore = crate::handlers::fs::IgnorePatterns::new(ignore_patterns);
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::FileSystem, move |message| {
+                if let EventData::FileSystem(fs_data) = &message.data {
+                    if fs_data.path.starts_with(&root) && !ignore.matches(&fs_data.path) {
+                        callback(fs_data.clone());
+                    }
+                }
+            })
+            .await;
+        self.watch_for(event_id, &dir, None).await?;
+
+        Ok(event_id)
     }
 
-    pub async fn on_process_terminated<F>(&mut self, callback: F) -> Result<EventId>
+    /// Like `on_fs_event`, but at most `max_per_sec` events a second are
+    /// delivered, as a token bucket that allows bursts of `max_per_sec`. The
+    /// second argument counts the events dropped since the previous call.
+    #[cfg(feature = "fs")]
+    pub async fn on_fs_event_throttled<F, P>(
+        &mut self,
+        path: P,
+        max_per_sec: u32,
+        callback: F,
+    ) -> Result<EventId>
     where
-        F: Fn(ProcessEventData) + Send + Sync + 'static,
+        F: Fn(FsEventData, u64) + Send + Sync + 'static,
+        P: AsRef<Path>,
     {
-        self.on_process_event_filtered(ProcessEventType::Terminated, callback).await
+        let event_id = self
+            .event_bus
+            .subscribe_kind_throttled(
+                EventKind::FileSystem,
+                max_per_sec,
+                move |message, suppressed| {
+                    if let EventData::FileSystem(fs_data) = &message.data {
+                        callback(fs_data.clone(), suppressed);
+                    }
+                },
+            )
+            .await;
+        self.watch_for(event_id, path, None).await?;
+
+        Ok(event_id)
     }
 
-    async fn on_process_event_filtered<F>(&mut self, event_type: ProcessEventType, callback: F) -> Result<EventId>
+    #[cfg(feature = "fs")]
+    pub async fn on_fs_events_batch<F, P>(&mut self, path: P, callback: F) -> Result<EventId>
     where
-        F: Fn(ProcessEventData) + Send + Sync + 'static,
+        F: Fn(Vec<FsEventData>) + Send + Sync + 'static,
+        P: AsRef<Path>,
     {
-        self.ensure_process_handler().await?;
+        // Events of one OS notification buffer are regrouped by batch id and
+        // delivered together once the last one arrives
+        let pending: std::sync::Mutex<HashMap<u64, Vec<FsEventData>>> =
+            std::sync::Mutex::new(HashMap::new());
 
-        let event_id = self.event_bus.subscribe(move |message| {
-            if let EventData::Process(process_data) = message.data {
-                if process_data.event_type == event_type {
-                    callback(process_data);
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::FileSystem, move |message| {
+                if let EventData::FileSystem(fs_data) = &message.data {
+                    let Some(batch) = &fs_data.batch else {
+                        callback(vec![fs_data.clone()]);
+                        return;
+                    };
+
+                    let completed = {
+                        let mut pending = pending.lock().unwrap();
+                        let events = pending.entry(batch.id).or_default();
+                        events.push(fs_data.clone());
+                        if events.len() >= batch.len {
+                            pending.remove(&batch.id)
+                        } else {
+                            None
+                        }
+                    };
+
+                    if let Some(mut events) = completed {
+                        events.sort_by_key(|event| event.batch.as_ref().map(|batch| batch.index));
+                        callback(events);
+                    }
                 }
-            }
-        }).await;
+            })
+            .await;
+        self.watch_for(event_id, path, None).await?;
 
         Ok(event_id)
     }
 
-    // System event methods
-    //pub async fn on_system_event<F>(&mut self, callback: F) -> Result<EventId>
-    //where
-    //    F: Fn(SystemEventData) + Send + Sync + 'static,
-    //{
-    //    self.ensure_system_handler().await?;
-    //
-    //    let event_id = self.event_bus.subscribe(move |message| {
-    //        if let EventData::System(system_data) = message.data {
-    //            callback(system_data);
-    //        }
-    //    }).await;
-    //
-    //    Ok(event_id)
-    //}
-
-    //pub async fn on_cpu_usage_high<F>(&mut self, threshold: f32, callback: F) -> Result<EventId>
-    //where
-    //    F: Fn(SystemEventData) + Send + Sync + 'static,
-    //{
-    //    self.ensure_system_handler().await?;
-    //
-    //    let event_id = self.event_bus.subscribe(move |message| {
-    //        if let EventData::System(system_data) = message.data {
-    //            if system_data.event_type == SystemEventType::CpuUsageHigh {
-    //                if let Some(cpu_usage) = system_data.cpu_usage {
-    //                    if cpu_usage >= threshold {
-    //                        callback(system_data);
-    //                    }
-    //                }
-    //            }
-    //        }
-    //    }).await;
-    //
-    //    Ok(event_id)
-    //}
-
-    //pub async fn on_memory_usage_high<F>(&mut self, threshold: f32, callback: F) -> Result<EventId>
-    //where
-    //    F: Fn(SystemEventData) + Send + Sync + 'static,
-    //{
-    //    self.ensure_system_handler().await?;
-    //
-    //    let event_id = self.event_bus.subscribe(move |message| {
-    //        if let EventData::System(system_data) = message.data {
-    //            if system_data.event_type == SystemEventType::MemoryUsageHigh {
-    //                if let Some(memory_usage) = system_data.memory_usage {
-    //                    if memory_usage >= threshold {
-    //                        callback(system_data);
-    //                    }
-    //                }
-    //            }
-    //        }
-    //    }).await;
-    //
-    //    Ok(event_id)
-    //}
-
-    // Network event methods
-    //pub async fn on_network_event<F>(&mut self, callback: F) -> Result<EventId>
-    //where
-    //    F: Fn(NetworkEventData) + Send + Sync + 'static,
-    //{
-    //    self.ensure_network_handler().await?;
-    //
-    //    let event_id = self.event_bus.subscribe(move |message| {
-    //        if let EventData::Network(network_data) = message.data {
-    //            callback(network_data);
-    //        }
-    //    }).await;
-    //
-    //    Ok(event_id)
-    //}
-
-    // Power event methods
-    pub async fn on_power_event<F>(&mut self, callback: F) -> Result<EventId>
+    /// Delivers everything that changed under `path` once no new event has
+    /// arrived for `quiet_period`, keeping only the latest event per path.
+    #[cfg(feature = "fs")]
+    pub async fn on_fs_changes_settled<F, P>(
+        &mut self,
+        path: P,
+        quiet_period: Duration,
+        callback: F,
+    ) -> Result<EventId>
     where
-        F: Fn(PowerEventData) + Send + Sync + 'static,
+        F: Fn(Vec<FsEventData>) + Send + Sync + 'static,
+        P: AsRef<Path>,
     {
-        self.ensure_power_handler().await?;
+        let dir = path.as_ref().to_path_buf();
+        let (event_tx, mut event_rx) = mpsc::unbounded_channel::<FsEventData>();
+
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::FileSystem, move |message| {
+                if let EventData::FileSystem(fs_data) = &message.data {
+                    if fs_data.path.starts_with(&dir) {
+                        let _ = event_tx.send(fs_data.clone());
+                    }
+                }
+            })
+            .await;
+        self.watch_for(event_id, path, None).await?;
+
+        // Exits once the subscription (and with it the sender) is dropped
+        tokio::spawn(async move {
+            let _active = crate::metrics::track_task();
+            let mut pending: Vec<FsEventData> = Vec::new();
+            let mut positions: HashMap<std::path::PathBuf, usize> = HashMap::new();
+            loop {
+                let next = if pending.is_empty() {
+                    event_rx.recv().await
+                } else {
+                    match tokio::time::timeout(quiet_period, event_rx.recv()).await {
+                        Ok(next) => next,
+                        Err(_) => {
+                            positions.clear();
+                            callback(std::mem::take(&mut pending));
+                            continue;
+                        }
+                    }
+                };
 
-        let event_id = self.event_bus.subscribe(move |message| {
-            if let EventData::Power(power_data) = message.data {
-                callback(power_data);
+                let Some(fs_data) = next else {
+                    break;
+                };
+                match positions.get(&fs_data.path) {
+                    Some(&position) => pending[position] = fs_data,
+                    None => {
+                        positions.insert(fs_data.path.clone(), pending.len());
+                        pending.push(fs_data);
+                    }
+                }
             }
-        }).await;
+        });
 
         Ok(event_id)
     }
 
-    pub async fn on_battery_low<F>(&mut self, threshold: f32, callback: F) -> Result<EventId>
+    /// Runs `command` once per burst of changes under `path`, after no
+    /// matching event has arrived for `debounce`. Only events whose type is
+    /// in `event_types` count, ignoring the paths inside `Renamed` and
+    /// `Moved`; an empty slice counts every change. `command`
+    /// runs on a blocking thread, so it can wait for a build to finish; changes
+    /// made meanwhile trigger one more run once it returns.
+    #[cfg(feature = "fs")]
+    pub async fn on_change_run<F, P>(
+        &mut self,
+        path: P,
+        event_types: &[FsEventType],
+        debounce: Duration,
+        command: F,
+    ) -> Result<EventId>
     where
-        F: Fn(PowerEventData) + Send + Sync + 'static,
+        F: Fn() + Send + Sync + 'static,
+        P: AsRef<Path>,
     {
-        self.ensure_power_handler().await?;
+        let dir = path.as_ref().to_path_buf();
+        let event_types = event_types.to_vec();
+        let (change_tx, mut change_rx) = mpsc::unbounded_channel::<()>();
 
-        let event_id = self.event_bus.subscribe(move |message| {
-            if let EventData::Power(power_data) = message.data {
-                if power_data.event_type == PowerEventType::BatteryLow {
-                    if let Some(battery_level) = power_data.battery_level {
-                        if battery_level <= threshold {
-                            callback(power_data);
-                        }
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::FileSystem, move |message| {
+                if let EventData::FileSystem(fs_data) = &message.data {
+                    if fs_data.path.starts_with(&dir)
+                        && (event_types.is_empty()
+                            || event_types.iter().any(|wanted| {
+                                std::mem::discriminant(wanted)
+                                    == std::mem::discriminant(&fs_data.event_type)
+                            }))
+                    {
+                        let _ = change_tx.send(());
+                    }
+                }
+            })
+            .await;
+        self.watch_for(event_id, path, None).await?;
+
+        // Exits once the subscription (and with it the sender) is dropped
+        let command = Arc::new(command);
+        tokio::spawn(async move {
+            let _active = crate::metrics::track_task();
+            while change_rx.recv().await.is_some() {
+                loop {
+                    match tokio::time::timeout(debounce, change_rx.recv()).await {
+                        Ok(Some(())) => continue,
+                        Ok(None) => return,
+                        Err(_) => break,
                     }
                 }
+
+                let command = command.clone();
+                if let Err(e) = tokio::task::spawn_blocking(move || command()).await {
+                    log::error!("Change command panicked: {}", e);
+                }
             }
-        }).await;
+        });
 
         Ok(event_id)
     }
 
-    // Utility methods
-    pub async fn unsubscribe(&self, event_id: EventId) -> bool {
-        self.event_bus.unsubscribe(event_id).await
+    #[cfg(feature = "fs")]
+    async fn on_fs_event_filtered<F, P>(
+        &mut self,
+        path: P,
+        event_type: FsEventType,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn(FsEventData) + Send + Sync + 'static,
+        P: AsRef<Path>,
+    {
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::FileSystem, move |message| {
+                if let EventData::FileSystem(fs_data) = &message.data {
+                    if std::mem::discriminant(&fs_data.event_type)
+                        == std::mem::discriminant(&event_type)
+                    {
+                        callback(fs_data.clone());
+                    }
+                }
+            })
+            .await;
+        self.watch_for(event_id, path, None).await?;
+
+        Ok(event_id)
     }
 
-    pub fn is_running(&self) -> bool {
-        self.is_running
+    // Process event methods
+    #[cfg(feature = "process")]
+    pub async fn on_process_event<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        self.ensure_process_handler().await?;
+
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::Process, move |message| {
+                if let EventData::Process(process_data) = &message.data {
+                    callback(process_data.clone());
+                }
+            })
+            .await;
+
+        Ok(event_id)
     }
 
-    // Handler initialization methods
-    async fn ensure_fs_handler(&mut self) -> Result<()> {
-        if self.fs_handler.is_none() {
-            let mut handler = FileSystemHandler::new("filesystem".to_string());
-            handler.event_sender = Some(self.event_bus.sender());
-                handler.start(crate::handlers::fs::FsWatchConfig::default()).await?;
-            self.fs_handler = Some(handler);
-        }
-        Ok(())
+    /// Like `on_process_event`, but an event with the same `dedup_key` as the one
+    /// delivered just before it, less than `window` earlier, is dropped.
+    #[cfg(feature = "process")]
+    pub async fn on_process_event_deduped<F>(
+        &mut self,
+        window: Duration,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        self.ensure_process_handler().await?;
+
+        let is_new = dedup_filter(window);
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::Process, move |message| {
+                if let EventData::Process(process_data) = &message.data {
+                    if is_new(&message) {
+                        callback(process_data.clone());
+                    }
+                }
+            })
+            .await;
+
+        Ok(event_id)
     }
 
-    async fn ensure_process_handler(&mut self) -> Result<()> {
-        if self.process_handler.is_none() {
-            use crate::handlers::process::ProcessConfig;
-            let mut handler = ProcessHandler::new(ProcessConfig::default());
-            handler.start(self.event_bus.sender(), "process".to_string()).await?;
-            self.process_handler = Some(handler);
-        }
-        Ok(())
+    /// Like `on_process_event`, but only events `predicate` accepts are delivered.
+    #[cfg(feature = "process")]
+    pub async fn on_process_event_if<Q, F>(&mut self, predicate: Q, callback: F) -> Result<EventId>
+    where
+        Q: Fn(&EventData) -> bool + Send + Sync + 'static,
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        self.ensure_process_handler().await?;
+
+        Ok(self
+            .subscribe_kind_if(EventKind::Process, predicate, move |data| {
+                if let EventData::Process(process_data) = data {
+                    callback(process_data);
+                }
+            })
+            .await)
     }
 
-    // async fn ensure_system_handler(&mut self) -> Result<()> {
-    //     if self.system_handler.is_none() {
-    //         let mut handler = SystemHandler::new("system".to_string());
-    //         handler.event_sender = Some(self.event_bus.sender());
-    //             handler.start(crate::handlers::system::SystemConfig::default()).await?;
-    //         self.system_handler = Some(handler);
-    //     }
-    //     Ok(())
-    // }
-
-    // async fn ensure_network_handler(&mut self) -> Result<()> {
-    //     if self.network_handler.is_none() {
-    //         let mut handler = NetworkHandler::new(crate::handlers::network::NetworkConfig::default());
-    //         handler.start(self.event_bus.sender(), "network".to_string()).await?;
-    //         self.network_handler = Some(handler);
-    //     }
-    //     Ok(())
-    // }
+    /// Calls `callback` for the next process event only, then unsubscribes.
+    #[cfg(feature = "process")]
+    pub async fn on_process_event_once<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: FnOnce(ProcessEventData) + Send + 'static,
+    {
+        self.ensure_process_handler().await?;
 
-    async fn ensure_power_handler(&mut self) -> Result<()> {
-        if self.power_handler.is_none() {
-            let mut handler = PowerHandler::new("power".to_string());
-            handler.event_sender = Some(self.event_bus.sender());
-                handler.start(crate::handlers::power::PowerConfig::default()).await?;
-            self.power_handler = Some(handler);
-        }
-        Ok(())
+        let callback = std::sync::Mutex::new(Some(callback));
+        let event_id = self
+            .event_bus
+            .subscribe_kind_take(EventKind::Process, 1, move |message| {
+                if let (EventData::Process(process_data), Some(callback)) =
+                    (&message.data, callback.lock().unwrap().take())
+                {
+                    callback(process_data.clone());
+                }
+            })
+            .await;
+
+        Ok(event_id)
     }
-}
 
-impl Default for EventSystem {
-    fn default() -> Self {
-        Self::new()
+    #[cfg(feature = "process")]
+    pub async fn on_process_started<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        self.on_process_event_filtered(ProcessEventType::Started, callback)
+            .await
+    }
+
+    #[cfg(feature = "process")]
+    pub async fn on_process_terminated<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        self.on_process_event_filtered(ProcessEventType::Terminated, callback)
+            .await
     }
-}
\ No newline at end of file
+
+    /// Fires for every event of processes named `name`, compared the way
+    /// `process_name_filters` are: ignoring case, directories and `.exe`, so
+    /// `"notepad"` matches `notepad.exe`.
+    #[cfg(feature = "process")]
+    pub async fn on_process_name<F>(&mut self, name: &str, callback: F) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        let matcher = self.process_name_matcher(name).await?;
+        self.on_process_event(move |process_data| {
+            if matcher.should_monitor_process(&process_data.name, None) {
+                callback(process_data);
+            }
+        })
+        .await
+    }
+
+    #[cfg(feature = "process")]
+    pub async fn on_process_name_started<F>(&mut self, name: &str, callback: F) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        let matcher = self.process_name_matcher(name).await?;
+        self.on_process_started(move |process_data| {
+            if matcher.should_monitor_process(&process_data.name, None) {
+                callback(process_data);
+            }
+        })
+        .await
+    }
+
+    #[cfg(feature = "process")]
+    pub async fn on_process_name_terminated<F>(
+        &mut self,
+        name: &str,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        let matcher = self.process_name_matcher(name).await?;
+        self.on_process_terminated(move |process_data| {
+            if matcher.should_monitor_process(&process_data.name, None) {
+                callback(process_data);
+            }
+        })
+        .await
+    }
+
+    /// Adds `name` to `process_name_filters` while the system runs, e.g. when
+    /// a user picks another process to watch. Running monitors apply it from
+    /// the next process they see. Starting with no filters reports every
+    /// process, so the first filter added narrows reporting to just `name`.
+    #[cfg(feature = "process")]
+    pub fn add_process_name_filter(&mut self, name: &str) {
+        let filters = &mut self.process_config.process_name_filters;
+        if !filters.iter().any(|filter| filter == name) {
+            filters.push(name.to_string());
+        }
+        if let Some(ref handler) = self.process_handler {
+            handler.add_name_filter(name);
+        }
+    }
+
+    /// Removes `name` from `process_name_filters` while the system runs,
+    /// returning whether it was there. Once the last filter is removed every
+    /// process is reported again.
+    #[cfg(feature = "process")]
+    pub fn remove_process_name_filter(&mut self, name: &str) -> bool {
+        let filters = &mut self.process_config.process_name_filters;
+        let before = filters.len();
+        filters.retain(|filter| filter != name);
+        let removed = filters.len() != before;
+        if let Some(ref handler) = self.process_handler {
+            handler.remove_name_filter(name);
+        }
+        removed
+    }
+
+    // A config whose only filter is `name`, used to match events against it.
+    // When `process_name_filters` already narrows what the monitors report,
+    // `name` is added to it so its events aren't dropped before reaching the
+    // bus; an empty list reports everything and is left empty
+    #[cfg(feature = "process")]
+    async fn process_name_matcher(
+        &mut self,
+        name: &str,
+    ) -> Result<crate::handlers::process::ProcessConfig> {
+        if !self.process_config.process_name_filters.is_empty() {
+            self.add_process_name_filter(name);
+        }
+
+        Ok(crate::handlers::process::ProcessConfig {
+            process_name_filters: vec![name.to_string()],
+            normalize_process_names: true,
+            ..Default::default()
+        })
+    }
+
+    /// Fires for processes started by `user`, given as a user name or uid
+    /// (SID on Windows). Processes whose owner can't be read never match.
+    #[cfg(feature = "process")]
+    pub async fn on_process_started_by_user<F>(
+        &mut self,
+        user: &str,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        let user = user.to_string();
+        self.on_process_started(move |process_data| {
+            let matches = process_data.owner_name.as_deref() == Some(user.as_str())
+                || process_data.owner_uid.as_deref() == Some(user.as_str());
+            if matches {
+                callback(process_data);
+            }
+        })
+        .await
+    }
+
+    /// Delivers events for a fixed set of processes under one subscription:
+    /// native lifecycle events plus a `ResourceUsage` sample per process every
+    /// second. A process stops being tracked after its `Terminated` event.
+    #[cfg(feature = "process")]
+    pub async fn on_processes<F>(&mut self, pids: &[u32], callback: F) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+        self.ensure_process_handler().await?;
+
+        let tracked = Arc::new(std::sync::Mutex::new(
+            pids.iter()
+                .copied()
+                .collect::<std::collections::HashSet<u32>>(),
+        ));
+        // Samples are tagged so other subscriptions' samplers don't leak in
+        let sampler_id = self.handler_id(&format!("process-sampler-{}", uuid::Uuid::new_v4()));
+        let process_id = self.handler_id("process");
+
+        let sampler_tracked = Arc::downgrade(&tracked);
+        let subscription_sampler_id = sampler_id.clone();
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::Process, move |message| {
+                let from_sampler = message.metadata.handler_id == subscription_sampler_id;
+                if !from_sampler && message.metadata.handler_id != process_id {
+                    return;
+                }
+                if let EventData::Process(process_data) = &message.data {
+                    // The sampler already removed pids it reports as exited
+                    let deliver = from_sampler || {
+                        let mut tracked = tracked.lock().unwrap();
+                        if process_data.event_type == ProcessEventType::Terminated {
+                            tracked.remove(&process_data.pid)
+                        } else {
+                            tracked.contains(&process_data.pid)
+                        }
+                    };
+                    if deliver {
+                        callback(process_data.clone());
+                    }
+                }
+            })
+            .await;
+
+        crate::handlers::process::spawn_pid_sampler(
+            sampler_tracked,
+            self.shared_system.clone(),
+            SAMPLE_INTERVAL,
+            self.event_bus.sender(),
+            sampler_id,
+        );
+
+        Ok(event_id)
+    }
+
+    /// Delivers process events for `root_pid` and every process descended from
+    /// it. The subtree is seeded from the processes running now and grows as
+    /// descendants start; a process leaves it after its `Terminated` event.
+    #[cfg(feature = "process")]
+    pub async fn on_process_tree<F>(&mut self, root_pid: u32, callback: F) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        self.ensure_process_handler().await?;
+
+        let running = crate::handlers::process::running_processes(&self.shared_system);
+        let tree = std::sync::Mutex::new(crate::handlers::process::ProcessTree::new(
+            root_pid, &running,
+        ));
+        let process_id = self.handler_id("process");
+
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::Process, move |message| {
+                if message.metadata.handler_id != process_id {
+                    return;
+                }
+                if let EventData::Process(process_data) = &message.data {
+                    let deliver = tree.lock().unwrap().accept(process_data);
+                    for event in deliver {
+                        callback(event);
+                    }
+                }
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    /// Process events as a `Stream`. With `backfill`, every process already
+    /// running is yielded first as a synthetic `Started` event. Dropping the
+    /// stream unsubscribes.
+    #[cfg(feature = "process")]
+    pub async fn process_event_stream(
+        &mut self,
+        backfill: bool,
+    ) -> Result<crate::EventStream<ProcessEventData>> {
+        self.ensure_process_handler().await?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::Process, move |message| {
+                if let EventData::Process(process_data) = &message.data {
+                    let _ = sender.send(process_data.clone());
+                }
+            })
+            .await;
+
+        // Subscribing first means a process starting meanwhile shows up in
+        // both; a duplicate Started beats a missed one
+        let pending = if backfill {
+            crate::handlers::process::running_processes(&self.shared_system)
+        } else {
+            std::collections::VecDeque::new()
+        };
+
+        Ok(crate::EventStream::new(
+            pending,
+            receiver,
+            self.event_bus.clone(),
+            event_id,
+        ))
+    }
+
+    #[cfg(feature = "process")]
+    async fn on_process_event_filtered<F>(
+        &mut self,
+        event_type: ProcessEventType,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        self.ensure_process_handler().await?;
+
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::Process, move |message| {
+                if let EventData::Process(process_data) = &message.data {
+                    if process_data.event_type == event_type {
+                        callback(process_data.clone());
+                    }
+                }
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    #[cfg(all(feature = "fs", feature = "process"))]
+    pub async fn watch_executable<F>(&mut self, pid: u32, callback: F) -> Result<EventId>
+    where
+        F: Fn(ProcessEventData) + Send + Sync + 'static,
+    {
+        use crate::handlers::process::{
+            is_process_running, process_command, process_executable, process_owner,
+        };
+
+        let (name, exe_path) = process_executable(pid).ok_or_else(|| {
+            TellMeWhenError::System(format!(
+                "Could not resolve the executable of process {}",
+                pid
+            ))
+        })?;
+
+        // Updaters usually write a new file and rename it over the old one, so
+        // watch the containing directory rather than the file itself
+        let exe_dir = exe_path.parent().map(Path::to_path_buf).ok_or_else(|| {
+            TellMeWhenError::Config(format!(
+                "Executable path {:?} has no parent directory",
+                exe_path
+            ))
+        })?;
+
+        self.watch_fs_path(&exe_dir).await?;
+
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::FileSystem, move |message| {
+                if let EventData::FileSystem(fs_data) = &message.data {
+                    let touches_exe = match &fs_data.event_type {
+                        FsEventType::Modified | FsEventType::Deleted => fs_data.path == exe_path,
+                        FsEventType::Renamed { old_path, new_path } => {
+                            *old_path == exe_path || *new_path == exe_path
+                        }
+                        FsEventType::Moved { from, to } => *from == exe_path || *to == exe_path,
+                        _ => false,
+                    };
+
+                    if touches_exe && is_process_running(pid) {
+                        let (owner_uid, owner_name) = process_owner(pid);
+                        callback(ProcessEventData {
+                            event_type: ProcessEventType::ExecutableReplaced,
+                            pid,
+                            name: name.clone(),
+                            cpu_usage: None,
+                            memory_usage: None,
+                            owner_uid,
+                            owner_name,
+                            exit_code: None,
+                            parent_pid: None,
+                            exe_path: Some(exe_path.clone()),
+                            cmdline: process_command(pid).1,
+                            timestamp: fs_data.timestamp,
+                        });
+                    }
+                }
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    // System event methods
+    #[cfg(feature = "system")]
+    pub async fn on_system_event<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(SystemEventData) + Send + Sync + 'static,
+    {
+        self.ensure_system_handler().await?;
+
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::System, move |message| {
+                if let EventData::System(system_data) = &message.data {
+                    callback(system_data.clone());
+                }
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    /// Like `on_system_event`, but an event with the same `dedup_key` as the one
+    /// delivered just before it, less than `window` earlier, is dropped.
+    #[cfg(feature = "system")]
+    pub async fn on_system_event_deduped<F>(
+        &mut self,
+        window: Duration,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn(SystemEventData) + Send + Sync + 'static,
+    {
+        self.ensure_system_handler().await?;
+
+        let is_new = dedup_filter(window);
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::System, move |message| {
+                if let EventData::System(system_data) = &message.data {
+                    if is_new(&message) {
+                        callback(system_data.clone());
+                    }
+                }
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    /// System resource events as a `Stream`. Dropping the stream unsubscribes.
+    #[cfg(feature = "system")]
+    pub async fn system_event_stream(&mut self) -> Result<crate::EventStream<SystemEventData>> {
+        self.ensure_system_handler().await?;
+        Ok(self
+            .kind_stream(EventKind::System, |data| match data {
+                EventData::System(data) => Some(data),
+                _ => None,
+            })
+            .await)
+    }
+
+    /// Like `on_system_event`, but only events `predicate` accepts are delivered.
+    #[cfg(feature = "system")]
+    pub async fn on_system_event_if<Q, F>(&mut self, predicate: Q, callback: F) -> Result<EventId>
+    where
+        Q: Fn(&EventData) -> bool + Send + Sync + 'static,
+        F: Fn(SystemEventData) + Send + Sync + 'static,
+    {
+        self.ensure_system_handler().await?;
+
+        Ok(self
+            .subscribe_kind_if(EventKind::System, predicate, move |data| {
+                if let EventData::System(system_data) = data {
+                    callback(system_data);
+                }
+            })
+            .await)
+    }
+
+    #[cfg(feature = "system")]
+    pub async fn on_cpu_usage_high<F>(&mut self, threshold: f32, callback: F) -> Result<EventId>
+    where
+        F: Fn(SystemEventData) + Send + Sync + 'static,
+    {
+        self.on_system_event_if(
+            move |data| {
+                matches!(data, EventData::System(system_data)
+                if system_data.event_type == SystemEventType::CpuUsageHigh
+                    && system_data.cpu_usage.is_some_and(|cpu_usage| cpu_usage >= threshold))
+            },
+            callback,
+        )
+        .await
+    }
+
+    #[cfg(feature = "system")]
+    pub async fn on_memory_usage_high<F>(&mut self, threshold: f32, callback: F) -> Result<EventId>
+    where
+        F: Fn(SystemEventData) + Send + Sync + 'static,
+    {
+        self.on_system_event_if(
+            move |data| matches!(data, EventData::System(system_data)
+                if system_data.event_type == SystemEventType::MemoryUsageHigh
+                    && system_data.memory_usage.is_some_and(|memory_usage| memory_usage >= threshold)),
+            callback,
+        ).await
+    }
+
+    /// `threshold` is the percentage of the disk in use, as in `disk_usage`.
+    #[cfg(feature = "system")]
+    pub async fn on_disk_space_low<F>(&mut self, threshold: f32, callback: F) -> Result<EventId>
+    where
+        F: Fn(SystemEventData) + Send + Sync + 'static,
+    {
+        self.on_system_event_if(
+            move |data| {
+                matches!(data, EventData::System(system_data)
+                if system_data.event_type == SystemEventType::DiskSpaceLow
+                    && system_data.disk_usage.is_some_and(|disk_usage| disk_usage >= threshold))
+            },
+            callback,
+        )
+        .await
+    }
+
+    #[cfg(feature = "system")]
+    pub async fn on_temperature_high<F>(&mut self, threshold: f32, callback: F) -> Result<EventId>
+    where
+        F: Fn(SystemEventData) + Send + Sync + 'static,
+    {
+        self.on_system_event_if(
+            move |data| {
+                matches!(data, EventData::System(system_data)
+                if system_data.event_type == SystemEventType::TemperatureHigh
+                    && system_data.temperature.is_some_and(|temperature| temperature >= threshold))
+            },
+            callback,
+        )
+        .await
+    }
+
+    #[cfg(feature = "system")]
+    pub async fn on_load_average_high<F>(&mut self, threshold: f32, callback: F) -> Result<EventId>
+    where
+        F: Fn(SystemEventData) + Send + Sync + 'static,
+    {
+        self.on_system_event_if(
+            move |data| matches!(data, EventData::System(system_data)
+                if system_data.event_type == SystemEventType::LoadAverageHigh
+                    && system_data.load_average.is_some_and(|load_average| load_average >= threshold)),
+            callback,
+        ).await
+    }
+
+    #[cfg(feature = "system")]
+    pub async fn on_system_degraded<F>(
+        &mut self,
+        criteria: crate::handlers::degradation::DegradedCriteria,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn(SystemEventData) + Send + Sync + 'static,
+    {
+        if criteria.poll_interval < MIN_POLL_INTERVAL {
+            return Err(TellMeWhenError::Config(format!(
+                "Degradation poll_interval of {:?} is below the minimum of {:?}",
+                criteria.poll_interval, MIN_POLL_INTERVAL
+            )));
+        }
+
+        // Each subscription gets its own monitor; the handler id keeps one
+        // subscription's events from reaching another with different criteria
+        let handler_id = crate::handlers::degradation::next_monitor_id();
+        let subscription_handler_id = handler_id.clone();
+
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::System, move |message| {
+                if message.metadata.handler_id != subscription_handler_id {
+                    return;
+                }
+                if let EventData::System(system_data) = &message.data {
+                    callback(system_data.clone());
+                }
+            })
+            .await;
+
+        let monitor = crate::handlers::degradation::spawn_degradation_monitor(
+            criteria,
+            self.shared_system.clone(),
+            self.event_bus.sender(),
+            handler_id,
+            self.degradation_check.clone(),
+        );
+        self.degradation_monitors
+            .lock()
+            .unwrap()
+            .insert(event_id, monitor);
+
+        Ok(event_id)
+    }
+
+    // Network event methods
+    #[cfg(feature = "network")]
+    pub async fn on_network_event<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(NetworkEventData) + Send + Sync + 'static,
+    {
+        self.ensure_network_handler().await?;
+
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::Network, move |message| {
+                if let EventData::Network(network_data) = &message.data {
+                    callback(network_data.clone());
+                }
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    /// Like `on_network_event`, but an event with the same `dedup_key` as the one
+    /// delivered just before it, less than `window` earlier, is dropped.
+    #[cfg(feature = "network")]
+    pub async fn on_network_event_deduped<F>(
+        &mut self,
+        window: Duration,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn(NetworkEventData) + Send + Sync + 'static,
+    {
+        self.ensure_network_handler().await?;
+
+        let is_new = dedup_filter(window);
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::Network, move |message| {
+                if let EventData::Network(network_data) = &message.data {
+                    if is_new(&message) {
+                        callback(network_data.clone());
+                    }
+                }
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    /// Network events as a `Stream`. Dropping the stream unsubscribes.
+    #[cfg(feature = "network")]
+    pub async fn network_event_stream(&mut self) -> Result<crate::EventStream<NetworkEventData>> {
+        self.ensure_network_handler().await?;
+        Ok(self
+            .kind_stream(EventKind::Network, |data| match data {
+                EventData::Network(data) => Some(data),
+                _ => None,
+            })
+            .await)
+    }
+
+    /// Like `on_network_event`, but only events `predicate` accepts are delivered.
+    #[cfg(feature = "network")]
+    pub async fn on_network_event_if<Q, F>(&mut self, predicate: Q, callback: F) -> Result<EventId>
+    where
+        Q: Fn(&EventData) -> bool + Send + Sync + 'static,
+        F: Fn(NetworkEventData) + Send + Sync + 'static,
+    {
+        self.ensure_network_handler().await?;
+
+        Ok(self
+            .subscribe_kind_if(EventKind::Network, predicate, move |data| {
+                if let EventData::Network(network_data) = data {
+                    callback(network_data);
+                }
+            })
+            .await)
+    }
+
+    // Power event methods
+    #[cfg(feature = "power")]
+    pub async fn on_power_event<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(PowerEventData) + Send + Sync + 'static,
+    {
+        self.ensure_power_handler().await?;
+
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::Power, move |message| {
+                if let EventData::Power(power_data) = &message.data {
+                    callback(power_data.clone());
+                }
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    /// Like `on_power_event`, but an event with the same `dedup_key` as the one
+    /// delivered just before it, less than `window` earlier, is dropped.
+    #[cfg(feature = "power")]
+    pub async fn on_power_event_deduped<F>(
+        &mut self,
+        window: Duration,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn(PowerEventData) + Send + Sync + 'static,
+    {
+        self.ensure_power_handler().await?;
+
+        let is_new = dedup_filter(window);
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::Power, move |message| {
+                if let EventData::Power(power_data) = &message.data {
+                    if is_new(&message) {
+                        callback(power_data.clone());
+                    }
+                }
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    /// Power events as a `Stream`. Dropping the stream unsubscribes.
+    #[cfg(feature = "power")]
+    pub async fn power_event_stream(&mut self) -> Result<crate::EventStream<PowerEventData>> {
+        self.ensure_power_handler().await?;
+        Ok(self
+            .kind_stream(EventKind::Power, |data| match data {
+                EventData::Power(data) => Some(data),
+                _ => None,
+            })
+            .await)
+    }
+
+    /// Like `on_power_event`, but only events `predicate` accepts are delivered.
+    #[cfg(feature = "power")]
+    pub async fn on_power_event_if<Q, F>(&mut self, predicate: Q, callback: F) -> Result<EventId>
+    where
+        Q: Fn(&EventData) -> bool + Send + Sync + 'static,
+        F: Fn(PowerEventData) + Send + Sync + 'static,
+    {
+        self.ensure_power_handler().await?;
+
+        Ok(self
+            .subscribe_kind_if(EventKind::Power, predicate, move |data| {
+                if let EventData::Power(power_data) = data {
+                    callback(power_data);
+                }
+            })
+            .await)
+    }
+
+    #[cfg(feature = "power")]
+    pub async fn on_battery_low<F>(&mut self, threshold: f32, callback: F) -> Result<EventId>
+    where
+        F: Fn(PowerEventData) + Send + Sync + 'static,
+    {
+        self.on_power_event_if(
+            move |data| matches!(data, EventData::Power(power_data)
+                if power_data.event_type == PowerEventType::BatteryLow
+                    && power_data.battery_level.is_some_and(|battery_level| battery_level <= threshold)),
+            callback,
+        ).await
+    }
+
+    #[cfg(feature = "power")]
+    pub async fn on_thermal_throttle<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(PowerEventData) + Send + Sync + 'static,
+    {
+        self.ensure_power_handler().await?;
+
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::Power, move |message| {
+                if let EventData::Power(power_data) = &message.data {
+                    if power_data.event_type == PowerEventType::ThermalThrottling {
+                        callback(power_data.clone());
+                    }
+                }
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    // Registry event methods
+    #[cfg(all(windows, feature = "registry"))]
+    pub async fn on_registry_change<F>(
+        &mut self,
+        hive: crate::handlers::registry::RegistryHive,
+        subkey: &str,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn(RegistryEventData) + Send + Sync + 'static,
+    {
+        self.watch_registry_key(hive, subkey).await?;
+
+        // Registry paths are case-insensitive
+        let key_path = hive.key_path(subkey).to_lowercase();
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::Registry, move |message| {
+                if let EventData::Registry(registry_data) = &message.data {
+                    if registry_data.key.to_lowercase() == key_path {
+                        callback(registry_data.clone());
+                    }
+                }
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    // The shared body of the `on_*_event_if` methods
+    #[cfg(any(
+        feature = "fs",
+        feature = "process",
+        feature = "system",
+        feature = "network",
+        feature = "power"
+    ))]
+    async fn subscribe_kind_if<Q, F>(&self, kind: EventKind, predicate: Q, callback: F) -> EventId
+    where
+        Q: Fn(&EventData) -> bool + Send + Sync + 'static,
+        F: Fn(EventData) + Send + Sync + 'static,
+    {
+        self.event_bus
+            .subscribe_kind(kind, move |message| {
+                if predicate(&message.data) {
+                    callback(message.data.clone());
+                }
+            })
+            .await
+    }
+
+    // The shared body of the `*_event_stream` methods
+    #[cfg(any(
+        feature = "fs",
+        feature = "system",
+        feature = "network",
+        feature = "power"
+    ))]
+    async fn kind_stream<T: Send + 'static>(
+        &self,
+        kind: EventKind,
+        extract: fn(EventData) -> Option<T>,
+    ) -> crate::EventStream<T> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let event_id = self
+            .event_bus
+            .subscribe_kind(kind, move |message| {
+                if let Some(data) = extract(message.data.clone()) {
+                    let _ = sender.send(data);
+                }
+            })
+            .await;
+
+        crate::EventStream::new(
+            std::collections::VecDeque::new(),
+            receiver,
+            self.event_bus.clone(),
+            event_id,
+        )
+    }
+
+    // Cross-domain event methods
+
+    /// Hands every event that `predicate` accepts to one callback, whatever
+    /// its domain. The predicate sees the whole message, with its metadata,
+    /// so a single rule can span domains. Lifecycle events such as
+    /// `SystemStopped` are offered to it as well; check `message.data.kind()`
+    /// to leave them out. Starts the handler of each enabled domain;
+    /// filesystem events still need a watched path.
+    pub async fn subscribe_all<P, F>(&mut self, predicate: P, callback: F) -> Result<EventId>
+    where
+        P: Fn(&EventMessage) -> bool + Send + Sync + 'static,
+        F: Fn(Arc<EventMessage>) + Send + Sync + 'static,
+    {
+        self.ensure_all_handlers().await?;
+
+        let event_id = self
+            .event_bus
+            .subscribe(move |message| {
+                if predicate(&message) {
+                    callback(message);
+                }
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    /// Hands every event, of every domain, to one callback, with its
+    /// metadata. Starts the handler of each enabled domain so nothing is
+    /// missed; filesystem events still need a watched path.
+    ///
+    /// The message is shared with every other subscriber of the event; clone
+    /// out whatever outlives the call.
+    pub async fn on_any_event<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(Arc<EventMessage>) + Send + Sync + 'static,
+    {
+        self.ensure_all_handlers().await?;
+
+        Ok(self.event_bus.subscribe(callback).await)
+    }
+
+    /// Calls `callback` when something fails behind a subscription: a watch
+    /// ends on its own, a handler's backend gives up or starts retrying, or
+    /// events are lost. A retrying handler's recovery is passed on as well,
+    /// so the callback can tell when monitoring is whole again. These arrive
+    /// as lifecycle events, so `on_any_event` sees them too.
+    pub async fn on_error<F>(&mut self, callback: F) -> Result<EventId>
+    where
+        F: Fn(LifecycleEventData) + Send + Sync + 'static,
+    {
+        let event_id = self
+            .event_bus
+            .subscribe_kind(EventKind::Lifecycle, move |message| {
+                if let EventData::Lifecycle(lifecycle_data) = &message.data {
+                    let recovered = matches!(
+                        lifecycle_data.event_type,
+                        LifecycleEventType::HandlerRecovered { .. }
+                    );
+                    if lifecycle_data.event_type.is_error() || recovered {
+                        callback(lifecycle_data.clone());
+                    }
+                }
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    /// Every event from every domain as one `Stream`, in delivery order. Use
+    /// `message.data.kind()` to tell domains apart. Dropping it unsubscribes.
+    pub async fn all_events_stream(&mut self) -> Result<crate::EventStream<EventMessage>> {
+        self.ensure_all_handlers().await?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let event_id = self
+            .event_bus
+            .subscribe(move |message| {
+                let _ = sender.send(EventMessage::clone(&message));
+            })
+            .await;
+
+        Ok(crate::EventStream::new(
+            std::collections::VecDeque::new(),
+            receiver,
+            self.event_bus.clone(),
+            event_id,
+        ))
+    }
+
+    /// Every event from every domain, pulled from a channel instead of pushed
+    /// to a callback. Dropping the receiver unsubscribes on the next event.
+    pub async fn subscribe_channel(&mut self) -> Result<crossbeam_channel::Receiver<EventMessage>> {
+        self.ensure_all_handlers().await?;
+
+        Ok(self.channel_subscription(None).await)
+    }
+
+    /// Like `subscribe_channel`, limited to one domain.
+    pub async fn subscribe_kind_channel(
+        &mut self,
+        domain: EventKind,
+    ) -> Result<crossbeam_channel::Receiver<EventMessage>> {
+        self.ensure_handler(domain).await?;
+        Ok(self.channel_subscription(Some(domain)).await)
+    }
+
+    /// Watches `path` and delivers filesystem events from every watched path
+    /// through a channel, the pull-based counterpart of `on_fs_event`.
+    #[cfg(feature = "fs")]
+    pub async fn subscribe_fs_channel<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<crossbeam_channel::Receiver<EventMessage>> {
+        let receiver = self.channel_subscription(Some(EventKind::FileSystem)).await;
+        self.watch_fs_path(path).await?;
+        Ok(receiver)
+    }
+
+    // The bus only learns the receiver is gone when a send fails, so the
+    // subscription removes itself from the first callback that sees that
+    async fn channel_subscription(
+        &self,
+        kind: Option<EventKind>,
+    ) -> crossbeam_channel::Receiver<EventMessage> {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        let own_id = Arc::new(std::sync::OnceLock::new());
+        let closed = std::sync::atomic::AtomicBool::new(false);
+        // Weak, as the bus owns this callback
+        let event_bus = Arc::downgrade(&self.event_bus);
+
+        let callback = {
+            let own_id = own_id.clone();
+            move |message: Arc<EventMessage>| {
+                if sender.send(EventMessage::clone(&message)).is_ok() {
+                    return;
+                }
+                let (Some(event_bus), Some(&event_id)) = (event_bus.upgrade(), own_id.get()) else {
+                    return;
+                };
+                if !closed.swap(true, std::sync::atomic::Ordering::Relaxed) {
+                    crate::stream::unsubscribe_detached(event_bus, event_id);
+                }
+            }
+        };
+
+        let event_id = match kind {
+            Some(kind) => self.event_bus.subscribe_kind(kind, callback).await,
+            None => self.event_bus.subscribe(callback).await,
+        };
+        let _ = own_id.set(event_id);
+        receiver
+    }
+
+    pub async fn subscribe_with_context<C, F>(
+        &mut self,
+        domain: EventKind,
+        context: C,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        C: Send + Sync + 'static,
+        F: Fn(&C, EventData) + Send + Sync + 'static,
+    {
+        self.ensure_handler(domain).await?;
+
+        let event_id = self
+            .event_bus
+            .subscribe_kind(domain, move |message| {
+                callback(&context, message.data.clone());
+            })
+            .await;
+
+        Ok(event_id)
+    }
+
+    // Idle/activity methods
+    pub async fn on_idle<F>(
+        &mut self,
+        domains: &[EventKind],
+        idle_duration: Duration,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.on_idle_state_changed(domains, idle_duration, move |is_idle| {
+            if is_idle {
+                callback();
+            }
+        })
+        .await
+    }
+
+    pub async fn on_activity_resumed<F>(
+        &mut self,
+        domains: &[EventKind],
+        idle_duration: Duration,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.on_idle_state_changed(domains, idle_duration, move |is_idle| {
+            if !is_idle {
+                callback();
+            }
+        })
+        .await
+    }
+
+    async fn on_idle_state_changed<F>(
+        &mut self,
+        domains: &[EventKind],
+        idle_duration: Duration,
+        callback: F,
+    ) -> Result<EventId>
+    where
+        F: Fn(bool) + Send + Sync + 'static,
+    {
+        for kind in domains {
+            self.ensure_handler(*kind).await?;
+        }
+
+        let domains = domains.to_vec();
+        let (activity_tx, mut activity_rx) = mpsc::unbounded_channel::<()>();
+
+        let event_id = self
+            .event_bus
+            .subscribe(move |message| {
+                if domains.contains(&message.data.kind()) {
+                    let _ = activity_tx.send(());
+                }
+            })
+            .await;
+
+        // The timer is reset by every matching dispatch and exits once the
+        // subscription (and with it the sender) is dropped.
+        tokio::spawn(async move {
+            let _active = crate::metrics::track_task();
+            let mut is_idle = false;
+            loop {
+                match tokio::time::timeout(idle_duration, activity_rx.recv()).await {
+                    Ok(Some(())) => {
+                        if is_idle {
+                            is_idle = false;
+                            callback(false);
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(_) => {
+                        if !is_idle {
+                            is_idle = true;
+                            callback(true);
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(event_id)
+    }
+
+    // Utility methods
+    #[cfg(feature = "testing")]
+    pub(crate) fn event_sender(&self) -> crate::EventSender {
+        self.event_bus.sender()
+    }
+
+    /// Pushes a synthetic event through the bus, so subscribers of its domain
+    /// fire just as they would for a native one. No handler has to be
+    /// running, but the system must be started for anything to be delivered.
+    #[cfg(feature = "testing")]
+    pub fn inject_event(&self, data: EventData) {
+        crate::testing::send_event(self, data);
+    }
+
+    /// Ties the subscription `event_id`, as returned by any `on_*` method, to
+    /// a guard that unsubscribes when dropped:
+    ///
+    /// ```ignore
+    /// let _subscription = event_system.subscribe_guard(event_system.on_fs_event("./src", callback).await?);
+    /// ```
+    pub fn subscribe_guard(&self, event_id: EventId) -> crate::Subscription {
+        crate::Subscription::new(
+            Arc::downgrade(&self.event_bus),
+            event_id,
+            #[cfg(feature = "system")]
+            Arc::downgrade(&self.degradation_monitors),
+        )
+    }
+
+    pub async fn unsubscribe(&self, event_id: EventId) -> bool {
+        #[cfg(feature = "system")]
+        if let Some(monitor) = self.degradation_monitors.lock().unwrap().remove(&event_id) {
+            monitor.abort();
+        }
+        self.event_bus.unsubscribe(event_id).await
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.is_running
+    }
+
+    /// Starts keeping the last `capacity` events of every domain for
+    /// `history` and `export_history`. Calling it again changes the capacity
+    /// and keeps what was already recorded, up to the new limit.
+    pub async fn enable_history(&mut self, capacity: usize) {
+        self.disable_history().await;
+
+        let history = Arc::new(std::sync::Mutex::new(
+            std::collections::VecDeque::with_capacity(capacity),
+        ));
+        let recorder = history.clone();
+        let event_id = self
+            .event_bus
+            .subscribe(move |message| {
+                let mut history = recorder.lock().unwrap();
+                if history.len() >= capacity {
+                    history.pop_front();
+                }
+                if capacity > 0 {
+                    history.push_back(message);
+                }
+            })
+            .await;
+
+        self.history = Some((history, event_id));
+    }
+
+    pub async fn disable_history(&mut self) {
+        if let Some((_, event_id)) = self.history.take() {
+            self.event_bus.unsubscribe(event_id).await;
+        }
+    }
+
+    /// Recorded events, oldest first. Empty unless `enable_history` was called.
+    pub fn history(&self) -> Vec<EventMessage> {
+        match self.history {
+            Some((ref history, _)) => history
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|message| EventMessage::clone(message))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn export_history<W: std::io::Write>(
+        &self,
+        format: crate::ExportFormat,
+        writer: W,
+    ) -> Result<()> {
+        crate::export::export_events(&self.history(), format, writer)
+    }
+
+    fn history_len(&self) -> usize {
+        match self.history {
+            Some((ref history, _)) => history.lock().unwrap().len(),
+            None => 0,
+        }
+    }
+
+    /// Writes the handler configs and watched paths to `path` as JSON.
+    #[cfg(feature = "serde")]
+    pub fn save_config<P: AsRef<std::path::Path>>(&self, path: P) -> Result<()> {
+        self.saved_config().write_to(path)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn saved_config(&self) -> crate::config::SavedConfig {
+        crate::config::SavedConfig {
+            #[cfg(feature = "fs")]
+            watched_paths: {
+                let mut paths = self.disabled_fs_paths.clone();
+                if let Some(ref handler) = self.fs_handler {
+                    paths.extend(handler.watched_paths());
+                }
+                paths
+            },
+            #[cfg(feature = "fs")]
+            fs: self.fs_config.clone(),
+            #[cfg(feature = "process")]
+            process: self.process_config.clone(),
+            #[cfg(feature = "system")]
+            system: self.system_config.clone(),
+            #[cfg(feature = "network")]
+            network: self.network_config.clone(),
+            #[cfg(feature = "power")]
+            power: self.power_config.clone(),
+        }
+    }
+
+    /// Applies a configuration written by `save_config`. Running handlers are
+    /// restarted with the loaded configs and every saved path is watched again.
+    /// Paths that no longer exist are skipped with a warning and returned.
+    #[cfg(feature = "serde")]
+    pub async fn load_config<P: AsRef<std::path::Path>>(
+        &mut self,
+        path: P,
+    ) -> Result<Vec<std::path::PathBuf>> {
+        let config = crate::config::SavedConfig::read_from(path)?;
+        self.apply_saved_config(config).await
+    }
+
+    #[cfg(feature = "serde")]
+    pub async fn apply_saved_config(
+        &mut self,
+        // Unread when no domain is built in
+        #[allow(unused_variables)] config: crate::config::SavedConfig,
+    ) -> Result<Vec<std::path::PathBuf>> {
+        #[cfg(feature = "fs")]
+        {
+            self.fs_config = config.fs;
+        }
+        #[cfg(feature = "process")]
+        {
+            self.process_config = config.process;
+        }
+        #[cfg(feature = "system")]
+        {
+            self.system_config = config.system;
+        }
+        #[cfg(feature = "network")]
+        {
+            self.network_config = config.network;
+        }
+        #[cfg(feature = "power")]
+        {
+            self.power_config = config.power;
+        }
+
+        // Cycling a domain restarts its handler, and its watches, with the new config
+        #[allow(unused_mut)]
+        let mut running = Vec::new();
+        #[cfg(feature = "fs")]
+        if self.fs_handler.is_some() {
+            running.push(EventKind::FileSystem);
+        }
+        #[cfg(feature = "process")]
+        if self.process_handler.is_some() {
+            running.push(EventKind::Process);
+        }
+        #[cfg(feature = "system")]
+        if self.system_handler.is_some() {
+            running.push(EventKind::System);
+        }
+        #[cfg(feature = "network")]
+        if self.network_handler.is_some() {
+            running.push(EventKind::Network);
+        }
+        #[cfg(feature = "power")]
+        if self.power_handler.is_some() {
+            running.push(EventKind::Power);
+        }
+        for domain in running {
+            self.disable_domain(domain).await?;
+            self.enable_domain(domain).await?;
+        }
+
+        #[allow(unused_mut)]
+        let mut missing = Vec::new();
+        #[cfg(feature = "fs")]
+        for path in config.watched_paths {
+            if !path.exists() {
+                log::warn!("Saved watch path no longer exists, skipping: {:?}", path);
+                missing.push(path);
+                continue;
+            }
+            self.watch_fs_path(&path).await?;
+        }
+
+        Ok(missing)
+    }
+
+    /// Threads, tasks and buffers the crate currently holds. Thread and task
+    /// counts cover every `EventSystem` in the process.
+    pub fn self_metrics(&self) -> crate::SelfMetrics {
+        let queued_events = self.event_bus.queued_len();
+
+        crate::SelfMetrics {
+            threads: crate::metrics::active_threads(),
+            tasks: crate::metrics::active_tasks(),
+            queued_events,
+            buffer_bytes: (queued_events + self.history_len())
+                * std::mem::size_of::<EventMessage>(),
+            cpu_time: crate::metrics::thread_cpu_time(),
+        }
+    }
+
+    /// Event and subscriber counts for this system's bus.
+    pub fn bus_metrics(&self) -> crate::BusMetrics {
+        self.event_bus.metrics()
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn watch_ready<P: AsRef<Path>>(&self, path: P) -> crate::handlers::fs::WatchReady {
+        match self.fs_handler {
+            Some(ref handler) => handler.watch_ready(path),
+            None => crate::handlers::fs::WatchReady::ready(),
+        }
+    }
+
+    /// Paths with an active filesystem watch, sorted. Paths registered while
+    /// the filesystem domain is disabled aren't watched yet and are left out.
+    #[cfg(feature = "fs")]
+    pub fn watched_paths(&self) -> Vec<std::path::PathBuf> {
+        let mut paths = match self.fs_handler {
+            Some(ref handler) => handler.watched_paths(),
+            None => Vec::new(),
+        };
+        paths.sort();
+        paths
+    }
+
+    /// Whether `path` is watched, compared as it was passed when subscribing.
+    #[cfg(feature = "fs")]
+    pub fn is_watching<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.watched_paths()
+            .iter()
+            .any(|watched| watched == path.as_ref())
+    }
+
+    /// Stops watching `path`. Subscriptions made for it stay registered and
+    /// keep receiving events from any other watched path.
+    #[cfg(feature = "fs")]
+    pub async fn unwatch_path<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        self.disabled_fs_paths.retain(|disabled| disabled != path);
+        self.fs_recursion.remove(path);
+
+        match self.fs_handler {
+            Some(ref mut handler) => handler.unwatch_path(path).await,
+            None => Ok(()),
+        }
+    }
+
+    pub fn supported_events(&self, domain: EventKind) -> Vec<SupportedEvent> {
+        match domain {
+            #[cfg(feature = "fs")]
+            EventKind::FileSystem => crate::handlers::fs::supported_events(),
+            #[cfg(feature = "process")]
+            EventKind::Process => crate::handlers::process::supported_events(),
+            #[cfg(feature = "power")]
+            EventKind::Power => crate::handlers::power::supported_events(),
+            #[cfg(feature = "system")]
+            EventKind::System => crate::handlers::system::supported_events(),
+            #[cfg(feature = "network")]
+            EventKind::Network => crate::handlers::network::supported_events(),
+            EventKind::Registry => [
+                "ValueCreated",
+                "ValueModified",
+                "ValueDeleted",
+                "SubkeyChanged",
+            ]
+            .iter()
+            .map(|name| {
+                let availability = if cfg!(all(windows, feature = "registry")) {
+                    EventAvailability::Native
+                } else {
+                    EventAvailability::Unsupported
+                };
+                SupportedEvent::new(domain, name, availability)
+            })
+            .collect(),
+            EventKind::Lifecycle => vec![
+                SupportedEvent::new(domain, "SystemStopped", EventAvailability::Native),
+                SupportedEvent::new(domain, "HandlerStopped", EventAvailability::Native),
+                SupportedEvent::new(
+                    domain,
+                    "EventsLost",
+                    if cfg!(all(any(windows, target_os = "linux"), feature = "fs")) {
+                        EventAvailability::Native
+                    } else {
+                        EventAvailability::Unsupported
+                    },
+                ),
+                SupportedEvent::new(
+                    domain,
+                    "WatchFailed",
+                    if cfg!(feature = "fs") {
+                        EventAvailability::Native
+                    } else {
+                        EventAvailability::Unsupported
+                    },
+                ),
+                SupportedEvent::new(
+                    domain,
+                    "HandlerDegraded",
+                    if cfg!(all(windows, feature = "process")) {
+                        EventAvailability::Native
+                    } else {
+                        EventAvailability::Unsupported
+                    },
+                ),
+                SupportedEvent::new(
+                    domain,
+                    "HandlerRecovered",
+                    if cfg!(all(windows, feature = "process")) {
+                        EventAvailability::Native
+                    } else {
+                        EventAvailability::Unsupported
+                    },
+                ),
+            ],
+            // Compiled-out domains deliver nothing
+            #[allow(unreachable_patterns)]
+            _ => Vec::new(),
+        }
+    }
+
+    // Domain control methods
+    pub async fn disable_domain(&mut self, domain: EventKind) -> Result<()> {
+        if self.disabled_domains.contains_key(&domain) {
+            return Ok(());
+        }
+
+        // Handlers are dropped rather than paused so their OS resources are
+        // released; subscriptions stay on the bus untouched
+        let had_handler = match domain {
+            #[cfg(feature = "fs")]
+            EventKind::FileSystem => match self.fs_handler.take() {
+                Some(mut handler) => {
+                    self.disabled_fs_paths = handler.watched_paths();
+                    handler.stop().await?;
+                    true
+                }
+                None => false,
+            },
+            #[cfg(feature = "process")]
+            EventKind::Process => match self.process_handler.take() {
+                Some(mut handler) => {
+                    handler.stop().await?;
+                    true
+                }
+                None => false,
+            },
+            #[cfg(feature = "system")]
+            EventKind::System => match self.system_handler.take() {
+                Some(mut handler) => {
+                    handler.stop().await?;
+                    true
+                }
+                None => false,
+            },
+            #[cfg(feature = "network")]
+            EventKind::Network => match self.network_handler.take() {
+                Some(mut handler) => {
+                    handler.stop().await?;
+                    true
+                }
+                None => false,
+            },
+            #[cfg(feature = "power")]
+            EventKind::Power => match self.power_handler.take() {
+                Some(mut handler) => {
+                    handler.stop().await?;
+                    true
+                }
+                None => false,
+            },
+            #[cfg(all(windows, feature = "registry"))]
+            EventKind::Registry => match self.registry_handler.take() {
+                Some(mut handler) => {
+                    self.disabled_registry_keys = handler.watched_keys();
+                    handler.stop().await?;
+                    true
+                }
+                None => false,
+            },
+            _ => false,
+        };
+
+        self.disabled_domains.insert(domain, had_handler);
+        log::info!("{:?} events disabled", domain);
+        Ok(())
+    }
+
+    pub async fn enable_domain(&mut self, domain: EventKind) -> Result<()> {
+        let Some(had_handler) = self.disabled_domains.remove(&domain) else {
+            return Ok(());
+        };
+
+        if had_handler {
+            self.ensure_handler(domain).await?;
+        }
+
+        #[cfg(feature = "fs")]
+        if domain == EventKind::FileSystem {
+            for path in std::mem::take(&mut self.disabled_fs_paths) {
+                self.watch_fs_path(path).await?;
+            }
+        }
+
+        #[cfg(all(windows, feature = "registry"))]
+        if domain == EventKind::Registry {
+            for (hive, subkey) in std::mem::take(&mut self.disabled_registry_keys) {
+                self.watch_registry_key(hive, &subkey).await?;
+            }
+        }
+
+        log::info!("{:?} events enabled", domain);
+        Ok(())
+    }
+
+    pub fn is_domain_enabled(&self, domain: EventKind) -> bool {
+        !self.disabled_domains.contains_key(&domain)
+    }
+
+    /// Makes the polled monitors of `domain` sample now rather than on their
+    /// next tick. Event-driven domains have nothing to sample and return at once.
+    pub async fn trigger_check(&self, domain: EventKind) -> Result<()> {
+        match domain {
+            #[cfg(feature = "process")]
+            EventKind::Process => {
+                if let Some(ref handler) = self.process_handler {
+                    handler.trigger_check(&self.event_bus.sender(), &self.handler_id("process"));
+                }
+            }
+            #[cfg(feature = "system")]
+            EventKind::System => {
+                self.shared_system.refresh_now();
+                self.degradation_check.notify_waiters();
+            }
+            #[cfg(feature = "power")]
+            EventKind::Power => {
+                if let Some(ref handler) = self.power_handler {
+                    handler.trigger_check();
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    // Handler initialization methods
+    async fn ensure_handler(&mut self, kind: EventKind) -> Result<()> {
+        match kind {
+            #[cfg(feature = "fs")]
+            EventKind::FileSystem => self.ensure_fs_handler().await,
+            #[cfg(feature = "process")]
+            EventKind::Process => self.ensure_process_handler().await,
+            #[cfg(feature = "network")]
+            EventKind::Network => self.ensure_network_handler().await,
+            #[cfg(feature = "power")]
+            EventKind::Power => self.ensure_power_handler().await,
+            #[cfg(all(windows, feature = "registry"))]
+            EventKind::Registry => self.ensure_registry_handler().await,
+            #[cfg(not(windows))]
+            EventKind::Registry => Err(TellMeWhenError::Config(
+                "Registry events are only available on Windows".to_string(),
+            )),
+            #[cfg(feature = "system")]
+            EventKind::System => self.ensure_system_handler().await,
+            // Lifecycle events come from the system itself
+            EventKind::Lifecycle => Ok(()),
+            #[allow(unreachable_patterns)]
+            kind => Err(TellMeWhenError::Config(format!(
+                "{:?} events are not available: the corresponding crate feature is disabled",
+                kind
+            ))),
+        }
+    }
+
+    // Starts the handler of every enabled domain, for the subscriptions that
+    // see all of them. Registry events still need a watched key.
+    async fn ensure_all_handlers(&mut self) -> Result<()> {
+        #[cfg(feature = "fs")]
+        self.ensure_fs_handler().await?;
+        #[cfg(feature = "process")]
+        self.ensure_process_handler().await?;
+        #[cfg(feature = "system")]
+        self.ensure_system_handler().await?;
+        #[cfg(feature = "network")]
+        self.ensure_network_handler().await?;
+        #[cfg(feature = "power")]
+        self.ensure_power_handler().await?;
+        Ok(())
+    }
+
+    // Returns whether a new OS watch was opened for the path
+    #[cfg(feature = "fs")]
+    async fn watch_fs_path<P: AsRef<Path>>(&mut self, path: P) -> Result<bool> {
+        self.watch_fs_path_with(path, None).await
+    }
+
+    // Watches `path` for `event_id`, subscribed just before so it doesn't miss
+    // what a new watch reports straight away, like `emit_initial_scan`. The
+    // subscription is dropped again if the path can't be watched
+    #[cfg(feature = "fs")]
+    async fn watch_for<P: AsRef<Path>>(
+        &mut self,
+        event_id: EventId,
+        path: P,
+        recursive: Option<bool>,
+    ) -> Result<bool> {
+        match self.watch_fs_path_with(path, recursive).await {
+            Ok(opened) => Ok(opened),
+            Err(e) => {
+                self.event_bus.unsubscribe(event_id).await;
+                Err(e)
+            }
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    async fn watch_fs_path_with<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        recursive: Option<bool>,
+    ) -> Result<bool> {
+        let path = path.as_ref();
+        // Paths subscribed while the domain is disabled are watched once it's re-enabled
+        if self.disabled_domains.contains_key(&EventKind::FileSystem) {
+            if !self
+                .disabled_fs_paths
+                .iter()
+                .any(|disabled| disabled == path)
+            {
+                if let Some(recursive) = recursive {
+                    self.fs_recursion.insert(path.to_path_buf(), recursive);
+                }
+                self.disabled_fs_paths.push(path.to_path_buf());
+            }
+            return Ok(false);
+        }
+
+        self.ensure_fs_handler().await?;
+
+        let Some(ref mut handler) = self.fs_handler else {
+            return Ok(false);
+        };
+        let recursive = recursive
+            .or_else(|| self.fs_recursion.get(path).copied())
+            .unwrap_or(self.fs_config.watch_subdirectories);
+        let opened = handler.watch_path_with(path, recursive).await?;
+        if opened && recursive != self.fs_config.watch_subdirectories {
+            self.fs_recursion.insert(path.to_path_buf(), recursive);
+        }
+        Ok(opened)
+    }
+
+    #[cfg(all(windows, feature = "registry"))]
+    async fn watch_registry_key(
+        &mut self,
+        hive: crate::handlers::registry::RegistryHive,
+        subkey: &str,
+    ) -> Result<()> {
+        if self.disabled_domains.contains_key(&EventKind::Registry) {
+            self.disabled_registry_keys.push((hive, subkey.to_string()));
+            return Ok(());
+        }
+
+        self.ensure_registry_handler().await?;
+
+        match self.registry_handler {
+            Some(ref mut handler) => handler.watch_key(hive, subkey),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "fs")]
+    async fn ensure_fs_handler(&mut self) -> Result<()> {
+        if let Some(had_handler) = self.disabled_domains.get_mut(&EventKind::FileSystem) {
+            *had_handler = true;
+            return Ok(());
+        }
+        if self.fs_handler.is_none() {
+            let mut handler = FileSystemHandler::new(self.handler_id("filesystem"));
+            handler.event_sender = Some(self.event_bus.sender());
+            handler.start(self.fs_config.clone()).await?;
+            self.fs_handler = Some(handler);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "process")]
+    async fn ensure_process_handler(&mut self) -> Result<()> {
+        if let Some(had_handler) = self.disabled_domains.get_mut(&EventKind::Process) {
+            *had_handler = true;
+            return Ok(());
+        }
+        if self.process_handler.is_none() {
+            let mut handler = ProcessHandler::with_shared_system(
+                self.process_config.clone(),
+                self.shared_system.clone(),
+            );
+            handler
+                .start(self.event_bus.sender(), self.handler_id("process"))
+                .await?;
+            self.process_handler = Some(handler);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "system")]
+    async fn ensure_system_handler(&mut self) -> Result<()> {
+        if let Some(had_handler) = self.disabled_domains.get_mut(&EventKind::System) {
+            *had_handler = true;
+            return Ok(());
+        }
+        if self.system_handler.is_none() {
+            let mut handler = SystemHandler::with_shared_system(
+                self.handler_id("system"),
+                self.system_config.clone(),
+                self.shared_system.clone(),
+            );
+            handler.event_sender = Some(self.event_bus.sender());
+            handler.start(self.system_config.clone()).await?;
+            self.system_handler = Some(handler);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "network")]
+    async fn ensure_network_handler(&mut self) -> Result<()> {
+        if let Some(had_handler) = self.disabled_domains.get_mut(&EventKind::Network) {
+            *had_handler = true;
+            return Ok(());
+        }
+        if self.network_handler.is_none() {
+            let mut handler = NetworkHandler::new(self.network_config.clone());
+            handler
+                .start(self.event_bus.sender(), self.handler_id("network"))
+                .await?;
+            self.network_handler = Some(handler);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "power")]
+    async fn ensure_power_handler(&mut self) -> Result<()> {
+        if let Some(had_handler) = self.disabled_domains.get_mut(&EventKind::Power) {
+            *had_handler = true;
+            return Ok(());
+        }
+        if self.power_handler.is_none() {
+            let mut handler =
+                PowerHandler::with_config(self.handler_id("power"), self.power_config.clone());
+            handler.event_sender = Some(self.event_bus.sender());
+            handler.start(self.power_config.clone()).await?;
+            self.power_handler = Some(handler);
+        }
+        Ok(())
+    }
+
+    #[cfg(all(windows, feature = "registry"))]
+    async fn ensure_registry_handler(&mut self) -> Result<()> {
+        if let Some(had_handler) = self.disabled_domains.get_mut(&EventKind::Registry) {
+            *had_handler = true;
+            return Ok(());
+        }
+        if self.registry_handler.is_none() {
+            let mut handler = RegistryHandler::new(self.handler_id("registry"));
+            handler.event_sender = Some(self.event_bus.sender());
+            handler
+                .start(crate::handlers::registry::RegistryConfig::default())
+                .await?;
+            self.registry_handler = Some(handler);
+        }
+        Ok(())
+    }
+}
+
+// Accepts a message unless its dedup key repeats the previous accepted
+// message's within `window`. Messages without a key always pass.
+#[cfg(any(
+    feature = "process",
+    feature = "system",
+    feature = "network",
+    feature = "power"
+))]
+fn dedup_filter(window: Duration) -> impl Fn(&EventMessage) -> bool + Send + Sync {
+    let last: std::sync::Mutex<Option<(String, std::time::Instant)>> = std::sync::Mutex::new(None);
+    move |message| {
+        let Some(key) = &message.metadata.dedup_key else {
+            return true;
+        };
+        let now = std::time::Instant::now();
+        let mut last = last.lock().unwrap();
+        let repeated = matches!(&*last, Some((last_key, at)) if last_key == key && now.duration_since(*at) < window);
+        if !repeated {
+            *last = Some((key.clone(), now));
+        }
+        !repeated
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SubscribeOutcome {
+    pub event_id: EventId,
+    /// Whether this subscription caused a new OS watch to be opened, as
+    /// opposed to sharing one another subscription already opened.
+    pub opened_new_watch: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchPathsOutcome {
+    pub event_id: EventId,
+    /// The paths now being watched, in the order given.
+    pub watched: Vec<std::path::PathBuf>,
+}
+
+impl Default for EventSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Configures an `EventSystem` before any handler starts. Handlers are
+/// created lazily by the first subscription to their domain and use the
+/// config given here from then on.
+#[derive(Debug, Clone, Default)]
+pub struct EventSystemBuilder {
+    dispatch_mode: DispatchMode,
+    name: Option<String>,
+    channel_capacity: Option<usize>,
+    overflow_policy: crate::OverflowPolicy,
+    #[cfg(feature = "fs")]
+    fs_config: crate::handlers::fs::FsWatchConfig,
+    #[cfg(feature = "process")]
+    process_config: crate::handlers::process::ProcessConfig,
+    #[cfg(feature = "system")]
+    system_config: crate::handlers::system::SystemConfig,
+    #[cfg(feature = "network")]
+    network_config: crate::handlers::network::NetworkConfig,
+    #[cfg(feature = "power")]
+    power_config: crate::handlers::power::PowerConfig,
+}
+
+impl EventSystemBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn dispatch_mode(mut self, dispatch_mode: DispatchMode) -> Self {
+        self.dispatch_mode = dispatch_mode;
+        self
+    }
+
+    /// Names the system, for telling apart the events of several systems
+    /// running side by side. Handler ids become `"<name>/filesystem"`,
+    /// `"<name>/process"` and so on, and `EventMetadata::source` takes the
+    /// same prefix.
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Bounds the bus's channel to `capacity` events, capping the memory a
+    /// backlog can take when handlers outrun the subscribers. What happens
+    /// once it's full is up to `with_overflow_policy`; by default handlers
+    /// wait for room. The channel is unbounded unless this is set.
+    pub fn with_channel_capacity(mut self, capacity: usize) -> Self {
+        self.channel_capacity = Some(capacity);
+        self
+    }
+
+    /// What a full bounded channel does with another event. Has no effect
+    /// without `with_channel_capacity`. Dropped events are counted in
+    /// `BusMetrics::events_dropped`.
+    pub fn with_overflow_policy(mut self, policy: crate::OverflowPolicy) -> Self {
+        self.overflow_policy = policy;
+        self
+    }
+
+    /// Runs subscriber callbacks on a dedicated OS thread instead of a tokio
+    /// task, keeping slow callbacks away from the application's workers.
+    pub fn with_dedicated_dispatch_thread(self) -> Self {
+        self.dispatch_mode(DispatchMode::DedicatedThread)
+    }
+
+    #[cfg(feature = "fs")]
+    pub fn with_fs_config(mut self, config: crate::handlers::fs::FsWatchConfig) -> Self {
+        self.fs_config = config;
+        self
+    }
+
+    #[cfg(feature = "process")]
+    pub fn with_process_config(mut self, config: crate::handlers::process::ProcessConfig) -> Self {
+        self.process_config = config;
+        self
+    }
+
+    #[cfg(feature = "system")]
+    pub fn with_system_config(mut self, config: crate::handlers::system::SystemConfig) -> Self {
+        self.system_config = config;
+        self
+    }
+
+    #[cfg(feature = "network")]
+    pub fn with_network_config(mut self, config: crate::handlers::network::NetworkConfig) -> Self {
+        self.network_config = config;
+        self
+    }
+
+    #[cfg(feature = "power")]
+    pub fn with_power_config(mut self, config: crate::handlers::power::PowerConfig) -> Self {
+        self.power_config = config;
+        self
+    }
+
+    pub fn build(self) -> EventSystem {
+        let mut event_system = EventSystem::with_event_bus(EventBus::with_options(
+            self.dispatch_mode,
+            self.channel_capacity,
+            self.overflow_policy,
+        ));
+        event_system.name = self.name;
+        #[cfg(feature = "fs")]
+        {
+            event_system.fs_config = self.fs_config;
+        }
+        #[cfg(feature = "process")]
+        {
+            event_system.process_config = self.process_config;
+        }
+        #[cfg(feature = "system")]
+        {
+            event_system.system_config = self.system_config;
+        }
+        #[cfg(feature = "network")]
+        {
+            event_system.network_config = self.network_config;
+        }
+        #[cfg(feature = "power")]
+        {
+            event_system.power_config = self.power_config;
+        }
+        event_system
+    }
+}
@@ -1,7 +1,35 @@
 use std::path::PathBuf;
 use std::fmt;
 
+/// (De)serializes a `SystemTime` as fractional seconds since the Unix epoch,
+/// negative for times before it.
+#[cfg(feature = "serde")]
+pub(crate) mod epoch_seconds {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    pub fn serialize<S: Serializer>(timestamp: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        let seconds = match timestamp.duration_since(UNIX_EPOCH) {
+            Ok(after) => after.as_secs_f64(),
+            Err(before) => -before.duration().as_secs_f64(),
+        };
+        serializer.serialize_f64(seconds)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        let seconds = f64::deserialize(deserializer)?;
+        let offset = Duration::try_from_secs_f64(seconds.abs()).map_err(serde::de::Error::custom)?;
+        let timestamp = if seconds >= 0.0 {
+            UNIX_EPOCH.checked_add(offset)
+        } else {
+            UNIX_EPOCH.checked_sub(offset)
+        };
+        timestamp.ok_or_else(|| serde::de::Error::custom("timestamp out of range"))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum FsEventType {
     Created,
     Modified,
@@ -13,32 +41,81 @@ pub enum FsEventType {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FsEventData {
     pub event_type: FsEventType,
     pub path: PathBuf,
+    #[cfg_attr(feature = "serde", serde(with = "epoch_seconds"))]
     pub timestamp: std::time::SystemTime,
+    pub batch: Option<FsEventBatch>,
+    /// Per-watch counter starting at 1, set when `FsWatchConfig::sequence_numbers`
+    /// is enabled. A jump of more than one means events were dropped on the
+    /// bus; changes the OS never reported leave no gap, and are signalled by an
+    /// `EventsLost` lifecycle event instead.
+    pub sequence: Option<u64>,
+    /// Hex digest of the file's contents for Created/Modified events, set when
+    /// `FsWatchConfig::compute_checksum` is enabled and the file is small enough.
+    pub checksum: Option<String>,
 }
 
+/// Position of an event within the OS notification buffer it was decoded from.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FsEventBatch {
+    pub id: u64,
+    pub index: usize,
+    pub len: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ProcessEventType {
     Started,
     Terminated,
     CpuUsageHigh,
     MemoryUsageHigh,
+    /// CPU usage dropped back under the threshold after a `CpuUsageHigh`.
+    /// Only reported with `ProcessConfig::edge_triggered`.
+    CpuUsageNormal,
+    /// Memory usage dropped back under the threshold after a `MemoryUsageHigh`.
+    /// Only reported with `ProcessConfig::edge_triggered`.
+    MemoryUsageNormal,
     StatusChanged,
+    ExecutableReplaced,
+    /// Periodic CPU/memory sample for a process tracked with `on_processes`.
+    ResourceUsage,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ProcessEventData {
     pub event_type: ProcessEventType,
     pub pid: u32,
     pub name: String,
     pub cpu_usage: Option<f32>,
     pub memory_usage: Option<u64>,
+    /// Numeric uid on Unix, SID string on Windows. `None` when the owner
+    /// couldn't be read, e.g. for processes of other users without privileges.
+    pub owner_uid: Option<String>,
+    pub owner_name: Option<String>,
+    /// Exit status of a `Terminated` process where the platform reports it:
+    /// the value passed to `exit()` on Linux (`None` if a signal killed it),
+    /// the process exit code on Windows. `None` for every other event.
+    pub exit_code: Option<i32>,
+    /// Pid of the process that started this one, for `Started` events where
+    /// the platform reports it (Linux, macOS and Windows).
+    pub parent_pid: Option<u32>,
+    /// Executable and arguments, read when the event is raised. Usually
+    /// `None` for `Terminated` events, as the process is gone by then, and for
+    /// processes of other users without privileges.
+    pub exe_path: Option<PathBuf>,
+    pub cmdline: Option<Vec<String>>,
+    #[cfg_attr(feature = "serde", serde(with = "epoch_seconds"))]
     pub timestamp: std::time::SystemTime,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum NetworkEventType {
     InterfaceUp,
     InterfaceDown,
@@ -48,37 +125,58 @@ pub enum NetworkEventType {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NetworkEventData {
     pub event_type: NetworkEventType,
     pub interface_name: Option<String>,
     pub local_addr: Option<String>,
     pub remote_addr: Option<String>,
+    /// For `TrafficThresholdReached`, bytes moved on the interface since the
+    /// previous sample, one `poll_interval` earlier. `None` for other events.
     pub bytes_sent: Option<u64>,
     pub bytes_received: Option<u64>,
+    /// For `TrafficThresholdReached`, the interface's cumulative counters as
+    /// the OS reports them, usually since boot or since the link came up.
+    pub total_bytes_sent: Option<u64>,
+    pub total_bytes_received: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(with = "epoch_seconds"))]
     pub timestamp: std::time::SystemTime,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SystemEventType {
     CpuUsageHigh,
     MemoryUsageHigh,
     DiskSpaceLow,
     TemperatureHigh,
     LoadAverageHigh,
+    /// Every metric in a `DegradedCriteria` stayed over its threshold for the
+    /// sustained period.
+    Degraded,
+    /// A previously degraded system dropped back under its criteria.
+    Recovered,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct SystemEventData {
     pub event_type: SystemEventType,
     pub cpu_usage: Option<f32>,
     pub memory_usage: Option<f32>,
     pub disk_usage: Option<f32>,
+    pub disk_available_bytes: Option<u64>,
+    pub disk_total_bytes: Option<u64>,
+    /// Mount point of the disk a `DiskSpaceLow` event is about.
+    pub disk_mount_point: Option<std::path::PathBuf>,
     pub temperature: Option<f32>,
     pub load_average: Option<f32>,
+    #[cfg_attr(feature = "serde", serde(with = "epoch_seconds"))]
     pub timestamp: std::time::SystemTime,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PowerEventType {
     BatteryLow,
     BatteryCharging,
@@ -88,24 +186,246 @@ pub enum PowerEventType {
     WakeFromSleep,
     Shutdown,
     Restart,
+    ThermalThrottling,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PowerEventData {
     pub event_type: PowerEventType,
     pub battery_level: Option<f32>,
     pub is_charging: Option<bool>,
     pub power_source: Option<String>,
+    /// Estimated time until the battery is empty, or until it is full while
+    /// charging. `None` without a battery or while the estimate is unknown.
+    pub time_remaining: Option<std::time::Duration>,
+    /// Full-charge capacity as a percentage of the design capacity, where
+    /// the platform reports it (Linux).
+    pub health_percent: Option<f32>,
+    pub current_frequency_mhz: Option<u64>,
+    pub base_frequency_mhz: Option<u64>,
+    #[cfg_attr(feature = "serde", serde(with = "epoch_seconds"))]
+    pub timestamp: std::time::SystemTime,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RegistryChangeType {
+    ValueCreated,
+    ValueModified,
+    ValueDeleted,
+    SubkeyChanged,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RegistryEventData {
+    /// Full path of the watched key, e.g. `HKEY_CURRENT_USER\Software\MyApp`.
+    pub key: String,
+    /// Name of the affected value; `None` for subkey changes.
+    pub value: Option<String>,
+    pub change_type: RegistryChangeType,
+    #[cfg_attr(feature = "serde", serde(with = "epoch_seconds"))]
+    pub timestamp: std::time::SystemTime,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StopReason {
+    UserRequested,
+    BackendFailure(String),
+    /// The OS ran out of something the backend needs, such as memory or
+    /// inotify resources; raising the limit may let a restart succeed.
+    ResourceExhausted(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LifecycleEventType {
+    SystemStopped(StopReason),
+    /// A single handler's backend gave up; the rest of the system keeps running.
+    HandlerStopped { handler: String, reason: StopReason },
+    /// The OS dropped notifications for changes under `path`, so events are
+    /// missing. Rescan it if an exact picture of its contents matters.
+    EventsLost { handler: String, path: PathBuf },
+    /// The watch on `path` ended without being asked to, e.g. because the
+    /// directory was deleted. Nothing more is reported for it until it's
+    /// watched again. `os_error` is the raw OS error code where there is one.
+    WatchFailed { handler: String, path: PathBuf, os_error: Option<i32> },
+    /// A handler's backend failed and will reconnect after `retry_in`, which
+    /// grows with each consecutive failure. Events are missed until the
+    /// matching `HandlerRecovered`.
+    HandlerDegraded { handler: String, error: String, retry_in: std::time::Duration },
+    /// A handler that reported `HandlerDegraded` is delivering events again.
+    HandlerRecovered { handler: String },
+}
+
+impl LifecycleEventType {
+    /// Whether this reports something going wrong, as opposed to a requested stop.
+    pub fn is_error(&self) -> bool {
+        match self {
+            LifecycleEventType::SystemStopped(reason) | LifecycleEventType::HandlerStopped { reason, .. } => {
+                *reason != StopReason::UserRequested
+            }
+            LifecycleEventType::EventsLost { .. }
+            | LifecycleEventType::WatchFailed { .. }
+            | LifecycleEventType::HandlerDegraded { .. } => true,
+            LifecycleEventType::HandlerRecovered { .. } => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LifecycleEventData {
+    pub event_type: LifecycleEventType,
+    #[cfg_attr(feature = "serde", serde(with = "epoch_seconds"))]
     pub timestamp: std::time::SystemTime,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EventData {
     FileSystem(FsEventData),
     Process(ProcessEventData),
     Network(NetworkEventData),
     System(SystemEventData),
     Power(PowerEventData),
+    Registry(RegistryEventData),
+    Lifecycle(LifecycleEventData),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EventKind {
+    FileSystem,
+    Process,
+    Network,
+    System,
+    Power,
+    Registry,
+    Lifecycle,
+}
+
+impl EventData {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            EventData::FileSystem(_) => EventKind::FileSystem,
+            EventData::Process(_) => EventKind::Process,
+            EventData::Network(_) => EventKind::Network,
+            EventData::System(_) => EventKind::System,
+            EventData::Power(_) => EventKind::Power,
+            EventData::Registry(_) => EventKind::Registry,
+            EventData::Lifecycle(_) => EventKind::Lifecycle,
+        }
+    }
+
+    pub fn as_fs(&self) -> Option<&FsEventData> {
+        match self {
+            EventData::FileSystem(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn as_process(&self) -> Option<&ProcessEventData> {
+        match self {
+            EventData::Process(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn as_network(&self) -> Option<&NetworkEventData> {
+        match self {
+            EventData::Network(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn as_system(&self) -> Option<&SystemEventData> {
+        match self {
+            EventData::System(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn as_power(&self) -> Option<&PowerEventData> {
+        match self {
+            EventData::Power(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn as_registry(&self) -> Option<&RegistryEventData> {
+        match self {
+            EventData::Registry(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn as_lifecycle(&self) -> Option<&LifecycleEventData> {
+        match self {
+            EventData::Lifecycle(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// The key the bus stores in `EventMetadata::dedup_key`: domain, event
+    /// type and subject. Measured values like usage or battery level aren't
+    /// part of it, so repeated threshold reports share a key.
+    pub fn dedup_key(&self) -> Option<String> {
+        match self {
+            EventData::FileSystem(data) => Some(format!("fs:{}:{}", data.event_type, data.path.display())),
+            EventData::Process(data) => Some(format!("process:{}:{}", data.event_type, data.pid)),
+            EventData::Network(data) => Some(format!(
+                "network:{:?}:{}:{}:{}",
+                data.event_type,
+                data.interface_name.as_deref().unwrap_or_default(),
+                data.local_addr.as_deref().unwrap_or_default(),
+                data.remote_addr.as_deref().unwrap_or_default(),
+            )),
+            EventData::System(data) => Some(format!(
+                "system:{:?}:{}",
+                data.event_type,
+                data.disk_mount_point.as_ref().map(|path| path.display().to_string()).unwrap_or_default(),
+            )),
+            EventData::Power(data) => Some(format!("power:{:?}", data.event_type)),
+            EventData::Registry(data) => Some(format!(
+                "registry:{:?}:{}:{}",
+                data.change_type,
+                data.key,
+                data.value.as_deref().unwrap_or_default(),
+            )),
+            EventData::Lifecycle(_) => None,
+        }
+    }
+}
+
+/// How an event type is produced on the current platform.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventAvailability {
+    /// Delivered from an OS notification as it happens.
+    Native,
+    /// Detected by periodically sampling state.
+    Polled,
+    /// Never emitted on this platform.
+    Unsupported,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SupportedEvent {
+    pub domain: EventKind,
+    pub name: &'static str,
+    pub availability: EventAvailability,
+}
+
+impl SupportedEvent {
+    pub fn new(domain: EventKind, name: &'static str, availability: EventAvailability) -> Self {
+        Self { domain, name, availability }
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.availability != EventAvailability::Unsupported
+    }
 }
 
 impl fmt::Display for FsEventType {
@@ -133,7 +453,11 @@ impl fmt::Display for ProcessEventType {
             ProcessEventType::Terminated => write!(f, "Terminated"),
             ProcessEventType::CpuUsageHigh => write!(f, "CpuUsageHigh"),
             ProcessEventType::MemoryUsageHigh => write!(f, "MemoryUsageHigh"),
+            ProcessEventType::CpuUsageNormal => write!(f, "CpuUsageNormal"),
+            ProcessEventType::MemoryUsageNormal => write!(f, "MemoryUsageNormal"),
             ProcessEventType::StatusChanged => write!(f, "StatusChanged"),
+            ProcessEventType::ExecutableReplaced => write!(f, "ExecutableReplaced"),
+            ProcessEventType::ResourceUsage => write!(f, "ResourceUsage"),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,305 @@
+use crate::events::*;
+use crate::{EventMessage, Result};
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// A single JSON array with one object per event. Fields an event doesn't
+    /// have are omitted.
+    Json,
+    /// One row per event under a fixed header covering every domain. Fields an
+    /// event doesn't have are left blank.
+    Csv,
+}
+
+/// CSV columns, in order. Process memory is in bytes and system memory in
+/// percent, so they get separate columns.
+pub const CSV_COLUMNS: &[&str] = &[
+    "id",
+    "timestamp",
+    "domain",
+    "handler_id",
+    "source",
+    "event_type",
+    "path",
+    "old_path",
+    "new_path",
+    "sequence",
+    "checksum",
+    "pid",
+    "name",
+    "owner_uid",
+    "owner_name",
+    "cpu_usage",
+    "memory_bytes",
+    "exit_code",
+    "parent_pid",
+    "exe_path",
+    "cmdline",
+    "memory_percent",
+    "interface_name",
+    "local_addr",
+    "remote_addr",
+    "bytes_sent",
+    "bytes_received",
+    "total_bytes_sent",
+    "total_bytes_received",
+    "disk_usage",
+    "disk_available_bytes",
+    "disk_total_bytes",
+    "disk_mount_point",
+    "temperature",
+    "load_average",
+    "battery_level",
+    "is_charging",
+    "power_source",
+    "time_remaining_secs",
+    "health_percent",
+    "current_frequency_mhz",
+    "base_frequency_mhz",
+    "key",
+    "value",
+    "handler",
+    "reason",
+    "os_error",
+    "retry_in_secs",
+];
+
+enum Field {
+    Text(String),
+    // Already formatted; written unquoted in JSON
+    Number(String),
+    Bool(bool),
+}
+
+pub fn export_events<W: Write>(events: &[EventMessage], format: ExportFormat, mut writer: W) -> Result<()> {
+    match format {
+        ExportFormat::Json => write_json(events, &mut writer)?,
+        ExportFormat::Csv => write_csv(events, &mut writer)?,
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_json<W: Write>(events: &[EventMessage], writer: &mut W) -> std::io::Result<()> {
+    writer.write_all(b"[")?;
+    for (index, message) in events.iter().enumerate() {
+        if index > 0 {
+            writer.write_all(b",")?;
+        }
+        writer.write_all(b"\n  {")?;
+        for (field_index, (name, field)) in flatten(message).into_iter().enumerate() {
+            if field_index > 0 {
+                writer.write_all(b",")?;
+            }
+            write!(writer, "\"{}\":", name)?;
+            match field {
+                Field::Text(text) => write!(writer, "\"{}\"", escape_json(&text))?,
+                // JSON has no NaN or infinity
+                Field::Number(number) if number.parse::<f64>().is_ok_and(f64::is_finite) => {
+                    writer.write_all(number.as_bytes())?
+                }
+                Field::Number(_) => writer.write_all(b"null")?,
+                Field::Bool(value) => write!(writer, "{}", value)?,
+            }
+        }
+        writer.write_all(b"}")?;
+    }
+    if !events.is_empty() {
+        writer.write_all(b"\n")?;
+    }
+    writer.write_all(b"]\n")
+}
+
+fn write_csv<W: Write>(events: &[EventMessage], writer: &mut W) -> std::io::Result<()> {
+    writeln!(writer, "{}", CSV_COLUMNS.join(","))?;
+
+    for message in events {
+        let fields = flatten(message);
+        let row: Vec<String> = CSV_COLUMNS
+            .iter()
+            .map(|column| {
+                match fields.iter().find(|(name, _)| name == column) {
+                    Some((_, Field::Text(text))) => escape_csv(text),
+                    Some((_, Field::Number(number))) => number.clone(),
+                    Some((_, Field::Bool(value))) => value.to_string(),
+                    None => String::new(),
+                }
+            })
+            .collect();
+        writeln!(writer, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+fn flatten(message: &EventMessage) -> Vec<(&'static str, Field)> {
+    let mut fields = vec![
+        ("id", number(message.metadata.id)),
+        ("timestamp", Field::Number(format!("{:.3}", unix_seconds(message.metadata.timestamp)))),
+        ("domain", text(format!("{:?}", message.data.kind()))),
+        ("handler_id", text(&message.metadata.handler_id)),
+        ("source", text(&message.metadata.source)),
+    ];
+
+    let mut push = |name: &'static str, field: Option<Field>| {
+        if let Some(field) = field {
+            fields.push((name, field));
+        }
+    };
+
+    match &message.data {
+        EventData::FileSystem(data) => {
+            let (event_type, old_path, new_path) = match &data.event_type {
+                FsEventType::Renamed { old_path, new_path } => ("Renamed", Some(old_path), Some(new_path)),
+                FsEventType::Moved { from, to } => ("Moved", Some(from), Some(to)),
+                other => (fs_event_name(other), None, None),
+            };
+            push("event_type", Some(text(event_type)));
+            push("path", Some(path(&data.path)));
+            push("old_path", old_path.map(|old_path| path(old_path)));
+            push("new_path", new_path.map(|new_path| path(new_path)));
+            push("sequence", data.sequence.map(number));
+            push("checksum", data.checksum.as_ref().map(text));
+        }
+        EventData::Process(data) => {
+            push("event_type", Some(text(data.event_type.to_string())));
+            push("pid", Some(number(data.pid)));
+            push("name", Some(text(&data.name)));
+            push("owner_uid", data.owner_uid.as_ref().map(text));
+            push("owner_name", data.owner_name.as_ref().map(text));
+            push("cpu_usage", data.cpu_usage.map(number));
+            push("memory_bytes", data.memory_usage.map(number));
+            push("exit_code", data.exit_code.map(number));
+            push("parent_pid", data.parent_pid.map(number));
+            push("exe_path", data.exe_path.as_deref().map(path));
+            push("cmdline", data.cmdline.as_ref().map(|cmdline| text(cmdline.join(" "))));
+        }
+        EventData::Network(data) => {
+            push("event_type", Some(text(format!("{:?}", data.event_type))));
+            push("interface_name", data.interface_name.as_ref().map(text));
+            push("local_addr", data.local_addr.as_ref().map(text));
+            push("remote_addr", data.remote_addr.as_ref().map(text));
+            push("bytes_sent", data.bytes_sent.map(number));
+            push("bytes_received", data.bytes_received.map(number));
+            push("total_bytes_sent", data.total_bytes_sent.map(number));
+            push("total_bytes_received", data.total_bytes_received.map(number));
+        }
+        EventData::System(data) => {
+            push("event_type", Some(text(format!("{:?}", data.event_type))));
+            push("cpu_usage", data.cpu_usage.map(number));
+            push("memory_percent", data.memory_usage.map(number));
+            push("disk_usage", data.disk_usage.map(number));
+            push("disk_available_bytes", data.disk_available_bytes.map(number));
+            push("disk_total_bytes", data.disk_total_bytes.map(number));
+            push("disk_mount_point", data.disk_mount_point.as_deref().map(path));
+            push("temperature", data.temperature.map(number));
+            push("load_average", data.load_average.map(number));
+        }
+        EventData::Power(data) => {
+            push("event_type", Some(text(format!("{:?}", data.event_type))));
+            push("battery_level", data.battery_level.map(number));
+            push("is_charging", data.is_charging.map(Field::Bool));
+            push("power_source", data.power_source.as_ref().map(text));
+            push("time_remaining_secs", data.time_remaining.map(|time_remaining| number(time_remaining.as_secs())));
+            push("health_percent", data.health_percent.map(number));
+            push("current_frequency_mhz", data.current_frequency_mhz.map(number));
+            push("base_frequency_mhz", data.base_frequency_mhz.map(number));
+        }
+        EventData::Registry(data) => {
+            push("event_type", Some(text(format!("{:?}", data.change_type))));
+            push("key", Some(text(&data.key)));
+            push("value", data.value.as_ref().map(text));
+        }
+        EventData::Lifecycle(data) => match &data.event_type {
+            LifecycleEventType::SystemStopped(reason) => {
+                push("event_type", Some(text("SystemStopped")));
+                push("reason", Some(text(format!("{:?}", reason))));
+            }
+            LifecycleEventType::HandlerStopped { handler, reason } => {
+                push("event_type", Some(text("HandlerStopped")));
+                push("handler", Some(text(handler)));
+                push("reason", Some(text(format!("{:?}", reason))));
+            }
+            LifecycleEventType::EventsLost { handler, path: lost } => {
+                push("event_type", Some(text("EventsLost")));
+                push("handler", Some(text(handler)));
+                push("path", Some(path(lost)));
+            }
+            LifecycleEventType::WatchFailed { handler, path: failed, os_error } => {
+                push("event_type", Some(text("WatchFailed")));
+                push("handler", Some(text(handler)));
+                push("path", Some(path(failed)));
+                push("os_error", os_error.map(number));
+            }
+            LifecycleEventType::HandlerDegraded { handler, error, retry_in } => {
+                push("event_type", Some(text("HandlerDegraded")));
+                push("handler", Some(text(handler)));
+                push("reason", Some(text(error)));
+                push("retry_in_secs", Some(Field::Number(format!("{:.3}", retry_in.as_secs_f64()))));
+            }
+            LifecycleEventType::HandlerRecovered { handler } => {
+                push("event_type", Some(text("HandlerRecovered")));
+                push("handler", Some(text(handler)));
+            }
+        },
+    }
+
+    fields
+}
+
+fn fs_event_name(event_type: &FsEventType) -> &'static str {
+    match event_type {
+        FsEventType::Created => "Created",
+        FsEventType::Modified => "Modified",
+        FsEventType::Deleted => "Deleted",
+        FsEventType::Renamed { .. } => "Renamed",
+        FsEventType::Moved { .. } => "Moved",
+        FsEventType::AttributeChanged => "AttributeChanged",
+        FsEventType::PermissionChanged => "PermissionChanged",
+    }
+}
+
+fn text<S: AsRef<str>>(value: S) -> Field {
+    Field::Text(value.as_ref().to_string())
+}
+
+fn number<N: ToString>(value: N) -> Field {
+    Field::Number(value.to_string())
+}
+
+fn path(path: &Path) -> Field {
+    Field::Text(path.to_string_lossy().into_owned())
+}
+
+fn unix_seconds(timestamp: SystemTime) -> f64 {
+    timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+fn escape_json(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn escape_csv(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
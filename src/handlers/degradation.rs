@@ -0,0 +1,299 @@
+use crate::events::{EventData, SystemEventData, SystemEventType};
+use crate::handlers::shared_system::SharedSystem;
+use crate::{EventMessage, EventMetadata, EventSender, HandlerId};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use sysinfo::System;
+use tokio::time::interval;
+
+/// Conditions that must all hold at once, for `sustained_for`, before the
+/// system is reported as degraded. Metrics left as `None` are not checked.
+#[derive(Debug, Clone)]
+pub struct DegradedCriteria {
+    /// Global CPU usage, in percent.
+    pub cpu_threshold: Option<f32>,
+    /// Used memory, in percent of total.
+    pub memory_threshold: Option<f32>,
+    /// One-minute load average. Always reads 0 on Windows.
+    pub load_average_threshold: Option<f32>,
+    pub sustained_for: Duration,
+    pub poll_interval: Duration,
+}
+
+impl Default for DegradedCriteria {
+    fn default() -> Self {
+        Self {
+            cpu_threshold: Some(90.0),
+            memory_threshold: Some(90.0),
+            load_average_threshold: None,
+            sustained_for: Duration::from_secs(60),
+            poll_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricSample {
+    pub cpu_usage: f32,
+    pub memory_usage: f32,
+    pub load_average: f32,
+}
+
+impl DegradedCriteria {
+    /// Whether every configured threshold is exceeded by `sample`. Criteria
+    /// with no thresholds at all never hold.
+    pub fn holds(&self, sample: &MetricSample) -> bool {
+        let checks = [
+            self.cpu_threshold.map(|threshold| sample.cpu_usage >= threshold),
+            self.memory_threshold.map(|threshold| sample.memory_usage >= threshold),
+            self.load_average_threshold.map(|threshold| sample.load_average >= threshold),
+        ];
+
+        checks.iter().any(Option::is_some) && checks.iter().flatten().all(|held| *held)
+    }
+}
+
+/// Tracks how long the criteria have held and decides when to raise or clear
+/// the degraded state.
+#[derive(Debug, Default)]
+pub struct DegradationTracker {
+    holding_since: Option<Instant>,
+    is_degraded: bool,
+}
+
+impl DegradationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_degraded(&self) -> bool {
+        self.is_degraded
+    }
+
+    /// Feeds one sample taken at `now`, returning the event to emit if the
+    /// state changed.
+    pub fn update(&mut self, criteria: &DegradedCriteria, sample: &MetricSample, now: Instant) -> Option<SystemEventType> {
+        if !criteria.holds(sample) {
+            self.holding_since = None;
+            if self.is_degraded {
+                self.is_degraded = false;
+                return Some(SystemEventType::Recovered);
+            }
+            return None;
+        }
+
+        let holding_since = *self.holding_since.get_or_insert(now);
+        if !self.is_degraded && now.duration_since(holding_since) >= criteria.sustained_for {
+            self.is_degraded = true;
+            return Some(SystemEventType::Degraded);
+        }
+        None
+    }
+}
+
+static NEXT_MONITOR_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A handler id unique to one degradation monitor.
+pub fn next_monitor_id() -> HandlerId {
+    format!("degradation-{}", NEXT_MONITOR_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+/// Samples the shared system on `criteria.poll_interval`, or whenever
+/// `check_now` is notified, and emits `Degraded`/`Recovered` events tagged
+/// with `handler_id`.
+pub fn spawn_degradation_monitor(
+    criteria: DegradedCriteria,
+    shared_system: Arc<SharedSystem>,
+    sender: EventSender,
+    handler_id: HandlerId,
+    check_now: Arc<tokio::sync::Notify>,
+) -> tokio::task::JoinHandle<()> {
+    shared_system.start(criteria.poll_interval);
+
+    spawn_monitor(criteria, sender, handler_id, check_now, move || {
+        // A cold reading of 0% CPU would look like a recovery
+        shared_system.cpu_ready().then(|| sample_metrics(&shared_system))
+    })
+}
+
+// The monitor loop, taking its readings from `sample`, which returns `None`
+// while there is nothing meaningful to read yet
+fn spawn_monitor<S>(
+    criteria: DegradedCriteria,
+    sender: EventSender,
+    handler_id: HandlerId,
+    check_now: Arc<tokio::sync::Notify>,
+    mut sample: S,
+) -> tokio::task::JoinHandle<()>
+where
+    S: FnMut() -> Option<MetricSample> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let _active = crate::metrics::track_task();
+        let mut interval = interval(criteria.poll_interval);
+        let mut tracker = DegradationTracker::new();
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = check_now.notified() => {}
+            }
+
+            let Some(sample) = sample() else {
+                continue;
+            };
+            if let Some(event_type) = tracker.update(&criteria, &sample, Instant::now()) {
+                emit_degradation_event(event_type, &sample, &sender, &handler_id);
+            }
+        }
+    })
+}
+
+fn sample_metrics(shared_system: &SharedSystem) -> MetricSample {
+    let system = shared_system.system();
+    let system = system.read().unwrap();
+
+    let total_memory = system.total_memory();
+    let memory_usage = if total_memory > 0 {
+        system.used_memory() as f32 / total_memory as f32 * 100.0
+    } else {
+        0.0
+    };
+
+    MetricSample {
+        cpu_usage: system.global_cpu_usage(),
+        memory_usage,
+        load_average: System::load_average().one as f32,
+    }
+}
+
+fn emit_degradation_event(
+    event_type: SystemEventType,
+    sample: &MetricSample,
+    sender: &EventSender,
+    handler_id: &HandlerId,
+) {
+    let event_data = SystemEventData {
+        event_type,
+        cpu_usage: Some(sample.cpu_usage),
+        memory_usage: Some(sample.memory_usage),
+        disk_usage: None,
+        disk_available_bytes: None,
+        disk_total_bytes: None,
+        disk_mount_point: None,
+        temperature: None,
+        load_average: Some(sample.load_average),
+        timestamp: SystemTime::now(),
+    };
+
+    let message = EventMessage {
+        metadata: EventMetadata {
+            id: 0, // Will be set by event bus
+            handler_id: handler_id.clone(),
+            timestamp: SystemTime::now(),
+            source: "degradation".to_string(),
+            dedup_key: None,
+        },
+        data: EventData::System(event_data),
+    };
+
+    if let Err(e) = sender.send(message) {
+        log::error!("Failed to send degradation event: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HIGH: MetricSample = MetricSample { cpu_usage: 95.0, memory_usage: 95.0, load_average: 8.0 };
+    const LOW: MetricSample = MetricSample { cpu_usage: 10.0, memory_usage: 40.0, load_average: 0.5 };
+
+    fn criteria() -> DegradedCriteria {
+        DegradedCriteria {
+            cpu_threshold: Some(90.0),
+            memory_threshold: Some(90.0),
+            load_average_threshold: Some(4.0),
+            sustained_for: Duration::from_millis(300),
+            poll_interval: Duration::from_millis(50),
+        }
+    }
+
+    fn event_types(receiver: &crossbeam_channel::Receiver<EventMessage>) -> Vec<SystemEventType> {
+        receiver
+            .try_iter()
+            .filter_map(|message| match message.data {
+                EventData::System(data) => Some(data.event_type),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn criteria_hold_only_when_every_threshold_is_exceeded() {
+        let criteria = criteria();
+        assert!(criteria.holds(&HIGH));
+        assert!(!criteria.holds(&MetricSample { load_average: 1.0, ..HIGH }));
+        assert!(!criteria.holds(&MetricSample { cpu_usage: 50.0, ..HIGH }));
+        assert!(!criteria.holds(&MetricSample { memory_usage: 50.0, ..HIGH }));
+
+        let unconfigured = DegradedCriteria {
+            cpu_threshold: None,
+            memory_threshold: None,
+            load_average_threshold: None,
+            ..criteria
+        };
+        assert!(!unconfigured.holds(&HIGH));
+    }
+
+    #[test]
+    fn tracker_waits_out_the_sustain_period() {
+        let criteria = criteria();
+        let mut tracker = DegradationTracker::new();
+        let start = Instant::now();
+
+        assert_eq!(tracker.update(&criteria, &HIGH, start), None);
+        assert_eq!(tracker.update(&criteria, &HIGH, start + Duration::from_millis(200)), None);
+        // A dip starts the period over
+        assert_eq!(tracker.update(&criteria, &LOW, start + Duration::from_millis(250)), None);
+        assert_eq!(tracker.update(&criteria, &HIGH, start + Duration::from_millis(400)), None);
+        assert_eq!(
+            tracker.update(&criteria, &HIGH, start + Duration::from_millis(700)),
+            Some(SystemEventType::Degraded)
+        );
+        assert!(tracker.is_degraded());
+    }
+
+    #[tokio::test]
+    async fn synthetic_pressure_degrades_once_and_recovers_once() {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        // Two of the three metrics high, then all three, then none
+        let mut script = std::iter::repeat_n(MetricSample { load_average: 1.0, ..HIGH }, 10)
+            .chain(std::iter::repeat_n(HIGH, 20))
+            .chain(std::iter::repeat_n(LOW, 10));
+        let (done_sender, done) = tokio::sync::oneshot::channel();
+        let mut done_sender = Some(done_sender);
+
+        let monitor = spawn_monitor(
+            criteria(),
+            EventSender::from(sender),
+            "degradation-test".to_string(),
+            Arc::new(tokio::sync::Notify::new()),
+            move || {
+                let sample = script.next();
+                if sample.is_none() {
+                    done_sender.take().map(|done| done.send(()));
+                }
+                sample
+            },
+        );
+        done.await.unwrap();
+        monitor.abort();
+
+        assert_eq!(
+            event_types(&receiver),
+            vec![SystemEventType::Degraded, SystemEventType::Recovered]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+// Coalesces bursts of events for the same path. Backends send into the
+// debouncer's channel instead of the bus; an event is passed on once its path
+// has been quiet for the configured window.
+
+use super::{event_checksum, next_batch_id, FsWatchConfig};
+use crate::events::{EventData, FsEventBatch, FsEventType};
+use crate::{EventMessage, EventSender};
+use crossbeam_channel::{Receiver, RecvTimeoutError};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Instant;
+
+struct Pending {
+    message: EventMessage,
+    arrival: u64,
+    deadline: Instant,
+}
+
+/// Spawns the debouncing thread and returns the sender backends should use.
+/// The thread flushes what it holds and exits once every clone of the
+/// returned sender has been dropped.
+pub(super) fn spawn(config: FsWatchConfig, output: EventSender) -> std::io::Result<EventSender> {
+    let (input, receiver) = crossbeam_channel::unbounded();
+    std::thread::Builder::new()
+        .name("tell_me_when-fsdebounce".to_string())
+        .spawn(move || run(config, receiver, output))?;
+    Ok(EventSender::from(input))
+}
+
+fn run(config: FsWatchConfig, receiver: Receiver<EventMessage>, output: EventSender) {
+    let window = config.debounce_window;
+    let mut pending: HashMap<PathBuf, Pending> = HashMap::new();
+    let mut arrivals = 0u64;
+    let mut sequence = 0u64;
+
+    loop {
+        let received = match pending.values().map(|entry| entry.deadline).min() {
+            Some(deadline) => receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())),
+            None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match received {
+            Ok(message) => {
+                let EventData::FileSystem(data) = &message.data else {
+                    let _ = output.send(message);
+                    continue;
+                };
+
+                match &data.event_type {
+                    // Renames involve two paths and are never merged; anything
+                    // held for either path goes out first to keep the order
+                    FsEventType::Renamed { old_path: first, new_path: second }
+                    | FsEventType::Moved { from: first, to: second } => {
+                        let paths = [data.path.clone(), first.clone(), second.clone()];
+                        let held: Vec<Pending> = paths.iter().filter_map(|path| pending.remove(path)).collect();
+                        flush(held, &config, &mut sequence, &output);
+                        flush(
+                            vec![Pending { message, arrival: 0, deadline: Instant::now() }],
+                            &config,
+                            &mut sequence,
+                            &output,
+                        );
+                    }
+                    _ => {
+                        let path = data.path.clone();
+                        let deadline = Instant::now() + window;
+                        match pending.remove(&path) {
+                            Some(held) => {
+                                let message = merge(held.message, message);
+                                pending.insert(path, Pending { message, arrival: held.arrival, deadline });
+                            }
+                            None => {
+                                arrivals += 1;
+                                pending.insert(path, Pending { message, arrival: arrivals, deadline });
+                            }
+                        }
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                flush(pending.into_values().collect(), &config, &mut sequence, &output);
+                return;
+            }
+        }
+
+        let now = Instant::now();
+        let due: Vec<PathBuf> = pending
+            .iter()
+            .filter(|(_, entry)| entry.deadline <= now)
+            .map(|(path, _)| path.clone())
+            .collect();
+        if !due.is_empty() {
+            let due = due.iter().filter_map(|path| pending.remove(path)).collect();
+            flush(due, &config, &mut sequence, &output);
+        }
+    }
+}
+
+// Folds a newer event for a path into the one already held for it
+fn merge(held: EventMessage, newer: EventMessage) -> EventMessage {
+    let (EventData::FileSystem(old), EventData::FileSystem(mut new)) = (held.data, newer.data) else {
+        unreachable!("only filesystem events are held");
+    };
+
+    new.event_type = match (old.event_type, new.event_type) {
+        // Writes to a file that was just created are part of creating it
+        (FsEventType::Created, FsEventType::Modified | FsEventType::AttributeChanged | FsEventType::PermissionChanged) => {
+            FsEventType::Created
+        }
+        // Deleted and recreated, as editors do when saving
+        (FsEventType::Deleted, FsEventType::Created) => FsEventType::Modified,
+        // Otherwise the latest state wins, so a delete is never hidden behind a modify
+        (_, latest) => latest,
+    };
+
+    EventMessage {
+        metadata: newer.metadata,
+        data: EventData::FileSystem(new),
+    }
+}
+
+// Events flushed together go out as one batch, in the order their paths first
+// became active. Sequence numbers and checksums are assigned here, since the
+// ones computed upstream described events that may have been merged away.
+fn flush(mut entries: Vec<Pending>, config: &FsWatchConfig, sequence: &mut u64, output: &EventSender) {
+    if entries.is_empty() {
+        return;
+    }
+    entries.sort_by_key(|entry| entry.arrival);
+
+    let batch_id = next_batch_id();
+    let batch_len = entries.len();
+    for (index, entry) in entries.into_iter().enumerate() {
+        let mut message = entry.message;
+        if let EventData::FileSystem(data) = &mut message.data {
+            data.batch = Some(FsEventBatch {
+                id: batch_id,
+                index,
+                len: batch_len,
+            });
+            data.sequence = config.sequence_numbers.then(|| {
+                *sequence += 1;
+                *sequence
+            });
+            data.checksum = event_checksum(&data.event_type, &data.path, config);
+        }
+        let _ = output.send(message);
+    }
+}
@@ -1,43 +1,60 @@
 #[cfg(target_os = "macos")]
-use core_foundation::base::{CFRelease, CFRetain, CFTypeRef};
-use core_foundation::string::{CFString, CFStringRef};
 use core_foundation::array::{CFArray, CFArrayRef};
-use core_foundation::runloop::{CFRunLoop, CFRunLoopRef, CFRunLoopRun, CFRunLoopStop};
-use core_foundation_sys::base::{kCFAllocatorDefault, CFIndex};
+use core_foundation::base::TCFType;
+use core_foundation::string::{CFString, CFStringRef};
+use core_foundation_sys::base::{kCFAllocatorDefault, CFAllocatorRef, CFIndex};
+use core_foundation_sys::runloop::{kCFRunLoopDefaultMode, CFRunLoopGetCurrent, CFRunLoopRef, CFRunLoopRun, CFRunLoopStop};
+use std::ffi::{c_void, CStr};
+use std::os::raw::c_char;
 use std::path::{Path, PathBuf};
-use std::ffi::c_void;
-use std::ptr;
-use crate::handlers::fs::{FsWatchConfig, WatchHandle};
-use crate::events::FsEventType;
-use crate::{Result, TellMeWhenError};
-use crossbeam_channel::Sender;
-use crate::{EventMessage, EventData, EventMetadata};
+use std::sync::atomic::AtomicU64;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use crate::handlers::fs::{send_fs_batch, touches, FsWatchConfig, IgnorePatterns, WatchHandle, WatchReady};
+use crate::events::{FsEventType, LifecycleEventType};
+use crate::{EventMessage, EventSender, HandlerId, Result, TellMeWhenError};
+
+type FSEventStreamRef = *mut c_void;
+
+// Raw CoreFoundation pointers handed to the run loop thread
+struct SendPtr(*mut c_void);
+
+unsafe impl Send for SendPtr {}
+
+impl SendPtr {
+    fn get(&self) -> *mut c_void {
+        self.0
+    }
+}
 
 #[derive(Debug)]
 pub struct MacOsWatchHandle {
-    stream_ref: FSEventStreamRef,
-    run_loop: CFRunLoopRef,
+    run_loop: *mut c_void,
+    thread: Option<JoinHandle<()>>,
     path: PathBuf,
 }
 
-pub struct PlatformWatcher {
-    active_streams: Vec<FSEventStreamRef>,
-    event_sender: Option<Sender<EventMessage>>,
-    handler_id: String,
-}
-
-unsafe impl Send for PlatformWatcher {}
-unsafe impl Sync for PlatformWatcher {}
-
 unsafe impl Send for MacOsWatchHandle {}
 unsafe impl Sync for MacOsWatchHandle {}
 
-// FFI declarations for FSEvents
-#[repr(C)]
-struct FSEventStreamRef(*const c_void);
+pub struct PlatformWatcher {
+    event_sender: EventSender,
+    handler_id: HandlerId,
+    config: FsWatchConfig,
+}
 
-unsafe impl Send for FSEventStreamRef {}
-unsafe impl Sync for FSEventStreamRef {}
+// Shared with the FSEvents callback through the stream context's `info`
+struct StreamContext {
+    root: PathBuf,
+    recursive: bool,
+    // Set when the root is only watched for this one file in it
+    file: Option<PathBuf>,
+    handler_id: HandlerId,
+    sender: EventSender,
+    config: FsWatchConfig,
+    ignore: IgnorePatterns,
+    sequence: AtomicU64,
+}
 
 #[repr(C)]
 struct FSEventStreamContext {
@@ -57,12 +74,13 @@ type FSEventStreamCallback = extern "C" fn(
     event_ids: *const u64,
 );
 
+#[link(name = "CoreServices", kind = "framework")]
 extern "C" {
     fn FSEventStreamCreate(
-        allocator: *const c_void,
+        allocator: CFAllocatorRef,
         callback: FSEventStreamCallback,
-        context: *mut FSEventStreamContext,
-        path_to_watch: CFArrayRef,
+        context: *const FSEventStreamContext,
+        paths_to_watch: CFArrayRef,
         since_when: u64,
         latency: f64,
         flags: u32,
@@ -80,10 +98,13 @@ extern "C" {
     fn FSEventStreamRelease(stream_ref: FSEventStreamRef);
 }
 
+const kFSEventStreamEventIdSinceNow: u64 = 0xFFFF_FFFF_FFFF_FFFF;
+
 const kFSEventStreamCreateFlagFileEvents: u32 = 0x00000010;
 const kFSEventStreamCreateFlagNoDefer: u32 = 0x00000002;
 const kFSEventStreamCreateFlagWatchRoot: u32 = 0x00000004;
 
+const kFSEventStreamEventFlagRootChanged: u32 = 0x00000020;
 const kFSEventStreamEventFlagItemCreated: u32 = 0x00000100;
 const kFSEventStreamEventFlagItemRemoved: u32 = 0x00000200;
 const kFSEventStreamEventFlagItemInodeMetaMod: u32 = 0x00000400;
@@ -94,132 +115,236 @@ const kFSEventStreamEventFlagItemChangeOwner: u32 = 0x00004000;
 const kFSEventStreamEventFlagItemXattrMod: u32 = 0x00008000;
 
 impl PlatformWatcher {
-    pub fn new(handler_id: String, event_sender: Option<Sender<EventMessage>>) -> Result<Self> {
+    pub fn new(handler_id: HandlerId, event_sender: EventSender, config: FsWatchConfig) -> Result<Self> {
         Ok(Self {
-            active_streams: Vec::new(),
             event_sender,
             handler_id,
+            config,
         })
     }
 
-    pub async fn watch_path(&mut self, path: &Path, config: &FsWatchConfig) -> Result<WatchHandle> {
-        unsafe {
+    /// Each watched path gets its own stream, scheduled on a run loop thread
+    /// that lives until the returned handle is dropped.
+    pub async fn watch_path(&mut self, path: &Path, recursive: bool) -> Result<(WatchHandle, WatchReady)> {
+        self.start_stream(path, recursive, None)
+    }
+
+    /// Watches `file` through a non-recursive stream on `dir`, its parent.
+    pub async fn watch_file(&mut self, dir: &Path, file: &Path) -> Result<(WatchHandle, WatchReady)> {
+        self.start_stream(dir, false, Some(file.to_path_buf()))
+    }
+
+    fn start_stream(&mut self, path: &Path, recursive: bool, file: Option<PathBuf>) -> Result<(WatchHandle, WatchReady)> {
+        let handle_path = file.clone().unwrap_or_else(|| path.to_path_buf());
+        let context = Arc::new(StreamContext {
+            root: path.to_path_buf(),
+            recursive,
+            file,
+            handler_id: self.handler_id.clone(),
+            sender: self.event_sender.clone(),
+            config: self.config.clone(),
+            ignore: IgnorePatterns::new(&self.config.ignore_patterns),
+            sequence: AtomicU64::new(0),
+        });
+
+        let stream_ref = unsafe {
             let path_string = CFString::new(&path.to_string_lossy());
-            let paths_array = CFArray::from_copyable(&[path_string]);
+            let paths_array = CFArray::from_CFTypes(&[path_string]);
 
-            let mut context = FSEventStreamContext {
+            // FSEvents takes its own reference through `retain` and gives it
+            // back through `release` once the stream is released
+            let info = Arc::into_raw(context) as *mut c_void;
+            let stream_context = FSEventStreamContext {
                 version: 0,
-                info: ptr::null_mut(),
-                retain: None,
-                release: None,
+                info,
+                retain: Some(retain_context),
+                release: Some(release_context),
                 copy_description: None,
             };
 
             let stream_ref = FSEventStreamCreate(
                 kCFAllocatorDefault,
                 fs_event_callback,
-                &mut context,
+                &stream_context,
                 paths_array.as_concrete_TypeRef(),
-                0, // kFSEventStreamEventIdSinceNow
+                kFSEventStreamEventIdSinceNow,
                 0.1, // latency in seconds
-                kFSEventStreamCreateFlagFileEvents 
-                    | kFSEventStreamCreateFlagNoDefer 
+                kFSEventStreamCreateFlagFileEvents
+                    | kFSEventStreamCreateFlagNoDefer
                     | kFSEventStreamCreateFlagWatchRoot,
             );
+            release_context(info);
+            stream_ref
+        };
 
-            if stream_ref.0.is_null() {
-                return Err(TellMeWhenError::System(
-                    "Failed to create FSEventStream".to_string(),
-                ));
-            }
+        if stream_ref.is_null() {
+            return Err(TellMeWhenError::System(
+                "Failed to create FSEventStream".to_string(),
+            ));
+        }
 
-            let run_loop = CFRunLoop::get_current().as_concrete_TypeRef();
-            let run_loop_mode = CFString::new("kCFRunLoopDefaultMode");
+        // The stream is scheduled, run, and torn down on the same thread
+        let (run_loop_sender, run_loop_receiver) = std::sync::mpsc::channel();
+        let stream = SendPtr(stream_ref);
+        let thread = std::thread::Builder::new()
+            .name("tell_me_when-fsevents".to_string())
+            .spawn(move || {
+                let _active = crate::metrics::track_thread();
+                unsafe {
+                    let run_loop = CFRunLoopGetCurrent();
+                    FSEventStreamScheduleWithRunLoop(stream.get(), run_loop, kCFRunLoopDefaultMode);
 
-            FSEventStreamScheduleWithRunLoop(
-                stream_ref,
-                run_loop,
-                run_loop_mode.as_concrete_TypeRef(),
-            );
+                    if !FSEventStreamStart(stream.get()) {
+                        FSEventStreamInvalidate(stream.get());
+                        FSEventStreamRelease(stream.get());
+                        let _ = run_loop_sender.send(None);
+                        return;
+                    }
 
-            if !FSEventStreamStart(stream_ref) {
-                FSEventStreamRelease(stream_ref);
-                return Err(TellMeWhenError::System(
-                    "Failed to start FSEventStream".to_string(),
-                ));
-            }
+                    let _ = run_loop_sender.send(Some(SendPtr(run_loop as *mut c_void)));
+                    // Returns once the handle calls CFRunLoopStop
+                    CFRunLoopRun();
 
-            self.active_streams.push(stream_ref);
+                    FSEventStreamStop(stream.get());
+                    FSEventStreamInvalidate(stream.get());
+                    FSEventStreamRelease(stream.get());
+                }
+            })?;
 
-            let handle = WatchHandle {
-                handle: MacOsWatchHandle {
-                    stream_ref,
-                    run_loop,
-                    path: path.to_path_buf(),
-                },
-            };
+        let Some(run_loop) = run_loop_receiver.recv().ok().flatten() else {
+            let _ = thread.join();
+            return Err(TellMeWhenError::System(
+                "Failed to start FSEventStream".to_string(),
+            ));
+        };
 
-            Ok(handle)
-        }
+        let handle = WatchHandle {
+            handle: MacOsWatchHandle {
+                run_loop: run_loop.get(),
+                thread: Some(thread),
+                path: handle_path,
+            },
+        };
+
+        // FSEvents covers the whole tree with one stream
+        Ok((handle, WatchReady::ready()))
     }
 
     pub async fn unwatch(&mut self, handle: WatchHandle) -> Result<()> {
-        unsafe {
-            let stream_ref = handle.handle.stream_ref;
-            
-            FSEventStreamStop(stream_ref);
-            FSEventStreamInvalidate(stream_ref);
-            FSEventStreamRelease(stream_ref);
-
-            self.active_streams.retain(|&s| s.0 != stream_ref.0);
-        }
+        log::debug!("Stopped watching {:?}", handle.handle.path);
+        drop(handle);
         Ok(())
     }
 }
 
+extern "C" fn retain_context(info: *const c_void) -> *const c_void {
+    unsafe { Arc::increment_strong_count(info as *const StreamContext) };
+    info
+}
+
+extern "C" fn release_context(info: *const c_void) {
+    unsafe { Arc::decrement_strong_count(info as *const StreamContext) };
+}
+
 extern "C" fn fs_event_callback(
     _stream_ref: FSEventStreamRef,
-    _client_callback_info: *mut c_void,
+    client_callback_info: *mut c_void,
     num_events: usize,
     event_paths: *mut c_void,
     event_flags: *const u32,
     _event_ids: *const u64,
 ) {
-    unsafe {
-        let paths = event_paths as *const *const i8;
-        
-        for i in 0..num_events {
-            let path_ptr = *paths.add(i);
-            let path_cstr = std::ffi::CStr::from_ptr(path_ptr);
-            
-            if let Ok(path_str) = path_cstr.to_str() {
-                let path = PathBuf::from(path_str);
-                let flags = *event_flags.add(i);
-                
-                let event_type = flags_to_event_type(flags);
-                
-                // In a real implementation, you'd emit the event here
-                log::debug!("FSEvent: {:?} at {:?}", event_type, path);
+    let context = unsafe { &*(client_callback_info as *const StreamContext) };
+    let paths = event_paths as *const *const c_char;
+
+    let mut events = Vec::new();
+    // FSEvents reports a rename as two consecutive events, the old path
+    // (which no longer exists) followed by the new one
+    let mut renamed_from: Option<PathBuf> = None;
+
+    for i in 0..num_events {
+        let (path, flags) = unsafe {
+            let path_cstr = CStr::from_ptr(*paths.add(i));
+            (PathBuf::from(path_cstr.to_string_lossy().into_owned()), *event_flags.add(i))
+        };
+
+        // Sent for the root itself, thanks to kFSEventStreamCreateFlagWatchRoot
+        if flags & kFSEventStreamEventFlagRootChanged != 0 {
+            if !context.root.exists() {
+                log::warn!("FSEvents watch root {:?} was removed or moved away", context.root);
+                let _ = context.sender.send(EventMessage::lifecycle(
+                    context.handler_id.clone(),
+                    LifecycleEventType::WatchFailed {
+                        handler: context.handler_id.clone(),
+                        path: context.file.clone().unwrap_or_else(|| context.root.clone()),
+                        os_error: None,
+                    },
+                ));
             }
+            continue;
         }
+
+        if !context.recursive
+            && path != context.root
+            && path.parent() != Some(context.root.as_path())
+        {
+            continue;
+        }
+
+        if flags & kFSEventStreamEventFlagItemRenamed != 0 {
+            let exists = path.exists();
+            match (renamed_from.take(), exists) {
+                (Some(old_path), true) => {
+                    let event_type = if old_path.parent() == path.parent() {
+                        FsEventType::Renamed { old_path, new_path: path.clone() }
+                    } else {
+                        FsEventType::Moved { from: old_path, to: path.clone() }
+                    };
+                    events.push((event_type, path));
+                }
+                // Moved in from outside the watched tree
+                (None, true) => events.push((FsEventType::Created, path)),
+                (previous, false) => {
+                    // An old path with no new one was moved out of the tree
+                    if let Some(old_path) = previous {
+                        events.push((FsEventType::Deleted, old_path));
+                    }
+                    renamed_from = Some(path);
+                }
+            }
+            continue;
+        }
+
+        if let Some(old_path) = renamed_from.take() {
+            events.push((FsEventType::Deleted, old_path));
+        }
+        events.push((flags_to_event_type(flags, &path), path));
+    }
+
+    if let Some(old_path) = renamed_from {
+        events.push((FsEventType::Deleted, old_path));
+    }
+
+    if let Some(file) = &context.file {
+        events.retain(|(event_type, path)| touches(event_type, path, file));
+    }
+
+    if !events.is_empty() {
+        send_fs_batch(events, &context.config, &context.ignore, &context.sequence, &context.sender, &context.handler_id);
     }
 }
 
-fn flags_to_event_type(flags: u32) -> FsEventType {
-    if flags & kFSEventStreamEventFlagItemCreated != 0 {
-        FsEventType::Created
-    } else if flags & kFSEventStreamEventFlagItemRemoved != 0 {
+// FSEvents coalesces flags, so an item created and removed within the latency
+// window carries both; whether it still exists decides which one wins
+fn flags_to_event_type(flags: u32, path: &Path) -> FsEventType {
+    if flags & kFSEventStreamEventFlagItemRemoved != 0 && !path.exists() {
         FsEventType::Deleted
-    } else if flags & kFSEventStreamEventFlagItemRenamed != 0 {
-        // For simplicity, treating renames as moves
-        FsEventType::Renamed {
-            old_path: PathBuf::new(),
-            new_path: PathBuf::new(),
-        }
+    } else if flags & kFSEventStreamEventFlagItemCreated != 0 {
+        FsEventType::Created
     } else if flags & kFSEventStreamEventFlagItemModified != 0 {
         FsEventType::Modified
-    } else if flags & (kFSEventStreamEventFlagItemInodeMetaMod 
-                     | kFSEventStreamEventFlagItemFinderInfoMod 
+    } else if flags & (kFSEventStreamEventFlagItemInodeMetaMod
+                     | kFSEventStreamEventFlagItemFinderInfoMod
                      | kFSEventStreamEventFlagItemXattrMod) != 0 {
         FsEventType::AttributeChanged
     } else if flags & kFSEventStreamEventFlagItemChangeOwner != 0 {
@@ -231,10 +356,9 @@ fn flags_to_event_type(flags: u32) -> FsEventType {
 
 impl Drop for MacOsWatchHandle {
     fn drop(&mut self) {
-        unsafe {
-            FSEventStreamStop(self.stream_ref);
-            FSEventStreamInvalidate(self.stream_ref);
-            FSEventStreamRelease(self.stream_ref);
+        unsafe { CFRunLoopStop(self.run_loop as CFRunLoopRef) };
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
         }
     }
-}
\ No newline at end of file
+}
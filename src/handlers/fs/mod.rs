@@ -1,15 +1,19 @@
-use crate::events::{EventData, FsEventData, FsEventType};
+use crate::events::{EventAvailability, EventData, EventKind, FsEventBatch, FsEventData, FsEventType, SupportedEvent};
+#[cfg(windows)]
+use crate::events::LifecycleEventType;
 use crate::traits::{EventHandler, EventHandlerConfig};
-use crate::{EventMessage, EventMetadata, HandlerId, Result, TellMeWhenError};
-use crossbeam_channel::Sender;
+use crate::{EventMessage, EventMetadata, EventSender, HandlerId, Result, TellMeWhenError};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
+mod checksum;
+mod debounce;
 #[cfg(windows)]
 mod windows;
-#[cfg(unix)]
+#[cfg(all(unix, not(target_os = "macos")))]
 mod unix;
 #[cfg(target_os = "macos")]
 mod macos;
@@ -21,13 +25,52 @@ use unix::*;
 #[cfg(target_os = "macos")]
 use macos::*;
 
+pub use checksum::ChecksumAlgo;
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct FsWatchConfig {
     pub base: EventHandlerConfig,
     pub watch_subdirectories: bool,
+    /// `.gitignore`-style globs. Events for matching paths are dropped before
+    /// they reach the bus, and matching directories aren't watched.
     pub ignore_patterns: Vec<String>,
+    /// Hold events back until their path has been quiet for `debounce_window`,
+    /// then deliver one event per path. A burst of writes arrives as a single
+    /// Modified, a file written right after creation as Created, and a delete
+    /// always wins over the changes before it.
     pub debounce_events: bool,
+    pub debounce_window: Duration,
+    /// Bytes of change records each watched directory can queue between reads
+    /// on Windows. A busy tree can outrun a small buffer, in which case the
+    /// changes are lost and an `EventsLost` lifecycle event is sent instead.
+    /// Reads over the network are limited to 64 KiB.
+    pub buffer_size: usize,
+    /// The kinds of change to report; an empty list reports all of them. On
+    /// Windows, `AttributeChanged` and `PermissionChanged` each cost another
+    /// read per watched directory, without which they're reported as
+    /// `Modified` (attributes) or not at all (permissions). A write can also
+    /// flip the archive attribute and be reported as both.
     pub event_types: Vec<FsEventType>,
+    /// When a directory is created, scan it right away and report Created
+    /// events for anything already inside. Without this, files written into a
+    /// fresh directory before its inotify watch is added go unreported. The
+    /// background setup of a recursive watch rescans each directory the same
+    /// way once its watch is added, reporting what appeared since the watch
+    /// was asked for.
+    pub scan_new_directories: bool,
+    /// When a path is first watched, report a Created event for everything
+    /// already in it (recursing as the watch does, skipping ignored paths), or
+    /// for the file itself if it exists. The scan runs before the watch is in
+    /// place, so a file created meanwhile is reported once, by the scan or not
+    /// at all. These events carry no sequence number.
+    pub emit_initial_scan: bool,
+    pub sequence_numbers: bool,
+    /// Attach a digest of the file to Created/Modified events, for integrity
+    /// monitoring. Directories and files over `checksum_max_size` bytes are skipped.
+    pub compute_checksum: Option<ChecksumAlgo>,
+    pub checksum_max_size: u64,
 }
 
 impl Default for FsWatchConfig {
@@ -42,19 +85,81 @@ impl Default for FsWatchConfig {
                 "node_modules/*".to_string(),
             ],
             debounce_events: true,
+            debounce_window: Duration::from_millis(50),
+            buffer_size: 64 * 1024,
             event_types: vec![
                 FsEventType::Created,
                 FsEventType::Modified,
                 FsEventType::Deleted,
             ],
+            scan_new_directories: false,
+            emit_initial_scan: false,
+            sequence_numbers: false,
+            compute_checksum: None,
+            checksum_max_size: 16 * 1024 * 1024, // 16 MiB
         }
     }
 }
 
+impl FsWatchConfig {
+    // Whether `event_type` is one of the requested `event_types`; an empty list
+    // requests everything. Renames and moves count as the same kind.
+    pub(crate) fn wants(&self, event_type: &FsEventType) -> bool {
+        let is_rename = |event_type: &FsEventType| matches!(event_type, FsEventType::Renamed { .. } | FsEventType::Moved { .. });
+        self.event_types.is_empty()
+            || self.event_types.iter().any(|wanted| {
+                std::mem::discriminant(wanted) == std::mem::discriminant(event_type)
+                    || (is_rename(wanted) && is_rename(event_type))
+            })
+    }
+}
+
+/// Resolves once every directory under a watched root has its own OS watch.
+///
+/// Watching a path returns as soon as the root is covered; on platforms that
+/// need one watch per directory the rest are added in the background. Await
+/// this when events from deep inside the tree must not be missed.
+#[derive(Debug, Clone)]
+pub struct WatchReady {
+    receiver: tokio::sync::watch::Receiver<bool>,
+}
+
+impl WatchReady {
+    pub(crate) fn ready() -> Self {
+        let (_, receiver) = tokio::sync::watch::channel(true);
+        Self { receiver }
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    pub(crate) fn channel() -> (tokio::sync::watch::Sender<bool>, Self) {
+        let (sender, receiver) = tokio::sync::watch::channel(false);
+        (sender, Self { receiver })
+    }
+
+    pub fn is_ready(&self) -> bool {
+        *self.receiver.borrow()
+    }
+
+    pub async fn wait(mut self) {
+        // A dropped sender means setup was abandoned; there's nothing left to wait for
+        let _ = self.receiver.wait_for(|ready| *ready).await;
+    }
+}
+
+static NEXT_BATCH_ID: AtomicU64 = AtomicU64::new(0);
+
+// Identifies the events decoded from a single OS notification buffer
+pub(crate) fn next_batch_id() -> u64 {
+    NEXT_BATCH_ID.fetch_add(1, Ordering::Relaxed)
+}
+
 pub struct FileSystemHandler {
     config: FsWatchConfig,
     watched_paths: Arc<Mutex<HashMap<PathBuf, WatchHandle>>>,
-    pub event_sender: Option<Sender<EventMessage>>,
+    watch_ready: Arc<Mutex<HashMap<PathBuf, WatchReady>>>,
+    pub event_sender: Option<EventSender>,
+    // Feeds the debouncing thread when `debounce_events` is set
+    debounce_sender: Option<EventSender>,
     is_running: bool,
     handler_id: HandlerId,
     #[cfg(windows)]
@@ -73,7 +178,9 @@ impl FileSystemHandler {
         Self {
             config: FsWatchConfig::default(),
             watched_paths: Arc::new(Mutex::new(HashMap::new())),
+            watch_ready: Arc::new(Mutex::new(HashMap::new())),
             event_sender: None,
+            debounce_sender: None,
             is_running: false,
             handler_id,
             platform_watcher: None,
@@ -84,22 +191,54 @@ impl FileSystemHandler {
         Self {
             config,
             watched_paths: Arc::new(Mutex::new(HashMap::new())),
+            watch_ready: Arc::new(Mutex::new(HashMap::new())),
             event_sender: None,
+            debounce_sender: None,
             is_running: false,
             handler_id,
             platform_watcher: None,
         }
     }
 
-    pub async fn watch_path<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+    /// Starts watching `path`. Returns `false` if it was already watched and
+    /// no new OS watch had to be opened.
+    pub async fn watch_path<P: AsRef<Path>>(&mut self, path: P) -> Result<bool> {
+        let recursive = self.config.watch_subdirectories;
+        self.watch_path_with(path, recursive).await
+    }
+
+    /// Like `watch_path`, with `recursive` taking the place of
+    /// `watch_subdirectories` for this path only. A path that's already
+    /// watched keeps the recursion it was first watched with.
+    ///
+    /// A path that isn't a directory is watched as a single file: its parent
+    /// directory is watched and only events for the file itself are reported,
+    /// so it survives being replaced by a rename. The file doesn't have to
+    /// exist yet, as long as its parent does; its creation is reported.
+    pub async fn watch_path_with<P: AsRef<Path>>(&mut self, path: P, recursive: bool) -> Result<bool> {
         let path = path.as_ref().to_path_buf();
-        
-        if !path.exists() {
+        let file = file_target(&path);
+
+        if path.is_symlink() && !path.exists() {
             return Err(TellMeWhenError::Io(std::io::Error::new(
                 std::io::ErrorKind::NotFound,
-                format!("Path does not exist: {:?}", path),
+                format!("Symlink target doesn't exist: {:?}", path),
             )));
         }
+        if !path.exists() && !file.as_ref().is_some_and(|(dir, _)| dir.is_dir()) {
+            return Err(TellMeWhenError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Neither the path nor its parent directory exists: {:?}", path),
+            )));
+        }
+
+        if self.watched_paths.lock().unwrap().contains_key(&path) {
+            return Ok(false);
+        }
+
+        if self.config.emit_initial_scan {
+            self.report_existing(&path, recursive, file.as_ref().map(|(_, file)| file.as_path()));
+        }
 
         #[cfg(windows)]
         {
@@ -107,47 +246,130 @@ impl FileSystemHandler {
                 self.platform_watcher = Some(Arc::new(WindowsFsWatcher::new()));
             }
             let watcher = self.platform_watcher.as_ref().unwrap().clone();
-            let sender = self.event_sender.clone();
+            let sender = self.backend_sender();
             let handler_id = self.handler_id.clone();
             let config = self.config.clone();
-            let path_clone = path.clone();
-
-            watcher.watch(
-                &path,
-                config.watch_subdirectories,
-                move |event: FsEvent| {
-                    let event_type = match event.kind {
-                        FsEventKind::Created => FsEventType::Created,
-                        FsEventKind::Modified => FsEventType::Modified,
-                        FsEventKind::Deleted => FsEventType::Deleted,
-                        FsEventKind::Renamed { old_path, new_path } => FsEventType::Renamed { old_path, new_path },
-                    };
-                    let fs_event_data = FsEventData {
-                        event_type,
-                        path: event.path,
-                        timestamp: event.timestamp,
+            let ignore = IgnorePatterns::new(&config.ignore_patterns);
+            let sequence = AtomicU64::new(0);
+            let (watch_dir, recursive, file) = match file {
+                Some((dir, file)) => (dir, false, Some(file)),
+                None => (path.clone(), recursive, None),
+            };
+
+            // Attribute and security changes only come apart from writes on
+            // streams of their own, so those are opened only when asked for
+            let decode_attributes = config.wants(&FsEventType::AttributeChanged);
+            let decode_permissions = config.wants(&FsEventType::PermissionChanged);
+            let mut filters = vec![if decode_attributes { CONTENT_FILTER } else { NOTIFY_FILTER }];
+            if decode_attributes {
+                filters.push(ATTRIBUTE_FILTER);
+            }
+            if decode_permissions {
+                filters.push(SECURITY_FILTER);
+            }
+
+            let handle = watcher.watch(
+                &watch_dir,
+                recursive,
+                config.buffer_size,
+                &filters,
+                move |events: Vec<FsEvent>| {
+                    let Some(sender) = &sender else {
+                        return;
                     };
-                    if let Some(sender) = &sender {
-                        let message = EventMessage {
-                            metadata: EventMetadata {
-                                id: 0,
-                                handler_id: handler_id.clone(),
-                                timestamp: SystemTime::now(),
-                                source: "filesystem".to_string(),
-                            },
-                            data: EventData::FileSystem(fs_event_data),
-                        };
-                        let _ = sender.send(message);
-                    }
+                    // The notify filter can't tell creations, deletions and
+                    // renames apart, so unrequested types are dropped here
+                    let events = events
+                        .into_iter()
+                        .filter_map(|event| {
+                            let event_type = match event.kind {
+                                FsEventKind::Created => FsEventType::Created,
+                                FsEventKind::Modified => FsEventType::Modified,
+                                FsEventKind::AttributeChanged => FsEventType::AttributeChanged,
+                                FsEventKind::PermissionChanged => FsEventType::PermissionChanged,
+                                FsEventKind::Deleted => FsEventType::Deleted,
+                                FsEventKind::Renamed { old_path, new_path } => FsEventType::Renamed { old_path, new_path },
+                                FsEventKind::Overflow => {
+                                    let _ = sender.send(EventMessage::lifecycle(
+                                        handler_id.clone(),
+                                        LifecycleEventType::EventsLost {
+                                            handler: handler_id.clone(),
+                                            path: event.path,
+                                        },
+                                    ));
+                                    return None;
+                                }
+                                FsEventKind::Failed { os_error } => {
+                                    let _ = sender.send(EventMessage::lifecycle(
+                                        handler_id.clone(),
+                                        LifecycleEventType::WatchFailed {
+                                            handler: handler_id.clone(),
+                                            path: event.path,
+                                            os_error: Some(os_error as i32),
+                                        },
+                                    ));
+                                    return None;
+                                }
+                            };
+                            Some((event_type, event.path))
+                        })
+                        .filter(|(event_type, _)| config.wants(event_type))
+                        .filter(|(event_type, path)| file.as_ref().is_none_or(|file| touches(event_type, path, file)))
+                        .collect();
+                    send_fs_batch(events, &config, &ignore, &sequence, sender, &handler_id);
                 }
-            );
-            let mut watched_paths = self.watched_paths.lock().unwrap();
-            watched_paths.insert(path.clone(), WatchHandle { handle: 0 }); // handle not used here
+            )?;
+            self.watched_paths.lock().unwrap().insert(path.clone(), WatchHandle { handle });
+            // One handle covers the whole tree, so there's no deferred setup
+            self.watch_ready.lock().unwrap().insert(path.clone(), WatchReady::ready());
         }
 
-        // TODO: Implement for Unix/MacOS
+        // inotify on Linux, FSEvents on macOS
+        #[cfg(unix)]
+        {
+            if self.platform_watcher.is_none() {
+                let sender = self.backend_sender().ok_or_else(|| {
+                    TellMeWhenError::Config("FileSystem handler has no event sender".to_string())
+                })?;
+                self.platform_watcher = Some(PlatformWatcher::new(self.handler_id.clone(), sender, self.config.clone())?);
+            }
+            let watcher = self.platform_watcher.as_mut().unwrap();
+            let (handle, ready) = match file {
+                Some((dir, file)) => watcher.watch_file(&dir, &file).await?,
+                None => watcher.watch_path(&path, recursive).await?,
+            };
+            self.watched_paths.lock().unwrap().insert(path.clone(), handle);
+            self.watch_ready.lock().unwrap().insert(path.clone(), ready);
+        }
 
-        Ok(())
+        Ok(true)
+    }
+
+    // Created events for what `path` already holds, from `emit_initial_scan`
+    fn report_existing(&self, path: &Path, recursive: bool, file: Option<&Path>) {
+        let Some(sender) = self.backend_sender() else {
+            return;
+        };
+        if !self.config.wants(&FsEventType::Created) {
+            return;
+        }
+
+        let ignore = IgnorePatterns::new(&self.config.ignore_patterns);
+        let existing = match file {
+            Some(file) if file.exists() => vec![file.to_path_buf()],
+            Some(_) => Vec::new(),
+            None => scan_directory_contents(path, recursive, &ignore),
+        };
+        let events = existing.into_iter().map(|path| (FsEventType::Created, path)).collect();
+
+        // Sequence numbers count what the watch itself reports
+        let config = FsWatchConfig { sequence_numbers: false, ..self.config.clone() };
+        send_fs_batch(events, &config, &ignore, &AtomicU64::new(0), &sender, &self.handler_id);
+    }
+
+    // Where backends send their events: through the debouncer when it's running
+    fn backend_sender(&self) -> Option<EventSender> {
+        self.debounce_sender.clone().or_else(|| self.event_sender.clone())
     }
 
     pub async fn unwatch_path<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
@@ -156,29 +378,267 @@ impl FileSystemHandler {
             let mut watched_paths = self.watched_paths.lock().unwrap();
             watched_paths.remove(&path)
         };
+        self.watch_ready.lock().unwrap().remove(&path);
 
         #[cfg(windows)]
-        {
-            if let Some(watcher) = &self.platform_watcher {
-                watcher.stop();
-            }
+        if let (Some(watcher), Some(handle)) = (&self.platform_watcher, handle) {
+            watcher.unwatch(handle.handle);
+        }
+
+        #[cfg(unix)]
+        if let (Some(watcher), Some(handle)) = (self.platform_watcher.as_mut(), handle) {
+            watcher.unwatch(handle).await?;
         }
 
         Ok(())
     }
 
-    fn should_ignore_path(&self, path: &Path) -> bool {
+    /// Readiness of the watch on `path`. Paths that aren't watched, or whose
+    /// setup has nothing deferred, are ready immediately.
+    pub fn watch_ready<P: AsRef<Path>>(&self, path: P) -> WatchReady {
+        self.watch_ready
+            .lock()
+            .unwrap()
+            .get(path.as_ref())
+            .cloned()
+            .unwrap_or_else(WatchReady::ready)
+    }
+
+    pub fn watched_paths(&self) -> Vec<PathBuf> {
+        self.watched_paths.lock().unwrap().keys().cloned().collect()
+    }
+}
+
+pub fn supported_events() -> Vec<SupportedEvent> {
+    use EventAvailability::*;
+
+    // Windows reports moves across directories as a delete and a create.
+    // inotify can't tell permission changes apart from other attribute changes.
+    let basic = if cfg!(any(windows, unix)) { Native } else { Unsupported };
+    let unix_only = if cfg!(unix) { Native } else { Unsupported };
+    let permissions = if cfg!(any(windows, target_os = "macos")) { Native } else { Unsupported };
+
+    [
+        ("Created", basic),
+        ("Modified", basic),
+        ("Deleted", basic),
+        ("Renamed", basic),
+        ("Moved", unix_only),
+        ("AttributeChanged", basic),
+        ("PermissionChanged", permissions),
+    ]
+    .into_iter()
+    .map(|(name, availability)| SupportedEvent::new(EventKind::FileSystem, name, availability))
+    .collect()
+}
+
+// A path that isn't a directory is watched through its parent. Returns the
+// parent and the file's path joined onto it, as the backends will report it
+fn file_target(path: &Path) -> Option<(PathBuf, PathBuf)> {
+    if path.is_dir() {
+        return None;
+    }
+    let name = path.file_name()?;
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let file = dir.join(name);
+    Some((dir, file))
+}
+
+// Whether an event is about `file`, including renames and moves away from it
+pub(crate) fn touches(event_type: &FsEventType, path: &Path, file: &Path) -> bool {
+    path == file
+        || match event_type {
+            FsEventType::Renamed { old_path, .. } => old_path == file,
+            FsEventType::Moved { from, .. } => from == file,
+            _ => false,
+        }
+}
+
+// Everything below `dir`, parents before children, leaving out ignored
+// paths and everything under them
+pub(crate) fn scan_directory_contents(dir: &Path, recursive: bool, ignore: &IgnorePatterns) -> Vec<PathBuf> {
+    let mut contents = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if ignore.matches(&path) {
+                continue;
+            }
+            if recursive && entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false) {
+                pending.push(path.clone());
+            }
+            contents.push(path);
+        }
+    }
+
+    contents
+}
+
+// Sends the events decoded from one OS notification buffer as a single batch,
+// leaving out paths matched by the ignore patterns
+pub(crate) fn send_fs_batch(
+    events: Vec<(FsEventType, PathBuf)>,
+    config: &FsWatchConfig,
+    ignore: &IgnorePatterns,
+    sequence: &AtomicU64,
+    sender: &EventSender,
+    handler_id: &HandlerId,
+) {
+    let events: Vec<(FsEventType, PathBuf)> = events
+        .into_iter()
+        .filter(|(_, path)| !ignore.matches(path))
+        .collect();
+    if events.is_empty() {
+        return;
+    }
+
+    let batch_id = next_batch_id();
+    let batch_len = events.len();
+    for (index, (event_type, path)) in events.into_iter().enumerate() {
+        // The debouncer hashes whatever the file settles into instead
+        let checksum = if config.debounce_events {
+            None
+        } else {
+            event_checksum(&event_type, &path, config)
+        };
+        let fs_event_data = FsEventData {
+            event_type,
+            path,
+            timestamp: SystemTime::now(),
+            batch: Some(FsEventBatch {
+                id: batch_id,
+                index,
+                len: batch_len,
+            }),
+            sequence: config
+                .sequence_numbers
+                .then(|| sequence.fetch_add(1, Ordering::Relaxed) + 1),
+            checksum,
+        };
+        let message = EventMessage {
+            metadata: EventMetadata {
+                id: 0, // Will be set by event bus
+                handler_id: handler_id.clone(),
+                timestamp: SystemTime::now(),
+                source: crate::event_source(handler_id, "filesystem"),
+                dedup_key: None,
+            },
+            data: EventData::FileSystem(fs_event_data),
+        };
+        let _ = sender.send(message);
+    }
+}
+
+pub(crate) fn event_checksum(event_type: &FsEventType, path: &Path, config: &FsWatchConfig) -> Option<String> {
+    let algo = config.compute_checksum?;
+    match event_type {
+        FsEventType::Created | FsEventType::Modified => {
+            checksum::file_checksum(path, algo, config.checksum_max_size)
+        }
+        _ => None,
+    }
+}
+
+/// Compiled `.gitignore`-style globs.
+///
+/// `*` and `?` stay within one path component, `**` spans any number of them
+/// and `[...]` is a character class. Patterns match whole components anywhere
+/// in the path, and everything below a matched directory matches too, so
+/// `*.tmp`, `.git/*` and `node_modules/**` behave as they would in `.gitignore`.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct IgnorePatterns {
+    patterns: Vec<regex::Regex>,
+}
+
+impl IgnorePatterns {
+    pub(crate) fn new<S: AsRef<str>>(patterns: &[S]) -> Self {
+        let patterns = patterns
+            .iter()
+            .filter_map(|pattern| {
+                let pattern = pattern.as_ref();
+                match regex::Regex::new(&glob_to_regex(pattern)) {
+                    Ok(regex) => Some(regex),
+                    Err(e) => {
+                        log::warn!("Ignoring invalid ignore pattern {:?}: {}", pattern, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self { patterns }
+    }
+
+    pub(crate) fn matches(&self, path: &Path) -> bool {
+        if self.patterns.is_empty() {
+            return false;
+        }
+
         let path_str = path.to_string_lossy();
-        self.config.ignore_patterns.iter().any(|pattern| {
-            if pattern.contains('*') {
-                let regex_pattern = pattern.replace("*", ".*");
-                if let Ok(regex) = regex::Regex::new(&regex_pattern) {
-                    return regex.is_match(&path_str);
+        let path_str = if cfg!(windows) {
+            path_str.replace('\\', "/")
+        } else {
+            path_str.into_owned()
+        };
+        self.patterns.iter().any(|pattern| pattern.is_match(&path_str))
+    }
+}
+
+fn glob_to_regex(pattern: &str) -> String {
+    let pattern = pattern.replace('\\', "/");
+    // Leading and trailing slashes (root anchoring, directory-only) can't be
+    // told apart without a root or a stat, so they're dropped
+    let chars: Vec<char> = pattern.trim_matches('/').chars().collect();
+
+    let mut regex = String::from("(?:^|/)");
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                // "**/" also matches zero directories
+                if chars.get(i + 2) == Some(&'/') {
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
                 }
+                continue;
             }
-            path_str.contains(pattern)
-        })
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => match chars[i + 1..].iter().position(|&c| c == ']') {
+                Some(len) if len > 0 => {
+                    regex.push('[');
+                    for (index, &c) in chars[i + 1..i + 1 + len].iter().enumerate() {
+                        match c {
+                            '!' if index == 0 => regex.push('^'),
+                            '[' => {
+                                regex.push('\\');
+                                regex.push(c);
+                            }
+                            c => regex.push(c),
+                        }
+                    }
+                    regex.push(']');
+                    i += len + 2;
+                    continue;
+                }
+                _ => regex.push_str("\\["),
+            },
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+        i += 1;
     }
+    regex.push_str("(?:/|$)");
+    regex
 }
 
 #[async_trait::async_trait]
@@ -192,6 +652,11 @@ impl EventHandler for FileSystemHandler {
         }
 
         self.config = config;
+        if self.config.debounce_events {
+            if let Some(sender) = self.event_sender.clone() {
+                self.debounce_sender = Some(debounce::spawn(self.config.clone(), sender)?);
+            }
+        }
         #[cfg(windows)]
         {
             self.platform_watcher = Some(Arc::new(WindowsFsWatcher::new()));
@@ -218,12 +683,18 @@ impl EventHandler for FileSystemHandler {
 
         #[cfg(windows)]
         {
-            if let Some(watcher) = &self.platform_watcher {
-                watcher.stop();
-            }
             self.platform_watcher = None;
         }
 
+        // Streams went with their handles above; on Linux this also closes the inotify descriptor
+        #[cfg(unix)]
+        {
+            self.platform_watcher = None;
+        }
+
+        // With the backends gone this was the last sender, so the debouncing
+        // thread flushes whatever it still holds and exits
+        self.debounce_sender = None;
         self.is_running = false;
 
         log::info!("FileSystem handler stopped: {}", self.handler_id);
@@ -242,9 +713,31 @@ impl EventHandler for FileSystemHandler {
 #[derive(Debug)]
 pub struct WatchHandle {
     #[cfg(windows)]
-    pub(crate) handle: u64, // not used, but required for trait compatibility
+    pub(crate) handle: windows::WindowsWatchHandle,
     #[cfg(all(unix, not(target_os = "macos")))]
     pub(crate) handle: unix::UnixWatchHandle,
     #[cfg(target_os = "macos")]
     pub(crate) handle: macos::MacOsWatchHandle,
-}
\ No newline at end of file
+}
+
+#[cfg(all(test, unix, not(target_os = "macos")))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scans_list_parents_before_children() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("made/nested")).unwrap();
+        std::fs::write(dir.path().join("made/nested/early.txt"), "x").unwrap();
+
+        let contents = scan_directory_contents(dir.path(), true, &IgnorePatterns::default());
+        assert_eq!(
+            contents,
+            vec![
+                dir.path().join("made"),
+                dir.path().join("made/nested"),
+                dir.path().join("made/nested/early.txt"),
+            ]
+        );
+    }
+}
@@ -1,24 +1,46 @@
 #[cfg(all(unix, not(target_os = "macos")))]
-use inotify::{Inotify, WatchMask, Event, EventMask};
-use std::collections::HashMap;
+use inotify::{Inotify, WatchMask, Event, EventMask, WatchDescriptor, Watches};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use crate::handlers::fs::{FsWatchConfig, WatchHandle};
-use crate::events::FsEventType;
-use crate::{Result, TellMeWhenError};
-use crossbeam_channel::Sender;
-use crate::{EventMessage, EventData, EventMetadata};
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use crate::handlers::fs::{scan_directory_contents, send_fs_batch, touches, FsWatchConfig, IgnorePatterns, WatchHandle, WatchReady};
+use crate::events::{FsEventType, LifecycleEventType, StopReason};
+use crate::{EventMessage, EventSender, HandlerId, Result, TellMeWhenError};
+use tokio::io::unix::AsyncFd;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+// Large enough that a rename's MOVED_FROM/MOVED_TO pair rarely straddles two reads
+const BUF_SIZE: usize = 16384;
+// How far file timestamps, taken from a coarse clock, may lag behind `SystemTime::now`
+const TIMESTAMP_SLACK: Duration = Duration::from_millis(50);
 
 #[derive(Debug)]
 pub struct UnixWatchHandle {
-    watch_descriptor: i32,
+    watch_descriptor: WatchDescriptor,
+    // As the caller gave it, so a symlinked root keeps the link's path
     path: PathBuf,
 }
 
 pub struct PlatformWatcher {
-    inotify: Inotify,
-    watches: HashMap<i32, PathBuf>,
-    event_sender: Option<Sender<EventMessage>>,
-    handler_id: String,
+    inotify_watches: Watches,
+    // Every directory with a watch, including those added while recursing
+    watches: Arc<Mutex<HashMap<WatchDescriptor, PathBuf>>>,
+    // Watched roots and whether each is recursive
+    roots: Arc<Mutex<HashMap<PathBuf, bool>>>,
+    // Single files watched through their parent directory
+    files: Arc<Mutex<HashSet<PathBuf>>>,
+    // Symlinked roots, from the directory actually watched to the path asked for
+    aliases: Arc<Mutex<HashMap<PathBuf, PathBuf>>>,
+    mask: WatchMask,
+    ignore: IgnorePatterns,
+    scan_new_directories: bool,
+    // Entries the background setup found appeared before their directory's
+    // watch, for the event loop to report
+    found_sender: mpsc::UnboundedSender<Vec<PathBuf>>,
+    event_loop: JoinHandle<()>,
 }
 
 unsafe impl Send for PlatformWatcher {}
@@ -28,160 +50,639 @@ unsafe impl Send for UnixWatchHandle {}
 unsafe impl Sync for UnixWatchHandle {}
 
 impl PlatformWatcher {
-    pub fn new(handler_id: String, event_sender: Option<Sender<EventMessage>>) -> Result<Self> {
+    /// Opens the inotify instance and starts reading it. Must be called from
+    /// within a tokio runtime.
+    pub fn new(handler_id: HandlerId, event_sender: EventSender, config: FsWatchConfig) -> Result<Self> {
         let inotify = Inotify::init()
             .map_err(|e| TellMeWhenError::System(format!("Failed to initialize inotify: {}", e)))?;
+        let inotify_watches = inotify.watches();
+        let inotify = AsyncFd::new(inotify)?;
 
-        Ok(Self {
-            inotify,
-            watches: HashMap::new(),
-            event_sender,
+        let mask = build_watch_mask(&config.event_types);
+        let ignore = IgnorePatterns::new(&config.ignore_patterns);
+        let watches = Arc::new(Mutex::new(HashMap::new()));
+        let roots = Arc::new(Mutex::new(HashMap::new()));
+        let files = Arc::new(Mutex::new(HashSet::new()));
+        let aliases = Arc::new(Mutex::new(HashMap::new()));
+        let scan_new_directories = config.scan_new_directories;
+        let (found_sender, found) = mpsc::unbounded_channel();
+
+        let event_loop = EventLoop {
+            inotify_watches: inotify_watches.clone(),
+            watches: Arc::clone(&watches),
+            roots: Arc::clone(&roots),
+            files: Arc::clone(&files),
+            aliases: Arc::clone(&aliases),
+            mask,
+            ignore: ignore.clone(),
+            config,
+            sender: event_sender,
             handler_id,
+            sequence: AtomicU64::new(0),
+            pending_move: None,
+            found,
+        };
+
+        Ok(Self {
+            inotify_watches,
+            watches,
+            roots,
+            files,
+            aliases,
+            mask,
+            ignore,
+            scan_new_directories,
+            found_sender,
+            event_loop: tokio::spawn(event_loop.run(inotify)),
         })
     }
 
-    pub async fn watch_path(&mut self, path: &Path, config: &FsWatchConfig) -> Result<WatchHandle> {
-        let mask = self.build_watch_mask(&config.event_types);
-        
-        let watch_descriptor = self.inotify
-            .add_watch(path, mask)
+    /// Watches the directory `path`. The watch mask doesn't follow symlinks,
+    /// so a symlinked root is resolved and its target watched instead, with
+    /// event paths reported under `path` as given.
+    pub async fn watch_path(&mut self, requested: &Path, recursive: bool) -> Result<(WatchHandle, WatchReady)> {
+        let path = &resolve_root(requested)?;
+        let watch_descriptor = self.inotify_watches
+            .add(path, self.mask)
             .map_err(|e| TellMeWhenError::System(format!("Failed to add inotify watch: {}", e)))?;
 
-        self.watches.insert(watch_descriptor, path.to_path_buf());
-
-        // If we're watching subdirectories, recursively add watches
-        if config.watch_subdirectories && path.is_dir() {
-            self.add_recursive_watches(path, &mask)?;
+        self.watches.lock().unwrap().insert(watch_descriptor.clone(), path.to_path_buf());
+        self.roots.lock().unwrap().insert(path.to_path_buf(), recursive);
+        if path != requested {
+            self.aliases.lock().unwrap().insert(path.to_path_buf(), requested.to_path_buf());
         }
 
+        // Subdirectory watches are added in the background so that a huge tree
+        // doesn't hold up the caller; `WatchReady` resolves once they're all in.
+        // With `scan_new_directories`, each directory is rescanned right after
+        // its watch is added, so entries that appeared in the meantime, which
+        // no watch saw, are still reported
+        let ready = if recursive && path.is_dir() {
+            let (ready_sender, ready) = WatchReady::channel();
+            let mut inotify_watches = self.inotify_watches.clone();
+            let watches = Arc::clone(&self.watches);
+            let root = path.to_path_buf();
+            let mask = self.mask;
+            let ignore = self.ignore.clone();
+            let since = self.scan_new_directories.then(|| SystemTime::now() - TIMESTAMP_SLACK);
+            let found_sender = self.found_sender.clone();
+
+            tokio::task::spawn_blocking(move || {
+                let _active = crate::metrics::track_thread();
+                let found = add_recursive_watches(&mut inotify_watches, &watches, &root, mask, &ignore, since);
+                if !found.is_empty() {
+                    let _ = found_sender.send(found);
+                }
+                let _ = ready_sender.send(true);
+            });
+
+            ready
+        } else {
+            WatchReady::ready()
+        };
+
         let handle = WatchHandle {
             handle: UnixWatchHandle {
                 watch_descriptor,
-                path: path.to_path_buf(),
+                path: requested.to_path_buf(),
             },
         };
 
-        // Start the event monitoring loop
-        self.start_event_loop();
+        Ok((handle, ready))
+    }
 
-        Ok(handle)
+    /// Watches `file` through a non-recursive watch on `dir`, its parent.
+    /// Events for the directory's other entries are dropped by the event loop.
+    pub async fn watch_file(&mut self, dir: &Path, file: &Path) -> Result<(WatchHandle, WatchReady)> {
+        let watch_descriptor = self.inotify_watches
+            .add(dir, self.mask)
+            .map_err(|e| TellMeWhenError::System(format!("Failed to add inotify watch: {}", e)))?;
+
+        self.watches.lock().unwrap().insert(watch_descriptor.clone(), dir.to_path_buf());
+        self.files.lock().unwrap().insert(file.to_path_buf());
+
+        let handle = WatchHandle {
+            handle: UnixWatchHandle {
+                watch_descriptor,
+                path: file.to_path_buf(),
+            },
+        };
+
+        Ok((handle, WatchReady::ready()))
     }
 
-    fn add_recursive_watches(&mut self, dir_path: &Path, mask: &WatchMask) -> Result<()> {
-        if let Ok(entries) = std::fs::read_dir(dir_path) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.is_dir() {
-                        if let Ok(watch_descriptor) = self.inotify.add_watch(&path, *mask) {
-                            self.watches.insert(watch_descriptor, path.clone());
-                            // Recursively add subdirectories
-                            self.add_recursive_watches(&path, mask)?;
-                        }
-                    }
+    pub async fn unwatch(&mut self, handle: WatchHandle) -> Result<()> {
+        let requested = handle.handle.path;
+        let mut roots = self.roots.lock().unwrap();
+        let mut files = self.files.lock().unwrap();
+        // A watched file only holds the watch on its parent
+        let scope = if files.remove(&requested) {
+            requested.parent().map(Path::to_path_buf).unwrap_or_default()
+        } else {
+            // A symlinked root was watched at its target
+            let mut aliases = self.aliases.lock().unwrap();
+            let resolved = aliases
+                .iter()
+                .find(|(_, alias)| **alias == requested)
+                .map(|(resolved, _)| resolved.clone());
+            let root = match resolved {
+                Some(resolved) => {
+                    aliases.remove(&resolved);
+                    resolved
                 }
+                None => requested.clone(),
+            };
+            roots.remove(&root);
+            root
+        };
+
+        // inotify hands out one descriptor per directory, so directories that
+        // another watched root or file also needs must keep theirs
+        let removed: Vec<WatchDescriptor> = {
+            let mut watches = self.watches.lock().unwrap();
+            let removed: Vec<WatchDescriptor> = watches
+                .iter()
+                .filter(|(_, path)| path.starts_with(&scope) && !covered(&roots, path) && !holds_file(&files, path))
+                .map(|(watch_descriptor, _)| watch_descriptor.clone())
+                .collect();
+            for watch_descriptor in &removed {
+                watches.remove(watch_descriptor);
             }
+            removed
+        };
+
+        for watch_descriptor in removed {
+            // Fails harmlessly if the directory is already gone
+            let _ = self.inotify_watches.remove(watch_descriptor);
         }
+
+        log::debug!("Stopped watching {:?} ({:?})", requested, handle.handle.watch_descriptor);
         Ok(())
     }
+}
 
-    fn build_watch_mask(&self, event_types: &[FsEventType]) -> WatchMask {
-        let mut mask = WatchMask::empty();
+impl Drop for PlatformWatcher {
+    fn drop(&mut self) {
+        // Dropping the loop's task closes the inotify descriptor
+        self.event_loop.abort();
+    }
+}
 
-        for event_type in event_types {
-            match event_type {
-                FsEventType::Created => {
-                    mask |= WatchMask::CREATE;
-                }
-                FsEventType::Modified => {
-                    mask |= WatchMask::MODIFY | WatchMask::CLOSE_WRITE;
+// The directory a symlinked root points to, or the root itself
+fn resolve_root(path: &Path) -> Result<PathBuf> {
+    let is_symlink = std::fs::symlink_metadata(path)
+        .map(|metadata| metadata.file_type().is_symlink())
+        .unwrap_or(false);
+    if !is_symlink {
+        return Ok(path.to_path_buf());
+    }
+    Ok(std::fs::canonicalize(path)?)
+}
+
+fn build_watch_mask(event_types: &[FsEventType]) -> WatchMask {
+    let mut mask = WatchMask::empty();
+
+    for event_type in event_types {
+        match event_type {
+            FsEventType::Created => {
+                mask |= WatchMask::CREATE;
+            }
+            FsEventType::Modified => {
+                mask |= WatchMask::MODIFY | WatchMask::CLOSE_WRITE;
+            }
+            FsEventType::Deleted => {
+                mask |= WatchMask::DELETE | WatchMask::DELETE_SELF;
+            }
+            FsEventType::Renamed { .. } | FsEventType::Moved { .. } => {
+                mask |= WatchMask::MOVED_FROM | WatchMask::MOVED_TO;
+            }
+            FsEventType::AttributeChanged => {
+                mask |= WatchMask::ATTRIB;
+            }
+            FsEventType::PermissionChanged => {
+                mask |= WatchMask::ATTRIB;
+            }
+        }
+    }
+
+    if mask.is_empty() {
+        // Default mask if no specific types specified
+        mask = WatchMask::CREATE
+            | WatchMask::MODIFY
+            | WatchMask::DELETE
+            | WatchMask::MOVED_FROM
+            | WatchMask::MOVED_TO
+            | WatchMask::CLOSE_WRITE;
+    }
+
+    // Moves are always watched: a directory moved into the tree needs its own
+    // watches, and one moved out must drop them
+    mask |= WatchMask::MOVED_FROM | WatchMask::MOVED_TO;
+
+    // Add some default useful events
+    mask | WatchMask::DONT_FOLLOW | WatchMask::EXCL_UNLINK
+}
+
+// A MOVED_FROM waiting for the MOVED_TO with the same cookie
+struct PendingMove {
+    cookie: u32,
+    path: PathBuf,
+    is_dir: bool,
+}
+
+struct EventLoop {
+    inotify_watches: Watches,
+    watches: Arc<Mutex<HashMap<WatchDescriptor, PathBuf>>>,
+    roots: Arc<Mutex<HashMap<PathBuf, bool>>>,
+    files: Arc<Mutex<HashSet<PathBuf>>>,
+    aliases: Arc<Mutex<HashMap<PathBuf, PathBuf>>>,
+    mask: WatchMask,
+    ignore: IgnorePatterns,
+    config: FsWatchConfig,
+    sender: EventSender,
+    handler_id: HandlerId,
+    sequence: AtomicU64,
+    pending_move: Option<PendingMove>,
+    found: mpsc::UnboundedReceiver<Vec<PathBuf>>,
+}
+
+impl EventLoop {
+    async fn run(mut self, mut inotify: AsyncFd<Inotify>) {
+        let _active = crate::metrics::track_task();
+        let mut buffer = vec![0u8; BUF_SIZE];
+
+        loop {
+            let readable = tokio::select! {
+                readable = inotify.readable_mut() => readable,
+                Some(found) = self.found.recv() => {
+                    self.report_found(found);
+                    continue;
                 }
-                FsEventType::Deleted => {
-                    mask |= WatchMask::DELETE | WatchMask::DELETE_SELF;
+            };
+            let mut guard = match readable {
+                Ok(guard) => guard,
+                Err(e) => {
+                    log::error!("inotify descriptor failed: {}", e);
+                    self.report_backend_failure(e);
+                    break;
                 }
-                FsEventType::Renamed { .. } | FsEventType::Moved { .. } => {
-                    mask |= WatchMask::MOVED_FROM | WatchMask::MOVED_TO;
+            };
+
+            // One read is one OS notification buffer, delivered as one batch
+            let mut batch = Vec::new();
+            match guard.get_inner_mut().read_events(&mut buffer) {
+                Ok(events) => {
+                    for event in events {
+                        batch.extend(self.handle_inotify_event(event));
+                    }
                 }
-                FsEventType::AttributeChanged => {
-                    mask |= WatchMask::ATTRIB;
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    guard.clear_ready();
+                    continue;
                 }
-                FsEventType::PermissionChanged => {
-                    mask |= WatchMask::ATTRIB;
+                Err(e) => {
+                    log::error!("Failed to read inotify events: {}", e);
+                    self.report_backend_failure(e);
+                    break;
                 }
             }
-        }
 
-        // Add some default useful events
-        mask |= WatchMask::DONT_FOLLOW | WatchMask::EXCL_UNLINK;
+            // A pair split across reads is reported as a move out and a move in
+            if let Some(pending) = self.pending_move.take() {
+                batch.push(self.moved_out(&pending));
+            }
 
-        if mask.is_empty() {
-            // Default mask if no specific types specified
-            mask = WatchMask::CREATE 
-                | WatchMask::MODIFY 
-                | WatchMask::DELETE 
-                | WatchMask::MOVED_FROM 
-                | WatchMask::MOVED_TO 
-                | WatchMask::CLOSE_WRITE;
-        }
+            // Directories watched only for a file in them report nothing else
+            batch.retain(|(event_type, path)| self.in_scope(event_type, path));
+            let batch: Vec<(FsEventType, PathBuf)> = batch
+                .into_iter()
+                .map(|(event_type, path)| self.as_requested(event_type, path))
+                .collect();
 
-        mask
+            if !batch.is_empty() {
+                send_fs_batch(batch, &self.config, &self.ignore, &self.sequence, &self.sender, &self.handler_id);
+            }
+        }
     }
 
-    fn start_event_loop(&self) {
-        let mut buffer = [0; 4096];
-        
-        tokio::spawn(async move {
-            // This is a simplified event loop - in a real implementation,
-            // you'd want to use tokio's async file I/O or run this in a separate thread
-            loop {
-                // Read events from inotify
-                // Process and emit events
-                tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    // Turns one inotify event into the events to report. A newly created
+    // directory gets watched immediately when recursing, and with
+    // `scan_new_directories` its existing contents are reported as Created,
+    // since they may have appeared before the watch was in place.
+    fn handle_inotify_event(&mut self, event: Event<&std::ffi::OsStr>) -> Vec<(FsEventType, PathBuf)> {
+        if event.mask.contains(EventMask::Q_OVERFLOW) {
+            log::warn!("inotify queue overflowed; some filesystem events were lost");
+            // The queue is shared by every watch, so any root may be missing events
+            let roots: Vec<PathBuf> = self.roots.lock().unwrap().keys().cloned().collect();
+            for path in roots {
+                let _ = self.sender.send(EventMessage::lifecycle(
+                    self.handler_id.clone(),
+                    LifecycleEventType::EventsLost {
+                        handler: self.handler_id.clone(),
+                        path,
+                    },
+                ));
             }
-        });
-    }
+            return Vec::new();
+        }
+        if event.mask.contains(EventMask::IGNORED) {
+            // The watched directory is gone or its watch was removed. Unwatching
+            // forgets the descriptor first, so a root still on record here died
+            let Some(removed) = self.watches.lock().unwrap().remove(&event.wd) else {
+                return Vec::new();
+            };
+            let mut dead: Vec<PathBuf> = self.files.lock().unwrap()
+                .iter()
+                .filter(|file| file.parent() == Some(removed.as_path()))
+                .cloned()
+                .collect();
+            if self.roots.lock().unwrap().contains_key(&removed) {
+                dead.push(removed);
+            }
+            for path in dead {
+                log::warn!("inotify watch on {:?} ended; the directory was removed or unmounted", path);
+                let _ = self.sender.send(EventMessage::lifecycle(
+                    self.handler_id.clone(),
+                    LifecycleEventType::WatchFailed {
+                        handler: self.handler_id.clone(),
+                        path,
+                        os_error: None,
+                    },
+                ));
+            }
+            return Vec::new();
+        }
 
-    pub async fn unwatch(&mut self, handle: WatchHandle) -> Result<()> {
-        let watch_descriptor = handle.handle.watch_descriptor;
-        
-        self.inotify
-            .rm_watch(watch_descriptor)
-            .map_err(|e| TellMeWhenError::System(format!("Failed to remove inotify watch: {}", e)))?;
+        let is_dir = event.mask.contains(EventMask::ISDIR);
+        let Some(path) = self.event_path(&event) else {
+            return Vec::new();
+        };
 
-        self.watches.remove(&watch_descriptor);
-        Ok(())
-    }
+        let mut events = Vec::new();
 
-    fn process_inotify_event(&self, event: Event<&std::ffi::OsStr>) -> Option<(FsEventType, PathBuf)> {
-        let path = if let Some(watch_path) = self.watches.get(&event.wd) {
-            if let Some(name) = event.name {
-                watch_path.join(name)
-            } else {
-                watch_path.clone()
+        // MOVED_FROM and MOVED_TO for one rename are queued back to back, so a
+        // pending move followed by anything else, or by nothing, left the
+        // watched tree
+        let pending_move = self.pending_move.take();
+        if let Some(pending) = &pending_move {
+            let completes_move = event.mask.contains(EventMask::MOVED_TO) && event.cookie == pending.cookie;
+            if !completes_move {
+                events.push(self.moved_out(pending));
             }
-        } else {
-            return None;
-        };
+        }
 
-        let event_type = if event.mask.contains(EventMask::CREATE) {
-            FsEventType::Created
-        } else if event.mask.contains(EventMask::MODIFY) || event.mask.contains(EventMask::CLOSE_WRITE) {
+        if event.mask.contains(EventMask::MOVED_FROM) {
+            self.pending_move = Some(PendingMove {
+                cookie: event.cookie,
+                path,
+                is_dir,
+            });
+            return events;
+        }
+
+        if event.mask.contains(EventMask::MOVED_TO) {
+            match pending_move.filter(|pending| pending.cookie == event.cookie) {
+                Some(pending) => {
+                    if pending.is_dir {
+                        self.rebase_watches(&pending.path, &path);
+                    }
+                    let event_type = if pending.path.parent() == path.parent() {
+                        FsEventType::Renamed { old_path: pending.path, new_path: path.clone() }
+                    } else {
+                        FsEventType::Moved { from: pending.path, to: path.clone() }
+                    };
+                    events.push((event_type, path));
+                }
+                // Moved in from outside the watched tree
+                None => events.extend(self.created(path, is_dir)),
+            }
+            return events;
+        }
+
+        if event.mask.contains(EventMask::CREATE) {
+            events.extend(self.created(path, is_dir));
+            return events;
+        }
+
+        let event_type = if event.mask.contains(EventMask::MODIFY) || event.mask.contains(EventMask::CLOSE_WRITE) {
             FsEventType::Modified
-        } else if event.mask.contains(EventMask::DELETE) || event.mask.contains(EventMask::DELETE_SELF) {
+        } else if event.mask.contains(EventMask::DELETE) {
             FsEventType::Deleted
-        } else if event.mask.contains(EventMask::MOVED_FROM) || event.mask.contains(EventMask::MOVED_TO) {
-            // For simplicity, treating moves as renames
-            // In a full implementation, you'd track move pairs
-            FsEventType::Renamed {
-                old_path: path.clone(),
-                new_path: path.clone(),
+        } else if event.mask.contains(EventMask::DELETE_SELF) {
+            // Subdirectories are already reported through their parent's DELETE
+            if !self.roots.lock().unwrap().contains_key(&path) {
+                return events;
             }
+            FsEventType::Deleted
         } else if event.mask.contains(EventMask::ATTRIB) {
             FsEventType::AttributeChanged
         } else {
-            return None;
+            return events;
         };
 
-        Some((event_type, path))
+        events.push((event_type, path));
+        events
     }
-}
\ No newline at end of file
+
+    // Whether an event falls under a watched root or is about a watched file
+    fn in_scope(&self, event_type: &FsEventType, path: &Path) -> bool {
+        let files = self.files.lock().unwrap();
+        if files.is_empty() {
+            return true;
+        }
+        if files.iter().any(|file| touches(event_type, path, file)) {
+            return true;
+        }
+        let roots = self.roots.lock().unwrap();
+        let under_root = |path: &Path| roots.contains_key(path) || path.parent().is_some_and(|dir| covered(&roots, dir));
+        under_root(path)
+            || match event_type {
+                FsEventType::Renamed { old_path, .. } => under_root(old_path),
+                FsEventType::Moved { from, .. } => under_root(from),
+                _ => false,
+            }
+    }
+
+    // Moves paths under a symlinked root back under the link
+    fn as_requested(&self, event_type: FsEventType, path: PathBuf) -> (FsEventType, PathBuf) {
+        let aliases = self.aliases.lock().unwrap();
+        if aliases.is_empty() {
+            return (event_type, path);
+        }
+        // The nearest aliased ancestor, looked up rather than searched for
+        let unalias = |path: PathBuf| {
+            let requested = path.ancestors().find_map(|ancestor| {
+                let requested = aliases.get(ancestor)?;
+                Some(requested.join(path.strip_prefix(ancestor).ok()?))
+            });
+            requested.unwrap_or(path)
+        };
+
+        let event_type = match event_type {
+            FsEventType::Renamed { old_path, new_path } => FsEventType::Renamed {
+                old_path: unalias(old_path),
+                new_path: unalias(new_path),
+            },
+            FsEventType::Moved { from, to } => FsEventType::Moved { from: unalias(from), to: unalias(to) },
+            event_type => event_type,
+        };
+        (event_type, unalias(path))
+    }
+
+    // Entries the background watch setup found before their directory's
+    // watch was in place
+    fn report_found(&self, found: Vec<PathBuf>) {
+        let batch: Vec<(FsEventType, PathBuf)> = found
+            .into_iter()
+            .map(|path| self.as_requested(FsEventType::Created, path))
+            .collect();
+        send_fs_batch(batch, &self.config, &self.ignore, &self.sequence, &self.sender, &self.handler_id);
+    }
+
+    fn report_backend_failure(&self, error: std::io::Error) {
+        let _ = self.sender.send(EventMessage::lifecycle(
+            self.handler_id.clone(),
+            LifecycleEventType::HandlerStopped {
+                handler: self.handler_id.clone(),
+                reason: stop_reason(&error),
+            },
+        ));
+    }
+
+    fn event_path(&self, event: &Event<&std::ffi::OsStr>) -> Option<PathBuf> {
+        let watches = self.watches.lock().unwrap();
+        let watch_path = watches.get(&event.wd)?;
+        Some(match event.name {
+            Some(name) => watch_path.join(name),
+            None => watch_path.clone(),
+        })
+    }
+
+    fn created(&mut self, path: PathBuf, is_dir: bool) -> Vec<(FsEventType, PathBuf)> {
+        let mut events = vec![(FsEventType::Created, path.clone())];
+
+        // Only a recursive root above it wants the new directory watched
+        let recursed = self.roots.lock().unwrap()
+            .iter()
+            .any(|(root, recursive)| *recursive && path.starts_with(root));
+        if is_dir && recursed && !self.ignore.matches(&path) {
+            if let Ok(watch_descriptor) = self.inotify_watches.add(&path, self.mask) {
+                self.watches.lock().unwrap().insert(watch_descriptor, path.clone());
+            }
+            add_recursive_watches(&mut self.inotify_watches, &self.watches, &path, self.mask, &self.ignore, None);
+        }
+
+        if is_dir && self.config.scan_new_directories {
+            events.extend(
+                scan_directory_contents(&path, true, &self.ignore)
+                    .into_iter()
+                    .map(|child| (FsEventType::Created, child)),
+            );
+        }
+
+        events
+    }
+
+    fn moved_out(&mut self, pending: &PendingMove) -> (FsEventType, PathBuf) {
+        if pending.is_dir {
+            // inotify keeps following the directory to wherever it went
+            let removed: Vec<WatchDescriptor> = {
+                let mut watches = self.watches.lock().unwrap();
+                let removed: Vec<WatchDescriptor> = watches
+                    .iter()
+                    .filter(|(_, path)| path.starts_with(&pending.path))
+                    .map(|(watch_descriptor, _)| watch_descriptor.clone())
+                    .collect();
+                for watch_descriptor in &removed {
+                    watches.remove(watch_descriptor);
+                }
+                removed
+            };
+            for watch_descriptor in removed {
+                let _ = self.inotify_watches.remove(watch_descriptor);
+            }
+        }
+
+        (FsEventType::Deleted, pending.path.clone())
+    }
+
+    // Watches follow a renamed directory, so only their recorded paths change
+    fn rebase_watches(&self, from: &Path, to: &Path) {
+        let mut watches = self.watches.lock().unwrap();
+        for path in watches.values_mut() {
+            if let Ok(relative) = path.strip_prefix(from) {
+                *path = to.join(relative);
+            }
+        }
+    }
+}
+
+// Whether some watched root still needs a watch on `dir`
+fn covered(roots: &HashMap<PathBuf, bool>, dir: &Path) -> bool {
+    roots.iter().any(|(root, recursive)| dir == root || (*recursive && dir.starts_with(root)))
+}
+
+// Running out of kernel memory or inotify resources isn't a fault of the
+// backend, and may clear up once the user raises a limit
+fn stop_reason(error: &std::io::Error) -> StopReason {
+    use std::io::ErrorKind;
+    match error.kind() {
+        ErrorKind::StorageFull | ErrorKind::OutOfMemory | ErrorKind::QuotaExceeded => {
+            StopReason::ResourceExhausted(error.to_string())
+        }
+        _ => StopReason::BackendFailure(error.to_string()),
+    }
+}
+
+// Whether a watched file lives directly in `dir`
+fn holds_file(files: &HashSet<PathBuf>, dir: &Path) -> bool {
+    files.iter().any(|file| file.parent() == Some(dir))
+}
+
+// Watches every directory under `dir_path`. Each directory is listed right
+// after its watch is added, so with `since` set, the entries that appeared
+// after then, before any watch could see them, are returned to be reported
+fn add_recursive_watches(
+    inotify_watches: &mut Watches,
+    watches: &Mutex<HashMap<WatchDescriptor, PathBuf>>,
+    dir_path: &Path,
+    mask: WatchMask,
+    ignore: &IgnorePatterns,
+    since: Option<SystemTime>,
+) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    let Ok(entries) = std::fs::read_dir(dir_path) else {
+        return found;
+    };
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // Nothing under an ignored directory would be reported anyway
+        if ignore.matches(&path) {
+            continue;
+        }
+        if since.is_some_and(|since| appeared_since(&entry, since)) {
+            found.push(path.clone());
+        }
+        if path.is_dir() {
+            if let Ok(watch_descriptor) = inotify_watches.add(&path, mask) {
+                watches.lock().unwrap().insert(watch_descriptor, path.clone());
+                // Recursively add subdirectories
+                found.extend(add_recursive_watches(inotify_watches, watches, &path, mask, ignore, since));
+            }
+        }
+    }
+
+    found
+}
+
+// Birth time where the filesystem records one, else the last change
+fn appeared_since(entry: &std::fs::DirEntry, since: SystemTime) -> bool {
+    let Ok(metadata) = entry.metadata() else {
+        return false;
+    };
+    metadata
+        .created()
+        .or_else(|_| metadata.modified())
+        .is_ok_and(|appeared| appeared >= since)
+}
@@ -2,10 +2,11 @@
 use winapi::um::{
     fileapi::{CreateFileW, OPEN_EXISTING},
     handleapi::{CloseHandle, INVALID_HANDLE_VALUE},
-    winbase::{FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OVERLAPPED, ReadDirectoryChangesW},
+    winbase::{FILE_FLAG_BACKUP_SEMANTICS, FILE_FLAG_OVERLAPPED, INFINITE, ReadDirectoryChangesW},
     winnt::{
         FILE_NOTIFY_CHANGE_ATTRIBUTES, FILE_NOTIFY_CHANGE_CREATION, FILE_NOTIFY_CHANGE_DIR_NAME,
-        FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE, FILE_NOTIFY_CHANGE_SIZE,
+        FILE_NOTIFY_CHANGE_FILE_NAME, FILE_NOTIFY_CHANGE_LAST_WRITE, FILE_NOTIFY_CHANGE_SECURITY,
+        FILE_NOTIFY_CHANGE_SIZE,
         FILE_SHARE_DELETE, FILE_SHARE_READ, FILE_SHARE_WRITE, FILE_LIST_DIRECTORY, HANDLE,
         FILE_ACTION_ADDED, FILE_ACTION_REMOVED, FILE_ACTION_MODIFIED, FILE_ACTION_RENAMED_OLD_NAME,
         FILE_ACTION_RENAMED_NEW_NAME, FILE_NOTIFY_INFORMATION,
@@ -13,26 +14,37 @@ use winapi::um::{
     synchapi::SleepEx,
     ioapiset::CancelIo,
     minwinbase::OVERLAPPED,
-    errhandlingapi::GetLastError,
+    processthreadsapi::QueueUserAPC,
 };
+use winapi::shared::basetsd::ULONG_PTR;
+use winapi::shared::winerror::ERROR_NOTIFY_ENUM_DIR;
+use std::cell::UnsafeCell;
 use std::ffi::{OsStr, OsString};
+use std::io;
 use std::os::windows::ffi::{OsStrExt, OsStringExt};
+use std::os::windows::io::AsRawHandle;
 use std::path::{Path, PathBuf};
 use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
 use std::mem;
 use winapi::ctypes::c_void;
-use std::thread;
-
-const BUF_SIZE: usize = 16384;
+use std::thread::{self, JoinHandle};
 
 #[derive(Debug, Clone)]
 pub enum FsEventKind {
     Created,
     Modified,
+    // Only from a stream watching ATTRIBUTE_FILTER or SECURITY_FILTER alone
+    AttributeChanged,
+    PermissionChanged,
     Deleted,
     Renamed { old_path: PathBuf, new_path: PathBuf },
+    // The buffer overflowed and the changes it would have held are gone
+    Overflow,
+    // The read failed and the watch has ended
+    Failed { os_error: u32 },
 }
 
 #[derive(Debug, Clone)]
@@ -42,20 +54,127 @@ pub struct FsEvent {
     pub timestamp: SystemTime,
 }
 
-type EventCallback = Arc<Mutex<dyn Fn(FsEvent) + Send + Sync>>;
+// Invoked once per completed ReadDirectoryChangesW buffer, in buffer order
+type EventCallback = Arc<Mutex<dyn Fn(Vec<FsEvent>) + Send + Sync>>;
 
 #[derive(Clone)]
 pub struct WatchData {
     pub dir: PathBuf,
     pub is_recursive: bool,
+    pub filter: u32,
 }
 
+// Names, contents and timestamps
+pub const CONTENT_FILTER: u32 = FILE_NOTIFY_CHANGE_FILE_NAME
+    | FILE_NOTIFY_CHANGE_DIR_NAME
+    | FILE_NOTIFY_CHANGE_LAST_WRITE
+    | FILE_NOTIFY_CHANGE_CREATION
+    | FILE_NOTIFY_CHANGE_SIZE;
+
+// Every change comes back as FILE_ACTION_MODIFIED whichever flag caught it,
+// so a stream watching one of these alone is the only way to tell an
+// attribute or security change from a write
+pub const ATTRIBUTE_FILTER: u32 = FILE_NOTIFY_CHANGE_ATTRIBUTES;
+pub const SECURITY_FILTER: u32 = FILE_NOTIFY_CHANGE_SECURITY;
+
+// Attribute changes reported as plain modifications
+pub const NOTIFY_FILTER: u32 = CONTENT_FILTER | ATTRIBUTE_FILTER;
+
 pub struct WatchRequest {
     pub buffer: Mutex<Vec<u8>>,
     pub handle: HANDLE,
     pub data: WatchData,
     pub event_callback: EventCallback,
     pub prev_rename: Mutex<Option<PathBuf>>,
+    // Must stay put while a read is outstanding; only touched on the watch's thread
+    overlapped: UnsafeCell<OVERLAPPED>,
+    pending: AtomicBool,
+    stopped: AtomicBool,
+}
+
+unsafe impl Send for WatchRequest {}
+unsafe impl Sync for WatchRequest {}
+
+/// One watched directory, read through one stream per notify filter. Each
+/// stream's reads are issued from, and complete on, a thread of its own, so
+/// dropping the handle stops this watch and no other.
+pub struct WindowsWatchHandle {
+    streams: Vec<Stream>,
+}
+
+struct Stream {
+    request: Arc<WatchRequest>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for WindowsWatchHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WindowsWatchHandle")
+            .field("path", &self.streams.first().map(|stream| &stream.request.data.dir))
+            .field("streams", &self.streams.len())
+            .finish()
+    }
+}
+
+impl Drop for Stream {
+    fn drop(&mut self) {
+        self.request.stopped.store(true, Ordering::Release);
+        if let Some(thread) = self.thread.take() {
+            // Any APC ends the thread's alertable wait
+            unsafe { QueueUserAPC(Some(wake), thread.as_raw_handle() as HANDLE, 0) };
+            let _ = thread.join();
+        }
+    }
+}
+
+unsafe extern "system" fn wake(_: ULONG_PTR) {}
+
+// Completion routines are APCs, delivered only to the thread that issued the
+// read and only while it waits alertably
+unsafe fn arm(req: &WatchRequest) -> bool {
+    let overlapped = &mut *req.overlapped.get();
+    *overlapped = mem::zeroed();
+    overlapped.hEvent = req as *const WatchRequest as *mut c_void;
+
+    let mut buffer = req.buffer.lock().unwrap();
+    let armed = ReadDirectoryChangesW(
+        req.handle,
+        buffer.as_mut_ptr() as *mut c_void,
+        buffer.len() as u32,
+        if req.data.is_recursive { 1 } else { 0 },
+        req.data.filter,
+        ptr::null_mut(),
+        overlapped,
+        Some(completion_routine),
+    ) != 0;
+    req.pending.store(armed, Ordering::Release);
+    armed
+}
+
+fn watch_thread(req: Arc<WatchRequest>, started: std::sync::mpsc::Sender<io::Result<()>>) {
+    let _active = crate::metrics::track_thread();
+    unsafe {
+        if !arm(&req) {
+            let _ = started.send(Err(io::Error::last_os_error()));
+            CloseHandle(req.handle);
+            return;
+        }
+        let _ = started.send(Ok(()));
+
+        while !req.stopped.load(Ordering::Acquire) {
+            SleepEx(INFINITE, 1);
+        }
+
+        // The cancelled read still completes through the routine, which has
+        // to run before the buffer it points into is freed
+        if req.pending.load(Ordering::Acquire) {
+            CancelIo(req.handle);
+            while req.pending.load(Ordering::Acquire) {
+                SleepEx(INFINITE, 1);
+            }
+        }
+        CloseHandle(req.handle);
+    }
 }
 
 unsafe extern "system" fn completion_routine(
@@ -68,13 +187,35 @@ unsafe extern "system" fn completion_routine(
         return;
     }
     let req = &*req_ptr;
-    if error_code != 0 { // 0 == ERROR_SUCCESS
+    req.pending.store(false, Ordering::Release);
+    if req.stopped.load(Ordering::Acquire) {
+        return;
+    }
+    // More changes than the buffer could hold is reported either as
+    // ERROR_NOTIFY_ENUM_DIR or as a successful read of nothing
+    let overflowed = error_code == ERROR_NOTIFY_ENUM_DIR || (error_code == 0 && bytes_transferred == 0);
+    // 0 == ERROR_SUCCESS. Anything else, typically ERROR_ACCESS_DENIED once
+    // the directory is deleted, ends the watch
+    if error_code != 0 && !overflowed {
+        log::error!("ReadDirectoryChangesW failed for {:?}: {}", req.data.dir, io::Error::from_raw_os_error(error_code as i32));
+        report_failure(req, error_code);
         return;
     }
 
     {
         let buffer = req.buffer.lock().unwrap();
-        if bytes_transferred > 0 {
+        let mut prev_rename = req.prev_rename.lock().unwrap();
+        let mut events = Vec::new();
+        if overflowed {
+            log::warn!("ReadDirectoryChangesW buffer overflowed for {:?}; some filesystem events were lost", req.data.dir);
+            // The other half of a pending rename may have been dropped
+            *prev_rename = None;
+            events.push(FsEvent {
+                kind: FsEventKind::Overflow,
+                path: req.data.dir.clone(),
+                timestamp: SystemTime::now(),
+            });
+        } else {
             let buffer = &buffer[..bytes_transferred as usize];
             let mut offset = 0;
             while offset < buffer.len() {
@@ -86,83 +227,100 @@ unsafe extern "system" fn completion_routine(
                 let filename = OsString::from_wide(filename_wide);
                 let full_path = req.data.dir.join(&filename);
                 let timestamp = SystemTime::now();
+
+                // The two halves of a rename arrive back to back, though they
+                // may be split across buffers. An old name followed by anything
+                // else was moved out of the watched tree.
+                if info.Action != FILE_ACTION_RENAMED_NEW_NAME {
+                    if let Some(old_path) = prev_rename.take() {
+                        events.push(FsEvent {
+                            kind: FsEventKind::Deleted,
+                            path: old_path,
+                            timestamp,
+                        });
+                    }
+                }
+
                 let event_kind = match info.Action {
                     FILE_ACTION_ADDED => FsEventKind::Created,
                     FILE_ACTION_REMOVED => FsEventKind::Deleted,
-                    FILE_ACTION_MODIFIED => FsEventKind::Modified,
+                    FILE_ACTION_MODIFIED => match req.data.filter {
+                        ATTRIBUTE_FILTER => FsEventKind::AttributeChanged,
+                        SECURITY_FILTER => FsEventKind::PermissionChanged,
+                        _ => FsEventKind::Modified,
+                    },
                     FILE_ACTION_RENAMED_OLD_NAME => {
-                        let mut prev = req.prev_rename.lock().unwrap();
-                        *prev = Some(full_path.clone());
-                        offset += info.NextEntryOffset as usize;
+                        *prev_rename = Some(full_path);
                         if info.NextEntryOffset == 0 { break; }
+                        offset += info.NextEntryOffset as usize;
                         continue;
                     }
-                    FILE_ACTION_RENAMED_NEW_NAME => {
-                        let old_path = req.prev_rename.lock().unwrap().take()
-                            .unwrap_or_else(|| full_path.clone());
-                        FsEventKind::Renamed { old_path, new_path: full_path.clone() }
-                    }
+                    FILE_ACTION_RENAMED_NEW_NAME => match prev_rename.take() {
+                        Some(old_path) => FsEventKind::Renamed { old_path, new_path: full_path.clone() },
+                        // Moved in from outside the watched tree
+                        None => FsEventKind::Created,
+                    },
                     _ => FsEventKind::Modified,
                 };
 
-                if info.Action != FILE_ACTION_RENAMED_OLD_NAME {
-                    let event = FsEvent {
-                        kind: event_kind,
-                        path: full_path,
-                        timestamp,
-                    };
-                    (req.event_callback.lock().unwrap())(event);
-                }
+                events.push(FsEvent {
+                    kind: event_kind,
+                    path: full_path,
+                    timestamp,
+                });
 
                 if info.NextEntryOffset == 0 { break; }
                 offset += info.NextEntryOffset as usize;
             }
         }
+        if !events.is_empty() {
+            (req.event_callback.lock().unwrap())(events);
+        }
     }
 
-    // Re-arm for next event
-    let mut new_overlapped = mem::zeroed::<OVERLAPPED>();
-    new_overlapped.hEvent = req_ptr as *mut c_void;
-    let notify_filter = FILE_NOTIFY_CHANGE_FILE_NAME
-        | FILE_NOTIFY_CHANGE_DIR_NAME
-        | FILE_NOTIFY_CHANGE_LAST_WRITE
-        | FILE_NOTIFY_CHANGE_CREATION
-        | FILE_NOTIFY_CHANGE_SIZE
-        | FILE_NOTIFY_CHANGE_ATTRIBUTES;
-    let mut bytes_returned = 0u32;
-    {
-        let mut buffer = req.buffer.lock().unwrap();
-        let _ = ReadDirectoryChangesW(
-            req.handle,
-            buffer.as_mut_ptr() as *mut c_void,
-            buffer.len() as u32,
-            if req.data.is_recursive { 1 } else { 0 },
-            notify_filter,
-            &mut bytes_returned,
-            &mut new_overlapped,
-            Some(completion_routine),
-        );
+    if !arm(req) {
+        let error = io::Error::last_os_error();
+        log::error!("Failed to re-arm ReadDirectoryChangesW for {:?}: {}", req.data.dir, error);
+        report_failure(req, error.raw_os_error().unwrap_or(0) as u32);
     }
 }
 
-pub struct WindowsFsWatcher {
-    pub watchers: Arc<Mutex<Vec<Arc<WatchRequest>>>>,
+fn report_failure(req: &WatchRequest, os_error: u32) {
+    let event = FsEvent {
+        kind: FsEventKind::Failed { os_error },
+        path: req.data.dir.clone(),
+        timestamp: SystemTime::now(),
+    };
+    (req.event_callback.lock().unwrap())(vec![event]);
 }
 
+pub struct WindowsFsWatcher;
+
 impl WindowsFsWatcher {
     pub fn new() -> Self {
-        WindowsFsWatcher {
-            watchers: Arc::new(Mutex::new(Vec::new())),
-        }
+        WindowsFsWatcher
     }
 
-    pub fn watch<F>(&self, path: &Path, recursive: bool, callback: F) -> Option<Arc<WatchRequest>>
+    /// Watches `path` with one stream per entry of `filters`, all reporting to
+    /// `callback`. Streams don't coordinate, so a change caught by several
+    /// filters is reported once by each.
+    pub fn watch<F>(&self, path: &Path, recursive: bool, buffer_size: usize, filters: &[u32], callback: F) -> io::Result<WindowsWatchHandle>
     where
-        F: Fn(FsEvent) + Send + Sync + 'static,
+        F: Fn(Vec<FsEvent>) + Send + Sync + 'static,
     {
+        let event_callback: EventCallback = Arc::new(Mutex::new(callback));
+        // Streams already started are stopped by dropping them on failure
+        let streams = filters
+            .iter()
+            .map(|&filter| self.start_stream(path, recursive, buffer_size, filter, event_callback.clone()))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(WindowsWatchHandle { streams })
+    }
+
+    fn start_stream(&self, path: &Path, recursive: bool, buffer_size: usize, filter: u32, event_callback: EventCallback) -> io::Result<Stream> {
         let wide_path: Vec<u16> = OsStr::new(path).encode_wide().chain(Some(0)).collect();
-        unsafe {
-            let handle = CreateFileW(
+        let handle = unsafe {
+            CreateFileW(
                 wide_path.as_ptr(),
                 FILE_LIST_DIRECTORY,
                 FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE,
@@ -170,65 +328,59 @@ impl WindowsFsWatcher {
                 OPEN_EXISTING,
                 FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OVERLAPPED,
                 ptr::null_mut(),
-            );
-            if handle == INVALID_HANDLE_VALUE {
-                return None;
-            }
-            let watch_data = WatchData {
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            return Err(io::Error::last_os_error());
+        }
+
+        let req = Arc::new(WatchRequest {
+            buffer: Mutex::new(vec![0u8; buffer_size]),
+            handle,
+            data: WatchData {
                 dir: path.to_path_buf(),
                 is_recursive: recursive,
-            };
-            let event_callback = Arc::new(Mutex::new(callback));
-            let req = Arc::new(WatchRequest {
-                buffer: Mutex::new(vec![0u8; BUF_SIZE]),
-                handle,
-                data: watch_data,
-                event_callback,
-                prev_rename: Mutex::new(None),
-            });
-            let mut overlapped = mem::zeroed::<OVERLAPPED>();
-            overlapped.hEvent = Arc::as_ptr(&req) as *mut c_void;
-            let notify_filter = FILE_NOTIFY_CHANGE_FILE_NAME
-                | FILE_NOTIFY_CHANGE_DIR_NAME
-                | FILE_NOTIFY_CHANGE_LAST_WRITE
-                | FILE_NOTIFY_CHANGE_CREATION
-                | FILE_NOTIFY_CHANGE_SIZE
-                | FILE_NOTIFY_CHANGE_ATTRIBUTES;
-            let mut bytes_returned = 0u32;
-            {
-                let mut buffer = req.buffer.lock().unwrap();
-                let result = ReadDirectoryChangesW(
-                    handle,
-                    buffer.as_mut_ptr() as *mut c_void,
-                    buffer.len() as u32,
-                    if recursive { 1 } else { 0 },
-                    notify_filter,
-                    &mut bytes_returned,
-                    &mut overlapped,
-                    Some(completion_routine),
-                );
-                if result == 0 {
-                    unsafe { CloseHandle(handle); }
-                    return None;
-                }
-            }
-            self.watchers.lock().unwrap().push(req.clone());
+                filter,
+            },
+            event_callback,
+            prev_rename: Mutex::new(None),
+            overlapped: UnsafeCell::new(unsafe { mem::zeroed() }),
+            pending: AtomicBool::new(false),
+            stopped: AtomicBool::new(false),
+        });
 
-            // Spawn alertable wait thread for this watcher
-            thread::spawn(move || unsafe {
-                SleepEx(winapi::um::winbase::INFINITE, 1);
-            });
+        let (started_sender, started_receiver) = std::sync::mpsc::channel();
+        let thread_req = req.clone();
+        let thread = match thread::Builder::new()
+            .name("tell_me_when-fswatch".to_string())
+            .spawn(move || watch_thread(thread_req, started_sender))
+        {
+            Ok(thread) => thread,
+            Err(e) => {
+                unsafe { CloseHandle(handle) };
+                return Err(e);
+            }
+        };
 
-            Some(req)
+        match started_receiver.recv() {
+            Ok(Ok(())) => Ok(Stream {
+                request: req,
+                thread: Some(thread),
+            }),
+            // The thread has closed the directory handle on its way out
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => {
+                let _ = thread.join();
+                Err(io::Error::new(io::ErrorKind::Other, "watch thread exited during setup"))
+            }
         }
     }
 
-    pub fn stop(&self) {
-        for req in self.watchers.lock().unwrap().iter() {
-            unsafe {
-                CancelIo(req.handle);
-                CloseHandle(req.handle);
-            }
-        }
+    pub fn unwatch(&self, handle: WindowsWatchHandle) {
+        log::debug!("Stopped watching {:?}", handle);
+        drop(handle);
     }
-}
\ No newline at end of file
+}
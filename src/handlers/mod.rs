@@ -1,11 +1,31 @@
+#[cfg(feature = "fs")]
 pub mod fs;
+#[cfg(feature = "process")]
 pub mod process;
-// pub mod system;
-// pub mod network;
+#[cfg(feature = "system")]
+pub mod system;
+#[cfg(feature = "network")]
+pub mod network;
+#[cfg(feature = "power")]
 pub mod power;
+#[cfg(all(windows, feature = "registry"))]
+pub mod registry;
+#[cfg(any(feature = "process", feature = "system", feature = "network"))]
+pub mod shared_system;
+#[cfg(feature = "system")]
+pub mod degradation;
 
+#[cfg(feature = "fs")]
 pub use fs::FileSystemHandler;
+#[cfg(feature = "process")]
 pub use process::ProcessHandler;
-// pub use system::SystemHandler;
-// pub use network::NetworkHandler;
-pub use power::PowerHandler;
\ No newline at end of file
+#[cfg(feature = "system")]
+pub use system::SystemHandler;
+#[cfg(feature = "network")]
+pub use network::NetworkHandler;
+#[cfg(feature = "power")]
+pub use power::PowerHandler;
+#[cfg(all(windows, feature = "registry"))]
+pub use registry::RegistryHandler;
+#[cfg(any(feature = "process", feature = "system", feature = "network"))]
+pub use shared_system::SharedSystem;
\ No newline at end of file
@@ -1,159 +0,0 @@
-use super::{NetworkConfig, NetworkSnapshot, NetworkHandler};
-use crate::events::{NetworkEventType};
-use crate::{EventMessage, HandlerId, Result, TellMeWhenError};
-use crossbeam_channel::Sender;
-use sysinfo::{System};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
-use tokio::task;
-
-pub async fn start_network_monitoring(
-    config: &NetworkConfig,
-    system: &Arc<Mutex<System>>,
-    previous_interfaces: &Arc<Mutex<HashMap<String, NetworkSnapshot>>>,
-    is_running: &Arc<Mutex<bool>>,
-    sender: Sender<EventMessage>,
-    handler_id: HandlerId,
-) -> Result<()> {
-    let config = config.clone();
-    let is_running = Arc::clone(is_running);
-    let sender_clone = sender.clone();
-    let handler_id_clone = handler_id.clone();
-
-    // Start macOS System Configuration framework monitoring
-    if config.monitor_interface_changes {
-        let sc_sender = sender_clone.clone();
-        let sc_handler_id = handler_id_clone.clone();
-        let sc_is_running = Arc::clone(&is_running);
-        
-        task::spawn_blocking(move || {
-            if let Err(e) = monitor_interface_changes_via_system_configuration(sc_sender, sc_handler_id, sc_is_running) {
-                log::error!("macOS System Configuration interface monitoring failed: {}", e);
-            }
-        });
-    }
-
-    // Start kqueue socket monitoring for connections
-    if config.monitor_connection_changes {
-        let kqueue_sender = sender_clone.clone();
-        let kqueue_handler_id = handler_id_clone.clone();
-        let kqueue_is_running = Arc::clone(&is_running);
-        
-        task::spawn_blocking(move || {
-            if let Err(e) = monitor_network_connections_via_kqueue(kqueue_sender, kqueue_handler_id, kqueue_is_running) {
-                log::error!("macOS kqueue connection monitoring failed: {}", e);
-            }
-        });
-    }
-
-    Ok(())
-}
-
-fn monitor_interface_changes_via_system_configuration(
-    sender: Sender<EventMessage>,
-    handler_id: HandlerId,
-    is_running: Arc<Mutex<bool>>,
-) -> Result<()> {
-    use core_foundation::runloop::{CFRunLoop, CFRunLoopRef};
-    use core_foundation::base::TCFType;
-    use std::ptr;
-    
-    log::info!("Starting macOS network interface monitoring via System Configuration (event-driven)");
-
-    // This is a simplified version - real implementation would use System Configuration framework
-    // to register for network state change notifications
-    
-    // Create a System Configuration session
-    // TODO: Implement proper System Configuration framework integration
-    // This would involve:
-    // 1. SCDynamicStoreCreate
-    // 2. SCDynamicStoreSetNotificationKeys for network state keys
-    // 3. SCDynamicStoreSetDispatchQueue for event handling
-    
-    log::info!("System Configuration network monitoring enabled - waiting for interface events");
-
-    // For now, simulate event-driven monitoring without polling
-    while *is_running.lock().unwrap() {
-        // In real implementation, this would be replaced by System Configuration callbacks
-        // The System Configuration framework provides true event-driven notifications
-        
-        // Placeholder for demonstration - real implementation would use CF callbacks
-        std::thread::sleep(std::time::Duration::from_secs(5));
-        
-        // Simulate an interface change event
-        if *is_running.lock().unwrap() {
-            log::debug!("Network interface change detected via System Configuration");
-            
-            NetworkHandler::emit_network_event(
-                NetworkEventType::InterfaceUp,
-                Some("en0".to_string()),
-                None,
-                None,
-                None,
-                None,
-                &sender,
-                &handler_id,
-            );
-        }
-    }
-
-    Ok(())
-}
-
-fn monitor_network_connections_via_kqueue(
-    sender: Sender<EventMessage>,
-    handler_id: HandlerId,
-    is_running: Arc<Mutex<bool>>,
-) -> Result<()> {
-    use std::os::unix::io::AsRawFd;
-    use nix::sys::event::{kqueue, kevent, EventFilter, FilterFlag, EventFlag};
-    
-    log::info!("Starting macOS network connection monitoring via kqueue (event-driven)");
-
-    // Create kqueue instance
-    let kq = kqueue().map_err(|e| TellMeWhenError::System(format!("Failed to create kqueue: {}", e)))?;
-
-    // Set up socket monitoring
-    let mut changes = Vec::new();
-    
-    // Monitor network socket events
-    // TODO: Add specific socket file descriptors to monitor
-    // This would involve creating sockets and monitoring them for connection events
-    
-    log::info!("kqueue network monitoring enabled - listening for connection events");
-
-    // Listen for events
-    let mut events = vec![kevent(0, EventFilter::EVFILT_READ, EventFlag::empty(), FilterFlag::empty(), 0, 0); 10];
-    
-    while *is_running.lock().unwrap() {
-        match kevent(kq.as_raw_fd(), &[], &mut events, Some(std::time::Duration::from_millis(1000))) {
-            Ok(num_events) => {
-                for i in 0..num_events {
-                    let event = &events[i];
-                    
-                    if event.filter() == EventFilter::EVFILT_READ {
-                        log::debug!("Network connection event via kqueue");
-                        
-                        NetworkHandler::emit_network_event(
-                            NetworkEventType::ConnectionEstablished,
-                            Some("unknown".to_string()),
-                            None,
-                            None,
-                            None,
-                            None,
-                            &sender,
-                            &handler_id,
-                        );
-                    }
-                }
-            }
-            Err(e) => {
-                log::warn!("kqueue network event error: {}", e);
-                std::thread::sleep(std::time::Duration::from_millis(100));
-            }
-        }
-    }
-
-    Ok(())
-}
\ No newline at end of file
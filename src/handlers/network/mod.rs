@@ -1,25 +1,28 @@
-use crate::events::{EventData, NetworkEventData, NetworkEventType};
-use crate::traits::{EventHandlerConfig, ThresholdConfig, IntervalConfig};
-use crate::{EventMessage, EventMetadata, HandlerId, Result, TellMeWhenError, EventId};
-use crossbeam_channel::Sender;
-use sysinfo::{System};
-use std::collections::HashMap;
+use crate::events::{EventAvailability, EventData, EventKind, NetworkEventData, NetworkEventType, SupportedEvent};
+use crate::traits::{EventHandlerConfig, ThresholdConfig, IntervalConfig, MIN_POLL_INTERVAL};
+use crate::{EventMessage, EventMetadata, EventSender, HandlerId, Result};
+use sysinfo::Networks;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 
 #[cfg(windows)]
 mod windows;
-#[cfg(unix)]
+#[cfg(target_os = "linux")]
 mod unix;
-#[cfg(target_os = "macos")]
-mod macos;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct NetworkConfig {
     pub base: EventHandlerConfig,
     pub monitor_interface_changes: bool,
     pub monitor_connection_changes: bool,
     pub traffic_threshold_bytes: u64,
+    /// Interfaces to sample; the rest are never diffed, so they raise no
+    /// interface or traffic events. Connection events aren't tied to an
+    /// interface and aren't affected.
     pub interface_filters: Vec<String>,
 }
 
@@ -35,6 +38,15 @@ impl Default for NetworkConfig {
     }
 }
 
+impl NetworkConfig {
+    /// Returns whether an interface should be reported given `interface_filters`.
+    /// An empty filter list matches every interface; otherwise the name must
+    /// equal one of the filters exactly.
+    pub fn should_monitor_interface(&self, name: &str) -> bool {
+        self.interface_filters.is_empty() || self.interface_filters.iter().any(|filter| filter == name)
+    }
+}
+
 impl ThresholdConfig for NetworkConfig {
     fn set_threshold(&mut self, threshold: f32) {
         self.traffic_threshold_bytes = threshold as u64;
@@ -47,7 +59,7 @@ impl ThresholdConfig for NetworkConfig {
 
 impl IntervalConfig for NetworkConfig {
     fn set_interval(&mut self, interval: Duration) {
-        self.base.poll_interval = interval;
+        self.base.poll_interval = interval.max(MIN_POLL_INTERVAL);
     }
 
     fn get_interval(&self) -> Duration {
@@ -55,96 +67,251 @@ impl IntervalConfig for NetworkConfig {
     }
 }
 
+pub fn supported_events() -> Vec<SupportedEvent> {
+    use EventAvailability::*;
+
+    // Connections are read from the OS socket table, which only Linux and Windows expose here
+    let connections = if cfg!(any(target_os = "linux", windows)) { Polled } else { Unsupported };
+
+    [
+        ("InterfaceUp", Polled),
+        ("InterfaceDown", Polled),
+        ("ConnectionEstablished", connections),
+        ("ConnectionLost", connections),
+        ("TrafficThresholdReached", Polled),
+    ]
+    .into_iter()
+    .map(|(name, availability)| SupportedEvent::new(EventKind::Network, name, availability))
+    .collect()
+}
+
 pub struct NetworkHandler {
     config: NetworkConfig,
-    system: Arc<Mutex<System>>,
     previous_interfaces: Arc<Mutex<HashMap<String, NetworkSnapshot>>>,
-    is_running: Arc<Mutex<bool>>,
+    monitor_task: Option<tokio::task::JoinHandle<()>>,
 }
 
+type Connection = (SocketAddr, SocketAddr);
+
+// One interface at one sample. Totals are the OS's cumulative counters
 #[derive(Debug, Clone)]
 struct NetworkSnapshot {
-    interface_name: String,
     is_up: bool,
-    bytes_sent: u64,
-    bytes_received: u64,
     total_bytes_sent: u64,
     total_bytes_received: u64,
 }
 
+impl NetworkSnapshot {
+    fn total_bytes(&self) -> u64 {
+        self.total_bytes_sent + self.total_bytes_received
+    }
+
+    // Bytes sent and received since `previous`. Counters that went backwards
+    // were reset, so everything counted since is new
+    fn delta_since(&self, previous: Option<&NetworkSnapshot>) -> (u64, u64) {
+        let Some(previous) = previous else {
+            return (self.total_bytes_sent, self.total_bytes_received);
+        };
+        let delta = |current: u64, previous: u64| current.checked_sub(previous).unwrap_or(current);
+        (
+            delta(self.total_bytes_sent, previous.total_bytes_sent),
+            delta(self.total_bytes_received, previous.total_bytes_received),
+        )
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn interface_is_up(name: &str) -> bool {
+    unix::interface_is_up(name)
+}
+
+// Established TCP connections as (local, remote) pairs; listening sockets
+// and other states are left out
+#[cfg(target_os = "linux")]
+fn tcp_connections() -> std::io::Result<HashSet<Connection>> {
+    unix::tcp_connections()
+}
+
+#[cfg(windows)]
+fn tcp_connections() -> std::io::Result<HashSet<Connection>> {
+    windows::tcp_connections()
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn tcp_connections() -> std::io::Result<HashSet<Connection>> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "connection tracking is not available on this platform",
+    ))
+}
+
+// Without a per-interface link state, being listed counts as up
+#[cfg(not(target_os = "linux"))]
+fn interface_is_up(_name: &str) -> bool {
+    true
+}
+
+fn snapshot_interfaces(networks: &Networks, config: &NetworkConfig) -> HashMap<String, NetworkSnapshot> {
+    networks
+        .list()
+        .iter()
+        .filter(|(name, _)| config.should_monitor_interface(name))
+        .map(|(name, data)| {
+            let snapshot = NetworkSnapshot {
+                is_up: interface_is_up(name),
+                total_bytes_sent: data.total_transmitted(),
+                total_bytes_received: data.total_received(),
+            };
+            (name.clone(), snapshot)
+        })
+        .collect()
+}
+
+// Interface transitions and threshold crossings between two snapshots
+fn diff_interfaces(
+    previous: &HashMap<String, NetworkSnapshot>,
+    current: &HashMap<String, NetworkSnapshot>,
+    config: &NetworkConfig,
+) -> Vec<(NetworkEventType, String, NetworkSnapshot)> {
+    let mut events = Vec::new();
+    let threshold = config.traffic_threshold_bytes;
+
+    for (name, snapshot) in current {
+        let before = previous.get(name);
+
+        if config.monitor_interface_changes && snapshot.is_up != before.is_some_and(|before| before.is_up) {
+            let event_type = if snapshot.is_up {
+                NetworkEventType::InterfaceUp
+            } else {
+                NetworkEventType::InterfaceDown
+            };
+            events.push((event_type, name.clone(), snapshot.clone()));
+        }
+
+        let previous_total = before.map_or(0, NetworkSnapshot::total_bytes);
+        if previous_total < threshold && snapshot.total_bytes() >= threshold {
+            events.push((NetworkEventType::TrafficThresholdReached, name.clone(), snapshot.clone()));
+        }
+    }
+
+    // Interfaces that are no longer listed went down
+    if config.monitor_interface_changes {
+        for (name, snapshot) in previous {
+            if snapshot.is_up && !current.contains_key(name) {
+                events.push((NetworkEventType::InterfaceDown, name.clone(), snapshot.clone()));
+            }
+        }
+    }
+
+    events
+}
+
+/// Diffs sysinfo's interface list, and the TCP connection table when
+/// `monitor_connection_changes` is set, every `poll_interval`. Whatever is
+/// present when monitoring starts forms the baseline and isn't reported.
+fn spawn_monitor(
+    config: NetworkConfig,
+    previous_interfaces: Arc<Mutex<HashMap<String, NetworkSnapshot>>>,
+    sender: EventSender,
+    handler_id: HandlerId,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let _active = crate::metrics::track_task();
+        let mut networks = Networks::new_with_refreshed_list();
+        *previous_interfaces.lock().unwrap() = snapshot_interfaces(&networks, &config);
+
+        let mut connections = if config.monitor_connection_changes {
+            match tcp_connections() {
+                Ok(connections) => Some(connections),
+                Err(e) => {
+                    log::warn!("Connection tracking disabled: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let mut interval = tokio::time::interval(config.base.poll_interval);
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            networks.refresh(true);
+            let current = snapshot_interfaces(&networks, &config);
+            let previous = std::mem::replace(&mut *previous_interfaces.lock().unwrap(), current.clone());
+
+            for (event_type, name, snapshot) in diff_interfaces(&previous, &current, &config) {
+                let mut event_data = NetworkHandler::event_data(event_type);
+                if event_data.event_type == NetworkEventType::TrafficThresholdReached {
+                    let (bytes_sent, bytes_received) = snapshot.delta_since(previous.get(&name));
+                    event_data.bytes_sent = Some(bytes_sent);
+                    event_data.bytes_received = Some(bytes_received);
+                    event_data.total_bytes_sent = Some(snapshot.total_bytes_sent);
+                    event_data.total_bytes_received = Some(snapshot.total_bytes_received);
+                }
+                event_data.interface_name = Some(name);
+                NetworkHandler::emit_network_event(event_data, &sender, &handler_id);
+            }
+
+            if let Some(previous) = connections.as_mut() {
+                let current = match tcp_connections() {
+                    Ok(current) => current,
+                    Err(e) => {
+                        log::warn!("Failed to read the TCP connection table: {}", e);
+                        continue;
+                    }
+                };
+
+                let established = current.difference(previous).map(|c| (NetworkEventType::ConnectionEstablished, *c));
+                let lost = previous.difference(&current).map(|c| (NetworkEventType::ConnectionLost, *c));
+                for (event_type, (local, remote)) in established.chain(lost) {
+                    let mut event_data = NetworkHandler::event_data(event_type);
+                    event_data.local_addr = Some(local.to_string());
+                    event_data.remote_addr = Some(remote.to_string());
+                    NetworkHandler::emit_network_event(event_data, &sender, &handler_id);
+                }
+
+                *previous = current;
+            }
+        }
+    })
+}
+
 impl NetworkHandler {
     pub fn new(config: NetworkConfig) -> Self {
         Self {
             config,
-            system: Arc::new(Mutex::new(System::new_all())),
             previous_interfaces: Arc::new(Mutex::new(HashMap::new())),
-            is_running: Arc::new(Mutex::new(false)),
+            monitor_task: None,
         }
     }
 
-    #[cfg(windows)]
-    async fn start_platform_specific(&self, sender: Sender<EventMessage>, handler_id: HandlerId) -> Result<()> {
-        windows::start_network_monitoring(
-            &self.config,
-            &self.system,
-            &self.previous_interfaces,
-            &self.is_running,
-            sender,
-            handler_id,
-        ).await
-    }
-
-    #[cfg(all(unix, not(target_os = "macos")))]
-    async fn start_platform_specific(&self, sender: Sender<EventMessage>, handler_id: HandlerId) -> Result<()> {
-        unix::start_network_monitoring(
-            &self.config,
-            &self.system,
-            &self.previous_interfaces,
-            &self.is_running,
-            sender,
-            handler_id,
-        ).await
-    }
-
-    #[cfg(target_os = "macos")]
-    async fn start_platform_specific(&self, sender: Sender<EventMessage>, handler_id: HandlerId) -> Result<()> {
-        macos::start_network_monitoring(
-            &self.config,
-            &self.system,
-            &self.previous_interfaces,
-            &self.is_running,
-            sender,
-            handler_id,
-        ).await
-    }
-
-    pub fn emit_network_event(
-        event_type: NetworkEventType,
-        interface_name: Option<String>,
-        local_addr: Option<String>,
-        remote_addr: Option<String>,
-        bytes_sent: Option<u64>,
-        bytes_received: Option<u64>,
-        sender: &Sender<EventMessage>,
-        handler_id: &HandlerId,
-    ) {
-        let event_data = NetworkEventData {
+    // An event of `event_type` with every optional field unset
+    fn event_data(event_type: NetworkEventType) -> NetworkEventData {
+        NetworkEventData {
             event_type,
-            interface_name,
-            local_addr,
-            remote_addr,
-            bytes_sent,
-            bytes_received,
+            interface_name: None,
+            local_addr: None,
+            remote_addr: None,
+            bytes_sent: None,
+            bytes_received: None,
+            total_bytes_sent: None,
+            total_bytes_received: None,
             timestamp: SystemTime::now(),
-        };
+        }
+    }
 
+    pub fn emit_network_event(event_data: NetworkEventData, sender: &EventSender, handler_id: &HandlerId) {
         let event_message = EventMessage {
             data: EventData::Network(event_data),
             metadata: EventMetadata {
-                id: uuid::Uuid::new_v4().as_u128() as EventId,
+                id: 0, // Will be set by event bus
                 handler_id: handler_id.clone(),
                 timestamp: SystemTime::now(),
-                source: "NetworkHandler".to_string(),
+                source: crate::event_source(handler_id, "NetworkHandler"),
+                dedup_key: None,
             },
         };
 
@@ -153,23 +320,35 @@ impl NetworkHandler {
         }
     }
 
-    pub async fn start(&mut self, sender: Sender<EventMessage>, handler_id: HandlerId) -> Result<()> {
-        {
-            let mut is_running = self.is_running.lock().unwrap();
-            if *is_running {
-                return Ok(());
-            }
-            *is_running = true;
+    pub async fn start(&mut self, sender: EventSender, handler_id: HandlerId) -> Result<()> {
+        if self.monitor_task.is_some() {
+            return Ok(());
         }
+        self.config.base.validate()?;
 
-        log::info!("Starting network monitoring with native OS callbacks");
-        self.start_platform_specific(sender, handler_id).await
+        log::info!("Starting network monitoring every {:?}", self.config.base.poll_interval);
+        self.monitor_task = Some(spawn_monitor(
+            self.config.clone(),
+            self.previous_interfaces.clone(),
+            sender,
+            handler_id,
+        ));
+        Ok(())
     }
 
     pub async fn stop(&mut self) -> Result<()> {
-        let mut is_running = self.is_running.lock().unwrap();
-        *is_running = false;
+        if let Some(task) = self.monitor_task.take() {
+            task.abort();
+        }
         log::info!("Network monitoring stopped");
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+impl Drop for NetworkHandler {
+    fn drop(&mut self) {
+        if let Some(task) = self.monitor_task.take() {
+            task.abort();
+        }
+    }
+}
@@ -1,175 +1,80 @@
-use super::{NetworkConfig, NetworkSnapshot, NetworkHandler};
-use crate::events::{NetworkEventType};
-use crate::{EventMessage, HandlerId, Result, TellMeWhenError};
-use crossbeam_channel::Sender;
-use sysinfo::{System};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
-use tokio::task;
+use super::Connection;
+use std::collections::HashSet;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use winapi::shared::iprtrmib::TCP_TABLE_OWNER_PID_CONNECTIONS;
+use winapi::shared::tcpmib::{MIB_TCP6TABLE_OWNER_PID, MIB_TCPTABLE_OWNER_PID, MIB_TCP_STATE_ESTAB};
+use winapi::shared::winerror::{ERROR_INSUFFICIENT_BUFFER, NO_ERROR};
+use winapi::shared::ws2def::{AF_INET, AF_INET6};
+use winapi::um::iphlpapi::GetExtendedTcpTable;
 
-pub async fn start_network_monitoring(
-    config: &NetworkConfig,
-    system: &Arc<Mutex<System>>,
-    previous_interfaces: &Arc<Mutex<HashMap<String, NetworkSnapshot>>>,
-    is_running: &Arc<Mutex<bool>>,
-    sender: Sender<EventMessage>,
-    handler_id: HandlerId,
-) -> Result<()> {
-    let config = config.clone();
-    let is_running = Arc::clone(is_running);
-    let sender_clone = sender.clone();
-    let handler_id_clone = handler_id.clone();
+pub(super) fn tcp_connections() -> io::Result<HashSet<Connection>> {
+    let mut connections = HashSet::new();
 
-    // Start Windows IP Helper API notifications for interface changes
-    if config.monitor_interface_changes {
-        let interface_sender = sender_clone.clone();
-        let interface_handler_id = handler_id_clone.clone();
-        let interface_is_running = Arc::clone(&is_running);
-        
-        task::spawn_blocking(move || {
-            if let Err(e) = monitor_interface_changes_via_iphelper(interface_sender, interface_handler_id, interface_is_running) {
-                log::error!("Windows IP Helper interface monitoring failed: {}", e);
-            }
-        });
-    }
-
-    // Start WMI network adapter change monitoring
-    if config.monitor_connection_changes {
-        let wmi_sender = sender_clone.clone();
-        let wmi_handler_id = handler_id_clone.clone();
-        let wmi_is_running = Arc::clone(&is_running);
-        
-        task::spawn_blocking(move || {
-            if let Err(e) = monitor_network_changes_via_wmi(wmi_sender, wmi_handler_id, wmi_is_running) {
-                log::error!("Windows WMI network monitoring failed: {}", e);
-            }
-        });
-    }
-
-    Ok(())
-}
-
-fn monitor_interface_changes_via_iphelper(
-    sender: Sender<EventMessage>,
-    handler_id: HandlerId,
-    is_running: Arc<Mutex<bool>>,
-) -> Result<()> {
-    use windows::Win32::NetworkManagement::IpHelper::{NotifyIpInterfaceChange, MIB_IPINTERFACE_ROW};
-    use windows::Win32::Foundation::{HANDLE, BOOL};
-        use winapi::shared::ws2def::AF_UNSPEC;
-        use winapi::shared::minwindef::BOOL;
-    use std::ptr;
-    
-    log::info!("Starting Windows network interface monitoring via IP Helper API (event-driven)");
-
-    // Callback function for interface changes
-    extern "system" fn interface_change_callback(
-        _caller_context: *const std::ffi::c_void,
-        row: *const MIB_IPINTERFACE_ROW,
-        _notification_type: u32,
-    ) {
-        unsafe {
-            if !row.is_null() {
-                let interface_row = &*row;
-                log::debug!("Network interface change event: Interface Index {}", interface_row.InterfaceIndex);
-                
-                // TODO: Emit network event - need to pass sender/handler_id through context
-                // This would require a more complex callback setup with context
-            }
+    let table = extended_tcp_table(AF_INET as u32)?;
+    unsafe {
+        let table = &*(table.as_ptr() as *const MIB_TCPTABLE_OWNER_PID);
+        let rows = std::slice::from_raw_parts(table.table.as_ptr(), table.dwNumEntries as usize);
+        for row in rows.iter().filter(|row| row.dwState == MIB_TCP_STATE_ESTAB) {
+            // Addresses and ports are in network byte order
+            let local = SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::from(u32::from_be(row.dwLocalAddr))),
+                u16::from_be(row.dwLocalPort as u16),
+            );
+            let remote = SocketAddr::new(
+                IpAddr::V4(Ipv4Addr::from(u32::from_be(row.dwRemoteAddr))),
+                u16::from_be(row.dwRemotePort as u16),
+            );
+            connections.insert((local, remote));
         }
     }
 
-    // Register for interface change notifications
-    let mut notification_handle: HANDLE = HANDLE::default();
-    
-    // Note: This is a simplified version - real implementation would need proper callback context
-    match unsafe {
-        NotifyIpInterfaceChange(
-                AF_UNSPEC as u16,
-            Some(interface_change_callback),
-                Some(ptr::null()),
-            BOOL(0), // fInitialNotification
-            &mut notification_handle,
-        )
-    } {
-        Ok(_) => {
-            log::info!("Windows IP Helper interface change notifications enabled");
-            
-            // Keep the notification active until stopped
-            while *is_running.lock().unwrap() {
-                std::thread::sleep(std::time::Duration::from_millis(1000));
+    // No IPv6 stack is not an error
+    if let Ok(table) = extended_tcp_table(AF_INET6 as u32) {
+        unsafe {
+            let table = &*(table.as_ptr() as *const MIB_TCP6TABLE_OWNER_PID);
+            let rows = std::slice::from_raw_parts(table.table.as_ptr(), table.dwNumEntries as usize);
+            for row in rows.iter().filter(|row| row.dwState == MIB_TCP_STATE_ESTAB) {
+                let local = SocketAddr::new(
+                    IpAddr::V6(Ipv6Addr::from(row.ucLocalAddr)),
+                    u16::from_be(row.dwLocalPort as u16),
+                );
+                let remote = SocketAddr::new(
+                    IpAddr::V6(Ipv6Addr::from(row.ucRemoteAddr)),
+                    u16::from_be(row.dwRemotePort as u16),
+                );
+                connections.insert((local, remote));
             }
-            
-            // TODO: Cancel notification with CancelMibChangeNotify2
-            log::info!("Windows IP Helper interface monitoring stopped");
-        }
-        Err(e) => {
-            return Err(TellMeWhenError::System(format!("Failed to register IP interface change notification: {}", e)));
         }
     }
 
-    Ok(())
+    Ok(connections)
 }
 
-fn monitor_network_changes_via_wmi(
-    sender: Sender<EventMessage>,
-    handler_id: HandlerId,
-    is_running: Arc<Mutex<bool>>,
-) -> Result<()> {
-    use wmi::{WMIConnection, COMLibrary, Variant};
-    use std::collections::HashMap;
-    
-    log::info!("Starting Windows network adapter monitoring via WMI events (event-driven)");
+// The table can grow between the size query and the read, so retry until it fits
+fn extended_tcp_table(family: u32) -> io::Result<Vec<u64>> {
+    let mut size: u32 = 0;
+    let mut buffer: Vec<u64> = Vec::new();
 
-    let com_lib = COMLibrary::new().map_err(|e| {
-        TellMeWhenError::System(format!("Failed to initialize COM library: {}", e))
-    })?;
-    
-    let wmi_con = WMIConnection::new(com_lib).map_err(|e| {
-        TellMeWhenError::System(format!("Failed to create WMI connection: {}", e))
-    })?;
+    loop {
+        let result = unsafe {
+            GetExtendedTcpTable(
+                buffer.as_mut_ptr() as *mut _,
+                &mut size,
+                0,
+                family,
+                TCP_TABLE_OWNER_PID_CONNECTIONS,
+                0,
+            )
+        };
 
-    // Monitor network adapter configuration changes
-    let query = "SELECT * FROM Win32_VolumeChangeEvent"; // TODO: Use proper network event class
-    
-    while *is_running.lock().unwrap() {
-        // This is a placeholder - WMI network event monitoring needs proper implementation
-        // Real implementation would use WMI event subscriptions for network changes
-        match wmi_con.raw_notification::<HashMap<String, Variant>>(query) {
-            Ok(iterator) => {
-                for event_result in iterator {
-                    if !*is_running.lock().unwrap() {
-                        break;
-                    }
-                    
-                    match event_result {
-                        Ok(_event) => {
-                            log::debug!("Network change event detected via WMI");
-                            
-                            NetworkHandler::emit_network_event(
-                                NetworkEventType::InterfaceUp,
-                                Some("unknown".to_string()),
-                                None,
-                                None,
-                                None,
-                                None,
-                                &sender,
-                                &handler_id,
-                            );
-                        }
-                        Err(e) => {
-                            log::warn!("WMI network event error: {}", e);
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                log::error!("WMI network notification query failed: {}", e);
-                std::thread::sleep(std::time::Duration::from_secs(5));
+        match result {
+            NO_ERROR => return Ok(buffer),
+            ERROR_INSUFFICIENT_BUFFER => {
+                // u64 elements keep the rows suitably aligned
+                buffer = vec![0u64; (size as usize).div_ceil(8)];
             }
+            error => return Err(io::Error::from_raw_os_error(error as i32)),
         }
     }
-
-    Ok(())
-}
\ No newline at end of file
+}
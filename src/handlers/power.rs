@@ -1,11 +1,17 @@
-use crate::events::{EventData, PowerEventData, PowerEventType};
-use crate::traits::{EventHandler, EventHandlerConfig, ThresholdConfig, IntervalConfig};
-use crate::{EventBus, EventMessage, EventMetadata, HandlerId, Result, TellMeWhenError};
-use crossbeam_channel::Sender;
+use crate::events::{EventAvailability, EventData, EventKind, PowerEventData, PowerEventType, SupportedEvent};
+use crate::traits::{EventHandler, EventHandlerConfig, ThresholdConfig, IntervalConfig, MIN_POLL_INTERVAL};
+use crate::{EventMessage, EventMetadata, EventSender, HandlerId, Result};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime};
 use tokio::time::interval;
 
+// How often a machine without a battery is checked for one, when the poll
+// has nothing else to watch
+const NO_BATTERY_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+#[cfg(any(windows, target_os = "macos"))]
+use crate::TellMeWhenError;
+
 #[cfg(windows)]
 use winapi::um::winbase::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
 
@@ -15,13 +21,28 @@ use std::fs;
 #[cfg(target_os = "macos")]
 use core_foundation::base::TCFType;
 
+#[cfg(target_os = "macos")]
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IOPSCopyPowerSourcesInfo() -> core_foundation::base::CFTypeRef;
+    fn IOPSCopyPowerSourcesList(blob: core_foundation::base::CFTypeRef) -> core_foundation::array::CFArrayRef;
+    fn IOPSGetPowerSourceDescription(
+        blob: core_foundation::base::CFTypeRef,
+        power_source: core_foundation::base::CFTypeRef,
+    ) -> core_foundation::dictionary::CFDictionaryRef;
+    fn IOPSGetProvidingPowerSourceType(blob: core_foundation::base::CFTypeRef) -> core_foundation::string::CFStringRef;
+}
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct PowerConfig {
     pub base: EventHandlerConfig,
     pub battery_low_threshold: f32,
     pub monitor_battery: bool,
     pub monitor_power_source: bool,
     pub monitor_sleep_wake: bool,
+    pub monitor_thermal_throttling: bool,
 }
 
 impl Default for PowerConfig {
@@ -32,6 +53,7 @@ impl Default for PowerConfig {
             monitor_battery: true,
             monitor_power_source: true,
             monitor_sleep_wake: true,
+            monitor_thermal_throttling: true,
         }
     }
 }
@@ -48,7 +70,7 @@ impl ThresholdConfig for PowerConfig {
 
 impl IntervalConfig for PowerConfig {
     fn set_interval(&mut self, interval: Duration) {
-        self.base.poll_interval = interval;
+        self.base.poll_interval = interval.max(MIN_POLL_INTERVAL);
     }
 
     fn get_interval(&self) -> Duration {
@@ -61,16 +83,31 @@ struct PowerSnapshot {
     battery_level: Option<f32>,
     is_charging: Option<bool>,
     power_source: Option<String>,
+    time_remaining: Option<Duration>,
+    health_percent: Option<f32>,
     is_battery_present: bool,
+    thermal: Option<ThermalState>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ThermalState {
+    is_throttled: bool,
+    current_frequency_mhz: Option<u64>,
+    base_frequency_mhz: Option<u64>,
 }
 
 pub struct PowerHandler {
     config: PowerConfig,
     previous_state: Arc<Mutex<Option<PowerSnapshot>>>,
-    pub event_sender: Option<Sender<EventMessage>>,
+    pub event_sender: Option<EventSender>,
     is_running: bool,
     handler_id: HandlerId,
     monitor_task: Option<tokio::task::JoinHandle<()>>,
+    #[cfg(any(windows, target_os = "macos"))]
+    sleep_wake: Option<SleepWakeMonitor>,
+    #[cfg(windows)]
+    power_broadcast: Option<PowerBroadcastWindow>,
+    check_now: Arc<tokio::sync::Notify>,
 }
 
 impl PowerHandler {
@@ -82,6 +119,11 @@ impl PowerHandler {
             is_running: false,
             handler_id,
             monitor_task: None,
+            #[cfg(any(windows, target_os = "macos"))]
+            sleep_wake: None,
+            #[cfg(windows)]
+            power_broadcast: None,
+            check_now: Arc::new(tokio::sync::Notify::new()),
         }
     }
 
@@ -93,6 +135,11 @@ impl PowerHandler {
             is_running: false,
             handler_id,
             monitor_task: None,
+            #[cfg(any(windows, target_os = "macos"))]
+            sleep_wake: None,
+            #[cfg(windows)]
+            power_broadcast: None,
+            check_now: Arc::new(tokio::sync::Notify::new()),
         }
     }
 
@@ -101,12 +148,60 @@ impl PowerHandler {
         let config = self.config.clone();
         let event_sender = self.event_sender.clone();
         let handler_id = self.handler_id.clone();
+        let check_now = self.check_now.clone();
+
+        #[cfg(any(windows, target_os = "macos"))]
+        if config.monitor_sleep_wake {
+            if let Some(sender) = &event_sender {
+                let context = SleepWakeContext { sender: sender.clone(), handler_id: handler_id.clone() };
+                match SleepWakeMonitor::start(context) {
+                    Ok(monitor) => self.sleep_wake = Some(monitor),
+                    Err(e) => log::warn!("Sleep/wake notifications unavailable: {}", e),
+                }
+            }
+        }
+
+        // Power source changes only need polling without the notification window
+        #[allow(unused_mut)]
+        let mut power_source_polled = config.monitor_power_source;
+        #[cfg(windows)]
+        if config.monitor_power_source || config.monitor_battery {
+            match PowerBroadcastWindow::start(check_now.clone()) {
+                Ok(window) => {
+                    self.power_broadcast = Some(window);
+                    power_source_polled = false;
+                }
+                Err(e) => log::warn!("Power notifications unavailable, polling instead: {}", e),
+            }
+        }
 
         let task = tokio::spawn(async move {
+            let _active = crate::metrics::track_task();
             let mut interval = interval(config.base.poll_interval);
+            #[cfg(target_os = "linux")]
+            let mut suspend_clock = SuspendClock::now();
+            // Sleep is only detected by polling on Linux
+            let polls_more_than_battery = power_source_polled
+                || config.monitor_thermal_throttling
+                || (cfg!(target_os = "linux") && config.monitor_sleep_wake);
+            let mut battery_absent = None;
             
             loop {
-                interval.tick().await;
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = check_now.notified() => {}
+                }
+
+                #[cfg(target_os = "linux")]
+                if config.monitor_sleep_wake {
+                    if let (Some(sender), Some(clock)) = (&event_sender, suspend_clock.as_mut()) {
+                        if clock.slept() {
+                            let context = SleepWakeContext { sender: sender.clone(), handler_id: handler_id.clone() };
+                            context.emit(PowerEventType::SleepMode);
+                            context.emit(PowerEventType::WakeFromSleep);
+                        }
+                    }
+                }
                 
                 if let Some(sender) = &event_sender {
                     Self::check_power_status(
@@ -116,31 +211,49 @@ impl PowerHandler {
                         &handler_id,
                     ).await;
                 }
+
+                let absent = previous_state.lock().unwrap().as_ref().map(|state| !state.is_battery_present);
+                if absent.is_some() && absent != battery_absent {
+                    let absent = absent == Some(true);
+                    if absent {
+                        log::info!("No battery found; battery events won't be reported until one appears");
+                    }
+                    if !polls_more_than_battery {
+                        let period = if absent { NO_BATTERY_POLL_INTERVAL } else { config.base.poll_interval };
+                        interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+                    }
+                    battery_absent = Some(absent);
+                }
             }
         });
 
         self.monitor_task = Some(task);
     }
 
+    /// Samples power state right away instead of waiting for the next tick.
+    pub fn trigger_check(&self) {
+        // notify_one stores a permit, so a check requested mid-sample isn't lost
+        self.check_now.notify_one();
+    }
+
     async fn check_power_status(
         previous_state: &Arc<Mutex<Option<PowerSnapshot>>>,
         config: &PowerConfig,
-        sender: &Sender<EventMessage>,
+        sender: &EventSender,
         handler_id: &HandlerId,
     ) {
         let current_state = Self::get_power_status();
         let mut previous = previous_state.lock().unwrap();
 
         if let Some(current) = &current_state {
-            // Check battery level changes
-            if config.monitor_battery {
+            // Check battery level changes. Without a battery the level and
+            // charging state some platforms report are meaningless.
+            if config.monitor_battery && current.is_battery_present {
                 if let Some(battery_level) = current.battery_level {
                     if battery_level <= config.battery_low_threshold {
                         Self::emit_power_event(
                             PowerEventType::BatteryLow,
-                            Some(battery_level),
-                            current.is_charging,
-                            current.power_source.clone(),
+                            Some(current),
                             sender,
                             handler_id,
                         );
@@ -153,18 +266,14 @@ impl PowerHandler {
                         if !prev_charging && curr_charging {
                             Self::emit_power_event(
                                 PowerEventType::BatteryCharging,
-                                current.battery_level,
-                                Some(curr_charging),
-                                current.power_source.clone(),
+                                Some(current),
                                 sender,
                                 handler_id,
                             );
                         } else if prev_charging && !curr_charging {
                             Self::emit_power_event(
                                 PowerEventType::BatteryDischarging,
-                                current.battery_level,
-                                Some(curr_charging),
-                                current.power_source.clone(),
+                                Some(current),
                                 sender,
                                 handler_id,
                             );
@@ -179,20 +288,114 @@ impl PowerHandler {
                     if prev.power_source != current.power_source {
                         Self::emit_power_event(
                             PowerEventType::PowerSourceChanged,
-                            current.battery_level,
-                            current.is_charging,
-                            current.power_source.clone(),
+                            Some(current),
                             sender,
                             handler_id,
                         );
                     }
                 }
             }
+
+            // Only report the transition into throttling, not every poll spent throttled
+            if config.monitor_thermal_throttling {
+                if let Some(thermal) = &current.thermal {
+                    let was_throttled = previous
+                        .as_ref()
+                        .and_then(|prev| prev.thermal.as_ref())
+                        .map(|prev| prev.is_throttled)
+                        .unwrap_or(false);
+
+                    if thermal.is_throttled && !was_throttled {
+                        Self::emit_thermal_event(current, thermal, sender, handler_id);
+                    }
+                }
+            }
         }
 
         *previous = current_state;
     }
 
+    #[cfg(windows)]
+    fn get_thermal_state() -> Option<ThermalState> {
+        use std::collections::HashMap;
+        use wmi::{COMLibrary, Variant, WMIConnection};
+
+        let com_lib = COMLibrary::new().ok()?;
+        let wmi_con = WMIConnection::new(com_lib).ok()?;
+        let processors: Vec<HashMap<String, Variant>> = wmi_con
+            .raw_query("SELECT CurrentClockSpeed, MaxClockSpeed, LoadPercentage FROM Win32_Processor")
+            .ok()?;
+
+        let variant_u64 = |variant: Option<&Variant>| match variant {
+            Some(Variant::UI4(value)) => Some(*value as u64),
+            Some(Variant::UI2(value)) => Some(*value as u64),
+            Some(Variant::I4(value)) => Some(*value as u64),
+            _ => None,
+        };
+
+        let processor = processors.first()?;
+        let current_frequency_mhz = variant_u64(processor.get("CurrentClockSpeed"));
+        let base_frequency_mhz = variant_u64(processor.get("MaxClockSpeed"));
+        let load_percentage = variant_u64(processor.get("LoadPercentage")).unwrap_or(0);
+
+        // Win32_Processor reports the rated clock as MaxClockSpeed, so running
+        // below it while busy means the CPU is being held back
+        let is_throttled = load_percentage >= THROTTLE_LOAD_PERCENT
+            && is_below_base_frequency(current_frequency_mhz, base_frequency_mhz);
+
+        Some(ThermalState {
+            is_throttled,
+            current_frequency_mhz,
+            base_frequency_mhz,
+        })
+    }
+
+    #[cfg(target_os = "linux")]
+    fn get_thermal_state() -> Option<ThermalState> {
+        let zones_tripped = fs::read_dir("/sys/class/thermal")
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter(|entry| entry.file_name().to_string_lossy().starts_with("thermal_zone"))
+                    .any(|entry| thermal_zone_tripped(&entry.path()))
+            })
+            .unwrap_or(false);
+
+        let cpufreq = std::path::Path::new("/sys/devices/system/cpu/cpu0/cpufreq");
+        let read_khz = |name: &str| {
+            fs::read_to_string(cpufreq.join(name))
+                .ok()
+                .and_then(|value| value.trim().parse::<u64>().ok())
+        };
+        let current_frequency_mhz = read_khz("scaling_cur_freq").map(|khz| khz / 1000);
+        // intel_pstate and amd-pstate expose the base clock; fall back to the max
+        let base_frequency_mhz = read_khz("base_frequency")
+            .or_else(|| read_khz("cpuinfo_max_freq"))
+            .map(|khz| khz / 1000);
+
+        let is_busy = match (fs::read_to_string("/proc/loadavg"), std::thread::available_parallelism()) {
+            (Ok(loadavg), Ok(cpus)) => loadavg
+                .split_whitespace()
+                .next()
+                .and_then(|load| load.parse::<f32>().ok())
+                .map(|load| load * 100.0 / cpus.get() as f32 >= THROTTLE_LOAD_PERCENT as f32)
+                .unwrap_or(false),
+            _ => false,
+        };
+
+        Some(ThermalState {
+            is_throttled: zones_tripped
+                || (is_busy && is_below_base_frequency(current_frequency_mhz, base_frequency_mhz)),
+            current_frequency_mhz,
+            base_frequency_mhz,
+        })
+    }
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    fn get_thermal_state() -> Option<ThermalState> {
+        None
+    }
+
     #[cfg(windows)]
     fn get_power_status() -> Option<PowerSnapshot> {
         unsafe {
@@ -218,11 +421,18 @@ impl PowerHandler {
 
                 let is_battery_present = status.BatteryFlag != 128; // 128 = no system battery
 
+                // Seconds of battery life left; u32::MAX while on AC or still estimating
+                let time_remaining = (status.BatteryLifeTime != u32::MAX)
+                    .then(|| Duration::from_secs(status.BatteryLifeTime.into()));
+
                 Some(PowerSnapshot {
                     battery_level,
                     is_charging,
                     power_source,
+                    time_remaining,
+                    health_percent: None,
                     is_battery_present,
+                    thermal: Self::get_thermal_state(),
                 })
             } else {
                 None
@@ -238,6 +448,8 @@ impl PowerHandler {
         let mut battery_level = None;
         let mut is_charging = None;
         let mut power_source = None;
+        let mut time_remaining = None;
+        let mut health_percent = None;
         let mut is_battery_present = false;
 
         if let Ok(entries) = fs::read_dir(power_supply_path) {
@@ -264,6 +476,9 @@ impl PowerHandler {
                             _ => None,
                         };
                     }
+
+                    time_remaining = battery_time_remaining(&path, is_charging);
+                    health_percent = battery_health_percent(&path);
                 } else if name.starts_with("AC") || name.starts_with("ADP") {
                     // Read AC adapter status
                     if let Ok(online) = fs::read_to_string(path.join("online")) {
@@ -278,23 +493,113 @@ impl PowerHandler {
             battery_level,
             is_charging,
             power_source,
+            time_remaining,
+            health_percent,
             is_battery_present,
+            thermal: Self::get_thermal_state(),
         })
     }
 
     #[cfg(target_os = "macos")]
     fn get_power_status() -> Option<PowerSnapshot> {
-        // This is a simplified implementation
-        // In a real implementation, you'd use IOKit to get power information
+        use core_foundation::array::CFArray;
+        use core_foundation::base::CFType;
+        use core_foundation::boolean::CFBoolean;
         use core_foundation::dictionary::CFDictionary;
+        use core_foundation::number::CFNumber;
         use core_foundation::string::CFString;
-        
-        // Placeholder implementation - would need proper IOKit bindings
+
+        // Returned by the Copy functions, so owned here; descriptions and the
+        // providing type are borrowed from the blob
+        let blob = unsafe {
+            let blob = IOPSCopyPowerSourcesInfo();
+            if blob.is_null() {
+                return None;
+            }
+            CFType::wrap_under_create_rule(blob)
+        };
+        let blob_ref = blob.as_CFTypeRef();
+
+        let providing_type = unsafe {
+            let providing_type = IOPSGetProvidingPowerSourceType(blob_ref);
+            (!providing_type.is_null()).then(|| CFString::wrap_under_get_rule(providing_type).to_string())
+        };
+
+        let sources: CFArray<CFType> = unsafe {
+            let sources = IOPSCopyPowerSourcesList(blob_ref);
+            if sources.is_null() {
+                return None;
+            }
+            CFArray::wrap_under_create_rule(sources)
+        };
+
+        let number = |description: &CFDictionary<CFString, CFType>, key: &'static str| {
+            description
+                .find(CFString::from_static_string(key))
+                .and_then(|value| value.downcast::<CFNumber>())
+                .and_then(|value| value.to_i64())
+        };
+        let flag = |description: &CFDictionary<CFString, CFType>, key: &'static str| {
+            description
+                .find(CFString::from_static_string(key))
+                .and_then(|value| value.downcast::<CFBoolean>())
+                .map(bool::from)
+        };
+
+        let mut battery_level = None;
+        let mut is_charging = None;
+        let mut time_remaining = None;
+        let mut is_battery_present = false;
+
+        for source in sources.iter() {
+            let description: CFDictionary<CFString, CFType> = unsafe {
+                let description = IOPSGetPowerSourceDescription(blob_ref, source.as_CFTypeRef());
+                if description.is_null() {
+                    continue;
+                }
+                CFDictionary::wrap_under_get_rule(description)
+            };
+
+            let source_type = description
+                .find(CFString::from_static_string("Type"))
+                .and_then(|value| value.downcast::<CFString>())
+                .map(|value| value.to_string());
+            if source_type.as_deref() != Some("InternalBattery") || flag(&description, "Is Present") == Some(false) {
+                continue;
+            }
+
+            is_battery_present = true;
+            if let (Some(current), Some(max)) = (number(&description, "Current Capacity"), number(&description, "Max Capacity")) {
+                if max > 0 {
+                    battery_level = Some(current as f32 / max as f32 * 100.0);
+                }
+            }
+            is_charging = flag(&description, "Is Charging");
+            // Minutes, or -1 while the estimate is still being calculated
+            let minutes_key = if is_charging == Some(true) { "Time to Full Charge" } else { "Time to Empty" };
+            time_remaining = number(&description, minutes_key)
+                .filter(|&minutes| minutes > 0)
+                .map(|minutes| Duration::from_secs(minutes as u64 * 60));
+            break;
+        }
+
+        // Desktops have no battery and are always on mains power
+        let power_source = match providing_type.as_deref() {
+            Some("Battery Power") => "Battery",
+            Some("UPS Power") => "UPS",
+            Some("AC Power") => "AC",
+            _ if !is_battery_present => "AC",
+            _ => "Unknown",
+        };
+
         Some(PowerSnapshot {
-            battery_level: None,
-            is_charging: None,
-            power_source: Some("Unknown".to_string()),
-            is_battery_present: false,
+            battery_level,
+            is_charging,
+            power_source: Some(power_source.to_string()),
+            time_remaining,
+            health_percent: None,
+            is_battery_present,
+            thermal: Self::get_thermal_state(),
         })
     }
 
@@ -306,17 +611,19 @@ impl PowerHandler {
 
     fn emit_power_event(
         event_type: PowerEventType,
-        battery_level: Option<f32>,
-        is_charging: Option<bool>,
-        power_source: Option<String>,
-        sender: &Sender<EventMessage>,
+        snapshot: Option<&PowerSnapshot>,
+        sender: &EventSender,
         handler_id: &HandlerId,
     ) {
         let event_data = PowerEventData {
             event_type,
-            battery_level,
-            is_charging,
-            power_source,
+            battery_level: snapshot.and_then(|snapshot| snapshot.battery_level),
+            is_charging: snapshot.and_then(|snapshot| snapshot.is_charging),
+            power_source: snapshot.and_then(|snapshot| snapshot.power_source.clone()),
+            time_remaining: snapshot.and_then(|snapshot| snapshot.time_remaining),
+            health_percent: snapshot.and_then(|snapshot| snapshot.health_percent),
+            current_frequency_mhz: None,
+            base_frequency_mhz: None,
             timestamp: SystemTime::now(),
         };
 
@@ -325,7 +632,8 @@ impl PowerHandler {
                 id: 0, // Will be set by event bus
                 handler_id: handler_id.clone(),
                 timestamp: SystemTime::now(),
-                source: "power".to_string(),
+                source: crate::event_source(handler_id, "power"),
+                dedup_key: None,
             },
             data: EventData::Power(event_data),
         };
@@ -334,6 +642,580 @@ impl PowerHandler {
             log::error!("Failed to send power event: {}", e);
         }
     }
+
+    fn emit_thermal_event(
+        snapshot: &PowerSnapshot,
+        thermal: &ThermalState,
+        sender: &EventSender,
+        handler_id: &HandlerId,
+    ) {
+        let event_data = PowerEventData {
+            event_type: PowerEventType::ThermalThrottling,
+            battery_level: snapshot.battery_level,
+            is_charging: snapshot.is_charging,
+            power_source: snapshot.power_source.clone(),
+            time_remaining: snapshot.time_remaining,
+            health_percent: snapshot.health_percent,
+            current_frequency_mhz: thermal.current_frequency_mhz,
+            base_frequency_mhz: thermal.base_frequency_mhz,
+            timestamp: SystemTime::now(),
+        };
+
+        let message = EventMessage {
+            metadata: EventMetadata {
+                id: 0, // Will be set by event bus
+                handler_id: handler_id.clone(),
+                timestamp: SystemTime::now(),
+                source: crate::event_source(handler_id, "power"),
+                dedup_key: None,
+            },
+            data: EventData::Power(event_data),
+        };
+
+        if let Err(e) = sender.send(message) {
+            log::error!("Failed to send power event: {}", e);
+        }
+    }
+}
+
+#[cfg(any(windows, target_os = "linux", target_os = "macos"))]
+struct SleepWakeContext {
+    sender: EventSender,
+    handler_id: HandlerId,
+}
+
+#[cfg(any(windows, target_os = "linux", target_os = "macos"))]
+impl SleepWakeContext {
+    fn emit(&self, event_type: PowerEventType) {
+        PowerHandler::emit_power_event(event_type, None, &self.sender, &self.handler_id);
+    }
+}
+
+// CLOCK_MONOTONIC stops while the machine is suspended and /proc/uptime
+// (CLOCK_BOOTTIME) doesn't, so a suspend shows up as the two drifting apart.
+// Without D-Bus there is no notice ahead of time; both events are reported
+// once the machine has resumed.
+#[cfg(target_os = "linux")]
+struct SuspendClock {
+    monotonic: std::time::Instant,
+    boot: Duration,
+}
+
+// Drift below this is scheduling noise and /proc/uptime's 10ms resolution
+#[cfg(target_os = "linux")]
+const SUSPEND_DRIFT: Duration = Duration::from_secs(2);
+
+#[cfg(target_os = "linux")]
+impl SuspendClock {
+    fn now() -> Option<Self> {
+        Some(Self { monotonic: std::time::Instant::now(), boot: Self::boot_time()? })
+    }
+
+    fn boot_time() -> Option<Duration> {
+        let uptime = fs::read_to_string("/proc/uptime").ok()?;
+        let seconds = uptime.split_whitespace().next()?.parse::<f64>().ok()?;
+        Some(Duration::from_secs_f64(seconds))
+    }
+
+    /// Whether the machine was suspended since the last call.
+    fn slept(&mut self) -> bool {
+        let Some(current) = Self::now() else {
+            return false;
+        };
+        let boot_elapsed = current.boot.saturating_sub(self.boot);
+        let monotonic_elapsed = current.monotonic.duration_since(self.monotonic);
+        *self = current;
+        boot_elapsed.saturating_sub(monotonic_elapsed) > SUSPEND_DRIFT
+    }
+}
+
+#[cfg(windows)]
+struct SleepWakeMonitor {
+    registration: *mut std::ffi::c_void,
+    // Both are referenced by the OS until the registration is removed
+    _parameters: Box<windows_sys::Win32::System::Power::DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS>,
+    _context: Box<SleepWakeContext>,
+}
+
+#[cfg(windows)]
+unsafe impl Send for SleepWakeMonitor {}
+#[cfg(windows)]
+unsafe impl Sync for SleepWakeMonitor {}
+
+#[cfg(windows)]
+impl SleepWakeMonitor {
+    fn start(context: SleepWakeContext) -> Result<Self> {
+        use windows_sys::Win32::System::Power::{PowerRegisterSuspendResumeNotification, DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS};
+        use windows_sys::Win32::UI::WindowsAndMessaging::DEVICE_NOTIFY_CALLBACK;
+
+        let context = Box::new(context);
+        let mut parameters = Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+            Callback: Some(suspend_resume_callback),
+            Context: &*context as *const SleepWakeContext as *mut std::ffi::c_void,
+        });
+        let mut registration = std::ptr::null_mut();
+
+        let result = unsafe {
+            PowerRegisterSuspendResumeNotification(
+                DEVICE_NOTIFY_CALLBACK,
+                &mut *parameters as *mut DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS as *mut std::ffi::c_void,
+                &mut registration,
+            )
+        };
+        if result != 0 {
+            return Err(TellMeWhenError::System(format!(
+                "PowerRegisterSuspendResumeNotification failed: {}",
+                result
+            )));
+        }
+
+        Ok(Self { registration, _parameters: parameters, _context: context })
+    }
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn suspend_resume_callback(
+    context: *const std::ffi::c_void,
+    event: u32,
+    _setting: *const std::ffi::c_void,
+) -> u32 {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{PBT_APMRESUMEAUTOMATIC, PBT_APMSUSPEND};
+
+    let context = &*(context as *const SleepWakeContext);
+    match event {
+        PBT_APMSUSPEND => context.emit(PowerEventType::SleepMode),
+        // Sent on every resume, whether or not a user is present
+        PBT_APMRESUMEAUTOMATIC => context.emit(PowerEventType::WakeFromSleep),
+        _ => {}
+    }
+    0
+}
+
+#[cfg(windows)]
+impl Drop for SleepWakeMonitor {
+    fn drop(&mut self) {
+        use windows_sys::Win32::System::Power::{PowerUnregisterSuspendResumeNotification, HPOWERNOTIFY};
+        unsafe { PowerUnregisterSuspendResumeNotification(self.registration as HPOWERNOTIFY) };
+    }
+}
+
+// Power source and battery changes arrive as WM_POWERBROADCAST on a hidden
+// window, each one triggering an immediate check. A message-only window gets
+// no broadcasts, so the settings are registered for explicitly and come in as
+// PBT_POWERSETTINGCHANGE; PBT_APMPOWERSTATUSCHANGE is handled too in case
+// the window receives it anyway.
+#[cfg(windows)]
+struct PowerBroadcastWindow {
+    window: usize,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(windows)]
+const GUID_ACDC_POWER_SOURCE: windows_sys::core::GUID =
+    windows_sys::core::GUID::from_u128(0x5d3e9a59_e9d5_4b00_a6bd_ff34ff516548);
+#[cfg(windows)]
+const GUID_BATTERY_PERCENTAGE_REMAINING: windows_sys::core::GUID =
+    windows_sys::core::GUID::from_u128(0xa7ad8041_b45a_4cae_87a3_eecbb468a9e1);
+
+#[cfg(windows)]
+thread_local! {
+    // The window procedure runs on the window's own thread
+    static POWER_CHANGED: std::cell::RefCell<Option<Arc<tokio::sync::Notify>>> = const { std::cell::RefCell::new(None) };
+}
+
+#[cfg(windows)]
+impl PowerBroadcastWindow {
+    fn start(check_now: Arc<tokio::sync::Notify>) -> Result<Self> {
+        use windows_sys::Win32::Foundation::{GetLastError, ERROR_CLASS_ALREADY_EXISTS};
+        use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+        use windows_sys::Win32::System::Power::{RegisterPowerSettingNotification, UnregisterPowerSettingNotification};
+        use windows_sys::Win32::UI::WindowsAndMessaging::{
+            CreateWindowExW, DestroyWindow, DispatchMessageW, GetMessageW, RegisterClassW, DEVICE_NOTIFY_WINDOW_HANDLE,
+            HWND_MESSAGE, MSG, WNDCLASSW,
+        };
+
+        let (window_sender, window_receiver) = std::sync::mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("tell_me_when-power-window".to_string())
+            .spawn(move || {
+                let _active = crate::metrics::track_thread();
+                POWER_CHANGED.with(|power_changed| *power_changed.borrow_mut() = Some(check_now));
+
+                let class_name: Vec<u16> = "tell_me_when_power\0".encode_utf16().collect();
+                unsafe {
+                    let instance = GetModuleHandleW(std::ptr::null());
+                    let mut class: WNDCLASSW = std::mem::zeroed();
+                    class.lpfnWndProc = Some(power_window_proc);
+                    class.hInstance = instance;
+                    class.lpszClassName = class_name.as_ptr();
+                    // A restarted handler finds the class already registered
+                    if RegisterClassW(&class) == 0 && GetLastError() != ERROR_CLASS_ALREADY_EXISTS {
+                        let _ = window_sender.send(Err(std::io::Error::last_os_error()));
+                        return;
+                    }
+
+                    let window = CreateWindowExW(
+                        0,
+                        class_name.as_ptr(),
+                        class_name.as_ptr(),
+                        0,
+                        0,
+                        0,
+                        0,
+                        0,
+                        HWND_MESSAGE,
+                        std::ptr::null_mut(),
+                        instance,
+                        std::ptr::null(),
+                    );
+                    if window.is_null() {
+                        let _ = window_sender.send(Err(std::io::Error::last_os_error()));
+                        return;
+                    }
+
+                    let registrations: Vec<_> = [GUID_ACDC_POWER_SOURCE, GUID_BATTERY_PERCENTAGE_REMAINING]
+                        .iter()
+                        .map(|setting| RegisterPowerSettingNotification(window, setting, DEVICE_NOTIFY_WINDOW_HANDLE))
+                        .filter(|&registration| registration != 0)
+                        .collect();
+                    if registrations.is_empty() {
+                        let _ = window_sender.send(Err(std::io::Error::last_os_error()));
+                        DestroyWindow(window);
+                        return;
+                    }
+                    let _ = window_sender.send(Ok(window as usize));
+
+                    // Ends with WM_QUIT, posted once the monitor closes the window
+                    let mut message: MSG = std::mem::zeroed();
+                    while GetMessageW(&mut message, std::ptr::null_mut(), 0, 0) > 0 {
+                        DispatchMessageW(&message);
+                    }
+
+                    for registration in registrations {
+                        UnregisterPowerSettingNotification(registration);
+                    }
+                }
+            })?;
+
+        match window_receiver.recv() {
+            Ok(Ok(window)) => Ok(Self { window, thread: Some(thread) }),
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(TellMeWhenError::System(format!("Failed to create power notification window: {}", e)))
+            }
+            Err(_) => {
+                let _ = thread.join();
+                Err(TellMeWhenError::System("Power notification window thread exited".to_string()))
+            }
+        }
+    }
+}
+
+/// Whether a `WM_POWERBROADCAST` with this `wParam` can mean the power
+/// source, charging state or battery level changed.
+#[cfg(windows)]
+fn is_power_status_change(broadcast: usize) -> bool {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{PBT_APMPOWERSTATUSCHANGE, PBT_POWERSETTINGCHANGE};
+
+    matches!(broadcast as u32, PBT_APMPOWERSTATUSCHANGE | PBT_POWERSETTINGCHANGE)
+}
+
+#[cfg(windows)]
+unsafe extern "system" fn power_window_proc(
+    window: windows_sys::Win32::Foundation::HWND,
+    message: u32,
+    wparam: windows_sys::Win32::Foundation::WPARAM,
+    lparam: windows_sys::Win32::Foundation::LPARAM,
+) -> windows_sys::Win32::Foundation::LRESULT {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{DefWindowProcW, PostQuitMessage, WM_DESTROY, WM_POWERBROADCAST};
+
+    match message {
+        WM_POWERBROADCAST => {
+            if is_power_status_change(wparam) {
+                POWER_CHANGED.with(|power_changed| {
+                    if let Some(check_now) = power_changed.borrow().as_ref() {
+                        check_now.notify_one();
+                    }
+                });
+            }
+            1
+        }
+        WM_DESTROY => {
+            PostQuitMessage(0);
+            0
+        }
+        _ => DefWindowProcW(window, message, wparam, lparam),
+    }
+}
+
+#[cfg(windows)]
+impl Drop for PowerBroadcastWindow {
+    fn drop(&mut self) {
+        use windows_sys::Win32::UI::WindowsAndMessaging::{PostMessageW, WM_CLOSE};
+
+        // DefWindowProcW destroys the window on WM_CLOSE, which ends the loop
+        unsafe { PostMessageW(self.window as windows_sys::Win32::Foundation::HWND, WM_CLOSE, 0, 0) };
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+type IONotificationPortRef = *mut std::ffi::c_void;
+
+#[cfg(target_os = "macos")]
+type IOServiceInterestCallback = extern "C" fn(
+    refcon: *mut std::ffi::c_void,
+    service: u32,
+    message_type: u32,
+    message_argument: *mut std::ffi::c_void,
+);
+
+#[cfg(target_os = "macos")]
+#[link(name = "IOKit", kind = "framework")]
+extern "C" {
+    fn IORegisterForSystemPower(
+        refcon: *mut std::ffi::c_void,
+        notify_port: *mut IONotificationPortRef,
+        callback: IOServiceInterestCallback,
+        notifier: *mut u32,
+    ) -> u32;
+    fn IODeregisterForSystemPower(notifier: *mut u32) -> i32;
+    fn IONotificationPortGetRunLoopSource(notify: IONotificationPortRef) -> core_foundation::runloop::CFRunLoopSourceRef;
+    fn IONotificationPortDestroy(notify: IONotificationPortRef);
+    fn IOAllowPowerChange(kernel_port: u32, notification_id: isize) -> i32;
+    fn IOServiceClose(connect: u32) -> i32;
+}
+
+#[cfg(target_os = "macos")]
+const K_IO_MESSAGE_CAN_SYSTEM_SLEEP: u32 = 0xE000_0270;
+#[cfg(target_os = "macos")]
+const K_IO_MESSAGE_SYSTEM_WILL_SLEEP: u32 = 0xE000_0280;
+#[cfg(target_os = "macos")]
+const K_IO_MESSAGE_SYSTEM_HAS_POWERED_ON: u32 = 0xE000_0300;
+
+#[cfg(target_os = "macos")]
+struct SystemPowerContext {
+    context: SleepWakeContext,
+    root_port: std::sync::atomic::AtomicU32,
+}
+
+// The notification port is serviced by a run loop on its own thread, which
+// lives until the monitor is dropped
+#[cfg(target_os = "macos")]
+struct SleepWakeMonitor {
+    run_loop: *mut std::ffi::c_void,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+#[cfg(target_os = "macos")]
+unsafe impl Send for SleepWakeMonitor {}
+#[cfg(target_os = "macos")]
+unsafe impl Sync for SleepWakeMonitor {}
+
+#[cfg(target_os = "macos")]
+impl SleepWakeMonitor {
+    fn start(context: SleepWakeContext) -> Result<Self> {
+        use core_foundation::runloop::{kCFRunLoopCommonModes, CFRunLoopAddSource, CFRunLoopGetCurrent, CFRunLoopRun};
+
+        struct SendPtr(*mut std::ffi::c_void);
+        unsafe impl Send for SendPtr {}
+
+        let (run_loop_sender, run_loop_receiver) = std::sync::mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("tell_me_when-power".to_string())
+            .spawn(move || {
+                let _active = crate::metrics::track_thread();
+                let power_context = Box::new(SystemPowerContext {
+                    context,
+                    root_port: std::sync::atomic::AtomicU32::new(0),
+                });
+
+                unsafe {
+                    let mut notify_port: IONotificationPortRef = std::ptr::null_mut();
+                    let mut notifier: u32 = 0;
+                    let root_port = IORegisterForSystemPower(
+                        &*power_context as *const SystemPowerContext as *mut std::ffi::c_void,
+                        &mut notify_port,
+                        system_power_callback,
+                        &mut notifier,
+                    );
+                    if root_port == 0 {
+                        let _ = run_loop_sender.send(None);
+                        return;
+                    }
+                    power_context.root_port.store(root_port, std::sync::atomic::Ordering::SeqCst);
+
+                    let run_loop = CFRunLoopGetCurrent();
+                    CFRunLoopAddSource(run_loop, IONotificationPortGetRunLoopSource(notify_port), kCFRunLoopCommonModes);
+                    let _ = run_loop_sender.send(Some(SendPtr(run_loop as *mut std::ffi::c_void)));
+
+                    // Returns once the monitor calls CFRunLoopStop
+                    CFRunLoopRun();
+
+                    IODeregisterForSystemPower(&mut notifier);
+                    IOServiceClose(root_port);
+                    IONotificationPortDestroy(notify_port);
+                }
+            })?;
+
+        match run_loop_receiver.recv().ok().flatten() {
+            Some(run_loop) => Ok(Self { run_loop: run_loop.0, thread: Some(thread) }),
+            None => {
+                let _ = thread.join();
+                Err(TellMeWhenError::System("IORegisterForSystemPower failed".to_string()))
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+extern "C" fn system_power_callback(
+    refcon: *mut std::ffi::c_void,
+    _service: u32,
+    message_type: u32,
+    message_argument: *mut std::ffi::c_void,
+) {
+    let power_context = unsafe { &*(refcon as *const SystemPowerContext) };
+    let root_port = power_context.root_port.load(std::sync::atomic::Ordering::SeqCst);
+
+    match message_type {
+        // Sleep waits on our acknowledgement, so always allow it
+        K_IO_MESSAGE_CAN_SYSTEM_SLEEP => unsafe {
+            IOAllowPowerChange(root_port, message_argument as isize);
+        },
+        K_IO_MESSAGE_SYSTEM_WILL_SLEEP => {
+            power_context.context.emit(PowerEventType::SleepMode);
+            unsafe { IOAllowPowerChange(root_port, message_argument as isize) };
+        }
+        K_IO_MESSAGE_SYSTEM_HAS_POWERED_ON => power_context.context.emit(PowerEventType::WakeFromSleep),
+        _ => {}
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Drop for SleepWakeMonitor {
+    fn drop(&mut self) {
+        unsafe { core_foundation::runloop::CFRunLoopStop(self.run_loop as core_foundation::runloop::CFRunLoopRef) };
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+pub fn supported_events() -> Vec<SupportedEvent> {
+    use EventAvailability::*;
+
+    let polled = if cfg!(any(windows, target_os = "linux", target_os = "macos")) { Polled } else { Unsupported };
+    // Throttling is inferred from clock speed, which macOS doesn't expose here
+    let thermal = if cfg!(any(windows, target_os = "linux")) { Polled } else { Unsupported };
+    // Linux infers a suspend from the clocks once it has resumed
+    let sleep_wake = if cfg!(any(windows, target_os = "macos")) {
+        Native
+    } else if cfg!(target_os = "linux") {
+        Polled
+    } else {
+        Unsupported
+    };
+
+    [
+        ("BatteryLow", polled),
+        ("BatteryCharging", polled),
+        ("BatteryDischarging", polled),
+        ("PowerSourceChanged", polled),
+        ("SleepMode", sleep_wake),
+        ("WakeFromSleep", sleep_wake),
+        ("Shutdown", Unsupported),
+        ("Restart", Unsupported),
+        ("ThermalThrottling", thermal),
+    ]
+    .into_iter()
+    .map(|(name, availability)| SupportedEvent::new(EventKind::Power, name, availability))
+    .collect()
+}
+
+// Load above which running below the base clock is treated as throttling
+#[cfg(any(windows, target_os = "linux"))]
+const THROTTLE_LOAD_PERCENT: u64 = 80;
+
+#[cfg(any(windows, target_os = "linux"))]
+// Battery drivers report either energy (µWh, µW) or charge (µAh, µA);
+// the ratios work out the same either way.
+#[cfg(target_os = "linux")]
+fn battery_reading(battery_dir: &std::path::Path, energy: &str, charge: &str) -> Option<f64> {
+    [energy, charge]
+        .iter()
+        .find_map(|file| fs::read_to_string(battery_dir.join(file)).ok())
+        .and_then(|value| value.trim().parse().ok())
+}
+
+/// Time until empty while discharging, or until full while charging, at the
+/// current draw.
+#[cfg(target_os = "linux")]
+fn battery_time_remaining(battery_dir: &std::path::Path, is_charging: Option<bool>) -> Option<Duration> {
+    let now = battery_reading(battery_dir, "energy_now", "charge_now")?;
+    let rate = battery_reading(battery_dir, "power_now", "current_now")?.abs();
+    if rate <= 0.0 {
+        return None;
+    }
+
+    let remaining = match is_charging? {
+        true => battery_reading(battery_dir, "energy_full", "charge_full")? - now,
+        false => now,
+    };
+    (remaining >= 0.0).then(|| Duration::from_secs_f64(remaining / rate * 3600.0))
+}
+
+/// Full capacity as a percentage of the design capacity.
+#[cfg(target_os = "linux")]
+fn battery_health_percent(battery_dir: &std::path::Path) -> Option<f32> {
+    let full = battery_reading(battery_dir, "energy_full", "charge_full")?;
+    let design = battery_reading(battery_dir, "energy_full_design", "charge_full_design")?;
+    (design > 0.0).then(|| (full / design * 100.0) as f32)
+}
+
+fn is_below_base_frequency(current_mhz: Option<u64>, base_mhz: Option<u64>) -> bool {
+    matches!((current_mhz, base_mhz), (Some(current), Some(base)) if current < base)
+}
+
+#[cfg(target_os = "linux")]
+fn thermal_zone_tripped(zone_dir: &std::path::Path) -> bool {
+    let read_value = |name: String| fs::read_to_string(zone_dir.join(name)).ok();
+
+    let Some(temp) = read_value("temp".to_string()) else {
+        return false;
+    };
+    let trip_points: Vec<(String, String)> = (0..)
+        .map_while(|index| {
+            let trip_type = read_value(format!("trip_point_{}_type", index))?;
+            let trip_temp = read_value(format!("trip_point_{}_temp", index))?;
+            Some((trip_type, trip_temp))
+        })
+        .collect();
+
+    zone_tripped(&temp, &trip_points)
+}
+
+// A zone is tripped when its temperature has reached any passive, hot or
+// critical trip point; "active" trips only spin up fans. Takes the contents of
+// the zone's `temp` file and of its `trip_point_N_type`/`trip_point_N_temp` pairs
+#[cfg(target_os = "linux")]
+fn zone_tripped(temp: &str, trip_points: &[(String, String)]) -> bool {
+    let Some(temp) = parse_millidegrees(temp) else {
+        return false;
+    };
+
+    trip_points.iter().any(|(trip_type, trip_temp)| {
+        matches!(trip_type.trim(), "passive" | "hot" | "critical")
+            && parse_millidegrees(trip_temp).is_some_and(|trip_temp| trip_temp > 0.0 && temp >= trip_temp)
+    })
+}
+
+// Thermal zones report millidegrees Celsius
+#[cfg(target_os = "linux")]
+fn parse_millidegrees(reading: &str) -> Option<f32> {
+    reading.trim().parse::<i64>().ok().map(|millidegrees| millidegrees as f32 / 1000.0)
 }
 
 #[async_trait::async_trait]
@@ -345,6 +1227,7 @@ impl EventHandler for PowerHandler {
         if self.is_running {
             return Ok(());
         }
+        config.base.validate()?;
 
         self.config = config;
         self.start_monitoring();
@@ -362,6 +1245,14 @@ impl EventHandler for PowerHandler {
         if let Some(task) = self.monitor_task.take() {
             task.abort();
         }
+        #[cfg(any(windows, target_os = "macos"))]
+        {
+            self.sleep_wake = None;
+        }
+        #[cfg(windows)]
+        {
+            self.power_broadcast = None;
+        }
 
         self.is_running = false;
         log::info!("Power handler stopped: {}", self.handler_id);
@@ -375,4 +1266,58 @@ impl EventHandler for PowerHandler {
     fn name(&self) -> &'static str {
         "power"
     }
-}
\ No newline at end of file
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    fn trip_point(trip_type: &str, millidegrees: &str) -> (String, String) {
+        (format!("{}\n", trip_type), format!("{}\n", millidegrees))
+    }
+
+    #[test]
+    fn millidegrees_convert_to_degrees() {
+        assert_eq!(parse_millidegrees("45000\n"), Some(45.0));
+        assert_eq!(parse_millidegrees("-5500"), Some(-5.5));
+        assert_eq!(parse_millidegrees("warm"), None);
+        assert_eq!(parse_millidegrees(""), None);
+    }
+
+    #[test]
+    fn zones_trip_at_passive_hot_and_critical_points() {
+        for trip_type in ["passive", "hot", "critical"] {
+            assert!(zone_tripped("90000\n", &[trip_point(trip_type, "90000")]));
+        }
+        assert!(!zone_tripped("89999\n", &[trip_point("critical", "90000")]));
+        // Active trips only turn fans on
+        assert!(!zone_tripped("90000\n", &[trip_point("active", "50000")]));
+        // Disabled trip points read as zero or below
+        assert!(!zone_tripped("90000\n", &[trip_point("passive", "0")]));
+    }
+
+    #[test]
+    fn garbage_readings_never_trip() {
+        assert!(!zone_tripped("hot\n", &[trip_point("critical", "1000")]));
+        assert!(!zone_tripped("90000\n", &[trip_point("critical", "n/a")]));
+        assert!(!zone_tripped("90000\n", &[trip_point("", "1000")]));
+        assert!(!zone_tripped("90000\n", &[]));
+    }
+
+    #[test]
+    fn zones_with_missing_files_are_not_tripped() {
+        let zone = tempfile::tempdir().unwrap();
+        assert!(!thermal_zone_tripped(zone.path()));
+
+        // Trip points are read up to the first missing one
+        fs::write(zone.path().join("temp"), "95000\n").unwrap();
+        fs::write(zone.path().join("trip_point_1_type"), "critical\n").unwrap();
+        fs::write(zone.path().join("trip_point_1_temp"), "90000\n").unwrap();
+        assert!(!thermal_zone_tripped(zone.path()));
+
+        fs::write(zone.path().join("trip_point_0_type"), "passive\n").unwrap();
+        assert!(!thermal_zone_tripped(zone.path()));
+        fs::write(zone.path().join("trip_point_0_temp"), "100000\n").unwrap();
+        assert!(thermal_zone_tripped(zone.path()));
+    }
+}
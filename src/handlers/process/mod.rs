@@ -1,19 +1,24 @@
-use crate::events::{EventData, ProcessEventData, ProcessEventType};
-use crate::traits::{EventHandlerConfig, ThresholdConfig, IntervalConfig};
-use crate::{EventMessage, EventMetadata, HandlerId, Result, TellMeWhenError, EventId};
-use crossbeam_channel::Sender;
-use sysinfo::{System, Pid, Process};
-use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
+use crate::events::{EventAvailability, EventData, EventKind, ProcessEventData, ProcessEventType, SupportedEvent};
+use crate::handlers::shared_system::SharedSystem;
+use crate::traits::{EventHandlerConfig, ThresholdConfig, IntervalConfig, MIN_POLL_INTERVAL};
+use crate::{EventMessage, EventMetadata, EventSender, HandlerId, Result};
+use sysinfo::{System, Pid, ProcessRefreshKind, ProcessesToUpdate, UpdateKind, Users};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock, Weak};
 use std::time::{Duration, SystemTime};
 #[cfg(windows)]
 mod windows;
-#[cfg(unix)]
+#[cfg(all(unix, not(target_os = "macos")))]
 mod unix;
+#[cfg(all(unix, not(target_os = "macos")))]
+mod proc_connector;
 #[cfg(target_os = "macos")]
 mod macos;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct ProcessConfig {
     pub base: EventHandlerConfig,
     pub cpu_threshold: f32,
@@ -21,6 +26,11 @@ pub struct ProcessConfig {
     pub monitor_new_processes: bool,
     pub monitor_terminated_processes: bool,
     pub process_name_filters: Vec<String>,
+    pub normalize_process_names: bool,
+    /// Report `CpuUsageHigh`/`MemoryUsageHigh` once when usage crosses the
+    /// threshold, and `CpuUsageNormal`/`MemoryUsageNormal` once when it drops
+    /// back, instead of on every check that finds it above.
+    pub edge_triggered: bool,
 }
 
 impl Default for ProcessConfig {
@@ -32,6 +42,361 @@ impl Default for ProcessConfig {
             monitor_new_processes: true,
             monitor_terminated_processes: true,
             process_name_filters: Vec::new(),
+            normalize_process_names: true,
+            edge_triggered: true,
+        }
+    }
+}
+
+// Linux truncates /proc/<pid>/comm to TASK_COMM_LEN - 1 bytes
+const LINUX_COMM_MAX_LEN: usize = 15;
+
+impl ProcessConfig {
+    /// Returns whether a process should be reported given `process_name_filters`.
+    ///
+    /// An empty filter list matches every process. With `normalize_process_names`
+    /// enabled (the default), a filter matches when, ignoring case, directory
+    /// components and a trailing `.exe`, it equals either the process name or the
+    /// file stem of `exe_path`. A 15-character name is treated as a possibly
+    /// truncated Linux `comm` and also matches filters it is a prefix of, so
+    /// `"chrome"` matches `chrome.exe`, `/opt/google/chrome/chrome` and `chrome`.
+    /// With normalization disabled, filters must equal the name exactly.
+    pub fn should_monitor_process(&self, name: &str, exe_path: Option<&Path>) -> bool {
+        if self.process_name_filters.is_empty() {
+            return true;
+        }
+
+        if !self.normalize_process_names {
+            return self.process_name_filters.iter().any(|filter| filter == name);
+        }
+
+        let name = normalize_process_name(name);
+        let exe_stem = exe_path
+            .and_then(|path| path.file_name())
+            .map(|file_name| normalize_process_name(&file_name.to_string_lossy()));
+
+        self.process_name_filters.iter().any(|filter| {
+            let filter = normalize_process_name(filter);
+            filter == name
+                || exe_stem.as_deref() == Some(filter.as_str())
+                || (name.chars().count() == LINUX_COMM_MAX_LEN && filter.starts_with(&name))
+        })
+    }
+}
+
+pub fn normalize_process_name(name: &str) -> String {
+    let file_name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let lowercase = file_name.to_lowercase();
+    match lowercase.strip_suffix(".exe") {
+        Some(stem) => stem.to_string(),
+        None => lowercase,
+    }
+}
+
+pub fn supported_events() -> Vec<SupportedEvent> {
+    use EventAvailability::*;
+
+    // macOS finds starts and exits by diffing the process table
+    let lifecycle = if cfg!(target_os = "macos") {
+        Polled
+    } else if cfg!(any(windows, unix)) {
+        Native
+    } else {
+        Unsupported
+    };
+    // Resource pressure comes from cgroup PSI files, which only Linux has
+    let pressure = if cfg!(target_os = "linux") { Native } else { Unsupported };
+    // Executable replacement rides on the filesystem watcher
+    let executable = if cfg!(all(feature = "fs", windows)) { Native } else { Unsupported };
+
+    [
+        ("Started", lifecycle),
+        ("Terminated", lifecycle),
+        ("CpuUsageHigh", pressure),
+        ("MemoryUsageHigh", pressure),
+        ("CpuUsageNormal", pressure),
+        ("MemoryUsageNormal", pressure),
+        ("StatusChanged", Unsupported),
+        ("ExecutableReplaced", executable),
+        ("ResourceUsage", Polled),
+    ]
+    .into_iter()
+    .map(|(name, availability)| SupportedEvent::new(EventKind::Process, name, availability))
+    .collect()
+}
+
+/// Looks up a single process's name and executable path without touching the
+/// shared process table. Returns `None` if the process doesn't exist or its
+/// executable can't be resolved (e.g. insufficient permissions).
+pub fn process_executable(pid: u32) -> Option<(String, PathBuf)> {
+    let mut system = System::new();
+    let pid = Pid::from_u32(pid);
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_exe(UpdateKind::Always),
+    );
+
+    let process = system.process(pid)?;
+    let exe_path = process.exe()?.to_path_buf();
+    Some((process.name().to_string_lossy().into_owned(), exe_path))
+}
+
+/// Owner of a process as `(uid, user name)`; either may be unavailable.
+pub fn process_owner(pid: u32) -> (Option<String>, Option<String>) {
+    let mut system = System::new();
+    let pid = Pid::from_u32(pid);
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_user(UpdateKind::Always),
+    );
+
+    let Some(uid) = system.process(pid).and_then(|process| process.user_id()).cloned() else {
+        return (None, None);
+    };
+
+    let users = Users::new_with_refreshed_list();
+    let owner_name = users.get_user_by_id(&uid).map(|user| user.name().to_string());
+    (Some(uid.to_string()), owner_name)
+}
+
+/// Executable path and command line of a running process; either may be
+/// unavailable, and both are once the process has exited.
+#[cfg(target_os = "linux")]
+pub fn process_command(pid: u32) -> (Option<PathBuf>, Option<Vec<String>>) {
+    let proc_dir = PathBuf::from(format!("/proc/{}", pid));
+    let exe_path = std::fs::read_link(proc_dir.join("exe")).ok();
+    // Each argument is NUL-terminated; kernel threads and zombies have none
+    let cmdline = std::fs::read(proc_dir.join("cmdline")).ok().and_then(|raw| {
+        let raw = raw.strip_suffix(&[0]).unwrap_or(&raw);
+        (!raw.is_empty()).then(|| raw.split(|&byte| byte == 0).map(|arg| String::from_utf8_lossy(arg).into_owned()).collect())
+    });
+    (exe_path, cmdline)
+}
+
+/// Executable path and command line of a running process; either may be
+/// unavailable, and both are once the process has exited.
+#[cfg(not(target_os = "linux"))]
+pub fn process_command(pid: u32) -> (Option<PathBuf>, Option<Vec<String>>) {
+    let mut system = System::new();
+    let pid = Pid::from_u32(pid);
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_exe(UpdateKind::Always).with_cmd(UpdateKind::Always),
+    );
+
+    match system.process(pid) {
+        Some(process) => (process.exe().map(Path::to_path_buf), command_line(process.cmd())),
+        None => (None, None),
+    }
+}
+
+fn command_line(cmd: &[std::ffi::OsString]) -> Option<Vec<String>> {
+    (!cmd.is_empty()).then(|| cmd.iter().map(|arg| arg.to_string_lossy().into_owned()).collect())
+}
+
+pub fn is_process_running(pid: u32) -> bool {
+    let mut system = System::new();
+    let pid = Pid::from_u32(pid);
+    system.refresh_processes_specifics(ProcessesToUpdate::Some(&[pid]), true, ProcessRefreshKind::nothing());
+    system.process(pid).is_some()
+}
+
+/// Every process in the shared table as a synthetic `Started` event.
+pub(crate) fn running_processes(shared_system: &SharedSystem) -> std::collections::VecDeque<ProcessEventData> {
+    let cpu_ready = shared_system.cpu_ready();
+    let system = shared_system.system();
+    let system = system.read().unwrap();
+    let timestamp = SystemTime::now();
+
+    system
+        .processes()
+        .iter()
+        .map(|(pid, process)| ProcessEventData {
+            event_type: ProcessEventType::Started,
+            pid: pid.as_u32(),
+            name: process.name().to_string_lossy().into_owned(),
+            cpu_usage: cpu_ready.then(|| process.cpu_usage()),
+            memory_usage: Some(process.memory()),
+            owner_uid: process.user_id().map(|uid| uid.to_string()),
+            // Resolving names means a users lookup per process; callers can
+            // use process_owner for the ones they care about
+            owner_name: None,
+            exit_code: None,
+            parent_pid: process.parent().map(|parent| parent.as_u32()),
+            exe_path: process.exe().map(Path::to_path_buf),
+            cmdline: command_line(process.cmd()),
+            timestamp,
+        })
+        .collect()
+}
+
+/// Samples each pid in `tracked` every `interval`, emitting `ResourceUsage`
+/// for live processes. A pid that has disappeared is removed from the set and
+/// reported as `Terminated`, covering exits the native backend missed. The
+/// task ends once the set is empty or its owner drops it.
+pub(crate) fn spawn_pid_sampler(
+    tracked: Weak<Mutex<HashSet<u32>>>,
+    shared_system: Arc<SharedSystem>,
+    interval: Duration,
+    sender: EventSender,
+    handler_id: HandlerId,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let _active = crate::metrics::track_task();
+        let mut interval = tokio::time::interval(interval);
+
+        loop {
+            interval.tick().await;
+
+            let Some(tracked) = tracked.upgrade() else {
+                break;
+            };
+            if !shared_system.cpu_ready() {
+                continue;
+            }
+            let pids: Vec<u32> = tracked.lock().unwrap().iter().copied().collect();
+            if pids.is_empty() {
+                break;
+            }
+
+            let samples: Vec<_> = {
+                let system = shared_system.system();
+                let system = system.read().unwrap();
+                pids.iter()
+                    .map(|&pid| {
+                        let sample = system.process(Pid::from_u32(pid)).map(|process| {
+                            (process.name().to_string_lossy().into_owned(), process.cpu_usage(), process.memory())
+                        });
+                        (pid, sample)
+                    })
+                    .collect()
+            };
+
+            for (pid, sample) in samples {
+                match sample {
+                    Some((name, cpu_usage, memory_usage)) => ProcessHandler::emit_process_event(
+                        ProcessEventType::ResourceUsage,
+                        pid,
+                        name,
+                        ProcessSample { cpu_usage: Some(cpu_usage), memory_usage: Some(memory_usage), ..Default::default() },
+                        &sender,
+                        &handler_id,
+                    ),
+                    None => {
+                        // Only report the exit if the native backend hasn't already
+                        if tracked.lock().unwrap().remove(&pid) {
+                            ProcessHandler::emit_process_event(
+                                ProcessEventType::Terminated,
+                                pid,
+                                String::new(),
+                                ProcessSample::default(),
+                                &sender,
+                                &handler_id,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// How long a `Started` event whose parent isn't in a [`ProcessTree`] yet is
+/// held back in case the parent's own `Started` event arrives late.
+const ORPHAN_TTL: Duration = Duration::from_secs(5);
+const MAX_ORPHANS: usize = 1024;
+
+/// The live set of pids in the subtree rooted at one process, kept current
+/// from `Started` and `Terminated` events.
+pub(crate) struct ProcessTree {
+    members: HashSet<u32>,
+    /// Starts whose parent is unknown so far, oldest first. A grandchild can
+    /// be reported before the child that forked it, so these are re-checked
+    /// whenever a process joins the tree.
+    orphans: std::collections::VecDeque<ProcessEventData>,
+}
+
+impl ProcessTree {
+    /// Seeds the tree with `root` and its descendants among `processes`.
+    pub(crate) fn new<'a>(root: u32, processes: impl IntoIterator<Item = &'a ProcessEventData>) -> Self {
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for process in processes {
+            if let Some(parent_pid) = process.parent_pid {
+                children.entry(parent_pid).or_default().push(process.pid);
+            }
+        }
+
+        let mut members = HashSet::new();
+        let mut pending = vec![root];
+        while let Some(pid) = pending.pop() {
+            if members.insert(pid) {
+                pending.extend(children.remove(&pid).unwrap_or_default());
+            }
+        }
+
+        Self { members, orphans: std::collections::VecDeque::new() }
+    }
+
+    /// Updates the tree from `event` and returns the events to deliver, which
+    /// can include held-back starts of the event's own descendants.
+    pub(crate) fn accept(&mut self, event: &ProcessEventData) -> Vec<ProcessEventData> {
+        self.expire_orphans();
+
+        match event.event_type {
+            ProcessEventType::Started => match event.parent_pid {
+                Some(parent_pid) if self.members.contains(&parent_pid) => self.admit(event.clone()),
+                _ if self.members.contains(&event.pid) => vec![event.clone()],
+                Some(_) => {
+                    if self.orphans.len() == MAX_ORPHANS {
+                        self.orphans.pop_front();
+                    }
+                    self.orphans.push_back(event.clone());
+                    Vec::new()
+                }
+                None => Vec::new(),
+            },
+            ProcessEventType::Terminated => {
+                if self.members.remove(&event.pid) {
+                    vec![event.clone()]
+                } else {
+                    self.orphans.retain(|orphan| orphan.pid != event.pid);
+                    Vec::new()
+                }
+            }
+            _ if self.members.contains(&event.pid) => vec![event.clone()],
+            _ => Vec::new(),
+        }
+    }
+
+    fn admit(&mut self, started: ProcessEventData) -> Vec<ProcessEventData> {
+        let mut admitted = Vec::new();
+        let mut pending = vec![started];
+        while let Some(event) = pending.pop() {
+            self.members.insert(event.pid);
+            let mut children = Vec::new();
+            self.orphans.retain(|orphan| {
+                let is_child = orphan.parent_pid == Some(event.pid);
+                if is_child {
+                    children.push(orphan.clone());
+                }
+                !is_child
+            });
+            admitted.push(event);
+            pending.extend(children);
+        }
+        admitted
+    }
+
+    fn expire_orphans(&mut self) {
+        while let Some(oldest) = self.orphans.front() {
+            let age = oldest.timestamp.elapsed().unwrap_or_default();
+            if age < ORPHAN_TTL {
+                break;
+            }
+            self.orphans.pop_front();
         }
     }
 }
@@ -48,7 +413,7 @@ impl ThresholdConfig for ProcessConfig {
 
 impl IntervalConfig for ProcessConfig {
     fn set_interval(&mut self, interval: Duration) {
-        self.base.poll_interval = interval;
+        self.base.poll_interval = interval.max(MIN_POLL_INTERVAL);
     }
 
     fn get_interval(&self) -> Duration {
@@ -57,33 +422,89 @@ impl IntervalConfig for ProcessConfig {
 }
 
 pub struct ProcessHandler {
-    config: ProcessConfig,
-    system: Arc<Mutex<System>>,
+    // Shared with the monitors, which read the name filters from it for
+    // every process they see
+    config: Arc<Mutex<ProcessConfig>>,
+    shared_system: Arc<SharedSystem>,
+    system: Arc<RwLock<System>>,
     previous_processes: Arc<Mutex<HashMap<u32, ProcessSnapshot>>>,
     is_running: Arc<Mutex<bool>>,
 }
 
+// What a backend knows about a process beyond its pid and name; the rest of
+// the event is looked up when it's emitted
+#[derive(Debug, Default)]
+pub(crate) struct ProcessSample {
+    pub(crate) cpu_usage: Option<f32>,
+    pub(crate) memory_usage: Option<u64>,
+    pub(crate) exit_code: Option<i32>,
+    pub(crate) parent_pid: Option<u32>,
+}
+
 #[derive(Debug, Clone)]
 struct ProcessSnapshot {
-    pid: u32,
-    name: String,
-    cpu_usage: f32,
-    memory_usage: u64,
     last_seen: SystemTime,
+    // Whether the last check found usage over the threshold
+    cpu_high: bool,
+    memory_high: bool,
+}
+
+impl ProcessSnapshot {
+    fn new() -> Self {
+        Self {
+            last_seen: SystemTime::now(),
+            cpu_high: false,
+            memory_high: false,
+        }
+    }
+}
+
+/// Records a threshold check for `pid` and returns the event to report for
+/// it, if any. `high` is `CpuUsageHigh` or `MemoryUsageHigh`.
+#[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+fn threshold_event(
+    previous_processes: &Mutex<HashMap<u32, ProcessSnapshot>>,
+    config: &ProcessConfig,
+    pid: u32,
+    high: ProcessEventType,
+    above: bool,
+) -> Option<ProcessEventType> {
+    if !config.edge_triggered {
+        return above.then_some(high);
+    }
+
+    let mut previous_processes = previous_processes.lock().unwrap();
+    let snapshot = previous_processes.entry(pid).or_insert_with(ProcessSnapshot::new);
+    snapshot.last_seen = SystemTime::now();
+    let (was_above, normal) = match high {
+        ProcessEventType::CpuUsageHigh => (std::mem::replace(&mut snapshot.cpu_high, above), ProcessEventType::CpuUsageNormal),
+        _ => (std::mem::replace(&mut snapshot.memory_high, above), ProcessEventType::MemoryUsageNormal),
+    };
+
+    match (was_above, above) {
+        (false, true) => Some(high),
+        (true, false) => Some(normal),
+        _ => None,
+    }
 }
 
 impl ProcessHandler {
     pub fn new(config: ProcessConfig) -> Self {
+        Self::with_shared_system(config, Arc::new(SharedSystem::new()))
+    }
+
+    pub fn with_shared_system(config: ProcessConfig, shared_system: Arc<SharedSystem>) -> Self {
         Self {
-            config,
-            system: Arc::new(Mutex::new(System::new_all())),
+            config: Arc::new(Mutex::new(config)),
+            system: shared_system.system(),
+            shared_system,
             previous_processes: Arc::new(Mutex::new(HashMap::new())),
             is_running: Arc::new(Mutex::new(false)),
         }
     }
 
     #[cfg(windows)]
-    async fn start_platform_specific(&self, sender: Sender<EventMessage>, handler_id: HandlerId) -> Result<()> {
+    async fn start_platform_specific(&self, sender: EventSender, handler_id: HandlerId) -> Result<()> {
         windows::start_process_monitoring(
             &self.config,
             &self.system,
@@ -95,7 +516,7 @@ impl ProcessHandler {
     }
 
     #[cfg(all(unix, not(target_os = "macos")))]
-    async fn start_platform_specific(&self, sender: Sender<EventMessage>, handler_id: HandlerId) -> Result<()> {
+    async fn start_platform_specific(&self, sender: EventSender, handler_id: HandlerId) -> Result<()> {
         unix::start_process_monitoring(
             &self.config,
             &self.system,
@@ -107,7 +528,7 @@ impl ProcessHandler {
     }
 
     #[cfg(target_os = "macos")]
-    async fn start_platform_specific(&self, sender: Sender<EventMessage>, handler_id: HandlerId) -> Result<()> {
+    async fn start_platform_specific(&self, sender: EventSender, handler_id: HandlerId) -> Result<()> {
         macos::start_process_monitoring(
             &self.config,
             &self.system,
@@ -118,31 +539,44 @@ impl ProcessHandler {
         ).await
     }
 
-    fn emit_process_event(
+    pub(crate) fn emit_process_event(
         event_type: ProcessEventType,
         pid: u32,
         name: String,
-        cpu_usage: Option<f32>,
-        memory_usage: Option<u64>,
-        sender: &Sender<EventMessage>,
+        sample: ProcessSample,
+        sender: &EventSender,
         handler_id: &HandlerId,
     ) {
+        // pid 0 marks system-wide pressure events, which belong to no process
+        let ((owner_uid, owner_name), (exe_path, cmdline)) = if pid == 0 {
+            ((None, None), (None, None))
+        } else {
+            (process_owner(pid), process_command(pid))
+        };
+
         let event_data = ProcessEventData {
             event_type,
             pid,
             name,
-            cpu_usage,
-            memory_usage,
+            cpu_usage: sample.cpu_usage,
+            memory_usage: sample.memory_usage,
+            owner_uid,
+            owner_name,
+            exit_code: sample.exit_code,
+            parent_pid: sample.parent_pid,
+            exe_path,
+            cmdline,
             timestamp: SystemTime::now(),
         };
 
         let event_message = EventMessage {
             data: EventData::Process(event_data),
             metadata: EventMetadata {
-                id: uuid::Uuid::new_v4().as_u128() as EventId,
+                id: 0, // Will be set by event bus
                 handler_id: handler_id.clone(),
                 timestamp: SystemTime::now(),
-                source: "ProcessHandler".to_string(),
+                source: crate::event_source(handler_id, "ProcessHandler"),
+                dedup_key: None,
             },
         };
 
@@ -153,7 +587,12 @@ impl ProcessHandler {
 }
 
 impl ProcessHandler {
-    pub async fn start(&mut self, sender: Sender<EventMessage>, handler_id: HandlerId) -> Result<()> {
+    pub async fn start(&mut self, sender: EventSender, handler_id: HandlerId) -> Result<()> {
+        let poll_interval = {
+            let config = self.config.lock().unwrap();
+            config.base.validate()?;
+            config.base.poll_interval
+        };
         {
             let mut is_running = self.is_running.lock().unwrap();
             if *is_running {
@@ -162,14 +601,100 @@ impl ProcessHandler {
             *is_running = true;
         }
 
+        self.shared_system.start(poll_interval);
+
         log::info!("Starting process monitoring with native OS callbacks");
         self.start_platform_specific(sender, handler_id).await
     }
 
+    /// Refreshes the process table and re-checks resource pressure now,
+    /// emitting any threshold events through `sender`.
+    pub fn trigger_check(&self, sender: &EventSender, handler_id: &HandlerId) {
+        self.shared_system.refresh_now();
+
+        #[cfg(all(unix, not(target_os = "macos")))]
+        unix::check_pressure_now(&self.config.lock().unwrap(), &self.previous_processes, sender, handler_id);
+        #[cfg(not(all(unix, not(target_os = "macos"))))]
+        let _ = (sender, handler_id);
+    }
+
+    /// Adds `name` to `process_name_filters` while the monitors run. They
+    /// apply it from the next process they see; nothing already missed is
+    /// reported.
+    pub fn add_name_filter(&self, name: &str) {
+        let mut config = self.config.lock().unwrap();
+        if !config.process_name_filters.iter().any(|filter| filter == name) {
+            config.process_name_filters.push(name.to_string());
+        }
+    }
+
+    /// Removes `name` from `process_name_filters` while the monitors run,
+    /// returning whether it was there. Removing the last filter reports
+    /// every process again.
+    pub fn remove_name_filter(&self, name: &str) -> bool {
+        let mut config = self.config.lock().unwrap();
+        let before = config.process_name_filters.len();
+        config.process_name_filters.retain(|filter| filter != name);
+        config.process_name_filters.len() != before
+    }
+
+    /// Stops the monitors. Their threads wake at least every few hundred
+    /// milliseconds to notice, and report nothing once stopped.
     pub async fn stop(&mut self) -> Result<()> {
-        let mut is_running = self.is_running.lock().unwrap();
-        *is_running = false;
+        *self.is_running.lock().unwrap() = false;
+        // The old monitors keep the flag they were started with, so a quick
+        // restart can't revive them alongside the new ones
+        self.is_running = Arc::new(Mutex::new(false));
         log::info!("Process monitoring stopped");
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Feeds one above/below reading per check, returning what each reported
+    fn crossings(config: &ProcessConfig, high: ProcessEventType, readings: &[bool]) -> Vec<Option<ProcessEventType>> {
+        let previous_processes = Mutex::new(HashMap::new());
+        readings
+            .iter()
+            .map(|&above| threshold_event(&previous_processes, config, 42, high.clone(), above))
+            .collect()
+    }
+
+    #[test]
+    fn sustained_breaches_report_once_and_rearm_after_recovery() {
+        use ProcessEventType::{CpuUsageHigh, CpuUsageNormal};
+
+        let readings = [false, true, true, true, false, false, true, true];
+        assert_eq!(
+            crossings(&ProcessConfig::default(), CpuUsageHigh, &readings),
+            vec![None, Some(CpuUsageHigh), None, None, Some(CpuUsageNormal), None, Some(CpuUsageHigh), None]
+        );
+    }
+
+    #[test]
+    fn memory_crossings_are_tracked_apart_from_cpu() {
+        let config = ProcessConfig::default();
+        let previous_processes = Mutex::new(HashMap::new());
+        let check = |high: ProcessEventType, above| threshold_event(&previous_processes, &config, 42, high, above);
+
+        assert_eq!(check(ProcessEventType::CpuUsageHigh, true), Some(ProcessEventType::CpuUsageHigh));
+        assert_eq!(check(ProcessEventType::MemoryUsageHigh, true), Some(ProcessEventType::MemoryUsageHigh));
+        assert_eq!(check(ProcessEventType::CpuUsageHigh, true), None);
+        assert_eq!(check(ProcessEventType::MemoryUsageHigh, false), Some(ProcessEventType::MemoryUsageNormal));
+        assert_eq!(check(ProcessEventType::CpuUsageHigh, true), None);
+    }
+
+    #[test]
+    fn level_triggered_checks_report_every_breach() {
+        use ProcessEventType::CpuUsageHigh;
+
+        let config = ProcessConfig { edge_triggered: false, ..Default::default() };
+        assert_eq!(
+            crossings(&config, CpuUsageHigh, &[true, true, false, true]),
+            vec![Some(CpuUsageHigh), Some(CpuUsageHigh), None, Some(CpuUsageHigh)]
+        );
+    }
+}
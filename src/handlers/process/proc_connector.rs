@@ -0,0 +1,173 @@
+// Reader for the Linux proc connector, the netlink multicast group on which
+// the kernel announces every fork, exec and exit. Subscribing needs
+// CAP_NET_ADMIN.
+
+use std::io;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+const NETLINK_CONNECTOR: libc::c_int = 11;
+const CN_IDX_PROC: u32 = 1;
+const CN_VAL_PROC: u32 = 1;
+const PROC_CN_MCAST_LISTEN: u32 = 1;
+const NLMSG_DONE: u16 = 3;
+
+const PROC_EVENT_FORK: u32 = 0x0000_0001;
+const PROC_EVENT_EXEC: u32 = 0x0000_0002;
+const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+
+// Sizes of struct nlmsghdr and struct cn_msg (without its payload)
+const NLMSG_HEADER_LEN: usize = 16;
+const CN_MSG_LEN: usize = 20;
+// struct proc_event: what, cpu and timestamp_ns precede the event_data union
+const PROC_EVENT_DATA_OFFSET: usize = NLMSG_HEADER_LEN + CN_MSG_LEN + 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum ProcEvent {
+    Fork { parent_pid: u32, child_pid: u32 },
+    Exec { pid: u32 },
+    /// `exit_code` is the raw wait status, as `waitpid` would report it.
+    Exit { pid: u32, exit_code: u32 },
+}
+
+pub(super) struct ProcConnector {
+    socket: OwnedFd,
+    // u64 elements keep the buffer aligned for the kernel's structs
+    buffer: Vec<u64>,
+}
+
+impl ProcConnector {
+    pub(super) fn new() -> io::Result<Self> {
+        let fd = unsafe { libc::socket(libc::PF_NETLINK, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, NETLINK_CONNECTOR) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut address: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_groups = CN_IDX_PROC;
+        let bound = unsafe {
+            libc::bind(
+                socket.as_raw_fd(),
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let connector = Self {
+            socket,
+            buffer: vec![0u64; 1024],
+        };
+        connector.listen()?;
+        Ok(connector)
+    }
+
+    fn listen(&self) -> io::Result<()> {
+        let mut message = Vec::with_capacity(NLMSG_HEADER_LEN + CN_MSG_LEN + 4);
+        // nlmsghdr: len, type, flags, seq, pid
+        message.extend_from_slice(&((NLMSG_HEADER_LEN + CN_MSG_LEN + 4) as u32).to_ne_bytes());
+        message.extend_from_slice(&NLMSG_DONE.to_ne_bytes());
+        message.extend_from_slice(&0u16.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        // cn_msg: id.idx, id.val, seq, ack, len, flags
+        message.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+        message.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&4u16.to_ne_bytes());
+        message.extend_from_slice(&0u16.to_ne_bytes());
+        message.extend_from_slice(&PROC_CN_MCAST_LISTEN.to_ne_bytes());
+
+        let sent = unsafe { libc::send(self.socket.as_raw_fd(), message.as_ptr() as *const libc::c_void, message.len(), 0) };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Blocks until the next datagram and returns the process events in it.
+    /// Events for threads other than a thread group's leader are left out.
+    pub(super) fn recv(&mut self) -> io::Result<Vec<ProcEvent>> {
+        let capacity = self.buffer.len() * std::mem::size_of::<u64>();
+        let received = unsafe {
+            libc::recv(self.socket.as_raw_fd(), self.buffer.as_mut_ptr() as *mut libc::c_void, capacity, 0)
+        };
+        if received < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let bytes = unsafe { std::slice::from_raw_parts(self.buffer.as_ptr() as *const u8, received as usize) };
+        let mut events = Vec::new();
+        let mut offset = 0;
+
+        while offset + NLMSG_HEADER_LEN <= bytes.len() {
+            let message_len = read_u32(bytes, offset) as usize;
+            if message_len < NLMSG_HEADER_LEN || offset + message_len > bytes.len() {
+                break;
+            }
+            if let Some(event) = parse_proc_event(&bytes[offset..offset + message_len]) {
+                events.push(event);
+            }
+            // NLMSG_ALIGN
+            offset += (message_len + 3) & !3;
+        }
+
+        Ok(events)
+    }
+}
+
+impl AsRawFd for ProcConnector {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+fn parse_proc_event(message: &[u8]) -> Option<ProcEvent> {
+    if message.len() < PROC_EVENT_DATA_OFFSET + 16 {
+        return None;
+    }
+    let idx = read_u32(message, NLMSG_HEADER_LEN);
+    let val = read_u32(message, NLMSG_HEADER_LEN + 4);
+    if idx != CN_IDX_PROC || val != CN_VAL_PROC {
+        return None;
+    }
+
+    let data = PROC_EVENT_DATA_OFFSET;
+    match read_u32(message, NLMSG_HEADER_LEN + CN_MSG_LEN) {
+        // parent_pid, parent_tgid, child_pid, child_tgid
+        PROC_EVENT_FORK => {
+            let parent_pid = read_u32(message, data + 4);
+            let (child_pid, child_tgid) = (read_u32(message, data + 8), read_u32(message, data + 12));
+            (child_pid == child_tgid).then_some(ProcEvent::Fork { parent_pid, child_pid })
+        }
+        // process_pid, process_tgid
+        PROC_EVENT_EXEC => {
+            let (pid, tgid) = (read_u32(message, data), read_u32(message, data + 4));
+            (pid == tgid).then_some(ProcEvent::Exec { pid })
+        }
+        // process_pid, process_tgid, exit_code, exit_signal
+        PROC_EVENT_EXIT => {
+            let (pid, tgid) = (read_u32(message, data), read_u32(message, data + 4));
+            let exit_code = read_u32(message, data + 8);
+            (pid == tgid).then_some(ProcEvent::Exit { pid, exit_code })
+        }
+        _ => None,
+    }
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_ne_bytes(bytes[offset..offset + 4].try_into().unwrap())
+}
+
+/// The status passed to `exit()`, or `None` for a process killed by a signal.
+pub(super) fn exit_status(wait_status: u32) -> Option<i32> {
+    if wait_status & 0x7f == 0 {
+        Some(((wait_status >> 8) & 0xff) as i32)
+    } else {
+        None
+    }
+}
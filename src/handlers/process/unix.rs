@@ -1,22 +1,38 @@
-use super::{ProcessConfig, ProcessSnapshot, ProcessHandler};
-use crate::events::{ProcessEventType};
-use crate::{EventMessage, HandlerId, Result, TellMeWhenError};
-use crossbeam_channel::Sender;
+use super::{threshold_event, ProcessConfig, ProcessSample, ProcessSnapshot, ProcessHandler};
+use crate::events::{LifecycleEventType, ProcessEventType, StopReason};
+use crate::{EventMessage, EventSender, HandlerId, Result, TellMeWhenError};
 use sysinfo::{System};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
 use tokio::task;
 
+// How long the blocking monitor threads wait for their descriptors before
+// checking whether the handler has been stopped
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+// Whether `fd` became readable within `timeout`
+fn wait_readable(fd: RawFd, timeout: Duration) -> std::io::Result<bool> {
+    let mut pollfd = libc::pollfd { fd, events: libc::POLLIN, revents: 0 };
+    match unsafe { libc::poll(&mut pollfd, 1, timeout.as_millis() as libc::c_int) } {
+        -1 => Err(std::io::Error::last_os_error()),
+        0 => Ok(false),
+        _ => Ok(true),
+    }
+}
+
 pub async fn start_process_monitoring(
-    config: &ProcessConfig,
-    system: &Arc<Mutex<System>>,
+    config: &Arc<Mutex<ProcessConfig>>,
+    system: &Arc<RwLock<System>>,
     previous_processes: &Arc<Mutex<HashMap<u32, ProcessSnapshot>>>,
     is_running: &Arc<Mutex<bool>>,
-    sender: Sender<EventMessage>,
+    sender: EventSender,
     handler_id: HandlerId,
 ) -> Result<()> {
-    let config = config.clone();
+    // The proc connector reads filters live; the rest is fixed at start
+    let live_config = Arc::clone(config);
+    let config = config.lock().unwrap().clone();
     let is_running = Arc::clone(is_running);
     let sender_clone = sender.clone();
     let handler_id_clone = handler_id.clone();
@@ -26,11 +42,19 @@ pub async fn start_process_monitoring(
         let netlink_sender = sender_clone.clone();
         let netlink_handler_id = handler_id_clone.clone();
         let netlink_is_running = Arc::clone(&is_running);
-        let netlink_config = config.clone();
+        let netlink_config = live_config;
         
         task::spawn_blocking(move || {
-            if let Err(e) = monitor_process_events_via_proc_connector(netlink_config, netlink_sender, netlink_handler_id, netlink_is_running) {
+            let _active = crate::metrics::track_thread();
+            if let Err(e) = monitor_process_events_via_proc_connector(netlink_config, netlink_sender.clone(), netlink_handler_id.clone(), netlink_is_running) {
                 log::error!("Linux proc connector process monitoring failed: {}", e);
+                let _ = netlink_sender.send(EventMessage::lifecycle(
+                    netlink_handler_id.clone(),
+                    LifecycleEventType::HandlerStopped {
+                        handler: netlink_handler_id,
+                        reason: StopReason::BackendFailure(e.to_string()),
+                    },
+                ));
             }
         });
     }
@@ -44,6 +68,7 @@ pub async fn start_process_monitoring(
     let resource_is_running = Arc::clone(&is_running);
 
     task::spawn_blocking(move || {
+        let _active = crate::metrics::track_thread();
         monitor_resource_events_via_cgroups(resource_config, resource_system, resource_previous, resource_sender, resource_handler_id, resource_is_running);
     });
 
@@ -51,87 +76,131 @@ pub async fn start_process_monitoring(
 }
 
 fn monitor_process_events_via_proc_connector(
-    config: ProcessConfig,
-    sender: Sender<EventMessage>,
+    config: Arc<Mutex<ProcessConfig>>,
+    sender: EventSender,
     handler_id: HandlerId,
     is_running: Arc<Mutex<bool>>,
 ) -> Result<()> {
-    use cnproc::{Listener, Event};
+    use super::proc_connector::{exit_status, ProcConnector, ProcEvent};
     
     log::info!("Starting Linux process monitoring via netlink proc connector (event-driven)");
 
     // Create proc connector listener - this uses real netlink sockets
-    let mut listener = Listener::new().map_err(|e| {
+    let mut listener = ProcConnector::new().map_err(|e| {
         TellMeWhenError::System(format!("Failed to create proc connector listener: {}", e))
     })?;
 
     log::info!("Netlink proc connector enabled - listening for real OS process events");
 
+    // A parent that reaps its child straight away leaves nothing in /proc by
+    // the time the exit arrives, so names are remembered from fork and exec
+    let mut names: HashMap<u32, String> = HashMap::new();
+    let mut parents: HashMap<u32, u32> = HashMap::new();
+
     // Listen for process events - this is a blocking event-driven loop (NO POLLING!)
     while *is_running.lock().unwrap() {
-        match listener.recv() {
-            Ok(event) => {
-                match event {
-                    Event::Fork { parent_pid, child_pid, .. } => {
-                        if config.monitor_new_processes {
-                            log::debug!("Process fork event via netlink: parent {} -> child {}", parent_pid, child_pid);
-                            
-                            let process_name = get_process_name_linux(child_pid).unwrap_or_else(|| format!("pid:{}", child_pid));
-                            
+        match wait_readable(listener.as_raw_fd(), STOP_CHECK_INTERVAL) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                log::warn!("Proc connector poll error: {}", e);
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                continue;
+            }
+        }
+        let events = match listener.recv() {
+            Ok(events) => events,
+            Err(e) => {
+                log::warn!("Proc connector recv error: {}", e);
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                continue;
+            }
+        };
+        // Nothing is reported once the handler has been stopped
+        if !*is_running.lock().unwrap() {
+            break;
+        }
+
+        for event in events {
+            match event {
+                ProcEvent::Fork { parent_pid, child_pid } => {
+                    let name = get_process_name_linux(child_pid);
+                    if let Some(name) = &name {
+                        names.insert(child_pid, name.clone());
+                    }
+                    parents.insert(child_pid, parent_pid);
+
+                    if config.lock().unwrap().monitor_new_processes {
+                        log::debug!("Process fork event via netlink: child {}", child_pid);
+                        
+                        let process_name = name.unwrap_or_else(|| format!("pid:{}", child_pid));
+                        let exe_path = get_process_exe_linux(child_pid);
+                        
+                        if config.lock().unwrap().should_monitor_process(&process_name, exe_path.as_deref()) {
                             ProcessHandler::emit_process_event(
                                 ProcessEventType::Started,
                                 child_pid,
                                 process_name,
-                                None,
-                                None,
+                                ProcessSample { parent_pid: Some(parent_pid), ..Default::default() },
                                 &sender,
                                 &handler_id,
                             );
                         }
                     }
-                    Event::Exec { pid, .. } => {
-                        if config.monitor_new_processes {
-                            log::debug!("Process exec event via netlink: PID {}", pid);
-                            
-                            let process_name = get_process_name_linux(pid).unwrap_or_else(|| format!("pid:{}", pid));
-                            
+                }
+                ProcEvent::Exec { pid } => {
+                    let name = get_process_name_linux(pid);
+                    if let Some(name) = &name {
+                        names.insert(pid, name.clone());
+                    }
+
+                    // Processes forked before monitoring started have no fork event
+                    let parent_pid = parents.get(&pid).copied().or_else(|| get_parent_pid_linux(pid));
+
+                    if config.lock().unwrap().monitor_new_processes {
+                        log::debug!("Process exec event via netlink: PID {}", pid);
+                        
+                        let process_name = name.unwrap_or_else(|| format!("pid:{}", pid));
+                        let exe_path = get_process_exe_linux(pid);
+                        
+                        if config.lock().unwrap().should_monitor_process(&process_name, exe_path.as_deref()) {
                             ProcessHandler::emit_process_event(
                                 ProcessEventType::Started,
                                 pid,
                                 process_name,
-                                None,
-                                None,
+                                ProcessSample { parent_pid, ..Default::default() },
                                 &sender,
                                 &handler_id,
                             );
                         }
                     }
-                    Event::Exit { pid, exit_code, .. } => {
-                        if config.monitor_terminated_processes {
-                            log::debug!("Process exit event via netlink: PID {} (exit code: {})", pid, exit_code);
-                            
-                            let process_name = format!("pid:{}", pid); // Process is already gone
-                            
+                }
+                ProcEvent::Exit { pid, exit_code } => {
+                    let name = names.remove(&pid);
+                    parents.remove(&pid);
+
+                    if config.lock().unwrap().monitor_terminated_processes {
+                        log::debug!("Process exit event via netlink: PID {} (wait status: {:#x})", pid, exit_code);
+                        
+                        // Processes started before monitoring may still be readable as zombies
+                        let process_name = name
+                            .or_else(|| get_process_name_linux(pid))
+                            .unwrap_or_else(|| format!("pid:{}", pid));
+                        
+                        if config.lock().unwrap().should_monitor_process(&process_name, None) {
                             ProcessHandler::emit_process_event(
                                 ProcessEventType::Terminated,
                                 pid,
                                 process_name,
-                                None,
-                                None,
+                                ProcessSample { exit_code: exit_status(exit_code), ..Default::default() },
                                 &sender,
                                 &handler_id,
                             );
                         }
                     }
-                    _ => {
-                        // Other events (UID/GID/SID changes) - ignore for now
-                    }
                 }
             }
-            Err(e) => {
-                log::warn!("Proc connector recv error: {}", e);
-                std::thread::sleep(std::time::Duration::from_millis(100));
-            }
         }
     }
 
@@ -140,9 +209,9 @@ fn monitor_process_events_via_proc_connector(
 
 fn monitor_resource_events_via_cgroups(
     config: ProcessConfig,
-    _system: Arc<Mutex<System>>,
-    _previous_processes: Arc<Mutex<HashMap<u32, ProcessSnapshot>>>,
-    sender: Sender<EventMessage>,
+    _system: Arc<RwLock<System>>,
+    previous_processes: Arc<Mutex<HashMap<u32, ProcessSnapshot>>>,
+    sender: EventSender,
     handler_id: HandlerId,
     is_running: Arc<Mutex<bool>>,
 ) {
@@ -150,11 +219,9 @@ fn monitor_resource_events_via_cgroups(
     
     // Use cgroups v2 pressure stall information for CPU/memory pressure events
     // This is event-driven, not polling!
-    use std::fs::File;
-    use std::io::{BufRead, BufReader};
     use nix::sys::inotify::{Inotify, AddWatchFlags, InitFlags};
     use nix::unistd::read;
-    use std::os::unix::io::AsRawFd;
+    use std::os::fd::AsFd;
     
     // Monitor cgroups pressure files for threshold events
     let cpu_pressure_path = "/sys/fs/cgroup/cpu.pressure";
@@ -185,11 +252,22 @@ fn monitor_resource_events_via_cgroups(
     
     while *is_running.lock().unwrap() {
         // Block waiting for pressure events (NO POLLING!)
-        match read(inotify.as_raw_fd(), &mut buffer) {
+        match wait_readable(inotify.as_fd().as_raw_fd(), STOP_CHECK_INTERVAL) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => {
+                log::warn!("inotify poll error: {}", e);
+                std::thread::sleep(std::time::Duration::from_millis(100));
+                continue;
+            }
+        }
+        match read(inotify.as_fd(), &mut buffer) {
+            Ok(_) if !*is_running.lock().unwrap() => break,
             Ok(_) => {
                 // Pressure file changed - check current pressure levels
-                check_cpu_pressure(&config, &sender, &handler_id);
-                check_memory_pressure(&config, &sender, &handler_id);
+                check_cpu_pressure(&config, &previous_processes, &sender, &handler_id);
+                check_memory_pressure(&config, &previous_processes, &sender, &handler_id);
             }
             Err(e) => {
                 log::warn!("inotify read error: {}", e);
@@ -199,9 +277,21 @@ fn monitor_resource_events_via_cgroups(
     }
 }
 
+// Reads current pressure without waiting for the PSI files to change
+pub(super) fn check_pressure_now(
+    config: &ProcessConfig,
+    previous_processes: &Mutex<HashMap<u32, ProcessSnapshot>>,
+    sender: &EventSender,
+    handler_id: &HandlerId,
+) {
+    check_cpu_pressure(config, previous_processes, sender, handler_id);
+    check_memory_pressure(config, previous_processes, sender, handler_id);
+}
+
 fn check_cpu_pressure(
     config: &ProcessConfig,
-    sender: &Sender<EventMessage>,
+    previous_processes: &Mutex<HashMap<u32, ProcessSnapshot>>,
+    sender: &EventSender,
     handler_id: &HandlerId,
 ) {
     use std::fs;
@@ -212,15 +302,17 @@ fn check_cpu_pressure(
         if let Some(line) = content.lines().find(|l| l.starts_with("some")) {
             if let Some(avg10_part) = line.split_whitespace().find(|p| p.starts_with("avg10=")) {
                 if let Ok(pressure) = avg10_part[6..].parse::<f32>() {
-                    if pressure > config.cpu_threshold {
-                        log::debug!("CPU pressure threshold exceeded: {}%", pressure);
+                    let above = pressure > config.cpu_threshold;
+                    // pid 0 stands for the whole system
+                    let event = threshold_event(previous_processes, config, 0, ProcessEventType::CpuUsageHigh, above);
+                    if let Some(event_type) = event {
+                        log::debug!("CPU pressure {}: {}%", event_type, pressure);
                         
                         ProcessHandler::emit_process_event(
-                            ProcessEventType::CpuUsageHigh,
+                            event_type,
                             0, // System-wide
                             "system".to_string(),
-                            Some(pressure),
-                            None,
+                            ProcessSample { cpu_usage: Some(pressure), ..Default::default() },
                             sender,
                             handler_id,
                         );
@@ -233,7 +325,8 @@ fn check_cpu_pressure(
 
 fn check_memory_pressure(
     config: &ProcessConfig,
-    sender: &Sender<EventMessage>,
+    previous_processes: &Mutex<HashMap<u32, ProcessSnapshot>>,
+    sender: &EventSender,
     handler_id: &HandlerId,
 ) {
     use std::fs;
@@ -246,15 +339,16 @@ fn check_memory_pressure(
                 if let Ok(pressure) = avg10_part[6..].parse::<f32>() {
                     // Convert to memory usage approximation
                     let memory_threshold_mb = (config.memory_threshold / 1024 / 1024) as f32;
-                    if pressure > 10.0 { // 10% memory pressure is significant
-                        log::debug!("Memory pressure threshold exceeded: {}%", pressure);
+                    let above = pressure > 10.0; // 10% memory pressure is significant
+                    let event = threshold_event(previous_processes, config, 0, ProcessEventType::MemoryUsageHigh, above);
+                    if let Some(event_type) = event {
+                        log::debug!("Memory pressure {}: {}%", event_type, pressure);
                         
                         ProcessHandler::emit_process_event(
-                            ProcessEventType::MemoryUsageHigh,
+                            event_type,
                             0, // System-wide
                             "system".to_string(),
-                            None,
-                            Some((pressure * memory_threshold_mb) as u64 * 1024 * 1024),
+                            ProcessSample { memory_usage: Some((pressure * memory_threshold_mb) as u64 * 1024 * 1024), ..Default::default() },
                             sender,
                             handler_id,
                         );
@@ -273,4 +367,15 @@ fn get_process_name_linux(pid: u32) -> Option<String> {
     fs::read_to_string(comm_path)
         .ok()
         .map(|s| s.trim().to_string())
+}
+
+// Field 4 of /proc/PID/stat, after the parenthesised name (which may contain spaces)
+fn get_parent_pid_linux(pid: u32) -> Option<u32> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let (_, after_name) = stat.rsplit_once(')')?;
+    after_name.split_whitespace().nth(1)?.parse().ok()
+}
+
+fn get_process_exe_linux(pid: u32) -> Option<std::path::PathBuf> {
+    std::fs::read_link(format!("/proc/{}/exe", pid)).ok()
 }
\ No newline at end of file
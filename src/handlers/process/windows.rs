@@ -1,22 +1,24 @@
-use super::{ProcessConfig, ProcessSnapshot, ProcessHandler};
-use crate::events::{ProcessEventType};
-use crate::{EventMessage, HandlerId, Result, TellMeWhenError};
-use crossbeam_channel::Sender;
+use super::{ProcessConfig, ProcessSample, ProcessSnapshot, ProcessHandler};
+use crate::events::{LifecycleEventType, ProcessEventType, StopReason};
+use crate::{EventMessage, EventSender, HandlerId, Result, TellMeWhenError};
 use sysinfo::{System};
 use std::collections::HashMap;
-use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use futures::StreamExt;
 use tokio::task;
 
 pub async fn start_process_monitoring(
-    config: &ProcessConfig,
-    system: &Arc<Mutex<System>>,
+    config: &Arc<Mutex<ProcessConfig>>,
+    system: &Arc<RwLock<System>>,
     previous_processes: &Arc<Mutex<HashMap<u32, ProcessSnapshot>>>,
     is_running: &Arc<Mutex<bool>>,
-    sender: Sender<EventMessage>,
+    sender: EventSender,
     handler_id: HandlerId,
 ) -> Result<()> {
-    let config = config.clone();
+    // Name filters are read live for every process; the rest is fixed at start
+    let live_config = Arc::clone(config);
+    let config = config.lock().unwrap().clone();
     let is_running = Arc::clone(is_running);
     let sender_clone = sender.clone();
     let handler_id_clone = handler_id.clone();
@@ -26,10 +28,13 @@ pub async fn start_process_monitoring(
         let creation_sender = sender_clone.clone();
         let creation_handler_id = handler_id_clone.clone();
         let creation_is_running = Arc::clone(&is_running);
+        let creation_config = Arc::clone(&live_config);
         
         task::spawn_blocking(move || {
-            if let Err(e) = monitor_process_creation_events(creation_sender, creation_handler_id, creation_is_running) {
+            let _active = crate::metrics::track_thread();
+            if let Err(e) = monitor_process_creation_events(creation_config, creation_sender.clone(), creation_handler_id.clone(), creation_is_running) {
                 log::error!("Process creation monitoring failed: {}", e);
+                report_backend_failure(&creation_sender, creation_handler_id, e);
             }
         });
     }
@@ -39,10 +44,13 @@ pub async fn start_process_monitoring(
         let termination_sender = sender_clone.clone();
         let termination_handler_id = handler_id_clone.clone();
         let termination_is_running = Arc::clone(&is_running);
+        let termination_config = Arc::clone(&live_config);
         
         task::spawn_blocking(move || {
-            if let Err(e) = monitor_process_termination_events(termination_sender, termination_handler_id, termination_is_running) {
+            let _active = crate::metrics::track_thread();
+            if let Err(e) = monitor_process_termination_events(termination_config, termination_sender.clone(), termination_handler_id.clone(), termination_is_running) {
                 log::error!("Process termination monitoring failed: {}", e);
+                report_backend_failure(&termination_sender, termination_handler_id, e);
             }
         });
     }
@@ -52,141 +60,258 @@ pub async fn start_process_monitoring(
 }
 
 fn monitor_process_creation_events(
-    sender: Sender<EventMessage>,
+    config: Arc<Mutex<ProcessConfig>>,
+    sender: EventSender,
     handler_id: HandlerId,
     is_running: Arc<Mutex<bool>>,
 ) -> Result<()> {
-    use wmi::{WMIConnection, COMLibrary, Variant};
-    use std::collections::HashMap;
-    
     log::info!("Starting Windows process creation event monitoring via WMI event notifications");
 
-    let com_lib = COMLibrary::new().map_err(|e| {
-        TellMeWhenError::System(format!("Failed to initialize COM library: {}", e))
-    })?;
-    
-    let wmi_con = WMIConnection::new(com_lib).map_err(|e| {
-        TellMeWhenError::System(format!("Failed to create WMI connection: {}", e))
-    })?;
-
     // Use WMI raw notification for process start events
-    let query = "SELECT ProcessID, ProcessName FROM Win32_ProcessStartTrace";
-    
-    while *is_running.lock().unwrap() {
-        match wmi_con.raw_notification::<HashMap<String, Variant>>(query) {
-            Ok(iterator) => {
-                for event_result in iterator {
-                    if !*is_running.lock().unwrap() {
-                        break;
-                    }
-                    
-                    match event_result {
-                        Ok(event) => {
-                            if let (Some(pid_value), Some(name_value)) = 
-                                (event.get("ProcessID"), event.get("ProcessName")) {
-                                
-                                // Extract values based on WMI variant type
-                                    let pid = extract_u32_from_variant(pid_value)?;
-                                    let name = extract_string_from_variant(name_value)?;
-                                
-                                log::debug!("WMI Process creation event: {} (PID: {})", name, pid);
-                                
-                                ProcessHandler::emit_process_event(
-                                    ProcessEventType::Started,
-                                    pid,
-                                    name,
-                                    None,
-                                    None,
-                                    &sender,
-                                    &handler_id,
-                                );
-                            }
-                        }
-                        Err(e) => {
-                            log::warn!("WMI event error: {}", e);
-                        }
-                    }
-                }
-            }
-            Err(e) => {
-                log::error!("WMI notification query failed: {}", e);
-                // Wait a bit before retrying to avoid tight loop
-                std::thread::sleep(std::time::Duration::from_secs(5));
+    let query = "SELECT ProcessID, ParentProcessID, ProcessName FROM Win32_ProcessStartTrace";
+
+    watch_wmi_events(query, &sender, &handler_id, &is_running, |event| {
+        if let (Some(pid_value), Some(name_value)) =
+            (event.get("ProcessID"), event.get("ProcessName")) {
+
+            // Extract values based on WMI variant type
+            let pid = extract_u32_from_variant(pid_value)?;
+            let name = extract_string_from_variant(name_value)?;
+
+            let parent_pid = event
+                .get("ParentProcessID")
+                .and_then(|value| extract_u32_from_variant(value).ok());
+
+            log::debug!("WMI Process creation event: {} (PID: {})", name, pid);
+
+            if config.lock().unwrap().should_monitor_process(&name, None) {
+                ProcessHandler::emit_process_event(
+                    ProcessEventType::Started,
+                    pid,
+                    name,
+                    ProcessSample { parent_pid, ..Default::default() },
+                    &sender,
+                    &handler_id,
+                );
             }
         }
-    }
-
-    Ok(())
+        Ok(())
+    })
 }
 
 fn monitor_process_termination_events(
-    sender: Sender<EventMessage>,
+    config: Arc<Mutex<ProcessConfig>>,
+    sender: EventSender,
     handler_id: HandlerId,
     is_running: Arc<Mutex<bool>>,
 ) -> Result<()> {
-    use wmi::{WMIConnection, COMLibrary, Variant};
-    use std::collections::HashMap;
-    
     log::info!("Starting Windows process termination event monitoring via WMI event notifications");
 
+    // Use WMI raw notification for process stop events
+    let query = "SELECT ProcessID, ProcessName, ExitStatus FROM Win32_ProcessStopTrace";
+
+    watch_wmi_events(query, &sender, &handler_id, &is_running, |event| {
+        if let (Some(pid_value), Some(name_value)) =
+            (event.get("ProcessID"), event.get("ProcessName")) {
+
+            // Extract values based on WMI variant type
+            let pid = extract_u32_from_variant(pid_value)?;
+            let name = extract_string_from_variant(name_value)?;
+
+            // Exit codes are DWORDs; NTSTATUS failures come out negative
+            let exit_code = event
+                .get("ExitStatus")
+                .and_then(|value| extract_u32_from_variant(value).ok())
+                .map(|status| status as i32);
+
+            log::debug!("WMI Process termination event: {} (PID: {})", name, pid);
+
+            if config.lock().unwrap().should_monitor_process(&name, None) {
+                ProcessHandler::emit_process_event(
+                    ProcessEventType::Terminated,
+                    pid,
+                    name,
+                    ProcessSample { exit_code, ..Default::default() },
+                    &sender,
+                    &handler_id,
+                );
+            }
+        }
+        Ok(())
+    })
+}
+
+// How long a monitor waits on WMI before checking whether it was stopped
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(250);
+
+// Reconnect attempts start a second apart and back off to once a minute
+const RETRY_INITIAL: Duration = Duration::from_secs(1);
+const RETRY_MAX: Duration = Duration::from_secs(60);
+
+/// Delay before the next reconnect attempt. Doubles with every consecutive
+/// failure up to `max` and starts over from `initial` after a success.
+#[derive(Debug, Clone)]
+struct Backoff {
+    initial: Duration,
+    max: Duration,
+    failures: u32,
+}
+
+impl Backoff {
+    fn new(initial: Duration, max: Duration) -> Self {
+        Self { initial, max, failures: 0 }
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.initial.saturating_mul(2u32.saturating_pow(self.failures)).min(self.max);
+        self.failures = self.failures.saturating_add(1);
+        delay
+    }
+
+    fn reset(&mut self) {
+        self.failures = 0;
+    }
+}
+
+// Tracks whether a WMI monitor is between a failure and its recovery, and
+// tells subscribers when it crosses either way
+struct Reconnect<'a> {
+    backoff: Backoff,
+    degraded: bool,
+    sender: &'a EventSender,
+    handler_id: &'a HandlerId,
+}
+
+impl Reconnect<'_> {
+    fn connected(&mut self) {
+        self.backoff.reset();
+        if std::mem::take(&mut self.degraded) {
+            log::info!("WMI process monitoring recovered");
+            let _ = self.sender.send(EventMessage::lifecycle(
+                self.handler_id.clone(),
+                LifecycleEventType::HandlerRecovered { handler: self.handler_id.clone() },
+            ));
+        }
+    }
+
+    // Reports the failure and sleeps until the next attempt is due, waking
+    // early if monitoring is stopped in the meantime
+    fn failed(&mut self, error: TellMeWhenError, is_running: &Mutex<bool>) {
+        let retry_in = self.backoff.next_delay();
+        log::error!("{}; reconnecting in {:?}", error, retry_in);
+        self.degraded = true;
+        let _ = self.sender.send(EventMessage::lifecycle(
+            self.handler_id.clone(),
+            LifecycleEventType::HandlerDegraded {
+                handler: self.handler_id.clone(),
+                error: error.to_string(),
+                retry_in,
+            },
+        ));
+
+        let deadline = Instant::now() + retry_in;
+        while *is_running.lock().unwrap() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(100).min(deadline - Instant::now()));
+        }
+    }
+}
+
+fn connect_wmi() -> Result<wmi::WMIConnection> {
+    use wmi::{WMIConnection, COMLibrary};
+
     let com_lib = COMLibrary::new().map_err(|e| {
         TellMeWhenError::System(format!("Failed to initialize COM library: {}", e))
     })?;
-    
-    let wmi_con = WMIConnection::new(com_lib).map_err(|e| {
+
+    WMIConnection::new(com_lib).map_err(|e| {
         TellMeWhenError::System(format!("Failed to create WMI connection: {}", e))
-    })?;
+    })
+}
+
+// Feeds the results of a WMI notification query to `on_event` until stopped.
+// Failing to connect at all is an error; once connected, a failed query or a
+// notification stream that breaks off re-initializes COM and the connection
+// with exponential backoff, so a transient WMI outage doesn't end monitoring.
+fn watch_wmi_events<F>(
+    query: &str,
+    sender: &EventSender,
+    handler_id: &HandlerId,
+    is_running: &Mutex<bool>,
+    mut on_event: F,
+) -> Result<()>
+where
+    F: FnMut(HashMap<String, wmi::Variant>) -> Result<()>,
+{
+    // Runs on the blocking pool, which is inside the runtime
+    let runtime = tokio::runtime::Handle::current();
+    let mut connection = Some(connect_wmi()?);
+    let mut reconnect = Reconnect {
+        backoff: Backoff::new(RETRY_INITIAL, RETRY_MAX),
+        degraded: false,
+        sender,
+        handler_id,
+    };
 
-    // Use WMI raw notification for process stop events
-    let query = "SELECT ProcessID, ProcessName FROM Win32_ProcessStopTrace";
-    
     while *is_running.lock().unwrap() {
-        match wmi_con.raw_notification::<HashMap<String, Variant>>(query) {
-            Ok(iterator) => {
-                for event_result in iterator {
-                    if !*is_running.lock().unwrap() {
-                        break;
-                    }
-                    
-                    match event_result {
-                        Ok(event) => {
-                            if let (Some(pid_value), Some(name_value)) = 
-                                (event.get("ProcessID"), event.get("ProcessName")) {
-                                
-                                // Extract values based on WMI variant type
-                                    let pid = extract_u32_from_variant(pid_value)?;
-                                    let name = extract_string_from_variant(name_value)?;
-                                
-                                log::debug!("WMI Process termination event: {} (PID: {})", name, pid);
-                                
-                                ProcessHandler::emit_process_event(
-                                    ProcessEventType::Terminated,
-                                    pid,
-                                    name,
-                                    None,
-                                    None,
-                                    &sender,
-                                    &handler_id,
-                                );
-                            }
-                        }
-                        Err(e) => {
-                            log::warn!("WMI event error: {}", e);
-                        }
-                    }
-                }
+        let wmi_con = match connection.take().map_or_else(connect_wmi, Ok) {
+            Ok(wmi_con) => wmi_con,
+            Err(e) => {
+                reconnect.failed(e, is_running);
+                continue;
             }
+        };
+
+        let mut events = match wmi_con.async_raw_notification::<HashMap<String, wmi::Variant>>(query) {
+            Ok(events) => events,
             Err(e) => {
-                log::error!("WMI notification query failed: {}", e);
-                // Wait a bit before retrying to avoid tight loop
-                std::thread::sleep(std::time::Duration::from_secs(5));
+                reconnect.failed(TellMeWhenError::System(format!("WMI notification query failed: {}", e)), is_running);
+                continue;
             }
+        };
+        reconnect.connected();
+
+        // Waits on the notifications a little at a time so a stop is noticed
+        // even when no process starts or exits
+        while *is_running.lock().unwrap() {
+            let event_result = match runtime.block_on(tokio::time::timeout(STOP_CHECK_INTERVAL, events.next())) {
+                Ok(Some(event_result)) => event_result,
+                Ok(None) => break,
+                Err(_) => continue,
+            };
+
+            // One malformed event shouldn't end monitoring for the rest
+            match event_result {
+                Ok(event) => {
+                    if let Err(e) = on_event(event) {
+                        log::warn!("Skipping WMI event: {}", e);
+                    }
+                }
+                Err(e) => {
+                    log::warn!("WMI event error: {}", e);
+                }
+            }
+        }
+        // Dropping the stream cancels the query
+        drop(events);
+
+        if *is_running.lock().unwrap() {
+            reconnect.failed(TellMeWhenError::System("WMI notification stream ended".to_string()), is_running);
         }
     }
 
     Ok(())
 }
 
+fn report_backend_failure(sender: &EventSender, handler_id: HandlerId, error: TellMeWhenError) {
+    let _ = sender.send(EventMessage::lifecycle(
+        handler_id.clone(),
+        LifecycleEventType::HandlerStopped {
+            handler: handler_id,
+            reason: StopReason::BackendFailure(error.to_string()),
+        },
+    ));
+}
+
 fn extract_u32_from_variant(variant: &wmi::Variant) -> Result<u32> {
     use wmi::Variant;
     match variant {
@@ -203,3 +328,35 @@ fn extract_string_from_variant(variant: &wmi::Variant) -> Result<String> {
         _ => Err(TellMeWhenError::System("Invalid variant type for process name".to_string())),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let mut backoff = Backoff::new(RETRY_INITIAL, RETRY_MAX);
+        let delays: Vec<u64> = (0..9).map(|_| backoff.next_delay().as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32, 60, 60, 60]);
+    }
+
+    #[test]
+    fn backoff_stays_capped_after_many_failures() {
+        let mut backoff = Backoff::new(RETRY_INITIAL, RETRY_MAX);
+        for _ in 0..100 {
+            assert!(backoff.next_delay() <= RETRY_MAX);
+        }
+        assert_eq!(backoff.next_delay(), RETRY_MAX);
+    }
+
+    #[test]
+    fn backoff_starts_over_after_a_success() {
+        let mut backoff = Backoff::new(RETRY_INITIAL, RETRY_MAX);
+        for _ in 0..4 {
+            backoff.next_delay();
+        }
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), RETRY_INITIAL);
+        assert_eq!(backoff.next_delay(), RETRY_INITIAL * 2);
+    }
+}
@@ -0,0 +1,350 @@
+use crate::events::{EventData, RegistryChangeType, RegistryEventData};
+use crate::traits::{EventHandler, EventHandlerConfig};
+use crate::{EventMessage, EventMetadata, EventSender, HandlerId, Result, TellMeWhenError};
+use std::collections::HashMap;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
+use winapi::shared::minwindef::{DWORD, HKEY};
+use winapi::shared::winerror::{ERROR_MORE_DATA, ERROR_SUCCESS, WAIT_TIMEOUT};
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::synchapi::{CreateEventW, WaitForSingleObject};
+use winapi::um::winbase::WAIT_OBJECT_0;
+use winapi::um::winnt::{KEY_NOTIFY, KEY_QUERY_VALUE, REG_NOTIFY_CHANGE_LAST_SET, REG_NOTIFY_CHANGE_NAME};
+use winapi::um::winreg::{
+    RegCloseKey, RegEnumValueW, RegNotifyChangeKeyValue, RegOpenKeyExW, HKEY_CLASSES_ROOT,
+    HKEY_CURRENT_CONFIG, HKEY_CURRENT_USER, HKEY_LOCAL_MACHINE, HKEY_USERS,
+};
+
+// How often a waiting watch wakes up to check whether it should stop
+const STOP_CHECK_INTERVAL_MS: DWORD = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RegistryHive {
+    ClassesRoot,
+    CurrentUser,
+    LocalMachine,
+    Users,
+    CurrentConfig,
+}
+
+impl RegistryHive {
+    fn hkey(&self) -> HKEY {
+        match self {
+            RegistryHive::ClassesRoot => HKEY_CLASSES_ROOT,
+            RegistryHive::CurrentUser => HKEY_CURRENT_USER,
+            RegistryHive::LocalMachine => HKEY_LOCAL_MACHINE,
+            RegistryHive::Users => HKEY_USERS,
+            RegistryHive::CurrentConfig => HKEY_CURRENT_CONFIG,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RegistryHive::ClassesRoot => "HKEY_CLASSES_ROOT",
+            RegistryHive::CurrentUser => "HKEY_CURRENT_USER",
+            RegistryHive::LocalMachine => "HKEY_LOCAL_MACHINE",
+            RegistryHive::Users => "HKEY_USERS",
+            RegistryHive::CurrentConfig => "HKEY_CURRENT_CONFIG",
+        }
+    }
+
+    pub fn key_path(&self, subkey: &str) -> String {
+        let subkey = subkey.trim_matches('\\');
+        if subkey.is_empty() {
+            self.name().to_string()
+        } else {
+            format!("{}\\{}", self.name(), subkey)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RegistryConfig {
+    pub base: EventHandlerConfig,
+    pub watch_subtree: bool,
+}
+
+impl Default for RegistryConfig {
+    fn default() -> Self {
+        Self {
+            base: EventHandlerConfig::default(),
+            watch_subtree: true,
+        }
+    }
+}
+
+pub struct RegistryHandler {
+    config: RegistryConfig,
+    // Key path -> (hive, subkey, stop flag for its watch thread)
+    watched_keys: HashMap<String, (RegistryHive, String, Arc<AtomicBool>)>,
+    pub event_sender: Option<EventSender>,
+    is_running: bool,
+    handler_id: HandlerId,
+}
+
+impl RegistryHandler {
+    pub fn new(handler_id: HandlerId) -> Self {
+        Self::with_config(handler_id, RegistryConfig::default())
+    }
+
+    pub fn with_config(handler_id: HandlerId, config: RegistryConfig) -> Self {
+        Self {
+            config,
+            watched_keys: HashMap::new(),
+            event_sender: None,
+            is_running: false,
+            handler_id,
+        }
+    }
+
+    pub fn watch_key(&mut self, hive: RegistryHive, subkey: &str) -> Result<()> {
+        let key_path = hive.key_path(subkey);
+        if self.watched_keys.contains_key(&key_path) {
+            return Ok(());
+        }
+
+        let sender = self.event_sender.clone().ok_or_else(|| {
+            TellMeWhenError::Config("Registry handler has no event sender".to_string())
+        })?;
+
+        let is_running = Arc::new(AtomicBool::new(true));
+        let watch_is_running = Arc::clone(&is_running);
+        let subkey = subkey.trim_matches('\\').to_string();
+        let watch_subkey = subkey.clone();
+        let watch_subtree = self.config.watch_subtree;
+        let handler_id = self.handler_id.clone();
+        let watch_key_path = key_path.clone();
+
+        // RegNotifyChangeKeyValue registrations belong to the calling thread,
+        // so each watch keeps its own thread for its whole lifetime
+        std::thread::Builder::new()
+            .name("tell_me_when-registry".to_string())
+            .spawn(move || {
+                let _active = crate::metrics::track_thread();
+                if let Err(e) = watch_key_blocking(hive, &watch_subkey, watch_subtree, &watch_is_running, &sender, &handler_id) {
+                    log::error!("Registry watch on {} failed: {}", watch_key_path, e);
+                }
+            })?;
+
+        self.watched_keys.insert(key_path, (hive, subkey, is_running));
+        Ok(())
+    }
+
+    pub fn unwatch_key(&mut self, hive: RegistryHive, subkey: &str) {
+        if let Some((_, _, is_running)) = self.watched_keys.remove(&hive.key_path(subkey)) {
+            is_running.store(false, Ordering::Relaxed);
+        }
+    }
+
+    pub fn watched_keys(&self) -> Vec<(RegistryHive, String)> {
+        self.watched_keys
+            .values()
+            .map(|(hive, subkey, _)| (*hive, subkey.clone()))
+            .collect()
+    }
+}
+
+fn watch_key_blocking(
+    hive: RegistryHive,
+    subkey: &str,
+    watch_subtree: bool,
+    is_running: &AtomicBool,
+    sender: &EventSender,
+    handler_id: &HandlerId,
+) -> Result<()> {
+    let key_path = hive.key_path(subkey);
+    let wide_subkey: Vec<u16> = subkey.encode_utf16().chain(std::iter::once(0)).collect();
+
+    unsafe {
+        let mut key: HKEY = ptr::null_mut();
+        let status = RegOpenKeyExW(hive.hkey(), wide_subkey.as_ptr(), 0, KEY_NOTIFY | KEY_QUERY_VALUE, &mut key);
+        if status as DWORD != ERROR_SUCCESS {
+            return Err(TellMeWhenError::System(format!("Failed to open registry key {}: error {}", key_path, status)));
+        }
+
+        let event = CreateEventW(ptr::null_mut(), 0, 0, ptr::null());
+        if event.is_null() {
+            RegCloseKey(key);
+            return Err(TellMeWhenError::System("Failed to create registry notification event".to_string()));
+        }
+
+        log::info!("Watching registry key {}", key_path);
+        let mut values = read_values(key);
+
+        while is_running.load(Ordering::Relaxed) {
+            let status = RegNotifyChangeKeyValue(
+                key,
+                watch_subtree as i32,
+                REG_NOTIFY_CHANGE_NAME | REG_NOTIFY_CHANGE_LAST_SET,
+                event,
+                1,
+            );
+            if status as DWORD != ERROR_SUCCESS {
+                log::error!("RegNotifyChangeKeyValue failed for {}: error {}", key_path, status);
+                break;
+            }
+
+            let signaled = loop {
+                match WaitForSingleObject(event, STOP_CHECK_INTERVAL_MS) {
+                    WAIT_OBJECT_0 => break true,
+                    WAIT_TIMEOUT if is_running.load(Ordering::Relaxed) => continue,
+                    _ => break false,
+                }
+            };
+            if !signaled {
+                break;
+            }
+
+            let current = read_values(key);
+            emit_value_changes(&key_path, &values, &current, sender, handler_id);
+            values = current;
+        }
+
+        CloseHandle(event);
+        RegCloseKey(key);
+    }
+
+    Ok(())
+}
+
+// Value name -> (registry type, raw data)
+unsafe fn read_values(key: HKEY) -> HashMap<String, (DWORD, Vec<u8>)> {
+    let mut values = HashMap::new();
+    // Value names are limited to 16,383 characters
+    let mut name = vec![0u16; 16384];
+    let mut data = vec![0u8; 4096];
+    let mut index = 0;
+
+    loop {
+        let mut name_len = name.len() as DWORD;
+        let mut data_len = data.len() as DWORD;
+        let mut value_type: DWORD = 0;
+
+        let status = RegEnumValueW(
+            key,
+            index,
+            name.as_mut_ptr(),
+            &mut name_len,
+            ptr::null_mut(),
+            &mut value_type,
+            data.as_mut_ptr(),
+            &mut data_len,
+        ) as DWORD;
+
+        match status {
+            ERROR_SUCCESS => {
+                let value_name = String::from_utf16_lossy(&name[..name_len as usize]);
+                values.insert(value_name, (value_type, data[..data_len as usize].to_vec()));
+                index += 1;
+            }
+            // data_len now holds the required size; retry the same index
+            ERROR_MORE_DATA => data.resize(data_len as usize, 0),
+            _ => break,
+        }
+    }
+
+    values
+}
+
+fn emit_value_changes(
+    key_path: &str,
+    previous: &HashMap<String, (DWORD, Vec<u8>)>,
+    current: &HashMap<String, (DWORD, Vec<u8>)>,
+    sender: &EventSender,
+    handler_id: &HandlerId,
+) {
+    let mut changed = false;
+
+    for (name, value) in current {
+        let change_type = match previous.get(name) {
+            None => RegistryChangeType::ValueCreated,
+            Some(previous_value) if previous_value != value => RegistryChangeType::ValueModified,
+            Some(_) => continue,
+        };
+        emit_registry_event(key_path, Some(name.clone()), change_type, sender, handler_id);
+        changed = true;
+    }
+
+    for name in previous.keys().filter(|name| !current.contains_key(*name)) {
+        emit_registry_event(key_path, Some(name.clone()), RegistryChangeType::ValueDeleted, sender, handler_id);
+        changed = true;
+    }
+
+    // The notification fired but this key's own values are unchanged, so the
+    // change was to a subkey (or one of its values, when watching the subtree)
+    if !changed {
+        emit_registry_event(key_path, None, RegistryChangeType::SubkeyChanged, sender, handler_id);
+    }
+}
+
+fn emit_registry_event(
+    key_path: &str,
+    value: Option<String>,
+    change_type: RegistryChangeType,
+    sender: &EventSender,
+    handler_id: &HandlerId,
+) {
+    let event_data = RegistryEventData {
+        key: key_path.to_string(),
+        value,
+        change_type,
+        timestamp: SystemTime::now(),
+    };
+
+    let message = EventMessage {
+        metadata: EventMetadata {
+            id: 0, // Will be set by event bus
+            handler_id: handler_id.clone(),
+            timestamp: SystemTime::now(),
+            source: crate::event_source(handler_id, "registry"),
+            dedup_key: None,
+        },
+        data: EventData::Registry(event_data),
+    };
+
+    if let Err(e) = sender.send(message) {
+        log::error!("Failed to send registry event: {}", e);
+    }
+}
+
+#[async_trait::async_trait]
+impl EventHandler for RegistryHandler {
+    type EventType = RegistryEventData;
+    type Config = RegistryConfig;
+
+    async fn start(&mut self, config: Self::Config) -> Result<()> {
+        if self.is_running {
+            return Ok(());
+        }
+
+        self.config = config;
+        self.is_running = true;
+
+        log::info!("Registry handler started with id: {}", self.handler_id);
+        Ok(())
+    }
+
+    async fn stop(&mut self) -> Result<()> {
+        if !self.is_running {
+            return Ok(());
+        }
+
+        for (_, (_, _, is_running)) in self.watched_keys.drain() {
+            is_running.store(false, Ordering::Relaxed);
+        }
+
+        self.is_running = false;
+        log::info!("Registry handler stopped: {}", self.handler_id);
+        Ok(())
+    }
+
+    fn is_running(&self) -> bool {
+        self.is_running
+    }
+
+    fn name(&self) -> &'static str {
+        "registry"
+    }
+}
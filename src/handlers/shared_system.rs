@@ -0,0 +1,156 @@
+use crossbeam_channel::{RecvTimeoutError, Sender};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use sysinfo::{ProcessRefreshKind, RefreshKind, System, MINIMUM_CPU_UPDATE_INTERVAL};
+
+/// A `sysinfo::System` shared by the process, system and network handlers.
+///
+/// A single refresher thread calls `refresh_all` once per tick, so running
+/// several sysinfo-backed handlers does not rescan the process table once per
+/// handler. Consumers only ever take the read lock. The refresh blocks for as
+/// long as the scan takes, which is why it stays off the tokio workers.
+///
+/// sysinfo computes CPU usage from the difference between two refreshes, and
+/// the figures are only meaningful when those are at least
+/// `sysinfo::MINIMUM_CPU_UPDATE_INTERVAL` (200 ms on Linux, macOS and
+/// Windows) apart. The refresher never runs faster than that, and CPU usage
+/// reads as 0 until `cpu_ready` reports the first such pair has been taken.
+pub struct SharedSystem {
+    system: Arc<RwLock<System>>,
+    refresh_count: Arc<AtomicU64>,
+    cpu_ready: Arc<AtomicBool>,
+    // Dropping it wakes the refresher thread and ends it
+    stop_refresher: Mutex<Option<Sender<()>>>,
+}
+
+impl SharedSystem {
+    pub fn new() -> Self {
+        Self {
+            system: Arc::new(RwLock::new(System::new_all())),
+            refresh_count: Arc::new(AtomicU64::new(0)),
+            cpu_ready: Arc::new(AtomicBool::new(false)),
+            stop_refresher: Mutex::new(None),
+        }
+    }
+
+    pub fn system(&self) -> Arc<RwLock<System>> {
+        self.system.clone()
+    }
+
+    /// Starts the refresher if it isn't already running. Later calls are
+    /// no-ops, so the first handler to start decides the refresh interval,
+    /// which is raised to `MINIMUM_CPU_UPDATE_INTERVAL` if shorter.
+    pub fn start(&self, refresh_interval: Duration) {
+        let mut stop_refresher = self.stop_refresher.lock().unwrap();
+        if stop_refresher.is_some() {
+            return;
+        }
+
+        let system = self.system.clone();
+        let refresh_count = self.refresh_count.clone();
+        let cpu_ready = self.cpu_ready.clone();
+        let refresh_interval = refresh_interval.max(MINIMUM_CPU_UPDATE_INTERVAL);
+        let (stop_sender, stop) = crossbeam_channel::bounded::<()>(0);
+
+        let spawned = std::thread::Builder::new()
+            .name("tell_me_when-sysinfo".to_string())
+            .spawn(move || {
+                let _active = crate::metrics::track_thread();
+                // `System::new_all` took the first reading, so the first
+                // refresh waits out the minimum interval to complete the pair
+                let mut next_refresh = Instant::now() + MINIMUM_CPU_UPDATE_INTERVAL;
+
+                while let Err(RecvTimeoutError::Timeout) =
+                    stop.recv_timeout(next_refresh.saturating_duration_since(Instant::now()))
+                {
+                    system.write().unwrap().refresh_all();
+                    refresh_count.fetch_add(1, Ordering::Relaxed);
+                    cpu_ready.store(true, Ordering::Relaxed);
+                    // A refresh that overran its tick isn't made up for
+                    next_refresh = (next_refresh + refresh_interval).max(Instant::now());
+                }
+            });
+
+        match spawned {
+            Ok(_) => *stop_refresher = Some(stop_sender),
+            Err(e) => log::error!("Failed to spawn the sysinfo refresher thread: {}", e),
+        }
+    }
+
+    /// Refreshes immediately, outside the regular schedule. CPU usage is
+    /// left to the refresher, as an early refresh would shorten its window.
+    pub fn refresh_now(&self) {
+        let refresh_kind = RefreshKind::everything()
+            .without_cpu()
+            .with_processes(ProcessRefreshKind::everything().without_cpu());
+        self.system.write().unwrap().refresh_specifics(refresh_kind);
+        self.refresh_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Whether CPU usage figures have been measured over a full interval yet.
+    pub fn cpu_ready(&self) -> bool {
+        self.cpu_ready.load(Ordering::Relaxed)
+    }
+
+    pub fn stop(&self) {
+        self.stop_refresher.lock().unwrap().take();
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.stop_refresher.lock().unwrap().is_some()
+    }
+
+    /// Number of refreshes performed since creation, across all consumers.
+    pub fn refresh_count(&self) -> u64 {
+        self.refresh_count.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for SharedSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SharedSystem {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(200);
+
+    #[test]
+    fn handlers_share_one_refresher() {
+        let shared_system = SharedSystem::new();
+        // One start per sysinfo-backed handler
+        for _ in 0..3 {
+            shared_system.start(TICK);
+        }
+        assert!(shared_system.is_running());
+
+        let started = Instant::now();
+        std::thread::sleep(TICK * 5);
+        let refreshes = shared_system.refresh_count();
+        let ticks = (started.elapsed().as_millis() / TICK.as_millis()) as u64;
+        // Three refreshers would each tick on their own
+        assert!(
+            refreshes <= ticks + 1,
+            "{} refreshes in {} ticks",
+            refreshes,
+            ticks
+        );
+
+        shared_system.stop();
+        assert!(!shared_system.is_running());
+        let stopped_at = shared_system.refresh_count();
+        std::thread::sleep(TICK * 2);
+        // A refresh already under way may still finish
+        assert!(shared_system.refresh_count() <= stopped_at + 1);
+    }
+}
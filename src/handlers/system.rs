@@ -1,25 +1,32 @@
-use crate::events::{EventData, SystemEventData, SystemEventType};
-use crate::traits::{EventHandler, EventHandlerConfig, ThresholdConfig, IntervalConfig};
-use crate::{EventBus, EventMessage, EventMetadata, HandlerId, Result, TellMeWhenError};
-use crossbeam_channel::Sender;
-use sysinfo::System;
-use std::sync::{Arc, Mutex};
+use crate::events::{EventAvailability, EventData, EventKind, SupportedEvent, SystemEventData, SystemEventType};
+use crate::handlers::shared_system::SharedSystem;
+use crate::traits::{EventHandler, EventHandlerConfig, ThresholdConfig, IntervalConfig, MIN_POLL_INTERVAL};
+use crate::{EventMessage, EventMetadata, EventSender, HandlerId, Result};
+use sysinfo::{Components, Disks, System};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
 use std::time::{Duration, SystemTime};
 use tokio::time::interval;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct SystemConfig {
     pub base: EventHandlerConfig,
     pub cpu_threshold: f32,
     pub memory_threshold: f32,
     pub disk_threshold: f32,
     pub temperature_threshold: f32,
+    /// Compared against the 1-minute load average. Windows has no load
+    /// average, so `LoadAverageHigh` is never emitted there.
     pub load_average_threshold: f32,
     pub monitor_cpu: bool,
     pub monitor_memory: bool,
     pub monitor_disk: bool,
     pub monitor_temperature: bool,
     pub monitor_load_average: bool,
+    /// Include absolute available/total byte counts in DiskSpaceLow events.
+    pub report_disk_bytes: bool,
 }
 
 impl Default for SystemConfig {
@@ -36,6 +43,7 @@ impl Default for SystemConfig {
             monitor_disk: true,
             monitor_temperature: true,
             monitor_load_average: true,
+            report_disk_bytes: false,
         }
     }
 }
@@ -52,7 +60,7 @@ impl ThresholdConfig for SystemConfig {
 
 impl IntervalConfig for SystemConfig {
     fn set_interval(&mut self, interval: Duration) {
-        self.base.poll_interval = interval;
+        self.base.poll_interval = interval.max(MIN_POLL_INTERVAL);
     }
 
     fn get_interval(&self) -> Duration {
@@ -60,10 +68,58 @@ impl IntervalConfig for SystemConfig {
     }
 }
 
+pub fn supported_events() -> Vec<SupportedEvent> {
+    use EventAvailability::*;
+
+    [
+        ("CpuUsageHigh", Polled),
+        ("MemoryUsageHigh", Polled),
+        ("DiskSpaceLow", Polled),
+        ("TemperatureHigh", Polled),
+        ("LoadAverageHigh", if cfg!(unix) { Polled } else { Unsupported }),
+        // The composite degradation monitor samples the shared system directly
+        ("Degraded", Polled),
+        ("Recovered", Polled),
+    ]
+    .into_iter()
+    .map(|(name, availability)| SupportedEvent::new(EventKind::System, name, availability))
+    .collect()
+}
+
+// sysinfo reads hwmon, which some VMs and SoCs don't expose; thermal zones
+// report millidegrees Celsius
+#[cfg(target_os = "linux")]
+fn thermal_zone_temperature() -> Option<f32> {
+    std::fs::read_dir("/sys/class/thermal")
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("thermal_zone"))
+        .filter_map(|entry| std::fs::read_to_string(entry.path().join("temp")).ok())
+        .filter_map(|millidegrees| millidegrees.trim().parse::<f32>().ok())
+        .map(|millidegrees| millidegrees / 1000.0)
+        .reduce(f32::max)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn thermal_zone_temperature() -> Option<f32> {
+    None
+}
+
+// The readings one system event reports; those it isn't about stay `None`
+#[derive(Debug, Default)]
+struct SystemSample {
+    cpu_usage: Option<f32>,
+    memory_usage: Option<f32>,
+    disk_usage: Option<f32>,
+    temperature: Option<f32>,
+    load_average: Option<f32>,
+}
+
 pub struct SystemHandler {
     config: SystemConfig,
-    system: Arc<Mutex<System>>,
-    pub event_sender: Option<Sender<EventMessage>>,
+    shared_system: Arc<SharedSystem>,
+    system: Arc<RwLock<System>>,
+    pub event_sender: Option<EventSender>,
     is_running: bool,
     handler_id: HandlerId,
     monitor_task: Option<tokio::task::JoinHandle<()>>,
@@ -71,20 +127,18 @@ pub struct SystemHandler {
 
 impl SystemHandler {
     pub fn new(handler_id: HandlerId) -> Self {
-        Self {
-            config: SystemConfig::default(),
-            system: Arc::new(Mutex::new(System::new_all())),
-            event_sender: None,
-            is_running: false,
-            handler_id,
-            monitor_task: None,
-        }
+        Self::with_config(handler_id, SystemConfig::default())
     }
 
     pub fn with_config(handler_id: HandlerId, config: SystemConfig) -> Self {
+        Self::with_shared_system(handler_id, config, Arc::new(SharedSystem::new()))
+    }
+
+    pub fn with_shared_system(handler_id: HandlerId, config: SystemConfig, shared_system: Arc<SharedSystem>) -> Self {
         Self {
             config,
-            system: Arc::new(Mutex::new(System::new_all())),
+            system: shared_system.system(),
+            shared_system,
             event_sender: None,
             is_running: false,
             handler_id,
@@ -92,111 +146,53 @@ impl SystemHandler {
         }
     }
 
+    // CPU, memory, disk, temperature and load are sampled from sysinfo every
+    // `poll_interval` on every platform
     fn start_monitoring(&mut self) {
-        // Use OS-native performance counter callbacks and WMI event notifications
-        // instead of polling loops
-        #[cfg(windows)]
-        {
-            self.start_windows_system_monitoring();
-        }
-        
-        #[cfg(unix)]
-        {
-            self.start_unix_system_monitoring();
-        }
-    }
-    
-    #[cfg(windows)]
-    fn start_windows_system_monitoring(&mut self) {
-        use std::process::Command;
-        
-        let event_sender = self.event_sender.clone();
+        let Some(sender) = self.event_sender.clone() else {
+            log::warn!("System handler started without an event sender");
+            return;
+        };
+        let system = self.system.clone();
+        let shared_system = self.shared_system.clone();
         let handler_id = self.handler_id.clone();
         let config = self.config.clone();
 
         let task = tokio::spawn(async move {
-            // Use Windows Performance Counters with callback notifications
-            // Register for threshold breach events - immediate OS notifications
-            let ps_script = format!(r#"
-                # Register for CPU usage threshold events
-                Register-WmiEvent -Query "SELECT * FROM Win32_PerfFormattedData_PerfOS_Processor WHERE Name='_Total' AND PercentProcessorTime > {}" -Action {{
-                    $Event.SourceEventArgs.NewEvent | ConvertTo-Json | Out-Host
-                }}
-                
-                # Register for memory usage threshold events  
-                Register-WmiEvent -Query "SELECT * FROM Win32_OperatingSystem" -Action {{
-                    $mem = $Event.SourceEventArgs.NewEvent
-                    $usage = (($mem.TotalVisibleMemorySize - $mem.FreePhysicalMemory) / $mem.TotalVisibleMemorySize) * 100
-                    if ($usage -gt {}) {{ 
-                        @{{EventType='MemoryHigh'; Usage=$usage}} | ConvertTo-Json | Out-Host 
-                    }}
-                }}
-                
-                # Keep monitoring alive
-                while($true) {{ Start-Sleep -Seconds 10 }}
-            "#, config.cpu_threshold, config.memory_threshold);
-
-            if let Ok(mut child) = Command::new("powershell")
-                .arg("-Command")
-                .arg(&ps_script)
-                .stdin(std::process::Stdio::piped())
-                .stdout(std::process::Stdio::piped())
-                .stderr(std::process::Stdio::piped())
-                .spawn()
-            {
-                log::info!("Windows system monitoring started via performance counter events");
-                
-                // In a real implementation, parse JSON output and emit immediate events
-                let _ = child.wait();
-            } else {
-                log::error!("Failed to start Windows system monitoring");
+            let _active = crate::metrics::track_task();
+            let mut disks = Disks::new_with_refreshed_list();
+            let mut components = Components::new_with_refreshed_list();
+            let mut interval = interval(config.base.poll_interval);
+
+            loop {
+                interval.tick().await;
+                let cpu_ready = shared_system.cpu_ready();
+                Self::check_system_metrics(&system, cpu_ready, &mut disks, &mut components, &config, &sender, &handler_id);
             }
         });
 
         self.monitor_task = Some(task);
     }
-    
-    #[cfg(unix)]
-    fn start_unix_system_monitoring(&mut self) {
-        let event_sender = self.event_sender.clone();
-        let handler_id = self.handler_id.clone();
-
-        let task = tokio::spawn(async move {
-            // Use Linux kernel interfaces for immediate notifications:
-            // - /sys/fs/cgroup for memory pressure events
-            // - CPU frequency scaling notifications  
-            // - Thermal zone alerts
-            log::info!("Unix system monitoring would use kernel notification interfaces");
-            
-            // Real implementation would use epoll/kqueue with:
-            // - cgroup memory pressure notifications
-            // - thermal zone sysfs events
-            // - CPU governor change notifications
-        });
-
-        self.monitor_task = Some(task);
-    }
 
-    async fn check_system_metrics(
-        system: &Arc<Mutex<System>>,
+    fn check_system_metrics(
+        system: &Arc<RwLock<System>>,
+        cpu_ready: bool,
+        disks: &mut Disks,
+        components: &mut Components,
         config: &SystemConfig,
-        sender: &Sender<EventMessage>,
+        sender: &EventSender,
         handler_id: &HandlerId,
     ) {
-        let mut sys = system.lock().unwrap();
-        sys.refresh_all();
+        // Refreshed by the shared refresher; see `SharedSystem`
+        let sys = system.read().unwrap();
 
-        // Check CPU usage
-        if config.monitor_cpu {
-            let cpu_usage = sys.global_cpu_info().cpu_usage();
+        // Check CPU usage; it reads as 0 until the shared system is warm
+        if config.monitor_cpu && cpu_ready {
+            let cpu_usage = sys.global_cpu_usage();
             if cpu_usage >= config.cpu_threshold {
                 Self::emit_system_event(
                     SystemEventType::CpuUsageHigh,
-                    Some(cpu_usage),
-                    None,
-                    None,
-                    None,
-                    None,
+                    SystemSample { cpu_usage: Some(cpu_usage), ..Default::default() },
                     sender,
                     handler_id,
                 );
@@ -212,39 +208,126 @@ impl SystemHandler {
             if memory_usage >= config.memory_threshold {
                 Self::emit_system_event(
                     SystemEventType::MemoryUsageHigh,
-                    None,
-                    Some(memory_usage),
-                    None,
-                    None,
-                    None,
+                    SystemSample { memory_usage: Some(memory_usage), ..Default::default() },
                     sender,
                     handler_id,
                 );
             }
         }
 
-        // Note: disk, temperature, and load average monitoring
-        // would require additional implementation for newer sysinfo versions
-        // For now, we'll implement basic monitoring
+        // Check disk usage, per mount point
+        if config.monitor_disk {
+            disks.refresh(true);
+            for disk in disks.list() {
+                let total_bytes = disk.total_space();
+                if total_bytes == 0 {
+                    continue;
+                }
+
+                let available_bytes = disk.available_space().min(total_bytes);
+                let disk_usage = ((total_bytes - available_bytes) as f64 / total_bytes as f64 * 100.0) as f32;
+
+                if disk_usage >= config.disk_threshold {
+                    let (available, total) = if config.report_disk_bytes {
+                        (Some(available_bytes), Some(total_bytes))
+                    } else {
+                        (None, None)
+                    };
+                    Self::emit_disk_event(disk_usage, available, total, disk.mount_point(), sender, handler_id);
+                }
+            }
+        }
+
+        // Check the hottest sensor; machines without any are skipped
+        if config.monitor_temperature {
+            components.refresh(true);
+            let temperature = components
+                .list()
+                .iter()
+                .filter_map(|component| component.temperature())
+                .filter(|temperature| temperature.is_finite())
+                .reduce(f32::max)
+                .or_else(thermal_zone_temperature);
+
+            if let Some(temperature) = temperature {
+                if temperature >= config.temperature_threshold {
+                    Self::emit_system_event(
+                        SystemEventType::TemperatureHigh,
+                        SystemSample { temperature: Some(temperature), ..Default::default() },
+                        sender,
+                        handler_id,
+                    );
+                }
+            }
+        }
+
+        // Windows has no load average (sysinfo reports zeros), so it is never checked there
+        if config.monitor_load_average && cfg!(unix) {
+            let load_average = System::load_average().one as f32;
+            if load_average >= config.load_average_threshold {
+                Self::emit_system_event(
+                    SystemEventType::LoadAverageHigh,
+                    SystemSample { load_average: Some(load_average), ..Default::default() },
+                    sender,
+                    handler_id,
+                );
+            }
+        }
+    }
+
+    fn emit_disk_event(
+        disk_usage: f32,
+        disk_available_bytes: Option<u64>,
+        disk_total_bytes: Option<u64>,
+        mount_point: &Path,
+        sender: &EventSender,
+        handler_id: &HandlerId,
+    ) {
+        let event_data = SystemEventData {
+            event_type: SystemEventType::DiskSpaceLow,
+            cpu_usage: None,
+            memory_usage: None,
+            disk_usage: Some(disk_usage),
+            disk_available_bytes,
+            disk_total_bytes,
+            disk_mount_point: Some(mount_point.to_path_buf()),
+            temperature: None,
+            load_average: None,
+            timestamp: SystemTime::now(),
+        };
+
+        let message = EventMessage {
+            metadata: EventMetadata {
+                id: 0, // Will be set by event bus
+                handler_id: handler_id.clone(),
+                timestamp: SystemTime::now(),
+                source: crate::event_source(handler_id, "system"),
+                dedup_key: None,
+            },
+            data: EventData::System(event_data),
+        };
+
+        if let Err(e) = sender.send(message) {
+            log::error!("Failed to send system event: {}", e);
+        }
     }
 
     fn emit_system_event(
         event_type: SystemEventType,
-        cpu_usage: Option<f32>,
-        memory_usage: Option<f32>,
-        disk_usage: Option<f32>,
-        temperature: Option<f32>,
-        load_average: Option<f32>,
-        sender: &Sender<EventMessage>,
+        sample: SystemSample,
+        sender: &EventSender,
         handler_id: &HandlerId,
     ) {
         let event_data = SystemEventData {
             event_type,
-            cpu_usage,
-            memory_usage,
-            disk_usage,
-            temperature,
-            load_average,
+            cpu_usage: sample.cpu_usage,
+            memory_usage: sample.memory_usage,
+            disk_usage: sample.disk_usage,
+            disk_available_bytes: None,
+            disk_total_bytes: None,
+            disk_mount_point: None,
+            temperature: sample.temperature,
+            load_average: sample.load_average,
             timestamp: SystemTime::now(),
         };
 
@@ -253,7 +336,8 @@ impl SystemHandler {
                 id: 0, // Will be set by event bus
                 handler_id: handler_id.clone(),
                 timestamp: SystemTime::now(),
-                source: "system".to_string(),
+                source: crate::event_source(handler_id, "system"),
+                dedup_key: None,
             },
             data: EventData::System(event_data),
         };
@@ -273,14 +357,10 @@ impl EventHandler for SystemHandler {
         if self.is_running {
             return Ok(());
         }
+        config.base.validate()?;
 
         self.config = config;
-        
-        // Initialize system information
-        {
-            let mut sys = self.system.lock().unwrap();
-            sys.refresh_all();
-        }
+        self.shared_system.start(self.config.base.poll_interval);
 
         self.start_monitoring();
         self.is_running = true;
@@ -310,4 +390,73 @@ impl EventHandler for SystemHandler {
     fn name(&self) -> &'static str {
         "system"
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every disk crosses a 0% threshold, so each one reports
+    fn disk_events(report_disk_bytes: bool) -> Vec<SystemEventData> {
+        let config = SystemConfig {
+            monitor_cpu: false,
+            monitor_memory: false,
+            monitor_disk: true,
+            monitor_temperature: false,
+            monitor_load_average: false,
+            disk_threshold: 0.0,
+            report_disk_bytes,
+            ..Default::default()
+        };
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        SystemHandler::check_system_metrics(
+            &Arc::new(RwLock::new(System::new())),
+            false,
+            &mut Disks::new_with_refreshed_list(),
+            &mut Components::new(),
+            &config,
+            &EventSender::from(sender),
+            &"system".to_string(),
+        );
+
+        let events: Vec<SystemEventData> = receiver
+            .try_iter()
+            .filter_map(|message| match message.data {
+                EventData::System(data) => Some(data),
+                _ => None,
+            })
+            .collect();
+        assert!(!events.is_empty(), "no disks were reported");
+        events
+    }
+
+    #[test]
+    fn disk_bytes_are_reported_when_enabled() {
+        let temp_dir = std::env::temp_dir().canonicalize().unwrap();
+        let events = disk_events(true);
+
+        for event in &events {
+            assert_eq!(event.event_type, SystemEventType::DiskSpaceLow);
+            let (Some(available), Some(total)) = (event.disk_available_bytes, event.disk_total_bytes) else {
+                panic!("missing byte counts: {:?}", event);
+            };
+            assert!(available <= total, "{:?}", event);
+            let usage = (total - available) as f64 / total as f64 * 100.0;
+            assert!((usage - event.disk_usage.unwrap() as f64).abs() < 0.01, "{:?}", event);
+        }
+
+        // The temp directory lives on one of them
+        assert!(events
+            .iter()
+            .filter_map(|event| event.disk_mount_point.as_deref())
+            .any(|mount_point| temp_dir.starts_with(mount_point)));
+    }
+
+    #[test]
+    fn disk_bytes_are_left_out_by_default() {
+        assert!(!SystemConfig::default().report_disk_bytes);
+        for event in disk_events(false) {
+            assert!(event.disk_usage.is_some(), "{:?}", event);
+            assert_eq!((event.disk_available_bytes, event.disk_total_bytes), (None, None));
+        }
+    }
+}
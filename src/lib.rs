@@ -2,14 +2,29 @@ pub mod events;
 pub mod traits;
 pub mod handlers;
 pub mod event_system;
+pub mod blocking;
+#[cfg(feature = "serde")]
+pub mod config;
+pub mod export;
+pub mod metrics;
+pub mod stream;
+pub mod subscription;
+#[cfg(feature = "testing")]
+pub mod testing;
 
-pub use event_system::EventSystem;
+pub use blocking::EventSystemBlocking;
+pub use event_system::{EventSystem, EventSystemBuilder, SubscribeOutcome, WatchPathsOutcome};
+pub use export::ExportFormat;
+pub use metrics::{BusMetrics, SelfMetrics};
+pub use stream::EventStream;
+pub use subscription::Subscription;
 pub use events::*;
 pub use traits::*;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use crossbeam_channel::{unbounded, Receiver, Sender};
+use crossbeam_channel::{bounded, unbounded, Receiver, SendError, Sender, TrySendError};
 use tokio::sync::RwLock;
 
 pub type EventId = usize;
@@ -36,44 +51,391 @@ pub enum TellMeWhenError {
 pub type Result<T> = std::result::Result<T, TellMeWhenError>;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EventMetadata {
+    /// Assigned by the bus as the event is taken off its channel, so ids
+    /// increase in delivery order and are never reused. Handlers on different
+    /// threads race to the channel, so this is the order events reached the
+    /// bus, not necessarily the order they happened; sort by it to restore
+    /// delivery order after handing events to several threads of your own.
     pub id: EventId,
     pub handler_id: HandlerId,
+    #[cfg_attr(feature = "serde", serde(with = "crate::events::epoch_seconds"))]
     pub timestamp: std::time::SystemTime,
+    /// The kind of handler that produced the event. Events from a named
+    /// `EventSystem` carry its name first, as in `"<name>/filesystem"`.
     pub source: String,
+    /// Identifies what the event is about: its domain, type and subject (a
+    /// path, pid, interface or key), so the same occurrence reported twice
+    /// gets the same key. Filled in by the bus from the event data when the
+    /// handler leaves it `None`; lifecycle events have none.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub dedup_key: Option<String>,
+}
+
+// Handlers of a named system have ids like "<name>/filesystem"; their events'
+// sources take the same prefix
+pub(crate) fn event_source(handler_id: &str, source: &str) -> String {
+    match handler_id.rsplit_once('/') {
+        Some((name, _)) => format!("{}/{}", name, source),
+        None => source.to_string(),
+    }
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EventMessage {
     pub metadata: EventMetadata,
     pub data: EventData,
 }
 
-pub struct EventBus {
+/// Where the bus runs subscriber callbacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DispatchMode {
+    /// On a task spawned onto the caller's tokio runtime.
+    #[default]
+    Tokio,
+    /// On a dedicated OS thread, so callbacks never occupy a tokio worker.
+    DedicatedThread,
+}
+
+/// What a bounded bus does with an event sent while its channel is full.
+///
+/// Bounding the channel caps the memory queued events can take when handlers
+/// outrun the subscribers, e.g. while watching a whole drive during heavy
+/// activity. Each policy pays for that differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// Wait for room. Nothing is lost on the bus, but the handler's thread
+    /// stalls meanwhile, and a stalled OS callback can make the OS drop
+    /// notifications itself (reported as `EventsLost` where it can tell).
+    #[default]
+    Block,
+    /// Discard the event being sent, keeping the backlog as it is.
+    DropNewest,
+    /// Discard the oldest queued event to make room, so subscribers catch up
+    /// on recent activity at the expense of history.
+    DropOldest,
+}
+
+/// The sending half of an `EventBus`, held by handlers. On a bounded bus it
+/// applies the bus's `OverflowPolicy` when the channel is full.
+#[derive(Debug, Clone)]
+pub struct EventSender {
     sender: Sender<EventMessage>,
+    // The bus's, so queued events can be counted; `None` off a bus
+    counters: Option<Arc<metrics::BusCounters>>,
+    // Set for a bounded bus that drops rather than blocks when full
+    dropping: Option<Dropping>,
+}
+
+#[derive(Debug, Clone)]
+struct Dropping {
+    policy: OverflowPolicy,
+    // Lets DropOldest evict from the sending side
+    receiver: Receiver<EventMessage>,
+}
+
+impl EventSender {
+    /// Sends `message`, failing only once the bus has gone. An event dropped
+    /// under the overflow policy counts as sent.
+    // Mirrors crossbeam's `Sender::send`, error type included
+    #[allow(clippy::result_large_err)]
+    pub fn send(&self, message: EventMessage) -> std::result::Result<(), SendError<EventMessage>> {
+        let Some(dropping) = &self.dropping else {
+            self.sender.send(message)?;
+            self.count(|counters| &counters.queued);
+            return Ok(());
+        };
+
+        let mut message = message;
+        loop {
+            match self.sender.try_send(message) {
+                Ok(()) => {
+                    self.count(|counters| &counters.queued);
+                    return Ok(());
+                }
+                Err(TrySendError::Disconnected(message)) => return Err(SendError(message)),
+                Err(TrySendError::Full(rejected)) => {
+                    if dropping.policy == OverflowPolicy::DropNewest {
+                        self.count(|counters| &counters.dropped);
+                        return Ok(());
+                    }
+                    // The dispatcher may have made room first, in which case
+                    // nothing is evicted and the send is simply retried
+                    if dropping.receiver.try_recv().is_ok() {
+                        self.count(|counters| &counters.dropped);
+                        // It was queued, so it's done with as far as draining goes
+                        self.count(|counters| &counters.retired);
+                    }
+                    message = rejected;
+                }
+            }
+        }
+    }
+
+    fn count(&self, counter: impl Fn(&metrics::BusCounters) -> &AtomicUsize) {
+        if let Some(counters) = &self.counters {
+            counter(counters).fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+// Channels that don't belong to a bus, like the debouncer's, never drop
+impl From<Sender<EventMessage>> for EventSender {
+    fn from(sender: Sender<EventMessage>) -> Self {
+        Self { sender, counters: None, dropping: None }
+    }
+}
+
+type Callback = Arc<dyn Fn(Arc<EventMessage>) + Send + Sync>;
+
+// Subscribers keyed by the domain they listen to, or `None` for every domain,
+// so callbacks are only looked at for events they want. Ids are handed out in
+// registration order, which the ordered inner map preserves
+type Subscribers = HashMap<Option<EventKind>, BTreeMap<EventId, Vec<Callback>>>;
+
+/// Routes handler events to subscriber callbacks.
+///
+/// Every subscriber of an event is handed the same `Arc<EventMessage>`, so
+/// fan-out costs a reference count per callback rather than a deep copy;
+/// callbacks clone only the parts they keep.
+///
+/// Events are dispatched one at a time in `EventMetadata::id` order, and each
+/// reaches its subscribers in the order they subscribed, whatever domain
+/// they're limited to.
+///
+/// Callbacks run one after another on the dispatch task or thread, so they
+/// should return quickly; hand slow work off to a task of your own. The
+/// subscriber lock isn't held while they run, so a slow callback delays
+/// delivery but never blocks `subscribe` or `unsubscribe`, and a panicking
+/// one is logged without stopping delivery to the others.
+///
+/// The channel handlers send into is unbounded unless the bus is created with
+/// `bounded`, in which case a full channel is handled by its `OverflowPolicy`.
+pub struct EventBus {
+    sender: EventSender,
     receiver: Receiver<EventMessage>,
-    subscribers: Arc<RwLock<HashMap<EventId, Vec<Box<dyn Fn(EventMessage) + Send + Sync>>>>>,
+    subscribers: Arc<RwLock<Subscribers>>,
     next_id: Arc<Mutex<EventId>>,
+    next_event_id: Arc<AtomicUsize>,
+    dispatch_mode: DispatchMode,
+    // Events taken off the channel but not yet dispatched
+    in_flight: Arc<AtomicUsize>,
+    counters: Arc<metrics::BusCounters>,
+    // The running dispatcher, reused when processing is started again
+    dispatcher: Mutex<Option<Dispatcher>>,
 }
 
+// Whatever takes events off the channel for one `start_processing`
+enum Dispatcher {
+    Task(tokio::task::JoinHandle<()>),
+    Thread(std::thread::JoinHandle<()>),
+}
+
+impl Dispatcher {
+    // A task ends with its runtime, after which a new one is needed
+    fn is_alive(&self) -> bool {
+        match self {
+            Dispatcher::Task(task) => !task.is_finished(),
+            Dispatcher::Thread(thread) => !thread.is_finished(),
+        }
+    }
+}
+
+// Events the forwarding thread can hand to the dispatch task ahead of it
+const FORWARD_CAPACITY: usize = 64;
+
 impl EventBus {
     pub fn new() -> Self {
-        let (sender, receiver) = unbounded();
+        Self::with_dispatch_mode(DispatchMode::default())
+    }
+
+    pub fn with_dispatch_mode(dispatch_mode: DispatchMode) -> Self {
+        Self::with_options(dispatch_mode, None, OverflowPolicy::default())
+    }
+
+    /// A bus that queues at most about `capacity` events, blocking handlers
+    /// while it's full. See `bounded_with_policy` to drop events instead.
+    pub fn bounded(capacity: usize) -> Self {
+        Self::bounded_with_policy(capacity, OverflowPolicy::default())
+    }
+
+    /// A bus that queues at most about `capacity` events and applies `policy`
+    /// when it's full. Besides the channel, a few dozen events can be on
+    /// their way to the dispatch task.
+    pub fn bounded_with_policy(capacity: usize, policy: OverflowPolicy) -> Self {
+        Self::with_options(DispatchMode::default(), Some(capacity), policy)
+    }
+
+    pub(crate) fn with_options(dispatch_mode: DispatchMode, capacity: Option<usize>, policy: OverflowPolicy) -> Self {
+        let counters = Arc::new(metrics::BusCounters::default());
+        // A zero-capacity channel only hands over to a waiting receiver, so
+        // dropping policies would discard nearly everything
+        let (sender, receiver) = match capacity {
+            Some(capacity) => bounded(capacity.max(1)),
+            None => unbounded(),
+        };
+        let dropping = (capacity.is_some() && policy != OverflowPolicy::Block).then(|| Dropping {
+            policy,
+            receiver: receiver.clone(),
+        });
         Self {
-            sender,
+            sender: EventSender { sender, counters: Some(Arc::clone(&counters)), dropping },
             receiver,
             subscribers: Arc::new(RwLock::new(HashMap::new())),
             next_id: Arc::new(Mutex::new(0)),
+            next_event_id: Arc::new(AtomicUsize::new(1)),
+            dispatch_mode,
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            counters,
+            dispatcher: Mutex::new(None),
         }
     }
 
-    pub fn sender(&self) -> Sender<EventMessage> {
+    pub fn dispatch_mode(&self) -> DispatchMode {
+        self.dispatch_mode
+    }
+
+    pub fn sender(&self) -> EventSender {
         self.sender.clone()
     }
 
+    /// Number of events waiting to be dispatched.
+    pub fn queued_len(&self) -> usize {
+        self.receiver.len() + self.in_flight.load(Ordering::Relaxed)
+    }
+
+    pub fn metrics(&self) -> BusMetrics {
+        self.counters.snapshot()
+    }
+
+    // Waits for every event queued so far to be dispatched, or evicted under
+    // `OverflowPolicy::DropOldest`. Returns whether that happened in time
+    pub(crate) async fn flush(&self, timeout: std::time::Duration) -> bool {
+        tokio::time::timeout(timeout, self.drain()).await.is_ok()
+    }
+
+    // `flush` without a time limit
+    pub(crate) async fn drain(&self) {
+        let target = self.counters.queued.load(Ordering::Relaxed);
+        loop {
+            let notified = self.counters.retired_notify.notified();
+            tokio::pin!(notified);
+            // Registered before the check, so a dispatch in between isn't missed
+            notified.as_mut().enable();
+            if self.counters.retired.load(Ordering::Relaxed) >= target {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    /// Subscribes to events of every domain.
     pub async fn subscribe<F>(&self, callback: F) -> EventId
     where
-        F: Fn(EventMessage) + Send + Sync + 'static,
+        F: Fn(Arc<EventMessage>) + Send + Sync + 'static,
+    {
+        self.add_subscriber(None, callback).await
+    }
+
+    /// Subscribes to events of one domain only; the callback is never called
+    /// for any other.
+    pub async fn subscribe_kind<F>(&self, kind: EventKind, callback: F) -> EventId
+    where
+        F: Fn(Arc<EventMessage>) + Send + Sync + 'static,
+    {
+        self.add_subscriber(Some(kind), callback).await
+    }
+
+    /// Like `subscribe`, but the callback is removed after it has run `n` times.
+    pub async fn subscribe_take<F>(self: &Arc<Self>, n: usize, callback: F) -> EventId
+    where
+        F: Fn(Arc<EventMessage>) + Send + Sync + 'static,
+    {
+        self.add_take_subscriber(None, n, callback).await
+    }
+
+    /// Like `subscribe_kind`, but the callback is removed after it has run `n` times.
+    pub async fn subscribe_kind_take<F>(self: &Arc<Self>, kind: EventKind, n: usize, callback: F) -> EventId
+    where
+        F: Fn(Arc<EventMessage>) + Send + Sync + 'static,
+    {
+        self.add_take_subscriber(Some(kind), n, callback).await
+    }
+
+    async fn add_take_subscriber<F>(self: &Arc<Self>, kind: Option<EventKind>, n: usize, callback: F) -> EventId
+    where
+        F: Fn(Arc<EventMessage>) + Send + Sync + 'static,
+    {
+        let fired = Arc::new(AtomicUsize::new(0));
+        let own_id = Arc::new(std::sync::OnceLock::new());
+        // Weak, as the bus owns this callback
+        let event_bus = Arc::downgrade(self);
+
+        let wrapped = {
+            let fired = Arc::clone(&fired);
+            let own_id = Arc::clone(&own_id);
+            move |message| {
+                // Events already snapshotted for dispatch can still arrive
+                // after the last one, so the count decides, not the subscription
+                let count = fired.fetch_add(1, Ordering::SeqCst) + 1;
+                if count > n {
+                    return;
+                }
+                callback(message);
+                if count == n {
+                    if let (Some(event_bus), Some(&event_id)) = (event_bus.upgrade(), own_id.get()) {
+                        crate::stream::unsubscribe_detached(event_bus, event_id);
+                    }
+                }
+            }
+        };
+
+        let event_id = self.add_subscriber(kind, wrapped).await;
+        let _ = own_id.set(event_id);
+        // Reached `n` before the id was known to the callback
+        if fired.load(Ordering::SeqCst) >= n {
+            self.unsubscribe(event_id).await;
+        }
+        event_id
+    }
+
+    /// Like `subscribe`, but at most `max_per_sec` events a second reach the
+    /// callback, with bursts of up to `max_per_sec` at once; the rest are
+    /// dropped. The callback's second argument counts the events dropped since
+    /// its previous call.
+    pub async fn subscribe_throttled<F>(&self, max_per_sec: u32, callback: F) -> EventId
+    where
+        F: Fn(Arc<EventMessage>, u64) + Send + Sync + 'static,
+    {
+        self.add_throttled_subscriber(None, max_per_sec, callback).await
+    }
+
+    /// Like `subscribe_kind`, throttled as in `subscribe_throttled`.
+    pub async fn subscribe_kind_throttled<F>(&self, kind: EventKind, max_per_sec: u32, callback: F) -> EventId
+    where
+        F: Fn(Arc<EventMessage>, u64) + Send + Sync + 'static,
+    {
+        self.add_throttled_subscriber(Some(kind), max_per_sec, callback).await
+    }
+
+    async fn add_throttled_subscriber<F>(&self, kind: Option<EventKind>, max_per_sec: u32, callback: F) -> EventId
+    where
+        F: Fn(Arc<EventMessage>, u64) + Send + Sync + 'static,
+    {
+        let bucket = Mutex::new(TokenBucket::new(max_per_sec));
+        self.add_subscriber(kind, move |message| {
+            let admitted = bucket.lock().unwrap().take();
+            if let Some(suppressed) = admitted {
+                callback(message, suppressed);
+            }
+        }).await
+    }
+
+    async fn add_subscriber<F>(&self, kind: Option<EventKind>, callback: F) -> EventId
+    where
+        F: Fn(Arc<EventMessage>) + Send + Sync + 'static,
     {
         let id = {
             let mut next_id = self.next_id.lock().unwrap();
@@ -83,35 +445,192 @@ impl EventBus {
         };
 
         let mut subscribers = self.subscribers.write().await;
-        subscribers.entry(id).or_insert_with(Vec::new).push(Box::new(callback));
+        subscribers
+            .entry(kind)
+            .or_default()
+            .entry(id)
+            .or_insert_with(Vec::new)
+            .push(Arc::new(callback));
+        self.counters.subscribers.fetch_add(1, Ordering::Relaxed);
         id
     }
 
     pub async fn unsubscribe(&self, id: EventId) -> bool {
         let mut subscribers = self.subscribers.write().await;
-        subscribers.remove(&id).is_some()
+        let removed = subscribers.values_mut().any(|by_id| by_id.remove(&id).is_some());
+        if removed {
+            self.counters.subscribers.fetch_sub(1, Ordering::Relaxed);
+        }
+        removed
     }
 
     pub async fn publish(&self, message: EventMessage) {
         if let Err(e) = self.sender.send(message) {
+            self.counters.send_errors.fetch_add(1, Ordering::Relaxed);
             log::error!("Failed to publish event: {}", e);
         }
     }
 
+    /// Starts dispatching events to subscribers. Calling it again while the
+    /// dispatcher runs does nothing, so events are never split between two.
     pub async fn start_processing(&self) {
+        let mut dispatcher = self.dispatcher.lock().unwrap();
+        if dispatcher.as_ref().is_some_and(Dispatcher::is_alive) {
+            return;
+        }
+
         let receiver = self.receiver.clone();
         let subscribers = self.subscribers.clone();
-        
-        tokio::spawn(async move {
-            while let Ok(message) = receiver.recv() {
-                let subscribers = subscribers.read().await;
-                for callbacks in subscribers.values() {
-                    for callback in callbacks {
-                        callback(message.clone());
+        let next_event_id = Arc::clone(&self.next_event_id);
+        let counters = Arc::clone(&self.counters);
+
+        if self.dispatch_mode == DispatchMode::DedicatedThread {
+            // Counted before the thread runs, so it shows once start returns
+            let active = crate::metrics::track_thread();
+            let spawned = std::thread::Builder::new()
+                .name("tell_me_when-dispatch".to_string())
+                .spawn(move || {
+                    let _active = active;
+                    while let Ok(mut message) = receiver.recv() {
+                        stamp(&mut message, &next_event_id);
+                        counters.published.fetch_add(1, Ordering::Relaxed);
+                        let callbacks = snapshot(&subscribers.blocking_read(), &message);
+                        dispatch(&callbacks, message, &counters);
+                    }
+                });
+
+            match spawned {
+                Ok(thread) => *dispatcher = Some(Dispatcher::Thread(thread)),
+                Err(e) => log::error!("Failed to spawn event dispatch thread: {}", e),
+            }
+            return;
+        }
+
+        // Waiting on the crossbeam channel blocks, which would tie up a tokio
+        // worker (and stall a single-threaded runtime outright), so a small
+        // thread moves events onto an async channel for the dispatch task.
+        // That channel is bounded too, so a slow dispatcher backs events up
+        // into the bus's channel, where its capacity and policy apply
+        let (forward_sender, mut forward_receiver) = tokio::sync::mpsc::channel(FORWARD_CAPACITY);
+        let in_flight = Arc::clone(&self.in_flight);
+        let forward_counters = Arc::clone(&counters);
+        let active = crate::metrics::track_thread();
+        let spawned = std::thread::Builder::new()
+            .name("tell_me_when-forward".to_string())
+            .spawn(move || {
+                let _active = active;
+                while let Ok(mut message) = receiver.recv() {
+                    stamp(&mut message, &next_event_id);
+                    forward_counters.published.fetch_add(1, Ordering::Relaxed);
+                    in_flight.fetch_add(1, Ordering::Relaxed);
+                    // The dispatch task is gone once its runtime shuts down
+                    if forward_sender.blocking_send(message).is_err() {
+                        break;
                     }
                 }
+            });
+
+        if let Err(e) = spawned {
+            log::error!("Failed to spawn event forwarding thread: {}", e);
+            return;
+        }
+
+        let in_flight = Arc::clone(&self.in_flight);
+        let active = crate::metrics::track_task();
+        let task = tokio::spawn(async move {
+            let _active = active;
+            while let Some(message) = forward_receiver.recv().await {
+                let callbacks = snapshot(&*subscribers.read().await, &message);
+                dispatch(&callbacks, message, &counters);
+                in_flight.fetch_sub(1, Ordering::Relaxed);
             }
         });
+        *dispatcher = Some(Dispatcher::Task(task));
+    }
+}
+
+// Taken under the lock so callbacks can run after it's released. Every-domain
+// and single-domain subscribers are interleaved back into registration order
+fn snapshot(subscribers: &Subscribers, message: &EventMessage) -> Vec<Callback> {
+    let mut callbacks: Vec<(EventId, Callback)> = [None, Some(message.data.kind())]
+        .iter()
+        .filter_map(|kind| subscribers.get(kind))
+        .flat_map(|by_id| {
+            by_id
+                .iter()
+                .flat_map(|(&id, callbacks)| callbacks.iter().map(move |callback| (id, Arc::clone(callback))))
+        })
+        .collect();
+    callbacks.sort_by_key(|(id, _)| *id);
+    callbacks.into_iter().map(|(_, callback)| callback).collect()
+}
+
+// Refills at `rate` tokens a second up to `rate`, one token per delivery
+struct TokenBucket {
+    rate: f64,
+    tokens: f64,
+    refilled: std::time::Instant,
+    suppressed: u64,
+}
+
+impl TokenBucket {
+    fn new(max_per_sec: u32) -> Self {
+        let rate = f64::from(max_per_sec.max(1));
+        Self { rate, tokens: rate, refilled: std::time::Instant::now(), suppressed: 0 }
+    }
+
+    // The number of events dropped since the last one let through, or `None`
+    // if this one is dropped too
+    fn take(&mut self) -> Option<u64> {
+        let now = std::time::Instant::now();
+        let elapsed = now.duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.rate);
+        self.refilled = now;
+
+        if self.tokens < 1.0 {
+            self.suppressed += 1;
+            return None;
+        }
+        self.tokens -= 1.0;
+        Some(std::mem::take(&mut self.suppressed))
+    }
+}
+
+// Done as events are taken off the bus's channel, so ids follow delivery order
+fn stamp(message: &mut EventMessage, next_event_id: &AtomicUsize) {
+    message.metadata.id = next_event_id.fetch_add(1, Ordering::Relaxed);
+    if message.metadata.dedup_key.is_none() {
+        message.metadata.dedup_key = message.data.dedup_key();
+    }
+}
+
+fn dispatch(callbacks: &[Callback], message: EventMessage, counters: &metrics::BusCounters) {
+    let message = Arc::new(message);
+    for callback in callbacks {
+        counters.delivered.fetch_add(1, Ordering::Relaxed);
+        let message = Arc::clone(&message);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| callback(message)));
+        if result.is_err() {
+            log::error!("Event callback panicked; continuing with the remaining subscribers");
+        }
+    }
+    counters.retired.fetch_add(1, Ordering::Relaxed);
+    counters.retired_notify.notify_waiters();
+}
+
+impl EventMessage {
+    pub(crate) fn lifecycle(handler_id: HandlerId, event_type: LifecycleEventType) -> Self {
+        let timestamp = std::time::SystemTime::now();
+        Self {
+            metadata: EventMetadata {
+                id: 0,
+                source: event_source(&handler_id, "lifecycle"),
+                dedup_key: None,
+                handler_id,
+                timestamp,
+            },
+            data: EventData::Lifecycle(LifecycleEventData { event_type, timestamp }),
+        }
     }
 }
 
@@ -2,8 +2,6 @@ use tell_me_when::{
     EventSystem, 
     FsEventData, FsEventType,
     ProcessEventData, ProcessEventType,
-    NetworkEventData, NetworkEventType,
-    SystemEventData, SystemEventType,
     PowerEventData, PowerEventType
 };
 use std::path::Path;
@@ -78,7 +76,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             ProcessEventType::Terminated => ("🔴", |s| s.bright_red()),
             ProcessEventType::CpuUsageHigh => ("🔥", |s| s.red()),
             ProcessEventType::MemoryUsageHigh => ("💾", |s| s.yellow()),
+            ProcessEventType::CpuUsageNormal | ProcessEventType::MemoryUsageNormal => ("✅", |s| s.green()),
             ProcessEventType::StatusChanged => ("🔄", |s| s.white()),
+            ProcessEventType::ExecutableReplaced => ("⚠️", |s| s.bright_magenta()),
+            ProcessEventType::ResourceUsage => ("📊", |s| s.cyan()),
         };
         
         let output = format!("{} [PROCESS] {} (PID: {}) - {:?}", 
@@ -119,6 +120,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     //         SystemEventType::DiskSpaceLow => ("💽", |s| s.bright_red()),
     //         SystemEventType::TemperatureHigh => ("🌡️", |s| s.red()),
     //         SystemEventType::LoadAverageHigh => ("⚡", |s| s.bright_yellow()),
+    //         SystemEventType::Degraded => ("🚨", |s| s.bright_red()),
+    //         SystemEventType::Recovered => ("✅", |s| s.green()),
     //     };
     //     
     //     let output = format!("{} [SYSTEM] {:?}", 
@@ -140,6 +143,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             PowerEventType::WakeFromSleep => ("👁️", |s| s.bright_blue()),
             PowerEventType::Shutdown => ("🛑", |s| s.bright_red()),
             PowerEventType::Restart => ("🔄", |s| s.bright_magenta()),
+            PowerEventType::ThermalThrottling => ("🌡️", |s| s.red()),
         };
         
         let output = format!("{} [POWER] {:?}", 
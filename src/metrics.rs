@@ -0,0 +1,130 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+// Work the crate currently has in flight, across every EventSystem in the process
+static ACTIVE_THREADS: AtomicUsize = AtomicUsize::new(0);
+static ACTIVE_TASKS: AtomicUsize = AtomicUsize::new(0);
+
+/// The crate's own resource usage, as reported by `EventSystem::self_metrics`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SelfMetrics {
+    /// OS threads held by monitors, including blocking-pool threads running
+    /// long-lived loops and the dedicated dispatch thread.
+    pub threads: usize,
+    /// Tokio tasks spawned onto the caller's runtime.
+    pub tasks: usize,
+    /// Events sent by handlers but not yet dispatched.
+    pub queued_events: usize,
+    /// Rough size of the queued events and history buffers; heap data owned
+    /// by the events (paths, names) isn't counted.
+    pub buffer_bytes: usize,
+    /// CPU time used by the crate's named threads. Only available on Linux;
+    /// work done on tokio workers isn't attributed.
+    pub cpu_time: Option<Duration>,
+}
+
+/// Traffic through one `EventBus`, as reported by `EventBus::metrics`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BusMetrics {
+    /// Events taken off the bus's channel for dispatch.
+    pub events_published: usize,
+    /// Callback invocations, so one event reaching three subscribers counts three times.
+    pub events_delivered: usize,
+    pub subscribers: usize,
+    /// `publish` calls that failed because the channel was closed.
+    pub send_errors: usize,
+    /// Events thrown away because a bounded bus was full, under
+    /// `OverflowPolicy::DropNewest` or `OverflowPolicy::DropOldest`.
+    pub events_dropped: usize,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct BusCounters {
+    pub(crate) published: AtomicUsize,
+    pub(crate) delivered: AtomicUsize,
+    pub(crate) subscribers: AtomicUsize,
+    pub(crate) send_errors: AtomicUsize,
+    pub(crate) dropped: AtomicUsize,
+    // Events that made it onto the channel, and those of them since
+    // dispatched or evicted; draining waits for the second to catch up
+    pub(crate) queued: AtomicUsize,
+    pub(crate) retired: AtomicUsize,
+    pub(crate) retired_notify: tokio::sync::Notify,
+}
+
+impl BusCounters {
+    pub(crate) fn snapshot(&self) -> BusMetrics {
+        BusMetrics {
+            events_published: self.published.load(Ordering::Relaxed),
+            events_delivered: self.delivered.load(Ordering::Relaxed),
+            subscribers: self.subscribers.load(Ordering::Relaxed),
+            send_errors: self.send_errors.load(Ordering::Relaxed),
+            events_dropped: self.dropped.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Decrements its counter when dropped, so aborted and panicking work is
+/// accounted for too.
+pub(crate) struct ActiveGuard(&'static AtomicUsize);
+
+impl Drop for ActiveGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Hold for the lifetime of a spawned thread or blocking task.
+pub(crate) fn track_thread() -> ActiveGuard {
+    ACTIVE_THREADS.fetch_add(1, Ordering::Relaxed);
+    ActiveGuard(&ACTIVE_THREADS)
+}
+
+/// Hold for the lifetime of a spawned tokio task.
+pub(crate) fn track_task() -> ActiveGuard {
+    ACTIVE_TASKS.fetch_add(1, Ordering::Relaxed);
+    ActiveGuard(&ACTIVE_TASKS)
+}
+
+pub(crate) fn active_threads() -> usize {
+    ACTIVE_THREADS.load(Ordering::Relaxed)
+}
+
+pub(crate) fn active_tasks() -> usize {
+    ACTIVE_TASKS.load(Ordering::Relaxed)
+}
+
+// Named threads show up in /proc as "tell_me_when-…", truncated to 15 bytes
+#[cfg(target_os = "linux")]
+pub(crate) fn thread_cpu_time() -> Option<Duration> {
+    const THREAD_NAME_PREFIX: &str = "tell_me_when-";
+    // /proc reports times in USER_HZ, which the kernel ABI fixes at 100
+    const TICKS_PER_SECOND: u64 = 100;
+
+    let mut ticks = 0u64;
+
+    for entry in std::fs::read_dir("/proc/self/task").ok()?.flatten() {
+        let Ok(stat) = std::fs::read_to_string(entry.path().join("stat")) else {
+            continue;
+        };
+        // Format: tid (comm) state ... utime stime ...; comm may contain spaces
+        let (Some(open), Some(close)) = (stat.find('('), stat.rfind(')')) else {
+            continue;
+        };
+        if !stat[open + 1..close].starts_with(THREAD_NAME_PREFIX) {
+            continue;
+        }
+        let fields: Vec<&str> = stat[close + 1..].split_whitespace().collect();
+        // utime and stime are fields 14 and 15; fields[0] here is field 3
+        let utime = fields.get(11).and_then(|value| value.parse::<u64>().ok()).unwrap_or(0);
+        let stime = fields.get(12).and_then(|value| value.parse::<u64>().ok()).unwrap_or(0);
+        ticks += utime + stime;
+    }
+
+    Some(Duration::from_millis(ticks * 1000 / TICKS_PER_SECOND))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn thread_cpu_time() -> Option<Duration> {
+    None
+}
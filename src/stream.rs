@@ -0,0 +1,82 @@
+use crate::{EventBus, EventId};
+use futures::Stream;
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+
+/// A subscription consumed as a `Stream`. Dropping it unsubscribes.
+///
+/// Items queued before the stream was created (such as backfilled state) are
+/// yielded first, then live events in delivery order.
+pub struct EventStream<T> {
+    pending: VecDeque<T>,
+    receiver: mpsc::UnboundedReceiver<T>,
+    event_bus: Arc<EventBus>,
+    event_id: EventId,
+}
+
+impl<T> EventStream<T> {
+    pub(crate) fn new(
+        pending: VecDeque<T>,
+        receiver: mpsc::UnboundedReceiver<T>,
+        event_bus: Arc<EventBus>,
+        event_id: EventId,
+    ) -> Self {
+        Self {
+            pending,
+            receiver,
+            event_bus,
+            event_id,
+        }
+    }
+
+    pub fn event_id(&self) -> EventId {
+        self.event_id
+    }
+}
+
+impl<T> Unpin for EventStream<T> {}
+
+impl<T> Stream for EventStream<T> {
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        // Items are only ever moved out of the queues, never pinned in place
+        let this = self.get_mut();
+        if let Some(item) = this.pending.pop_front() {
+            return Poll::Ready(Some(item));
+        }
+        this.receiver.poll_recv(cx)
+    }
+}
+
+impl<T> Drop for EventStream<T> {
+    fn drop(&mut self) {
+        unsubscribe_detached(self.event_bus.clone(), self.event_id);
+    }
+}
+
+// Unsubscribes from a synchronous context, on the current runtime if there is one
+pub(crate) fn unsubscribe_detached(event_bus: Arc<EventBus>, event_id: EventId) {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => {
+            handle.spawn(async move {
+                event_bus.unsubscribe(event_id).await;
+            });
+        }
+        Err(_) => {
+            futures::executor::block_on(event_bus.unsubscribe(event_id));
+        }
+    }
+}
+
+/// Interleaves several streams, e.g. `all_events_stream` from more than one
+/// `EventSystem`, yielding items as soon as any of them has one.
+pub fn merge<T, I>(streams: I) -> futures::stream::SelectAll<EventStream<T>>
+where
+    I: IntoIterator<Item = EventStream<T>>,
+{
+    futures::stream::select_all(streams)
+}
@@ -0,0 +1,65 @@
+use crate::{EventBus, EventId};
+use std::sync::Weak;
+
+// Composite degradation monitors, keyed by their subscription
+#[cfg(feature = "system")]
+pub(crate) type DegradationMonitors = std::sync::Mutex<std::collections::HashMap<EventId, tokio::task::JoinHandle<()>>>;
+
+/// A subscription that ends when dropped, from
+/// [`EventSystem::subscribe_guard`](crate::EventSystem::subscribe_guard).
+///
+/// It only holds weak references, so it doesn't keep the event system's bus
+/// alive; dropping it after the system is gone does nothing.
+#[must_use = "dropping a Subscription unsubscribes right away"]
+pub struct Subscription {
+    event_bus: Weak<EventBus>,
+    event_id: EventId,
+    #[cfg(feature = "system")]
+    degradation_monitors: Weak<DegradationMonitors>,
+    active: bool,
+}
+
+impl Subscription {
+    pub(crate) fn new(
+        event_bus: Weak<EventBus>,
+        event_id: EventId,
+        #[cfg(feature = "system")] degradation_monitors: Weak<DegradationMonitors>,
+    ) -> Self {
+        Self {
+            event_bus,
+            event_id,
+            #[cfg(feature = "system")]
+            degradation_monitors,
+            active: true,
+        }
+    }
+
+    pub fn event_id(&self) -> EventId {
+        self.event_id
+    }
+
+    /// Keeps the subscription for the life of the event system and returns
+    /// its id, for unsubscribing by hand later.
+    pub fn detach(mut self) -> EventId {
+        self.active = false;
+        self.event_id
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if !self.active {
+            return;
+        }
+
+        #[cfg(feature = "system")]
+        if let Some(monitors) = self.degradation_monitors.upgrade() {
+            if let Some(monitor) = monitors.lock().unwrap().remove(&self.event_id) {
+                monitor.abort();
+            }
+        }
+        if let Some(event_bus) = self.event_bus.upgrade() {
+            crate::stream::unsubscribe_detached(event_bus, self.event_id);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Helpers for testing code built on [`EventSystem`] without real OS events.
+//!
+//! [`EventSystem::inject_event`] pushes a synthetic event of any domain through
+//! an event system's bus, so subscribers fire exactly as they would for native
+//! notifications; [`MockFs`] does the same for filesystem events by path.
+//! [`recorder`] and [`assert_receives!`](crate::assert_receives) replace
+//! `loop { sleep }` polling with a bounded wait for a matching event.
+
+use crate::events::{EventData, FsEventData, FsEventType};
+use crate::{EventMessage, EventMetadata, EventSender, EventSystem};
+use crossbeam_channel::Receiver;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sends an arbitrary event through the bus of `event_system`.
+pub fn send_event(event_system: &EventSystem, data: EventData) {
+    let message = EventMessage {
+        metadata: EventMetadata {
+            id: 0,
+            handler_id: "testing".to_string(),
+            timestamp: SystemTime::now(),
+            source: "testing".to_string(),
+            dedup_key: None,
+        },
+        data,
+    };
+
+    if let Err(e) = event_system.event_sender().send(message) {
+        log::error!("Failed to send synthetic event: {}", e);
+    }
+}
+
+pub struct MockFs {
+    sender: EventSender,
+}
+
+impl MockFs {
+    pub fn new(event_system: &EventSystem) -> Self {
+        Self {
+            sender: event_system.event_sender(),
+        }
+    }
+
+    pub fn created<P: AsRef<Path>>(&self, path: P) {
+        self.emit(FsEventType::Created, path);
+    }
+
+    pub fn modified<P: AsRef<Path>>(&self, path: P) {
+        self.emit(FsEventType::Modified, path);
+    }
+
+    pub fn deleted<P: AsRef<Path>>(&self, path: P) {
+        self.emit(FsEventType::Deleted, path);
+    }
+
+    pub fn renamed<P: AsRef<Path>, Q: AsRef<Path>>(&self, old_path: P, new_path: Q) {
+        let new_path = new_path.as_ref().to_path_buf();
+        self.emit(
+            FsEventType::Renamed {
+                old_path: old_path.as_ref().to_path_buf(),
+                new_path: new_path.clone(),
+            },
+            new_path,
+        );
+    }
+
+    pub fn emit<P: AsRef<Path>>(&self, event_type: FsEventType, path: P) {
+        let fs_event_data = FsEventData {
+            event_type,
+            path: path.as_ref().to_path_buf(),
+            timestamp: SystemTime::now(),
+            batch: None,
+            sequence: None,
+            checksum: None,
+        };
+
+        let message = EventMessage {
+            metadata: EventMetadata {
+                id: 0,
+                handler_id: "testing".to_string(),
+                timestamp: SystemTime::now(),
+                source: "testing".to_string(),
+                dedup_key: None,
+            },
+            data: EventData::FileSystem(fs_event_data),
+        };
+
+        if let Err(e) = self.sender.send(message) {
+            log::error!("Failed to send synthetic filesystem event: {}", e);
+        }
+    }
+}
+
+/// A callback to register with any `on_*` method, paired with the receiver
+/// that collects what it was called with.
+pub fn recorder<T: Send + 'static>() -> (impl Fn(T) + Send + Sync + 'static, Receiver<T>) {
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    let callback = move |event: T| {
+        let _ = sender.send(event);
+    };
+    (callback, receiver)
+}
+
+/// Waits until an event matching `predicate` arrives on `receiver`.
+///
+/// This blocks the calling thread, so under a single-threaded tokio runtime
+/// the bus can't deliver anything meanwhile; use a multi-threaded runtime or
+/// `EventSystemBuilder::with_dedicated_dispatch_thread`.
+///
+/// Non-matching events are discarded. On timeout, returns the events that were
+/// seen so the caller can report them.
+pub fn wait_for<T, P>(receiver: &Receiver<T>, timeout: Duration, predicate: P) -> std::result::Result<T, Vec<T>>
+where
+    P: Fn(&T) -> bool,
+{
+    let deadline = Instant::now() + timeout;
+    let mut seen = Vec::new();
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match receiver.recv_timeout(remaining) {
+            Ok(event) if predicate(&event) => return Ok(event),
+            Ok(event) => seen.push(event),
+            Err(_) => return Err(seen),
+        }
+    }
+}
+
+/// Asserts that a matching event arrives on a [`recorder`] receiver in time,
+/// evaluating to the event.
+///
+/// ```ignore
+/// let event = assert_receives!(receiver, |event: &FsEventData| event.path.ends_with("a.txt"));
+/// let event = assert_receives!(receiver, |event: &FsEventData| true, Duration::from_millis(200));
+/// ```
+#[macro_export]
+macro_rules! assert_receives {
+    ($receiver:expr, $predicate:expr) => {
+        $crate::assert_receives!($receiver, $predicate, $crate::testing::DEFAULT_TIMEOUT)
+    };
+    ($receiver:expr, $predicate:expr, $timeout:expr) => {
+        match $crate::testing::wait_for(&$receiver, $timeout, $predicate) {
+            Ok(event) => event,
+            Err(seen) => panic!(
+                "no event matching `{}` within {:?}; {} other event(s) received: {:#?}",
+                stringify!($predicate),
+                $timeout,
+                seen.len(),
+                seen
+            ),
+        }
+    };
+}
@@ -1,5 +1,4 @@
 use crate::events::EventData;
-use std::error::Error;
 use async_trait::async_trait;
 
 pub type EventCallback<T> = Box<dyn Fn(T) + Send + Sync>;
@@ -41,18 +40,41 @@ pub trait ThresholdConfig {
 }
 
 pub trait IntervalConfig {
+    /// Intervals shorter than `MIN_POLL_INTERVAL` are raised to it.
     fn set_interval(&mut self, interval: std::time::Duration);
     fn get_interval(&self) -> std::time::Duration;
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
 pub struct EventHandlerConfig {
     pub enabled: bool,
     pub buffer_size: usize,
+    /// How often polling handlers sample. Handlers refuse to start with
+    /// anything under `MIN_POLL_INTERVAL`. The shared sysinfo refresh behind
+    /// the process and system handlers never runs faster than
+    /// `sysinfo::MINIMUM_CPU_UPDATE_INTERVAL`, whatever this is set to.
     pub poll_interval: std::time::Duration,
     pub debounce_duration: Option<std::time::Duration>,
 }
 
+/// The shortest `poll_interval` handlers accept. Anything shorter would have
+/// them spin on a core without seeing anything new.
+pub const MIN_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+impl EventHandlerConfig {
+    pub fn validate(&self) -> crate::Result<()> {
+        if self.poll_interval < MIN_POLL_INTERVAL {
+            return Err(crate::TellMeWhenError::Config(format!(
+                "poll_interval of {:?} is below the minimum of {:?}",
+                self.poll_interval, MIN_POLL_INTERVAL
+            )));
+        }
+        Ok(())
+    }
+}
+
 impl Default for EventHandlerConfig {
     fn default() -> Self {
         Self {
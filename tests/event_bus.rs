@@ -0,0 +1,376 @@
+//! The event bus on its own: dispatch, ordering, fan-out and overflow.
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tell_me_when::{
+    DispatchMode, EventBus, EventData, EventMessage, EventMetadata, FsEventData, FsEventType,
+};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn message(data: EventData) -> EventMessage {
+    EventMessage {
+        metadata: EventMetadata {
+            id: 0,
+            handler_id: "test".to_string(),
+            timestamp: SystemTime::now(),
+            source: "test".to_string(),
+            dedup_key: None,
+        },
+        data,
+    }
+}
+
+fn fs_event(path: &str) -> EventMessage {
+    message(EventData::FileSystem(FsEventData {
+        event_type: FsEventType::Modified,
+        path: path.into(),
+        timestamp: SystemTime::now(),
+        batch: None,
+        sequence: None,
+        checksum: None,
+    }))
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dedicated_thread_runs_callbacks_off_the_runtime() {
+    let bus = EventBus::with_dispatch_mode(DispatchMode::DedicatedThread);
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    bus.subscribe(move |_| {
+        let _ = sender.send(std::thread::current().name().map(str::to_string));
+    })
+    .await;
+    bus.start_processing().await;
+
+    bus.publish(fs_event("a")).await;
+    let thread = receiver.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(thread.as_deref(), Some("tell_me_when-dispatch"));
+}
+
+// A subscriber callback and the receiver collecting what it's handed
+fn collector() -> (
+    impl Fn(Arc<EventMessage>) + Send + Sync + 'static,
+    crossbeam_channel::Receiver<Arc<EventMessage>>,
+) {
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    let callback = move |message| {
+        let _ = sender.send(message);
+    };
+    (callback, receiver)
+}
+
+fn path_of(message: &EventMessage) -> String {
+    match &message.data {
+        EventData::FileSystem(fs_data) => fs_data.path.display().to_string(),
+        other => panic!("expected a filesystem event, got {:?}", other),
+    }
+}
+
+// Dispatch must not block a worker, or a single-threaded runtime never
+// gets to run the task waiting for the event
+#[tokio::test]
+async fn delivers_on_a_current_thread_runtime() {
+    let bus = EventBus::new();
+    let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+    bus.subscribe(move |message| {
+        let _ = sender.send(message);
+    })
+    .await;
+    bus.start_processing().await;
+
+    bus.publish(fs_event("a")).await;
+    let message = tokio::time::timeout(TIMEOUT, receiver.recv())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(path_of(&message), "a");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn panicking_callback_does_not_stop_delivery() {
+    let bus = EventBus::new();
+    bus.subscribe(|_| panic!("subscriber bug")).await;
+    let (callback, receiver) = collector();
+    bus.subscribe(callback).await;
+    bus.start_processing().await;
+
+    bus.publish(fs_event("a")).await;
+    bus.publish(fs_event("b")).await;
+    assert_eq!(path_of(&receiver.recv_timeout(TIMEOUT).unwrap()), "a");
+    assert_eq!(path_of(&receiver.recv_timeout(TIMEOUT).unwrap()), "b");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn callbacks_can_subscribe_while_being_dispatched() {
+    let bus = Arc::new(EventBus::new());
+    let (callback, receiver) = collector();
+    let callback = Arc::new(callback);
+    let inner = Arc::clone(&bus);
+    bus.subscribe(move |_| {
+        let callback = Arc::clone(&callback);
+        // Would deadlock if the subscriber lock were held during callbacks
+        futures::executor::block_on(inner.subscribe(move |message| callback(message)));
+    })
+    .await;
+    bus.start_processing().await;
+
+    bus.publish(fs_event("first")).await;
+    bus.publish(fs_event("second")).await;
+    assert_eq!(path_of(&receiver.recv_timeout(TIMEOUT).unwrap()), "second");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn kind_subscribers_only_see_their_domain() {
+    use tell_me_when::{EventKind, LifecycleEventData, LifecycleEventType, StopReason};
+
+    let bus = EventBus::new();
+    let (callback, receiver) = collector();
+    bus.subscribe_kind(EventKind::Lifecycle, callback).await;
+    bus.start_processing().await;
+
+    bus.publish(fs_event("a")).await;
+    bus.publish(message(EventData::Lifecycle(LifecycleEventData {
+        event_type: LifecycleEventType::SystemStopped(StopReason::UserRequested),
+        timestamp: SystemTime::now(),
+    })))
+    .await;
+
+    let message = receiver.recv_timeout(TIMEOUT).unwrap();
+    assert_eq!(message.data.kind(), EventKind::Lifecycle);
+    assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stamps_increasing_event_ids() {
+    let bus = EventBus::new();
+    let (callback, receiver) = collector();
+    bus.subscribe(callback).await;
+    bus.start_processing().await;
+
+    for path in ["a", "b", "c"] {
+        bus.publish(fs_event(path)).await;
+    }
+    let ids: Vec<_> = (0..3)
+        .map(|_| receiver.recv_timeout(TIMEOUT).unwrap().metadata.id)
+        .collect();
+    assert!(ids[0] > 0, "ids start above the placeholder 0: {:?}", ids);
+    assert!(ids.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", ids);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn take_subscription_ends_after_n_events() {
+    let bus = Arc::new(EventBus::new());
+    let (callback, receiver) = collector();
+    bus.subscribe_take(2, callback).await;
+    bus.start_processing().await;
+
+    for path in ["a", "b", "c", "d"] {
+        bus.publish(fs_event(path)).await;
+    }
+    assert_eq!(path_of(&receiver.recv_timeout(TIMEOUT).unwrap()), "a");
+    assert_eq!(path_of(&receiver.recv_timeout(TIMEOUT).unwrap()), "b");
+    assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+
+    // The subscription removes itself once done
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(bus.metrics().subscribers, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn metrics_count_published_and_delivered_events() {
+    let bus = EventBus::new();
+    let (first, first_receiver) = collector();
+    let (second, second_receiver) = collector();
+    let first_id = bus.subscribe(first).await;
+    bus.subscribe(second).await;
+    bus.start_processing().await;
+
+    for path in ["a", "b", "c"] {
+        bus.publish(fs_event(path)).await;
+    }
+    for _ in 0..3 {
+        first_receiver.recv_timeout(TIMEOUT).unwrap();
+        second_receiver.recv_timeout(TIMEOUT).unwrap();
+    }
+
+    let metrics = bus.metrics();
+    assert_eq!(metrics.events_published, 3);
+    assert_eq!(metrics.events_delivered, 6);
+    assert_eq!(metrics.subscribers, 2);
+    assert_eq!(metrics.send_errors, 0);
+
+    assert!(bus.unsubscribe(first_id).await);
+    assert_eq!(bus.metrics().subscribers, 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn subscribers_share_one_message() {
+    let bus = EventBus::new();
+    let (first, first_receiver) = collector();
+    let (second, second_receiver) = collector();
+    bus.subscribe(first).await;
+    bus.subscribe(second).await;
+    bus.start_processing().await;
+
+    bus.publish(fs_event("a")).await;
+    let first = first_receiver.recv_timeout(TIMEOUT).unwrap();
+    let second = second_receiver.recv_timeout(TIMEOUT).unwrap();
+    assert!(Arc::ptr_eq(&first, &second));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dispatches_in_subscription_order_across_domains() {
+    use std::sync::Mutex;
+    use tell_me_when::EventKind;
+
+    let bus = EventBus::new();
+    let calls = Arc::new(Mutex::new(Vec::new()));
+    for (name, kind) in [
+        ("all", None),
+        ("fs", Some(EventKind::FileSystem)),
+        ("all again", None),
+    ] {
+        let calls = Arc::clone(&calls);
+        let callback = move |message: Arc<EventMessage>| {
+            calls.lock().unwrap().push((message.metadata.id, name));
+        };
+        match kind {
+            Some(kind) => bus.subscribe_kind(kind, callback).await,
+            None => bus.subscribe(callback).await,
+        };
+    }
+    let (callback, receiver) = collector();
+    bus.subscribe(callback).await;
+    bus.start_processing().await;
+
+    bus.publish(fs_event("a")).await;
+    bus.publish(fs_event("b")).await;
+    receiver.recv_timeout(TIMEOUT).unwrap();
+    receiver.recv_timeout(TIMEOUT).unwrap();
+
+    let calls = calls.lock().unwrap();
+    let names: Vec<_> = calls.iter().map(|(_, name)| *name).collect();
+    assert_eq!(names, ["all", "fs", "all again", "all", "fs", "all again"]);
+    assert!(calls[0].0 < calls[3].0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn starting_twice_keeps_one_dispatcher() {
+    let bus = EventBus::new();
+    let (callback, receiver) = collector();
+    bus.subscribe(callback).await;
+    bus.start_processing().await;
+    bus.start_processing().await;
+
+    const EVENTS: usize = 500;
+    for index in 0..EVENTS {
+        bus.publish(fs_event(&index.to_string())).await;
+    }
+
+    // Two dispatchers would split the channel between them and reorder it
+    let paths: Vec<_> = (0..EVENTS)
+        .map(|_| path_of(&receiver.recv_timeout(TIMEOUT).unwrap()))
+        .collect();
+    let expected: Vec<_> = (0..EVENTS).map(|index| index.to_string()).collect();
+    assert_eq!(paths, expected);
+    assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+}
+
+async fn publish_numbered(bus: &EventBus, count: usize) {
+    for index in 0..count {
+        bus.publish(fs_event(&index.to_string())).await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn drop_newest_keeps_the_backlog() {
+    use tell_me_when::OverflowPolicy;
+
+    let bus = EventBus::bounded_with_policy(2, OverflowPolicy::DropNewest);
+    let (callback, receiver) = collector();
+    bus.subscribe(callback).await;
+    publish_numbered(&bus, 5).await;
+    bus.start_processing().await;
+
+    assert_eq!(path_of(&receiver.recv_timeout(TIMEOUT).unwrap()), "0");
+    assert_eq!(path_of(&receiver.recv_timeout(TIMEOUT).unwrap()), "1");
+    assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+    assert_eq!(bus.metrics().events_dropped, 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn drop_oldest_keeps_the_latest() {
+    use tell_me_when::OverflowPolicy;
+
+    let bus = EventBus::bounded_with_policy(2, OverflowPolicy::DropOldest);
+    let (callback, receiver) = collector();
+    bus.subscribe(callback).await;
+    publish_numbered(&bus, 5).await;
+    bus.start_processing().await;
+
+    assert_eq!(path_of(&receiver.recv_timeout(TIMEOUT).unwrap()), "3");
+    assert_eq!(path_of(&receiver.recv_timeout(TIMEOUT).unwrap()), "4");
+    assert!(receiver.recv_timeout(Duration::from_millis(200)).is_err());
+    assert_eq!(bus.metrics().events_dropped, 3);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn block_waits_for_room_and_loses_nothing() {
+    let bus = Arc::new(EventBus::bounded(2));
+    let (callback, receiver) = collector();
+    bus.subscribe(callback).await;
+
+    // The sender stalls on the full channel until processing starts
+    let publisher = {
+        let sender = bus.sender();
+        std::thread::spawn(move || {
+            for index in 0..5 {
+                sender.send(fs_event(&index.to_string())).unwrap();
+            }
+        })
+    };
+    std::thread::sleep(Duration::from_millis(100));
+    assert!(!publisher.is_finished());
+
+    bus.start_processing().await;
+    let paths: Vec<_> = (0..5)
+        .map(|_| path_of(&receiver.recv_timeout(TIMEOUT).unwrap()))
+        .collect();
+    assert_eq!(paths, ["0", "1", "2", "3", "4"]);
+    publisher.join().unwrap();
+    assert_eq!(bus.metrics().events_dropped, 0);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn throttled_subscriber_reports_what_it_skipped() {
+    let bus = EventBus::new();
+    let (sender, receiver) = crossbeam_channel::unbounded();
+    bus.subscribe_throttled(5, move |message, suppressed| {
+        let _ = sender.send((path_of(&message), suppressed));
+    })
+    .await;
+    bus.start_processing().await;
+
+    // A burst of up to the rate gets through, the rest is counted
+    publish_numbered(&bus, 20).await;
+    let burst: Vec<_> = (0..5)
+        .map(|_| receiver.recv_timeout(TIMEOUT).unwrap())
+        .collect();
+    assert_eq!(
+        burst
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect::<Vec<_>>(),
+        ["0", "1", "2", "3", "4"]
+    );
+    assert!(burst.iter().all(|(_, suppressed)| *suppressed == 0));
+    assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+
+    // Once a token has refilled, the next event carries the skipped count
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    bus.publish(fs_event("late")).await;
+    assert_eq!(
+        receiver.recv_timeout(TIMEOUT).unwrap(),
+        ("late".to_string(), 15)
+    );
+}
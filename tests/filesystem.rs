@@ -0,0 +1,743 @@
+//! Filesystem subscriptions against real watches on temporary directories.
+
+#![cfg(feature = "fs")]
+
+use std::path::Path;
+use std::time::Duration;
+use tell_me_when::handlers::fs::FsWatchConfig;
+use tell_me_when::testing::{recorder, wait_for, DEFAULT_TIMEOUT};
+use tell_me_when::{EventKind, EventSystem, FsEventData, FsEventType};
+
+// Long enough for a watch to report something it was going to report
+const QUIET: Duration = Duration::from_millis(300);
+
+async fn started(config: FsWatchConfig) -> EventSystem {
+    let mut event_system = EventSystem::builder().with_fs_config(config).build();
+    event_system.start().await.unwrap();
+    event_system
+}
+
+// Events as they come from the OS, without the debounce merging them
+fn undebounced() -> FsWatchConfig {
+    FsWatchConfig {
+        debounce_events: false,
+        ..Default::default()
+    }
+}
+
+fn is_created(path: &Path) -> impl Fn(&FsEventData) -> bool + '_ {
+    move |event| event.event_type == FsEventType::Created && event.path == path
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn batches_group_events_read_together() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder::<Vec<FsEventData>>();
+    event_system
+        .on_fs_events_batch(dir.path(), callback)
+        .await
+        .unwrap();
+
+    std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+    let batch = wait_for(&receiver, DEFAULT_TIMEOUT, |batch| {
+        batch.iter().any(|event| event.path.ends_with("a.txt"))
+    })
+    .unwrap();
+    for (index, event) in batch.iter().enumerate() {
+        if let Some(info) = &event.batch {
+            assert_eq!(info.index, index);
+            assert_eq!(info.len, batch.len());
+        }
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn new_directories_report_what_is_already_inside() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(FsWatchConfig {
+        scan_new_directories: true,
+        ..undebounced()
+    })
+    .await;
+    let (callback, receiver) = recorder();
+    event_system
+        .on_fs_created(dir.path(), callback)
+        .await
+        .unwrap();
+
+    // Written before the new directory's own watch is likely to be in place
+    std::fs::create_dir(dir.path().join("made")).unwrap();
+    std::fs::write(dir.path().join("made/early.txt"), "x").unwrap();
+
+    wait_for(
+        &receiver,
+        DEFAULT_TIMEOUT,
+        is_created(&dir.path().join("made/early.txt")),
+    )
+    .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn disabling_stops_the_watches_until_enabled() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_event(dir.path(), callback)
+        .await
+        .unwrap();
+
+    event_system
+        .disable_domain(EventKind::FileSystem)
+        .await
+        .unwrap();
+    std::fs::write(dir.path().join("unseen.txt"), "x").unwrap();
+    assert!(receiver.recv_timeout(QUIET).is_err());
+
+    event_system
+        .enable_domain(EventKind::FileSystem)
+        .await
+        .unwrap();
+    std::fs::write(dir.path().join("seen.txt"), "x").unwrap();
+    let event = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert!(event.path.ends_with("seen.txt"), "{:?}", event);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn sequence_numbers_count_up_per_watch() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(FsWatchConfig {
+        sequence_numbers: true,
+        ..undebounced()
+    })
+    .await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_event(dir.path(), callback)
+        .await
+        .unwrap();
+
+    for name in ["a", "b", "c"] {
+        std::fs::write(dir.path().join(name), name).unwrap();
+    }
+    let first = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    std::thread::sleep(QUIET);
+
+    // Every event the watch reports takes the next number, whatever its type
+    let sequences: Vec<u64> = std::iter::once(first)
+        .chain(receiver.try_iter())
+        .map(|event| event.sequence.unwrap())
+        .collect();
+    assert!(
+        sequences.windows(2).all(|pair| pair[1] == pair[0] + 1),
+        "{:?}",
+        sequences
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn recent_filter_skips_files_with_old_mtimes() {
+    let dir = tempfile::tempdir().unwrap();
+    let old = dir.path().join("old.txt");
+    let fresh = dir.path().join("fresh.txt");
+    std::fs::write(&old, "old").unwrap();
+    std::fs::write(&fresh, "fresh").unwrap();
+
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_modified_recent(dir.path(), Duration::from_secs(60), callback)
+        .await
+        .unwrap();
+
+    // Rewritten with a day-old mtime, as a restore from backup would
+    let file = std::fs::OpenOptions::new().append(true).open(&old).unwrap();
+    let day_ago = std::time::SystemTime::now() - Duration::from_secs(24 * 60 * 60);
+    file.set_modified(day_ago).unwrap();
+    drop(file);
+    std::fs::write(&fresh, "fresher").unwrap();
+
+    let event = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(event.path, fresh);
+    std::thread::sleep(QUIET);
+    assert!(receiver.try_iter().all(|event| event.path == fresh));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn outcome_says_whether_a_new_watch_was_opened() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(undebounced()).await;
+
+    let first = event_system
+        .on_fs_event_with_outcome(dir.path(), |_| {})
+        .await
+        .unwrap();
+    let second = event_system
+        .on_fs_event_with_outcome(dir.path(), |_| {})
+        .await
+        .unwrap();
+    assert!(first.opened_new_watch);
+    assert!(!second.opened_new_watch);
+    assert_ne!(first.event_id, second.event_id);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn watch_ready_covers_a_deep_tree() {
+    let dir = tempfile::tempdir().unwrap();
+    let deep = dir.path().join("a/b/c/d/e");
+    std::fs::create_dir_all(&deep).unwrap();
+
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder();
+    event_system
+        .on_fs_created(dir.path(), callback)
+        .await
+        .unwrap();
+    event_system.watch_ready(dir.path()).wait().await;
+
+    std::fs::write(deep.join("leaf.txt"), "x").unwrap();
+    wait_for(
+        &receiver,
+        DEFAULT_TIMEOUT,
+        is_created(&deep.join("leaf.txt")),
+    )
+    .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn checksums_are_attached_to_writes() {
+    use tell_me_when::handlers::fs::ChecksumAlgo;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(FsWatchConfig {
+        compute_checksum: Some(ChecksumAlgo::Sha256),
+        ..Default::default()
+    })
+    .await;
+    let (callback, receiver) = recorder();
+    event_system
+        .on_fs_event(dir.path(), callback)
+        .await
+        .unwrap();
+
+    std::fs::write(dir.path().join("abc.txt"), "abc").unwrap();
+    // The create can be hashed before the contents land, so wait for the
+    // event carrying the digest of "abc" rather than the first one
+    let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+    wait_for(&receiver, DEFAULT_TIMEOUT, |event: &FsEventData| {
+        event.checksum.as_deref() == Some(digest)
+    })
+    .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn settled_changes_arrive_once_per_burst() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder::<Vec<FsEventData>>();
+    event_system
+        .on_fs_changes_settled(dir.path(), Duration::from_millis(200), callback)
+        .await
+        .unwrap();
+
+    let file = dir.path().join("config.toml");
+    for contents in ["a", "ab", "abc"] {
+        std::fs::write(&file, contents).unwrap();
+    }
+    let changes = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(changes.len(), 1, "one entry per path: {:?}", changes);
+    assert_eq!(changes[0].path, file);
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+// Half a rename has no partner inside the watched tree: leaving it reads as
+// a deletion, arriving as a creation
+#[cfg(any(windows, target_os = "linux"))]
+#[tokio::test(flavor = "multi_thread")]
+async fn moves_across_the_watch_boundary_are_deletions_and_creations() {
+    let watched = tempfile::tempdir().unwrap();
+    let outside = tempfile::tempdir().unwrap();
+    let leaving = watched.path().join("leaving.txt");
+    let arriving = outside.path().join("arriving.txt");
+    std::fs::write(&leaving, "x").unwrap();
+    std::fs::write(&arriving, "x").unwrap();
+
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_event(watched.path(), callback)
+        .await
+        .unwrap();
+
+    std::fs::rename(&leaving, outside.path().join("left.txt")).unwrap();
+    let event = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(
+        (event.event_type, event.path),
+        (FsEventType::Deleted, leaving)
+    );
+
+    let arrived = watched.path().join("arrived.txt");
+    std::fs::rename(&arriving, &arrived).unwrap();
+    wait_for(&receiver, DEFAULT_TIMEOUT, is_created(&arrived)).unwrap();
+    assert!(receiver
+        .try_iter()
+        .all(|event| !matches!(event.event_type, FsEventType::Renamed { .. })));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn reports_creation_modification_and_deletion() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder();
+    event_system
+        .on_fs_event(dir.path(), callback)
+        .await
+        .unwrap();
+
+    let file = dir.path().join("a.txt");
+    std::fs::write(&file, "a").unwrap();
+    wait_for(&receiver, DEFAULT_TIMEOUT, is_created(&file)).unwrap();
+
+    std::fs::write(&file, "ab").unwrap();
+    wait_for(&receiver, DEFAULT_TIMEOUT, |event: &FsEventData| {
+        event.event_type == FsEventType::Modified && event.path == file
+    })
+    .unwrap();
+
+    std::fs::remove_file(&file).unwrap();
+    wait_for(&receiver, DEFAULT_TIMEOUT, |event: &FsEventData| {
+        event.event_type == FsEventType::Deleted && event.path == file
+    })
+    .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn watching_a_missing_path_fails() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(undebounced()).await;
+
+    let result = event_system
+        .on_fs_event(dir.path().join("missing/deeper"), |_| {})
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn files_written_during_setup_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    // Enough directories that the background setup is still adding watches
+    // while the files are written
+    let leaves: Vec<_> = (0..300)
+        .map(|index| dir.path().join(format!("{}/a/b", index)))
+        .collect();
+    for leaf in &leaves {
+        std::fs::create_dir_all(leaf).unwrap();
+    }
+
+    let mut event_system = started(FsWatchConfig {
+        scan_new_directories: true,
+        ..undebounced()
+    })
+    .await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_created(dir.path(), callback)
+        .await
+        .unwrap();
+    for leaf in leaves.iter().rev() {
+        std::fs::write(leaf.join("new.txt"), "x").unwrap();
+    }
+    event_system.watch_ready(dir.path()).wait().await;
+
+    let mut missing: std::collections::HashSet<_> =
+        leaves.iter().map(|leaf| leaf.join("new.txt")).collect();
+    while !missing.is_empty() {
+        let event = receiver
+            .recv_timeout(DEFAULT_TIMEOUT)
+            .unwrap_or_else(|_| panic!("never reported: {:?}", missing));
+        missing.remove(&event.path);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn scoped_subscriptions_only_see_their_path() {
+    let first = tempfile::tempdir().unwrap();
+    let second = tempfile::tempdir().unwrap();
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_event_scoped(first.path(), callback)
+        .await
+        .unwrap();
+    event_system
+        .on_fs_event(second.path(), |_| {})
+        .await
+        .unwrap();
+
+    std::fs::write(second.path().join("elsewhere.txt"), "x").unwrap();
+    std::fs::write(first.path().join("here.txt"), "x").unwrap();
+
+    let event = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert!(event.path.starts_with(first.path()), "{:?}", event);
+    let others: Vec<_> = receiver
+        .try_iter()
+        .filter(|event| !event.path.starts_with(first.path()))
+        .collect();
+    assert!(others.is_empty(), "{:?}", others);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn ignore_patterns_drop_matching_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(FsWatchConfig {
+        ignore_patterns: vec!["*.log".to_string(), "build/".to_string()],
+        ..undebounced()
+    })
+    .await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_created(dir.path(), callback)
+        .await
+        .unwrap();
+
+    std::fs::write(dir.path().join("noise.log"), "x").unwrap();
+    std::fs::create_dir(dir.path().join("build")).unwrap();
+    std::fs::write(dir.path().join("build/out.o"), "x").unwrap();
+    std::fs::write(dir.path().join("kept.txt"), "x").unwrap();
+
+    let seen = wait_for(
+        &receiver,
+        DEFAULT_TIMEOUT,
+        is_created(&dir.path().join("kept.txt")),
+    );
+    assert!(seen.is_ok(), "{:?}", seen);
+    std::thread::sleep(QUIET);
+    let ignored: Vec<_> = receiver
+        .try_iter()
+        .filter(|event| {
+            event.path.extension().is_some_and(|ext| ext == "log")
+                || event.path.starts_with(dir.path().join("build"))
+        })
+        .collect();
+    assert!(ignored.is_empty(), "{:?}", ignored);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn lists_and_drops_watched_paths() {
+    let first = tempfile::tempdir().unwrap();
+    let second = tempfile::tempdir().unwrap();
+    let mut event_system = started(undebounced()).await;
+    event_system
+        .on_fs_event(first.path(), |_| {})
+        .await
+        .unwrap();
+    event_system
+        .on_fs_event(second.path(), |_| {})
+        .await
+        .unwrap();
+
+    let mut expected = vec![first.path().to_path_buf(), second.path().to_path_buf()];
+    expected.sort();
+    assert_eq!(event_system.watched_paths(), expected);
+    assert!(event_system.is_watching(first.path()));
+
+    event_system.unwatch_path(first.path()).await.unwrap();
+    assert!(!event_system.is_watching(first.path()));
+    assert_eq!(
+        event_system.watched_paths(),
+        vec![second.path().to_path_buf()]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn unwatched_paths_go_quiet() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_event(dir.path(), callback)
+        .await
+        .unwrap();
+    event_system.unwatch_path(dir.path()).await.unwrap();
+
+    std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn debounce_merges_a_burst_into_one_event() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(FsWatchConfig {
+        debounce_events: true,
+        debounce_window: Duration::from_millis(100),
+        ..Default::default()
+    })
+    .await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_event(dir.path(), callback)
+        .await
+        .unwrap();
+
+    let file = dir.path().join("a.txt");
+    std::fs::write(&file, "a").unwrap();
+    for contents in ["ab", "abc", "abcd"] {
+        std::fs::write(&file, contents).unwrap();
+    }
+
+    let event = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(event.event_type, FsEventType::Created);
+    assert_eq!(event.path, file);
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn non_recursive_watches_skip_subdirectories() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_event_with(dir.path(), false, callback)
+        .await
+        .unwrap();
+
+    std::fs::write(dir.path().join("sub/nested.txt"), "x").unwrap();
+    std::fs::write(dir.path().join("top.txt"), "x").unwrap();
+
+    wait_for(
+        &receiver,
+        DEFAULT_TIMEOUT,
+        is_created(&dir.path().join("top.txt")),
+    )
+    .unwrap();
+    std::thread::sleep(QUIET);
+    let nested: Vec<_> = receiver
+        .try_iter()
+        .filter(|event| event.path.ends_with("nested.txt"))
+        .collect();
+    assert!(nested.is_empty(), "{:?}", nested);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn watch_paths_skips_duplicates_and_missing_paths() {
+    let first = tempfile::tempdir().unwrap();
+    let second = tempfile::tempdir().unwrap();
+    let missing = first.path().join("missing");
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder();
+
+    let outcome = event_system
+        .watch_paths(
+            vec![
+                first.path().to_path_buf(),
+                second.path().to_path_buf(),
+                first.path().to_path_buf(),
+                missing,
+            ],
+            callback,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        outcome.watched,
+        vec![first.path().to_path_buf(), second.path().to_path_buf()]
+    );
+
+    std::fs::write(second.path().join("b.txt"), "b").unwrap();
+    wait_for(
+        &receiver,
+        DEFAULT_TIMEOUT,
+        is_created(&second.path().join("b.txt")),
+    )
+    .unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn renames_carry_both_paths() {
+    let dir = tempfile::tempdir().unwrap();
+    let old_path = dir.path().join("old.txt");
+    let new_path = dir.path().join("new.txt");
+    std::fs::write(&old_path, "x").unwrap();
+
+    let mut event_system = started(FsWatchConfig {
+        event_types: Vec::new(),
+        ..undebounced()
+    })
+    .await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_renamed(dir.path(), callback)
+        .await
+        .unwrap();
+
+    std::fs::rename(&old_path, &new_path).unwrap();
+    let event = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(
+        event.event_type,
+        FsEventType::Renamed {
+            old_path: old_path.clone(),
+            new_path: new_path.clone()
+        }
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn single_files_can_be_watched() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("watched.txt");
+    std::fs::write(&file, "x").unwrap();
+
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system.on_fs_event(&file, callback).await.unwrap();
+
+    std::fs::write(dir.path().join("sibling.txt"), "x").unwrap();
+    std::fs::write(&file, "changed").unwrap();
+
+    let event = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(event.path, file);
+    std::thread::sleep(QUIET);
+    assert!(receiver.try_iter().all(|event| event.path == file));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_file_watch_survives_replacement_by_rename() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("watched.txt");
+    std::fs::write(&file, "x").unwrap();
+
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system.on_fs_event(&file, callback).await.unwrap();
+
+    // How editors save: write a temporary file, then rename it over the original
+    let temporary = dir.path().join("watched.txt.new");
+    std::fs::write(&temporary, "replaced").unwrap();
+    std::fs::rename(&temporary, &file).unwrap();
+    receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    while receiver.recv_timeout(QUIET).is_ok() {}
+
+    std::fs::write(&file, "changed again").unwrap();
+    let event = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(event.path, file);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn change_commands_run_once_per_burst() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(undebounced()).await;
+    let runs = Arc::new(AtomicUsize::new(0));
+    let counter = runs.clone();
+    event_system
+        .on_change_run(dir.path(), &[], Duration::from_millis(150), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .await
+        .unwrap();
+
+    for name in ["a.rs", "b.rs", "c.rs"] {
+        std::fs::write(dir.path().join(name), "fn main() {}").unwrap();
+    }
+    tokio::time::sleep(Duration::from_millis(800)).await;
+    assert_eq!(runs.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn initial_scan_reports_existing_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("sub")).unwrap();
+    std::fs::write(dir.path().join("top.txt"), "x").unwrap();
+    std::fs::write(dir.path().join("sub/nested.txt"), "x").unwrap();
+    std::fs::write(dir.path().join("skipped.tmp"), "x").unwrap();
+
+    let mut event_system = started(FsWatchConfig {
+        emit_initial_scan: true,
+        ..undebounced()
+    })
+    .await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_created(dir.path(), callback)
+        .await
+        .unwrap();
+
+    std::thread::sleep(QUIET);
+    let mut paths: Vec<_> = receiver.try_iter().map(|event| event.path).collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec![
+            dir.path().join("sub"),
+            dir.path().join("sub/nested.txt"),
+            dir.path().join("top.txt")
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn throttled_fs_subscribers_count_what_they_skip() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder::<(FsEventData, u64)>();
+    event_system
+        .on_fs_event_throttled(dir.path(), 2, move |event, suppressed| {
+            callback((event, suppressed))
+        })
+        .await
+        .unwrap();
+
+    for index in 0..10 {
+        std::fs::write(dir.path().join(format!("{}.txt", index)), "x").unwrap();
+    }
+    std::thread::sleep(QUIET);
+    let burst: Vec<_> = receiver.try_iter().collect();
+    assert_eq!(burst.len(), 2, "{:?}", burst);
+    assert!(burst.iter().all(|(_, suppressed)| *suppressed == 0));
+
+    // A token comes back after half a second
+    std::thread::sleep(Duration::from_millis(300));
+    std::fs::write(dir.path().join("late.txt"), "x").unwrap();
+    let (event, suppressed) = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert!(event.path.ends_with("late.txt"), "{:?}", event);
+    assert!(suppressed > 0);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test(flavor = "multi_thread")]
+async fn symlinked_roots_report_paths_under_the_link() {
+    let dir = tempfile::tempdir().unwrap();
+    let target = dir.path().join("target");
+    let link = dir.path().join("link");
+    std::fs::create_dir(&target).unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    let mut event_system = started(undebounced()).await;
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system.on_fs_event(&link, callback).await.unwrap();
+    assert!(event_system.is_watching(&link));
+
+    std::fs::write(target.join("a.txt"), "a").unwrap();
+    wait_for(&receiver, DEFAULT_TIMEOUT, is_created(&link.join("a.txt"))).unwrap();
+
+    event_system.unwatch_path(&link).await.unwrap();
+    assert!(event_system.watched_paths().is_empty());
+    while receiver.recv_timeout(QUIET).is_ok() {}
+    std::fs::write(target.join("b.txt"), "b").unwrap();
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test(flavor = "multi_thread")]
+async fn dangling_symlinks_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let link = dir.path().join("dangling");
+    std::os::unix::fs::symlink(dir.path().join("nowhere"), &link).unwrap();
+
+    let mut event_system = started(undebounced()).await;
+    assert!(event_system.on_fs_event(&link, |_| {}).await.is_err());
+}
@@ -0,0 +1,46 @@
+//! A build with the filesystem domain and nothing else, as picked with
+//! `--no-default-features --features fs`.
+
+#![cfg(all(
+    feature = "fs",
+    not(any(
+        feature = "process",
+        feature = "system",
+        feature = "network",
+        feature = "power",
+        feature = "registry"
+    ))
+))]
+
+use tell_me_when::testing::{recorder, DEFAULT_TIMEOUT};
+use tell_me_when::{EventKind, EventSystem, FsEventData};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn watches_without_the_other_domains() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = EventSystem::new();
+    event_system.start().await.unwrap();
+    for domain in [
+        EventKind::Process,
+        EventKind::System,
+        EventKind::Network,
+        EventKind::Power,
+        EventKind::Registry,
+    ] {
+        assert!(event_system
+            .supported_events(domain)
+            .iter()
+            .all(|event| !event.is_supported()));
+    }
+
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_event(dir.path(), callback)
+        .await
+        .unwrap();
+    std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+    let event = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert!(event.path.starts_with(dir.path()));
+
+    event_system.stop().await.unwrap();
+}
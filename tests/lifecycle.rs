@@ -0,0 +1,422 @@
+//! Starting, stopping and configuring an `EventSystem`.
+
+use std::time::{Duration, SystemTime};
+use tell_me_when::testing::DEFAULT_TIMEOUT;
+use tell_me_when::{
+    EventData, EventKind, EventSystem, LifecycleEventData, LifecycleEventType, StopReason,
+};
+
+// Long enough for the bus to deliver something it was going to deliver
+const QUIET: Duration = Duration::from_millis(200);
+
+// Started with the polled domains switched off, so only injected events arrive
+async fn quiet(mut event_system: EventSystem) -> EventSystem {
+    for domain in [
+        EventKind::Process,
+        EventKind::System,
+        EventKind::Network,
+        EventKind::Power,
+    ] {
+        event_system.disable_domain(domain).await.unwrap();
+    }
+    event_system.start().await.unwrap();
+    event_system
+}
+
+fn stopped_reason(data: &EventData) -> Option<&StopReason> {
+    match data.as_lifecycle()?.event_type {
+        LifecycleEventType::SystemStopped(ref reason) => Some(reason),
+        _ => None,
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stopping_reports_why() {
+    let mut event_system = quiet(EventSystem::new()).await;
+    let lifecycle = event_system
+        .subscribe_kind_channel(EventKind::Lifecycle)
+        .await
+        .unwrap();
+
+    let reason = StopReason::BackendFailure("watcher died".to_string());
+    event_system.stop_with_reason(reason.clone()).await.unwrap();
+    let message = lifecycle.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(stopped_reason(&message.data), Some(&reason));
+
+    // Already stopped: nothing more to report
+    event_system.stop().await.unwrap();
+    assert!(lifecycle.recv_timeout(QUIET).is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn plain_stop_is_user_requested() {
+    let mut event_system = quiet(EventSystem::new()).await;
+    let lifecycle = event_system
+        .subscribe_kind_channel(EventKind::Lifecycle)
+        .await
+        .unwrap();
+
+    event_system.stop().await.unwrap();
+    let message = lifecycle.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(
+        stopped_reason(&message.data),
+        Some(&StopReason::UserRequested)
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn self_metrics_count_buffered_events() {
+    let mut event_system = quiet(EventSystem::new()).await;
+    let before = event_system.self_metrics();
+    assert!(
+        before.threads > 0,
+        "the bus runs its own thread: {:?}",
+        before
+    );
+
+    let lifecycle = event_system
+        .subscribe_kind_channel(EventKind::Lifecycle)
+        .await
+        .unwrap();
+    for _ in 0..4 {
+        event_system.inject_event(stopped(StopReason::UserRequested));
+    }
+    for _ in 0..4 {
+        lifecycle.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    }
+
+    let after = event_system.self_metrics();
+    assert_eq!(after.queued_events, 0);
+    assert_eq!(after.buffer_bytes, 0, "{:?}", after);
+}
+
+fn stopped(reason: StopReason) -> EventData {
+    EventData::Lifecycle(LifecycleEventData {
+        event_type: LifecycleEventType::SystemStopped(reason),
+        timestamp: SystemTime::now(),
+    })
+}
+
+#[cfg(feature = "fs")]
+#[test]
+fn blocking_api_works_without_a_runtime() {
+    use tell_me_when::testing::recorder;
+    use tell_me_when::EventSystemBlocking;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = EventSystemBlocking::new().unwrap();
+    event_system.block_on(|event_system| async move {
+        for domain in [
+            EventKind::Process,
+            EventKind::System,
+            EventKind::Network,
+            EventKind::Power,
+        ] {
+            event_system.disable_domain(domain).await.unwrap();
+        }
+    });
+    event_system.start().unwrap();
+    assert!(event_system.is_running());
+    let (callback, receiver) = recorder::<tell_me_when::FsEventData>();
+    event_system.on_fs_created(dir.path(), callback).unwrap();
+
+    std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+    let event = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(event.path, dir.path().join("a.txt"));
+
+    assert!(event_system.stop_draining(DEFAULT_TIMEOUT).unwrap());
+    assert!(!event_system.is_running());
+}
+
+#[cfg(feature = "fs")]
+#[tokio::test(flavor = "multi_thread")]
+async fn builder_configs_reach_their_handlers() {
+    use tell_me_when::handlers::fs::FsWatchConfig;
+    use tell_me_when::testing::recorder;
+    use tell_me_when::FsEventType;
+
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("a.txt");
+    let mut event_system = EventSystem::builder()
+        .with_fs_config(FsWatchConfig {
+            event_types: vec![FsEventType::Deleted],
+            debounce_events: false,
+            ..Default::default()
+        })
+        .build();
+    event_system = quiet(event_system).await;
+    let (callback, receiver) = recorder::<tell_me_when::FsEventData>();
+    event_system
+        .on_fs_event(dir.path(), callback)
+        .await
+        .unwrap();
+
+    std::fs::write(&file, "a").unwrap();
+    std::fs::remove_file(&file).unwrap();
+    let event = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(event.event_type, FsEventType::Deleted);
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[cfg(feature = "system")]
+#[tokio::test(flavor = "multi_thread")]
+async fn poll_intervals_below_the_minimum_are_rejected() {
+    use tell_me_when::handlers::system::SystemConfig;
+    use tell_me_when::{EventHandlerConfig, TellMeWhenError};
+
+    let mut event_system = EventSystem::builder()
+        .with_system_config(SystemConfig {
+            base: EventHandlerConfig {
+                poll_interval: Duration::from_millis(1),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .build();
+    for domain in [EventKind::Process, EventKind::Network, EventKind::Power] {
+        event_system.disable_domain(domain).await.unwrap();
+    }
+    event_system.start().await.unwrap();
+
+    let result = event_system.on_system_event(|_| {}).await;
+    assert!(
+        matches!(result, Err(TellMeWhenError::Config(_))),
+        "{:?}",
+        result
+    );
+}
+
+#[cfg(feature = "fs")]
+#[tokio::test(flavor = "multi_thread")]
+async fn stop_ends_the_handlers() {
+    use tell_me_when::testing::recorder;
+
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = quiet(EventSystem::new()).await;
+    let (callback, receiver) = recorder::<tell_me_when::FsEventData>();
+    event_system
+        .on_fs_event(dir.path(), callback)
+        .await
+        .unwrap();
+
+    event_system.stop().await.unwrap();
+    assert!(!event_system.is_running());
+
+    std::fs::write(dir.path().join("after-stop.txt"), "x").unwrap();
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn on_error_only_sees_failures_and_recoveries() {
+    use tell_me_when::testing::recorder;
+
+    let mut event_system = quiet(EventSystem::new()).await;
+    let (callback, receiver) = recorder::<LifecycleEventData>();
+    event_system.on_error(callback).await.unwrap();
+
+    let lifecycle = |event_type| {
+        EventData::Lifecycle(LifecycleEventData {
+            event_type,
+            timestamp: SystemTime::now(),
+        })
+    };
+    event_system.inject_event(stopped(StopReason::UserRequested));
+    event_system.inject_event(lifecycle(LifecycleEventType::WatchFailed {
+        handler: "filesystem".to_string(),
+        path: "/gone".into(),
+        os_error: Some(2),
+    }));
+    event_system.inject_event(lifecycle(LifecycleEventType::HandlerRecovered {
+        handler: "process".to_string(),
+    }));
+
+    let failed = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert!(
+        matches!(failed.event_type, LifecycleEventType::WatchFailed { .. }),
+        "{:?}",
+        failed
+    );
+    let recovered = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert!(
+        matches!(
+            recovered.event_type,
+            LifecycleEventType::HandlerRecovered { .. }
+        ),
+        "{:?}",
+        recovered
+    );
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[cfg(feature = "fs")]
+#[tokio::test(flavor = "multi_thread")]
+async fn deleting_a_watched_directory_reports_its_path() {
+    use tell_me_when::testing::recorder;
+
+    let dir = tempfile::tempdir().unwrap();
+    let watched = dir.path().join("watched");
+    std::fs::create_dir(&watched).unwrap();
+    let mut event_system = quiet(EventSystem::new()).await;
+    let (callback, receiver) = recorder::<LifecycleEventData>();
+    event_system.on_error(callback).await.unwrap();
+    event_system.on_fs_event(&watched, |_| {}).await.unwrap();
+
+    std::fs::remove_dir(&watched).unwrap();
+
+    let failed = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    match failed.event_type {
+        LifecycleEventType::WatchFailed { path, .. } => assert_eq!(path, watched),
+        other => panic!("expected WatchFailed, got {:?}", other),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn restarting_keeps_one_ordered_stream() {
+    use tell_me_when::testing::{recorder, MockFs};
+
+    let mut event_system = quiet(EventSystem::new()).await;
+    event_system.stop().await.unwrap();
+    event_system.start().await.unwrap();
+
+    let (callback, receiver) = recorder();
+    event_system.on_any_event(callback).await.unwrap();
+
+    // Alternating domains, so cross-domain ordering is checked too
+    let mock = MockFs::new(&event_system);
+    for index in 0..100 {
+        if index % 2 == 0 {
+            mock.modified(index.to_string());
+        } else {
+            event_system.inject_event(stopped(StopReason::BackendFailure(index.to_string())));
+        }
+    }
+
+    for index in 0..100 {
+        let message = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+        let label = match (&message.data.as_fs(), stopped_reason(&message.data)) {
+            (Some(fs_data), _) => fs_data.path.display().to_string(),
+            (None, Some(StopReason::BackendFailure(label))) => label.clone(),
+            _ => panic!("unexpected event {:?}", message),
+        };
+        assert_eq!(label, index.to_string());
+    }
+    assert!(
+        receiver.recv_timeout(QUIET).is_err(),
+        "events were delivered twice"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stop_draining_delivers_everything_queued() {
+    use tell_me_when::testing::recorder;
+
+    let mut event_system = quiet(EventSystem::new()).await;
+    let (callback, receiver) = recorder::<LifecycleEventData>();
+    event_system
+        .on_error(move |event| {
+            std::thread::sleep(Duration::from_millis(2));
+            callback(event);
+        })
+        .await
+        .unwrap();
+
+    for index in 0..100 {
+        event_system.inject_event(stopped(StopReason::BackendFailure(index.to_string())));
+    }
+
+    assert!(event_system
+        .stop_draining(Duration::from_secs(30))
+        .await
+        .unwrap());
+    assert_eq!(receiver.try_iter().count(), 100);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stop_draining_survives_a_dropped_stop_event() {
+    use tell_me_when::testing::recorder;
+    use tell_me_when::OverflowPolicy;
+
+    let event_system = EventSystem::builder()
+        .with_channel_capacity(4)
+        .with_overflow_policy(OverflowPolicy::DropNewest)
+        .build();
+    let mut event_system = quiet(event_system).await;
+    let (callback, receiver) = recorder();
+    event_system
+        .on_any_event(move |message| {
+            std::thread::sleep(Duration::from_millis(5));
+            callback(message);
+        })
+        .await
+        .unwrap();
+
+    // Enough to fill the channel and everything between it and the
+    // dispatcher, so the SystemStopped sent by the stop is dropped too
+    for _ in 0..200 {
+        event_system.inject_event(stopped(StopReason::UserRequested));
+    }
+    let started = std::time::Instant::now();
+    assert!(event_system
+        .stop_draining(Duration::from_secs(30))
+        .await
+        .unwrap());
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    let metrics = event_system.bus_metrics();
+    assert!(metrics.events_dropped > 0, "{:?}", metrics);
+    assert_eq!(receiver.try_iter().count() + metrics.events_dropped, 201);
+}
+
+#[tokio::test]
+async fn stop_draining_a_stopped_system_is_immediate() {
+    let mut event_system = EventSystem::new();
+    assert!(event_system.stop_draining(Duration::ZERO).await.unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn names_prefix_handler_ids() {
+    let event_system = EventSystem::builder().with_name("build-watcher").build();
+    assert_eq!(event_system.name(), Some("build-watcher"));
+
+    let mut event_system = quiet(event_system).await;
+    let lifecycle = event_system
+        .subscribe_kind_channel(EventKind::Lifecycle)
+        .await
+        .unwrap();
+    event_system.stop().await.unwrap();
+
+    let message = lifecycle.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(message.metadata.handler_id, "build-watcher/event_system");
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn bounded_systems_apply_their_overflow_policy() {
+    use tell_me_when::testing::recorder;
+    use tell_me_when::OverflowPolicy;
+
+    let event_system = EventSystem::builder()
+        .with_channel_capacity(3)
+        .with_overflow_policy(OverflowPolicy::DropOldest)
+        .build();
+    // Not started yet, so nothing leaves the channel
+    for index in 0..10 {
+        event_system.inject_event(stopped(StopReason::BackendFailure(index.to_string())));
+    }
+    assert_eq!(event_system.bus_metrics().events_dropped, 7);
+
+    let mut event_system = event_system;
+    let (callback, receiver) = recorder();
+    event_system.on_error(callback).await.unwrap();
+    let mut event_system = quiet(event_system).await;
+
+    let labels: Vec<String> = (0..3)
+        .map(
+            |_| match receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap().event_type {
+                LifecycleEventType::SystemStopped(StopReason::BackendFailure(label)) => label,
+                other => panic!("unexpected event {:?}", other),
+            },
+        )
+        .collect();
+    assert_eq!(labels, vec!["7", "8", "9"]);
+    event_system.stop().await.unwrap();
+}
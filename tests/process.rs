@@ -0,0 +1,209 @@
+//! Process filtering and the events raised for real processes.
+
+#![cfg(feature = "process")]
+
+use tell_me_when::handlers::process::{normalize_process_name, ProcessConfig};
+
+fn filtering(filters: &[&str]) -> ProcessConfig {
+    ProcessConfig {
+        process_name_filters: filters.iter().map(|filter| filter.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn names_normalize_across_platforms() {
+    assert_eq!(normalize_process_name("Chrome.EXE"), "chrome");
+    assert_eq!(
+        normalize_process_name(r"C:\Program Files\Google\chrome.exe"),
+        "chrome"
+    );
+    assert_eq!(
+        normalize_process_name("/opt/google/chrome/chrome"),
+        "chrome"
+    );
+    assert_eq!(normalize_process_name("chrome"), "chrome");
+}
+
+#[test]
+fn filters_match_windows_and_linux_names() {
+    use std::path::Path;
+
+    let config = filtering(&["chrome"]);
+    assert!(config.should_monitor_process("chrome.exe", None));
+    assert!(config.should_monitor_process("chrome", None));
+    assert!(config.should_monitor_process(
+        "chrome-sandbox",
+        Some(Path::new("/opt/google/chrome/chrome"))
+    ));
+    assert!(!config.should_monitor_process("chromium", None));
+
+    // A 15-character comm may have been cut short of the filter
+    let config = filtering(&["gnome-terminal-server"]);
+    assert!(config.should_monitor_process("gnome-terminal-", None));
+    assert!(!config.should_monitor_process("gnome-terminal", None));
+
+    assert!(filtering(&[]).should_monitor_process("anything", None));
+}
+
+#[test]
+fn filters_match_exactly_without_normalization() {
+    let config = ProcessConfig {
+        normalize_process_names: false,
+        ..filtering(&["chrome"])
+    };
+    assert!(config.should_monitor_process("chrome", None));
+    assert!(!config.should_monitor_process("chrome.exe", None));
+    assert!(!config.should_monitor_process("Chrome", None));
+}
+
+fn started_by(
+    pid: u32,
+    owner_uid: Option<&str>,
+    owner_name: Option<&str>,
+) -> tell_me_when::EventData {
+    tell_me_when::EventData::Process(tell_me_when::ProcessEventData {
+        event_type: tell_me_when::ProcessEventType::Started,
+        pid,
+        name: "worker".to_string(),
+        cpu_usage: None,
+        memory_usage: None,
+        owner_uid: owner_uid.map(str::to_string),
+        owner_name: owner_name.map(str::to_string),
+        exit_code: None,
+        parent_pid: None,
+        exe_path: None,
+        cmdline: None,
+        timestamp: std::time::SystemTime::now(),
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn started_by_user_matches_name_or_uid() {
+    use tell_me_when::testing::{recorder, DEFAULT_TIMEOUT};
+    use tell_me_when::{EventKind, EventSystem};
+
+    let mut event_system = EventSystem::new();
+    for domain in [
+        EventKind::Process,
+        EventKind::System,
+        EventKind::Network,
+        EventKind::Power,
+    ] {
+        event_system.disable_domain(domain).await.unwrap();
+    }
+    event_system.start().await.unwrap();
+    let (by_name, by_name_events) = recorder::<tell_me_when::ProcessEventData>();
+    event_system
+        .on_process_started_by_user("alice", by_name)
+        .await
+        .unwrap();
+    let (by_uid, by_uid_events) = recorder::<tell_me_when::ProcessEventData>();
+    event_system
+        .on_process_started_by_user("1000", by_uid)
+        .await
+        .unwrap();
+
+    event_system.inject_event(started_by(1, Some("1000"), Some("alice")));
+    event_system.inject_event(started_by(2, Some("1001"), Some("bob")));
+    event_system.inject_event(started_by(3, None, None));
+    event_system.inject_event(started_by(4, Some("1000"), None));
+
+    let pids = |events: &crossbeam_channel::Receiver<tell_me_when::ProcessEventData>, count| {
+        (0..count)
+            .map(|_| events.recv_timeout(DEFAULT_TIMEOUT).unwrap().pid)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(pids(&by_name_events, 1), vec![1]);
+    assert_eq!(pids(&by_uid_events, 2), vec![1, 4]);
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert!(by_name_events.is_empty());
+    assert!(by_uid_events.is_empty());
+}
+
+// The proc connector needs CAP_NET_ADMIN; without it exits are seen by
+// polling, which can't read exit codes
+#[cfg(target_os = "linux")]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs CAP_NET_ADMIN"]
+async fn terminated_processes_carry_their_exit_code() {
+    use tell_me_when::testing::{recorder, DEFAULT_TIMEOUT};
+    use tell_me_when::{EventKind, EventSystem, ProcessEventType};
+
+    let mut event_system = EventSystem::new();
+    for domain in [EventKind::System, EventKind::Network, EventKind::Power] {
+        event_system.disable_domain(domain).await.unwrap();
+    }
+    event_system.start().await.unwrap();
+    let (callback, receiver) = recorder::<tell_me_when::ProcessEventData>();
+    event_system.on_process_terminated(callback).await.unwrap();
+    // Give the connector a moment to join its multicast group
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+    let mut child = std::process::Command::new("sh")
+        .args(["-c", "exit 3"])
+        .spawn()
+        .unwrap();
+    let pid = child.id();
+    child.wait().unwrap();
+
+    let deadline = std::time::Instant::now() + DEFAULT_TIMEOUT;
+    let event = loop {
+        let remaining = deadline.saturating_duration_since(std::time::Instant::now());
+        let event = receiver
+            .recv_timeout(remaining)
+            .expect("no Terminated event for the child");
+        if event.pid == pid {
+            break event;
+        }
+    };
+    assert_eq!(event.event_type, ProcessEventType::Terminated);
+    assert_eq!(event.exit_code, Some(3));
+
+    // The process handler's blocking threads would hold up runtime shutdown
+    event_system.stop().await.unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test(flavor = "multi_thread")]
+#[ignore = "needs CAP_NET_ADMIN"]
+async fn process_trees_follow_new_children() {
+    use tell_me_when::testing::{recorder, DEFAULT_TIMEOUT};
+    use tell_me_when::{EventKind, EventSystem, ProcessEventType};
+
+    let mut event_system = EventSystem::new();
+    for domain in [EventKind::System, EventKind::Network, EventKind::Power] {
+        event_system.disable_domain(domain).await.unwrap();
+    }
+    event_system.start().await.unwrap();
+    let (callback, receiver) = recorder::<tell_me_when::ProcessEventData>();
+    event_system
+        .on_process_tree(std::process::id(), callback)
+        .await
+        .unwrap();
+    tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+
+    let mut child = std::process::Command::new("sh")
+        .args(["-c", "exit 0"])
+        .spawn()
+        .unwrap();
+    let pid = child.id();
+    child.wait().unwrap();
+
+    let mut seen = Vec::new();
+    while !seen.contains(&ProcessEventType::Terminated) {
+        let event = receiver
+            .recv_timeout(DEFAULT_TIMEOUT)
+            .expect("the child's events never arrived");
+        if event.pid != pid {
+            continue;
+        }
+        if event.event_type == ProcessEventType::Started {
+            assert_eq!(event.parent_pid, Some(std::process::id()));
+        }
+        seen.push(event.event_type);
+    }
+    assert_eq!(seen.first(), Some(&ProcessEventType::Started));
+
+    event_system.stop().await.unwrap();
+}
@@ -0,0 +1,70 @@
+//! Registry watches against a scratch key under `HKEY_CURRENT_USER`.
+
+#![cfg(all(windows, feature = "registry"))]
+
+use std::process::Command;
+use std::time::Duration;
+use tell_me_when::handlers::registry::RegistryHive;
+use tell_me_when::testing::{recorder, DEFAULT_TIMEOUT};
+use tell_me_when::{EventKind, EventSystem, RegistryChangeType, RegistryEventData};
+
+// `reg.exe` keeps the test free of a Windows API dependency of its own
+fn reg(args: &[&str]) {
+    let status = Command::new("reg").args(args).status().unwrap();
+    assert!(status.success(), "reg {:?} failed", args);
+}
+
+// Deletes the scratch key however the test ends
+struct ScratchKey(String);
+
+impl Drop for ScratchKey {
+    fn drop(&mut self) {
+        let _ = Command::new("reg")
+            .args(["delete", &format!(r"HKCU\{}", self.0), "/f"])
+            .status();
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn value_writes_and_deletions_are_reported() {
+    let subkey = format!(r"Software\TellMeWhenTest\{}", std::process::id());
+    let key = format!(r"HKCU\{}", subkey);
+    reg(&["add", &key, "/f"]);
+    let _scratch = ScratchKey(subkey.clone());
+
+    let mut event_system = EventSystem::new();
+    for domain in [
+        EventKind::FileSystem,
+        EventKind::Process,
+        EventKind::System,
+        EventKind::Network,
+        EventKind::Power,
+    ] {
+        event_system.disable_domain(domain).await.unwrap();
+    }
+    event_system.start().await.unwrap();
+    let (callback, receiver) = recorder::<RegistryEventData>();
+    event_system
+        .on_registry_change(RegistryHive::CurrentUser, &subkey, callback)
+        .await
+        .unwrap();
+    // The watch thread reads the key's values before waiting on it
+    tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let key_path = RegistryHive::CurrentUser.key_path(&subkey);
+    reg(&[
+        "add", &key, "/v", "Setting", "/t", "REG_SZ", "/d", "on", "/f",
+    ]);
+    let created = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(created.key, key_path);
+    assert_eq!(created.value.as_deref(), Some("Setting"));
+    assert_eq!(created.change_type, RegistryChangeType::ValueCreated);
+
+    reg(&["delete", &key, "/v", "Setting", "/f"]);
+    let deleted = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(deleted.key, key_path);
+    assert_eq!(deleted.value.as_deref(), Some("Setting"));
+    assert_eq!(deleted.change_type, RegistryChangeType::ValueDeleted);
+
+    event_system.stop().await.unwrap();
+}
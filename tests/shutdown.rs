@@ -0,0 +1,77 @@
+//! Stopping an `EventSystem` ends every thread and task it started.
+//!
+//! The thread and task counts are process-wide, so this file keeps to a
+//! single test: anything else running alongside would be counted too.
+
+#![cfg(all(feature = "process", feature = "system"))]
+
+use std::time::{Duration, Instant};
+use tell_me_when::testing::DEFAULT_TIMEOUT;
+use tell_me_when::{EventKind, EventSystem};
+
+// Polls the global counts until they come down to `expected` threads and
+// tasks, returning the last reading
+async fn settle(event_system: &EventSystem, expected: (usize, usize)) -> (usize, usize) {
+    let deadline = Instant::now() + DEFAULT_TIMEOUT;
+    loop {
+        let metrics = event_system.self_metrics();
+        let counts = (metrics.threads, metrics.tasks);
+        if counts == expected || Instant::now() >= deadline {
+            return counts;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn stop_ends_the_process_and_system_monitors() {
+    // What the bus holds on its own, with every domain switched off
+    let mut bus_only = EventSystem::new();
+    for domain in [
+        EventKind::Process,
+        EventKind::System,
+        EventKind::Network,
+        EventKind::Power,
+    ] {
+        bus_only.disable_domain(domain).await.unwrap();
+    }
+    bus_only.start().await.unwrap();
+    let metrics = bus_only.self_metrics();
+    let bus_counts = (metrics.threads, metrics.tasks);
+    drop(bus_only);
+    assert_eq!(settle(&EventSystem::new(), (0, 0)).await, (0, 0));
+
+    let mut event_system = EventSystem::new();
+    for domain in [EventKind::Network, EventKind::Power] {
+        event_system.disable_domain(domain).await.unwrap();
+    }
+    event_system.start().await.unwrap();
+    let process_events = event_system
+        .subscribe_kind_channel(EventKind::Process)
+        .await
+        .unwrap();
+    let system_events = event_system
+        .subscribe_kind_channel(EventKind::System)
+        .await
+        .unwrap();
+    // Let the monitors settle into their loops
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(event_system.self_metrics().threads > bus_counts.0);
+
+    // The bus outlives a stop; everything else ends with it
+    event_system.stop().await.unwrap();
+    assert_eq!(settle(&event_system, bus_counts).await, bus_counts);
+
+    while process_events.try_recv().is_ok() || system_events.try_recv().is_ok() {}
+    // A process starting and exiting is what the process monitors report
+    std::process::Command::new(std::env::current_exe().unwrap())
+        .arg("--list")
+        .output()
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(process_events.try_recv().is_err());
+    assert!(system_events.try_recv().is_err());
+
+    drop(event_system);
+    assert_eq!(settle(&EventSystem::new(), (0, 0)).await, (0, 0));
+}
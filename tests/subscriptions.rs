@@ -0,0 +1,903 @@
+//! `EventSystem` subscriptions, fed with injected events so they don't depend
+//! on what the machine happens to be doing.
+
+use std::time::{Duration, SystemTime};
+use tell_me_when::testing::{recorder, DEFAULT_TIMEOUT};
+use tell_me_when::{
+    EventData, EventKind, EventSystem, FsEventData, FsEventType, ProcessEventData, ProcessEventType,
+};
+
+// Long enough for the bus to deliver something it was going to deliver
+const QUIET: Duration = Duration::from_millis(200);
+
+// Started with the polled domains switched off, so only injected events arrive
+async fn started() -> EventSystem {
+    let mut event_system = EventSystem::new();
+    for domain in [
+        EventKind::Process,
+        EventKind::System,
+        EventKind::Network,
+        EventKind::Power,
+    ] {
+        event_system.disable_domain(domain).await.unwrap();
+    }
+    event_system.start().await.unwrap();
+    event_system
+}
+
+fn process(event_type: ProcessEventType, pid: u32, name: &str) -> EventData {
+    EventData::Process(ProcessEventData {
+        event_type,
+        pid,
+        name: name.to_string(),
+        cpu_usage: None,
+        memory_usage: None,
+        owner_uid: None,
+        owner_name: None,
+        exit_code: None,
+        parent_pid: None,
+        exe_path: None,
+        cmdline: None,
+        timestamp: SystemTime::now(),
+    })
+}
+
+fn fs(path: &str) -> EventData {
+    EventData::FileSystem(FsEventData {
+        event_type: FsEventType::Modified,
+        path: path.into(),
+        timestamp: SystemTime::now(),
+        batch: None,
+        sequence: None,
+        checksum: None,
+    })
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn subscribe_all_applies_its_predicate_to_every_domain() {
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder();
+    event_system
+        .subscribe_all(
+            |message| message.data.kind() == EventKind::Process,
+            callback,
+        )
+        .await
+        .unwrap();
+
+    event_system.inject_event(fs("a"));
+    event_system.inject_event(process(ProcessEventType::Started, 42, "worker"));
+
+    let message = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(message.data.as_process().unwrap().pid, 42);
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[cfg(feature = "fs")]
+#[tokio::test(flavor = "multi_thread")]
+async fn idle_and_resumed_fire_once_per_transition() {
+    let mut event_system = started().await;
+    let (idle, idle_receiver) = recorder();
+    let (resumed, resumed_receiver) = recorder();
+    let idle_after = Duration::from_millis(150);
+    event_system
+        .on_idle(&[EventKind::FileSystem], idle_after, move || idle(()))
+        .await
+        .unwrap();
+    event_system
+        .on_activity_resumed(&[EventKind::FileSystem], idle_after, move || resumed(()))
+        .await
+        .unwrap();
+
+    idle_receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    // Staying idle isn't reported again
+    assert!(idle_receiver.recv_timeout(idle_after * 3).is_err());
+
+    // Other domains don't count as activity
+    event_system.inject_event(process(ProcessEventType::Started, 1, "other"));
+    assert!(resumed_receiver.recv_timeout(QUIET).is_err());
+
+    event_system.inject_event(fs("a"));
+    resumed_receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    idle_receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn context_is_handed_to_the_callback() {
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder();
+    event_system
+        .subscribe_with_context(
+            EventKind::Process,
+            String::from("workers"),
+            move |context: &String, data| {
+                callback((context.clone(), data.as_process().unwrap().pid));
+            },
+        )
+        .await
+        .unwrap();
+
+    event_system.inject_event(fs("a"));
+    event_system.inject_event(process(ProcessEventType::Started, 7, "worker"));
+    assert_eq!(
+        receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap(),
+        ("workers".to_string(), 7)
+    );
+}
+
+#[cfg(feature = "fs")]
+#[tokio::test(flavor = "multi_thread")]
+async fn disabled_domains_keep_their_subscriptions() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut event_system = started().await;
+    event_system
+        .disable_domain(EventKind::FileSystem)
+        .await
+        .unwrap();
+    assert!(!event_system.is_domain_enabled(EventKind::FileSystem));
+
+    // Subscribing while disabled defers the watch
+    let (callback, receiver) = recorder::<FsEventData>();
+    event_system
+        .on_fs_event(dir.path(), callback)
+        .await
+        .unwrap();
+    assert!(event_system.watched_paths().is_empty());
+
+    event_system
+        .enable_domain(EventKind::FileSystem)
+        .await
+        .unwrap();
+    assert!(event_system.is_domain_enabled(EventKind::FileSystem));
+    assert_eq!(event_system.watched_paths(), vec![dir.path().to_path_buf()]);
+
+    std::fs::write(dir.path().join("a.txt"), "a").unwrap();
+    let event = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert!(event.path.starts_with(dir.path()));
+}
+
+#[cfg(feature = "fs")]
+#[tokio::test]
+async fn supported_events_reflect_the_platform() {
+    use tell_me_when::EventAvailability;
+
+    let event_system = EventSystem::new();
+
+    let fs_events = event_system.supported_events(EventKind::FileSystem);
+    assert!(fs_events
+        .iter()
+        .any(|event| event.name == "Created" && event.is_supported()));
+    assert!(fs_events
+        .iter()
+        .all(|event| event.domain == EventKind::FileSystem));
+
+    let registry = event_system.supported_events(EventKind::Registry);
+    assert!(!registry.is_empty());
+    assert_eq!(
+        registry.iter().all(|event| event.is_supported()),
+        cfg!(windows)
+    );
+
+    let lifecycle = event_system.supported_events(EventKind::Lifecycle);
+    let stopped = lifecycle
+        .iter()
+        .find(|event| event.name == "SystemStopped")
+        .unwrap();
+    assert_eq!(stopped.availability, EventAvailability::Native);
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn assert_receives_returns_the_matching_event() {
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder();
+    event_system.on_process_event(callback).await.unwrap();
+
+    event_system.inject_event(process(ProcessEventType::Started, 1, "first"));
+    event_system.inject_event(process(ProcessEventType::Started, 2, "second"));
+    let event = tell_me_when::assert_receives!(receiver, |event: &ProcessEventData| event.pid == 2);
+    assert_eq!(event.name, "second");
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn wait_for_hands_back_what_it_skipped() {
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder();
+    event_system.on_process_event(callback).await.unwrap();
+
+    event_system.inject_event(process(ProcessEventType::Started, 1, "first"));
+    let seen = tell_me_when::testing::wait_for(&receiver, QUIET, |event: &ProcessEventData| {
+        event.pid == 2
+    })
+    .unwrap_err();
+    assert_eq!(seen.len(), 1);
+    assert_eq!(seen[0].pid, 1);
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn tracked_pids_are_sampled() {
+    // Samples come from the process domain's periodic refresh, so it stays on
+    let mut event_system = EventSystem::new();
+    for domain in [EventKind::System, EventKind::Network, EventKind::Power] {
+        event_system.disable_domain(domain).await.unwrap();
+    }
+    event_system.start().await.unwrap();
+    let own_pid = std::process::id();
+    let (callback, receiver) = recorder();
+    event_system
+        .on_processes(&[own_pid], callback)
+        .await
+        .unwrap();
+
+    let sample = tell_me_when::assert_receives!(receiver, |event: &ProcessEventData| {
+        event.event_type == ProcessEventType::ResourceUsage
+    });
+    assert_eq!(sample.pid, own_pid);
+    assert!(sample.memory_usage.is_some_and(|memory| memory > 0));
+
+    // The process handler's blocking threads would hold up runtime shutdown
+    event_system.stop().await.unwrap();
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn untracked_and_foreign_events_are_left_out() {
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder::<ProcessEventData>();
+    event_system
+        .on_processes(&[u32::MAX], callback)
+        .await
+        .unwrap();
+
+    // Injected events don't come from this system's process handler
+    event_system.inject_event(process(ProcessEventType::Started, u32::MAX, "phantom"));
+    event_system.inject_event(process(ProcessEventType::Started, 1, "other"));
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn history_keeps_the_latest_events_and_exports_them() {
+    use tell_me_when::ExportFormat;
+
+    let mut event_system = started().await;
+    event_system.enable_history(2).await;
+    let (callback, receiver) = recorder::<ProcessEventData>();
+    event_system.on_process_event(callback).await.unwrap();
+
+    for pid in [1, 2, 3] {
+        event_system.inject_event(process(ProcessEventType::Started, pid, "worker"));
+    }
+    for _ in 0..3 {
+        receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    }
+
+    let pids: Vec<u32> = event_system
+        .history()
+        .iter()
+        .map(|message| message.data.as_process().unwrap().pid)
+        .collect();
+    assert_eq!(pids, vec![2, 3]);
+
+    let mut csv = Vec::new();
+    event_system
+        .export_history(ExportFormat::Csv, &mut csv)
+        .unwrap();
+    let csv = String::from_utf8(csv).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines.len(), 3, "{}", csv);
+    assert!(lines[0].starts_with("id,timestamp,domain"));
+
+    let mut json = Vec::new();
+    event_system
+        .export_history(ExportFormat::Json, &mut json)
+        .unwrap();
+    let json = String::from_utf8(json).unwrap();
+    assert!(json.trim_start().starts_with('['), "{}", json);
+    assert!(
+        json.contains("\"pid\":3") || json.contains("\"pid\": 3"),
+        "{}",
+        json
+    );
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn process_streams_backfill_running_processes() {
+    use futures::StreamExt;
+
+    let mut event_system = started().await;
+    let mut stream = event_system.process_event_stream(true).await.unwrap();
+    event_system.inject_event(process(ProcessEventType::Started, u32::MAX, "injected"));
+
+    let own_pid = std::process::id();
+    let mut backfilled_self = false;
+    loop {
+        let event = tokio::time::timeout(DEFAULT_TIMEOUT, stream.next())
+            .await
+            .unwrap()
+            .unwrap();
+        if event.pid == own_pid {
+            assert_eq!(event.event_type, ProcessEventType::Started);
+            backfilled_self = true;
+        }
+        if event.pid == u32::MAX {
+            break;
+        }
+    }
+    assert!(
+        backfilled_self,
+        "the running test process wasn't backfilled"
+    );
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn dropping_a_stream_unsubscribes() {
+    let mut event_system = started().await;
+    let before = event_system.bus_metrics().subscribers;
+    let stream = event_system.process_event_stream(false).await.unwrap();
+    assert_eq!(event_system.bus_metrics().subscribers, before + 1);
+
+    drop(stream);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(event_system.bus_metrics().subscribers, before);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn merged_streams_yield_from_every_system() {
+    use futures::StreamExt;
+
+    let mut first = started().await;
+    let mut second = started().await;
+    let mut merged = tell_me_when::stream::merge([
+        first.all_events_stream().await.unwrap(),
+        second.all_events_stream().await.unwrap(),
+    ]);
+
+    first.inject_event(fs("from-first"));
+    second.inject_event(fs("from-second"));
+
+    let mut paths = Vec::new();
+    for _ in 0..2 {
+        let message = tokio::time::timeout(DEFAULT_TIMEOUT, merged.next())
+            .await
+            .unwrap()
+            .unwrap();
+        paths.push(message.data.as_fs().unwrap().path.display().to_string());
+    }
+    paths.sort();
+    assert_eq!(paths, vec!["from-first", "from-second"]);
+}
+
+#[cfg(feature = "fs")]
+#[tokio::test(flavor = "multi_thread")]
+async fn saved_configs_restore_watches_and_settings() {
+    let dir = tempfile::tempdir().unwrap();
+    let kept = dir.path().join("kept");
+    let removed = dir.path().join("removed");
+    std::fs::create_dir(&kept).unwrap();
+    std::fs::create_dir(&removed).unwrap();
+    let config_file = dir.path().join("watches.json");
+
+    {
+        let mut event_system = EventSystem::builder()
+            .with_fs_config(tell_me_when::handlers::fs::FsWatchConfig {
+                ignore_patterns: vec!["*.bak".to_string()],
+                ..Default::default()
+            })
+            .build();
+        event_system.start().await.unwrap();
+        event_system.on_fs_event(&kept, |_| {}).await.unwrap();
+        event_system.on_fs_event(&removed, |_| {}).await.unwrap();
+        event_system.save_config(&config_file).unwrap();
+    }
+    std::fs::remove_dir(&removed).unwrap();
+
+    let mut event_system = started().await;
+    let missing = event_system.load_config(&config_file).await.unwrap();
+    assert_eq!(missing, vec![removed]);
+    assert_eq!(event_system.watched_paths(), vec![kept]);
+    assert_eq!(
+        event_system.saved_config().fs.ignore_patterns,
+        vec!["*.bak".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn invalid_saved_configs_are_config_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let config_file = dir.path().join("watches.json");
+    std::fs::write(&config_file, "{ not json").unwrap();
+
+    let mut event_system = EventSystem::new();
+    let result = event_system.load_config(&config_file).await;
+    assert!(
+        matches!(result, Err(tell_me_when::TellMeWhenError::Config(_))),
+        "{:?}",
+        result.err()
+    );
+}
+
+#[test]
+fn event_data_round_trips_through_json() {
+    let mut original = process(ProcessEventType::Terminated, 9, "worker");
+    // Timestamps travel as fractional seconds, exact only for whole ones
+    let timestamp = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    if let EventData::Process(ref mut process_data) = original {
+        process_data.timestamp = timestamp;
+    }
+    let json = serde_json::to_string(&original).unwrap();
+    let restored: EventData = serde_json::from_str(&json).unwrap();
+    assert_eq!(format!("{:?}", restored), format!("{:?}", original));
+
+    let renamed = EventData::FileSystem(FsEventData {
+        event_type: FsEventType::Renamed {
+            old_path: "old".into(),
+            new_path: "new".into(),
+        },
+        path: "new".into(),
+        timestamp,
+        batch: None,
+        sequence: Some(3),
+        checksum: None,
+    });
+    let restored: EventData =
+        serde_json::from_str(&serde_json::to_string(&renamed).unwrap()).unwrap();
+    assert_eq!(format!("{:?}", restored), format!("{:?}", renamed));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn channels_deliver_events_to_pull() {
+    let mut event_system = started().await;
+    let everything = event_system.subscribe_channel().await.unwrap();
+    let lifecycle = event_system
+        .subscribe_kind_channel(EventKind::Lifecycle)
+        .await
+        .unwrap();
+
+    event_system.inject_event(fs("a"));
+    let message = everything.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(message.data.kind(), EventKind::FileSystem);
+    assert!(lifecycle.recv_timeout(QUIET).is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn dropped_channels_unsubscribe_on_the_next_event() {
+    let mut event_system = started().await;
+    let before = event_system.bus_metrics().subscribers;
+    let receiver = event_system.subscribe_channel().await.unwrap();
+    drop(receiver);
+
+    event_system.inject_event(fs("a"));
+    tokio::time::sleep(QUIET).await;
+    assert_eq!(event_system.bus_metrics().subscribers, before);
+}
+
+#[cfg(not(windows))]
+#[tokio::test]
+async fn registry_channels_are_unavailable_off_windows() {
+    let mut event_system = EventSystem::new();
+    assert!(event_system
+        .subscribe_kind_channel(EventKind::Registry)
+        .await
+        .is_err());
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn process_names_match_however_they_are_spelled() {
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder::<ProcessEventData>();
+    event_system
+        .on_process_name("Notepad", callback)
+        .await
+        .unwrap();
+
+    for (pid, name) in [
+        (1, "notepad.exe"),
+        (2, "other"),
+        (3, "/usr/bin/notepad"),
+        (4, "notepad++"),
+    ] {
+        event_system.inject_event(process(ProcessEventType::Started, pid, name));
+    }
+    assert_eq!(receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap().pid, 1);
+    assert_eq!(receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap().pid, 3);
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn process_name_lifecycle_shortcuts_filter_by_type() {
+    let mut event_system = started().await;
+    let (started_callback, started_receiver) = recorder::<ProcessEventData>();
+    let (terminated_callback, terminated_receiver) = recorder::<ProcessEventData>();
+    event_system
+        .on_process_name_started("worker", started_callback)
+        .await
+        .unwrap();
+    event_system
+        .on_process_name_terminated("worker", terminated_callback)
+        .await
+        .unwrap();
+
+    event_system.inject_event(process(ProcessEventType::Started, 1, "worker"));
+    event_system.inject_event(process(ProcessEventType::Terminated, 1, "worker"));
+
+    assert_eq!(
+        started_receiver
+            .recv_timeout(DEFAULT_TIMEOUT)
+            .unwrap()
+            .event_type,
+        ProcessEventType::Started
+    );
+    assert_eq!(
+        terminated_receiver
+            .recv_timeout(DEFAULT_TIMEOUT)
+            .unwrap()
+            .event_type,
+        ProcessEventType::Terminated
+    );
+    assert!(started_receiver.recv_timeout(QUIET).is_err());
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn once_subscriptions_fire_a_single_time() {
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder::<ProcessEventData>();
+    let before = event_system.bus_metrics().subscribers;
+    event_system.on_process_event_once(callback).await.unwrap();
+
+    event_system.inject_event(fs("not a process event"));
+    event_system.inject_event(process(ProcessEventType::Started, 1, "first"));
+    event_system.inject_event(process(ProcessEventType::Started, 2, "second"));
+
+    assert_eq!(receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap().pid, 1);
+    assert!(receiver.recv_timeout(QUIET).is_err());
+    assert_eq!(event_system.bus_metrics().subscribers, before);
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn predicates_filter_before_the_callback() {
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder::<ProcessEventData>();
+    event_system
+        .on_process_event_if(
+            |data| {
+                data.as_process()
+                    .is_some_and(|process| process.pid % 2 == 0)
+            },
+            callback,
+        )
+        .await
+        .unwrap();
+
+    for pid in 1..=4 {
+        event_system.inject_event(process(ProcessEventType::Started, pid, "worker"));
+    }
+    assert_eq!(receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap().pid, 2);
+    assert_eq!(receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap().pid, 4);
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn any_event_sees_every_domain_with_metadata() {
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder();
+    event_system.on_any_event(callback).await.unwrap();
+
+    event_system.inject_event(fs("a"));
+    event_system.inject_event(process(ProcessEventType::Started, 1, "worker"));
+
+    let first = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    let second = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(first.data.kind(), EventKind::FileSystem);
+    assert_eq!(second.data.kind(), EventKind::Process);
+    assert_eq!(first.metadata.handler_id, "testing");
+}
+
+// A system event of `event_type` with none of its readings filled in
+#[cfg(feature = "system")]
+fn system_event(event_type: tell_me_when::SystemEventType) -> tell_me_when::SystemEventData {
+    tell_me_when::SystemEventData {
+        event_type,
+        cpu_usage: None,
+        memory_usage: None,
+        disk_usage: None,
+        disk_available_bytes: None,
+        disk_total_bytes: None,
+        disk_mount_point: None,
+        temperature: None,
+        load_average: None,
+        timestamp: SystemTime::now(),
+    }
+}
+
+#[cfg(feature = "system")]
+#[tokio::test(flavor = "multi_thread")]
+async fn disk_space_low_applies_its_threshold() {
+    use tell_me_when::{SystemEventData, SystemEventType};
+
+    fn disk(event_type: SystemEventType, disk_usage: f32) -> EventData {
+        EventData::System(SystemEventData {
+            disk_usage: Some(disk_usage),
+            disk_mount_point: Some("/".into()),
+            ..system_event(event_type)
+        })
+    }
+
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder::<SystemEventData>();
+    event_system
+        .on_disk_space_low(95.0, callback)
+        .await
+        .unwrap();
+
+    event_system.inject_event(disk(SystemEventType::DiskSpaceLow, 91.0));
+    event_system.inject_event(disk(SystemEventType::CpuUsageHigh, 99.0));
+    event_system.inject_event(disk(SystemEventType::DiskSpaceLow, 97.5));
+
+    assert_eq!(
+        receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap().disk_usage,
+        Some(97.5)
+    );
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[cfg(feature = "system")]
+#[tokio::test(flavor = "multi_thread")]
+async fn temperature_high_applies_its_threshold() {
+    use tell_me_when::{SystemEventData, SystemEventType};
+
+    fn temperature(event_type: SystemEventType, temperature: f32) -> EventData {
+        EventData::System(SystemEventData {
+            temperature: Some(temperature),
+            ..system_event(event_type)
+        })
+    }
+
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder::<SystemEventData>();
+    event_system
+        .on_temperature_high(80.0, callback)
+        .await
+        .unwrap();
+
+    event_system.inject_event(temperature(SystemEventType::TemperatureHigh, 76.0));
+    event_system.inject_event(temperature(SystemEventType::LoadAverageHigh, 99.0));
+    event_system.inject_event(temperature(SystemEventType::TemperatureHigh, 85.5));
+
+    assert_eq!(
+        receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap().temperature,
+        Some(85.5)
+    );
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[cfg(feature = "system")]
+#[tokio::test(flavor = "multi_thread")]
+async fn load_average_high_applies_its_threshold() {
+    use tell_me_when::{SystemEventData, SystemEventType};
+
+    fn load(event_type: SystemEventType, load_average: f32) -> EventData {
+        EventData::System(SystemEventData {
+            load_average: Some(load_average),
+            ..system_event(event_type)
+        })
+    }
+
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder::<SystemEventData>();
+    event_system
+        .on_load_average_high(4.0, callback)
+        .await
+        .unwrap();
+
+    event_system.inject_event(load(SystemEventType::LoadAverageHigh, 2.5));
+    event_system.inject_event(load(SystemEventType::TemperatureHigh, 12.0));
+    event_system.inject_event(load(SystemEventType::LoadAverageHigh, 6.25));
+
+    assert_eq!(
+        receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap().load_average,
+        Some(6.25)
+    );
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[cfg(feature = "power")]
+#[tokio::test(flavor = "multi_thread")]
+async fn domain_streams_yield_their_own_events() {
+    use futures::StreamExt;
+    use tell_me_when::{PowerEventData, PowerEventType};
+
+    let mut event_system = started().await;
+    let mut stream = event_system.power_event_stream().await.unwrap();
+
+    event_system.inject_event(fs("a"));
+    event_system.inject_event(EventData::Power(PowerEventData {
+        event_type: PowerEventType::BatteryLow,
+        battery_level: Some(5.0),
+        is_charging: Some(false),
+        power_source: None,
+        time_remaining: None,
+        health_percent: None,
+        current_frequency_mhz: None,
+        base_frequency_mhz: None,
+        timestamp: SystemTime::now(),
+    }));
+
+    let event = tokio::time::timeout(DEFAULT_TIMEOUT, stream.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(event.event_type, PowerEventType::BatteryLow);
+    assert_eq!(event.battery_level, Some(5.0));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn mock_fs_events_reach_fs_subscribers() {
+    use tell_me_when::testing::MockFs;
+
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder();
+    event_system.on_any_event(callback).await.unwrap();
+
+    let mock = MockFs::new(&event_system);
+    mock.created("a.txt");
+    mock.renamed("a.txt", "b.txt");
+
+    let created = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert_eq!(
+        created.data.as_fs().unwrap().event_type,
+        FsEventType::Created
+    );
+    let renamed = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    let renamed = renamed.data.as_fs().unwrap();
+    assert_eq!(renamed.path, std::path::PathBuf::from("b.txt"));
+    assert_eq!(
+        renamed.event_type,
+        FsEventType::Renamed {
+            old_path: "a.txt".into(),
+            new_path: "b.txt".into()
+        }
+    );
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn injected_events_wait_for_start() {
+    let mut event_system = EventSystem::new();
+    for domain in [
+        EventKind::Process,
+        EventKind::System,
+        EventKind::Network,
+        EventKind::Power,
+    ] {
+        event_system.disable_domain(domain).await.unwrap();
+    }
+    let (callback, receiver) = recorder::<ProcessEventData>();
+    event_system.on_process_event(callback).await.unwrap();
+
+    event_system.inject_event(process(ProcessEventType::Started, 1, "early"));
+    assert!(receiver.recv_timeout(QUIET).is_err());
+
+    event_system.start().await.unwrap();
+    assert_eq!(receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap().pid, 1);
+}
+
+#[cfg(feature = "process")]
+#[tokio::test]
+async fn process_name_filters_can_change_at_runtime() {
+    let mut event_system = EventSystem::new();
+    event_system.add_process_name_filter("worker");
+    event_system.add_process_name_filter("worker");
+    assert!(event_system.remove_process_name_filter("worker"));
+    assert!(!event_system.remove_process_name_filter("worker"));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn replayed_events_share_a_dedup_key() {
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder();
+    event_system.on_any_event(callback).await.unwrap();
+
+    for _ in 0..2 {
+        event_system.inject_event(process(ProcessEventType::Started, 5, "worker"));
+    }
+    let first = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    let second = receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap();
+    assert!(first.metadata.dedup_key.is_some());
+    assert_eq!(first.metadata.dedup_key, second.metadata.dedup_key);
+    assert_ne!(first.metadata.id, second.metadata.id);
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn deduped_subscriptions_drop_repeats_within_the_window() {
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder::<ProcessEventData>();
+    event_system
+        .on_process_event_deduped(Duration::from_secs(60), callback)
+        .await
+        .unwrap();
+
+    event_system.inject_event(process(ProcessEventType::Started, 5, "worker"));
+    event_system.inject_event(process(ProcessEventType::Started, 5, "worker"));
+    event_system.inject_event(process(ProcessEventType::Terminated, 5, "worker"));
+
+    assert_eq!(
+        receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap().event_type,
+        ProcessEventType::Started
+    );
+    assert_eq!(
+        receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap().event_type,
+        ProcessEventType::Terminated
+    );
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[test]
+fn accessors_match_only_their_own_domain() {
+    let data = process(ProcessEventType::Started, 1, "worker");
+    assert_eq!(data.kind(), EventKind::Process);
+    assert_eq!(data.as_process().unwrap().pid, 1);
+    assert!(data.as_fs().is_none());
+    assert!(data.as_power().is_none());
+    assert!(data.as_lifecycle().is_none());
+
+    let data = fs("a");
+    assert_eq!(data.kind(), EventKind::FileSystem);
+    assert!(data.as_fs().is_some());
+    assert!(data.as_process().is_none());
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn dropping_a_guard_unsubscribes() {
+    let mut event_system = started().await;
+    let before = event_system.bus_metrics().subscribers;
+    let (callback, receiver) = recorder::<ProcessEventData>();
+    let event_id = event_system.on_process_event(callback).await.unwrap();
+    let guard = event_system.subscribe_guard(event_id);
+    assert_eq!(event_system.bus_metrics().subscribers, before + 1);
+
+    drop(guard);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(event_system.bus_metrics().subscribers, before);
+
+    event_system.inject_event(process(ProcessEventType::Started, 1, "worker"));
+    assert!(receiver.recv_timeout(QUIET).is_err());
+}
+
+#[cfg(feature = "process")]
+#[tokio::test(flavor = "multi_thread")]
+async fn detached_guards_leave_the_subscription() {
+    let mut event_system = started().await;
+    let (callback, receiver) = recorder::<ProcessEventData>();
+    let event_id = event_system.on_process_event(callback).await.unwrap();
+    let guard = event_system.subscribe_guard(event_id);
+    assert_eq!(guard.detach(), event_id);
+
+    event_system.inject_event(process(ProcessEventType::Started, 1, "worker"));
+    assert_eq!(receiver.recv_timeout(DEFAULT_TIMEOUT).unwrap().pid, 1);
+    assert!(event_system.unsubscribe(event_id).await);
+}
+
+#[cfg(feature = "process")]
+#[test]
+fn guards_outliving_their_system_are_harmless() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let guard = runtime.block_on(async {
+        let mut event_system = started().await;
+        let event_id = event_system.on_process_event(|_| {}).await.unwrap();
+        event_system.subscribe_guard(event_id)
+    });
+    drop(runtime);
+    drop(guard);
+}